@@ -0,0 +1,223 @@
+//! Auditing the parameter cache directory.
+//!
+//! Every file listed in the parameter manifests (`parameters.json`, `srs-inner-product.json`) is
+//! reported as valid, corrupt or missing. Files which are not listed are either stale, i.e.
+//! parameters of an older `VERSION` or leftovers of interrupted downloads and imports, or unknown,
+//! e.g. parameters generated locally for sector sizes which are not published.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{read_dir, remove_file};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use storage_proofs_core::{
+    parameter_cache::{ParameterMap, VERSION},
+    parameter_ledger::{
+        log_progress, verify_file, VerificationLedger, LEDGER_FILE, LEDGER_LOCK_FILE,
+    },
+};
+
+use crate::bundle::STAGING_EXT;
+
+/// Suffixes of files left behind by paramfetch: partial http downloads and files which did not
+/// match their digest.
+const LEFTOVER_SUFFIXES: &[&str] = &[".partial", "-invalid-digest"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryStatus {
+    /// Listed in a manifest and matches its digest.
+    Valid,
+    /// Listed in a manifest but does not match its digest.
+    Corrupt,
+    /// Listed in a manifest but not in the cache.
+    Missing,
+    /// Parameters of an older version, or a leftover of an interrupted download or import.
+    Stale,
+    /// Not listed in a manifest.
+    Unknown,
+}
+
+impl fmt::Display for EntryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EntryStatus::Valid => "valid",
+            EntryStatus::Corrupt => "corrupt",
+            EntryStatus::Missing => "missing",
+            EntryStatus::Stale => "stale",
+            EntryStatus::Unknown => "unknown",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub filename: String,
+    pub status: EntryStatus,
+    /// Length of the file in bytes, `None` if it is missing.
+    pub size: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub cache_dir: PathBuf,
+    pub version: usize,
+    /// All entries, sorted by file name.
+    pub entries: Vec<AuditEntry>,
+    /// Files removed by [`AuditReport::prune`].
+    pub pruned: Vec<String>,
+    /// Files removed by [`AuditReport::remove_corrupt`], to be fetched again.
+    pub removed_corrupt: Vec<String>,
+}
+
+impl AuditReport {
+    pub fn with_status(&self, status: EntryStatus) -> impl Iterator<Item = &AuditEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.status == status)
+    }
+
+    /// Returns the number of entries per status.
+    pub fn summary(&self) -> BTreeMap<EntryStatus, usize> {
+        let mut summary = BTreeMap::new();
+        for entry in &self.entries {
+            *summary.entry(entry.status).or_insert(0) += 1;
+        }
+        summary
+    }
+
+    /// Deletes all stale files. Unknown files are never deleted, they may be parameters generated
+    /// for unpublished sector sizes.
+    pub fn prune(&mut self) -> Result<()> {
+        self.pruned = self.remove(EntryStatus::Stale)?;
+        Ok(())
+    }
+
+    /// Deletes all corrupt files, so they can be fetched again.
+    pub fn remove_corrupt(&mut self) -> Result<()> {
+        self.removed_corrupt = self.remove(EntryStatus::Corrupt)?;
+        Ok(())
+    }
+
+    fn remove(&self, status: EntryStatus) -> Result<Vec<String>> {
+        let ledger = VerificationLedger::new(&self.cache_dir);
+        let mut removed = Vec::new();
+        for entry in self.with_status(status) {
+            let path = self.cache_dir.join(&entry.filename);
+            info!("removing {} file: {}", status, path.display());
+            match remove_file(&path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to remove {}", path.display()))
+                }
+            }
+            if let Err(err) = ledger.remove(&path) {
+                warn!("failed to remove verification stamp: {:?}", err);
+            }
+            removed.push(entry.filename.clone());
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Audits `cache_dir` against the `known` manifests. Files with a valid verification stamp are
+/// not hashed again, unless `force` is set.
+pub fn audit_cache(cache_dir: &Path, known: &[&ParameterMap], force: bool) -> Result<AuditReport> {
+    let mut present = BTreeMap::new();
+    match read_dir(cache_dir) {
+        Ok(dir) => {
+            for entry in dir {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                match entry.file_name().into_string() {
+                    Ok(filename) => {
+                        present.insert(filename, metadata.len());
+                    }
+                    Err(filename) => warn!("ignoring file with invalid name: {:?}", filename),
+                }
+            }
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            warn!("parameter cache {} does not exist", cache_dir.display());
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", cache_dir.display()))
+        }
+    }
+
+    let ledger = VerificationLedger::new(cache_dir);
+    let mut entries = BTreeMap::new();
+    for (filename, data) in known.iter().flat_map(|manifest| manifest.iter()) {
+        let size = present.get(filename).copied();
+        let status = match size {
+            Some(_) => {
+                let path = cache_dir.join(filename);
+                if verify_file(&ledger, &path, &data.digest, force, Some(&log_progress))? {
+                    EntryStatus::Valid
+                } else {
+                    EntryStatus::Corrupt
+                }
+            }
+            None => EntryStatus::Missing,
+        };
+        entries.insert(filename.clone(), (status, size));
+    }
+
+    for (filename, size) in present {
+        if entries.contains_key(&filename)
+            || filename == LEDGER_FILE
+            || filename == LEDGER_LOCK_FILE
+        {
+            continue;
+        }
+        let status = if is_stale(&filename) {
+            EntryStatus::Stale
+        } else {
+            EntryStatus::Unknown
+        };
+        entries.insert(filename, (status, Some(size)));
+    }
+
+    Ok(AuditReport {
+        cache_dir: cache_dir.to_path_buf(),
+        version: VERSION,
+        entries: entries
+            .into_iter()
+            .map(|(filename, (status, size))| AuditEntry {
+                filename,
+                status,
+                size,
+            })
+            .collect(),
+        pruned: Vec::new(),
+        removed_corrupt: Vec::new(),
+    })
+}
+
+fn is_stale(filename: &str) -> bool {
+    if LEFTOVER_SUFFIXES
+        .iter()
+        .any(|suffix| filename.ends_with(suffix))
+        || filename.ends_with(&format!(".{}", STAGING_EXT))
+    {
+        return true;
+    }
+
+    // Parameters are prefixed with `v<VERSION>-`.
+    filename
+        .strip_prefix('v')
+        .and_then(|rest| rest.splitn(2, '-').next())
+        .and_then(|version| version.parse::<usize>().ok())
+        .map(|version| version < VERSION)
+        .unwrap_or(false)
+}
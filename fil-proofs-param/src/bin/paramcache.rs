@@ -5,8 +5,8 @@ use std::str::FromStr;
 use dialoguer::{theme::ColorfulTheme, MultiSelect};
 use filecoin_proofs::{
     constants::{
-        DefaultPieceHasher, POREP_PARTITIONS, PUBLISHED_SECTOR_SIZES, WINDOW_POST_CHALLENGE_COUNT,
-        WINDOW_POST_SECTOR_COUNT, WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
+        is_registered_sector_size, registered_sector_sizes, sector_size_info, DefaultPieceHasher,
+        WINDOW_POST_CHALLENGE_COUNT, WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
     },
     parameters::{public_params, window_post_public_params, winning_post_public_params},
     types::{PaddedBytesAmount, PoRepConfig, PoRepProofPartitions, PoStConfig, SectorSize},
@@ -197,11 +197,9 @@ fn generate_params_post(sector_size: u64, api_version: ApiVersion) {
         &PoStConfig {
             sector_size: SectorSize(sector_size),
             challenge_count: WINDOW_POST_CHALLENGE_COUNT,
-            sector_count: *WINDOW_POST_SECTOR_COUNT
-                .read()
-                .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-                .get(&sector_size)
-                .expect("unknown sector size"),
+            sector_count: sector_size_info(sector_size)
+                .expect("unknown sector size")
+                .window_post_sector_count,
             typ: PoStType::Window,
             priority: true,
             api_version,
@@ -209,6 +207,8 @@ fn generate_params_post(sector_size: u64, api_version: ApiVersion) {
     );
 }
 
+// Non-interactive PoRep has more partitions, which may change the number of challenges per
+// partition and therefore the circuit.
 fn generate_params_porep(sector_size: u64, api_version: ApiVersion) {
     with_shape!(
         sector_size,
@@ -216,11 +216,10 @@ fn generate_params_porep(sector_size: u64, api_version: ApiVersion) {
         PoRepConfig {
             sector_size: SectorSize(sector_size),
             partitions: PoRepProofPartitions(
-                *POREP_PARTITIONS
-                    .read()
-                    .expect("POREP_PARTITIONS poisoned")
-                    .get(&sector_size)
-                    .expect("unknown sector size"),
+                sector_size_info(sector_size)
+                    .expect("unknown sector size")
+                    .porep_partitions_for(api_version)
+                    .expect("api version not supported for sector size"),
             ),
             porep_id: [0; 32],
             api_version,
@@ -235,11 +234,9 @@ fn generate_params_empty_sector_update(sector_size: u64, api_version: ApiVersion
         PoRepConfig {
             sector_size: SectorSize(sector_size),
             partitions: PoRepProofPartitions(
-                *POREP_PARTITIONS
-                    .read()
-                    .expect("POREP_PARTITIONS poisoned")
-                    .get(&sector_size)
-                    .expect("unknown sector size"),
+                sector_size_info(sector_size)
+                    .expect("unknown sector size")
+                    .porep_partitions,
             ),
             porep_id: [0; 32],
             api_version,
@@ -257,14 +254,21 @@ pub fn main() {
     // If no sector-sizes were given provided via. the CLI, display an interactive menu. Otherwise,
     // filter out invalid CLI sector-size arguments.
     if opts.sector_sizes.is_empty() {
-        let sector_size_strings: Vec<String> = PUBLISHED_SECTOR_SIZES
+        // Sector sizes registered for devnets are offered as well.
+        let sector_sizes = registered_sector_sizes();
+        let sector_size_strings: Vec<String> = sector_sizes
             .iter()
-            .map(|sector_size| {
-                let human_size = sector_size
+            .map(|info| {
+                let human_size = info
+                    .sector_size
                     .file_size(file_size_opts::BINARY)
                     .expect("failed to format sector size");
                 // Right align numbers for easier reading.
-                format!("{: >7}", human_size)
+                if info.published {
+                    format!("{: >7}", human_size)
+                } else {
+                    format!("{: >7} (unpublished)", human_size)
+                }
             })
             .collect();
 
@@ -277,11 +281,11 @@ pub fn main() {
             .interact()
             .expect("interaction failed")
             .into_iter()
-            .map(|i| PUBLISHED_SECTOR_SIZES[i])
+            .map(|i| sector_sizes[i].sector_size)
             .collect();
     } else {
         opts.sector_sizes.retain(|size| {
-            if PUBLISHED_SECTOR_SIZES.contains(size) {
+            if is_registered_sector_size(*size) {
                 true
             } else {
                 let human_size = size
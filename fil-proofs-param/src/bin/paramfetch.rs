@@ -1,11 +1,16 @@
 use std::env;
-use std::fs::{create_dir_all, rename, File};
-use std::io::{self, copy, stderr, stdout, Read, Stdout, Write};
+use std::fs::{create_dir_all, metadata, remove_file, rename, File, OpenOptions};
+use std::io::{self, copy, stderr, stdout, BufReader, BufWriter, Read, Stdout, Write};
 use std::path::{Path, PathBuf};
 use std::process::{exit, Command};
+use std::time::Duration;
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use dialoguer::{theme::ColorfulTheme, MultiSelect, Select};
+use fil_proofs_param::{
+    audit::{audit_cache, AuditReport},
+    bundle::{export_bundle, import_bundle, BundleFilter, ProofKind},
+};
 use filecoin_proofs::param::{
     get_digest_for_file_within_cache, get_full_path_for_file_within_cache, has_extension,
 };
@@ -14,16 +19,21 @@ use humansize::{file_size_opts, FileSize};
 use lazy_static::lazy_static;
 use log::{error, info, trace, warn};
 use pbr::{ProgressBar, Units};
-use reqwest::{blocking::Client, header, Proxy, Url};
-use storage_proofs_core::parameter_cache::{
-    parameter_cache_dir, parameter_cache_dir_name, ParameterMap, GROTH_PARAMETER_EXT,
+use reqwest::{blocking::Client, header, Proxy, StatusCode, Url};
+use storage_proofs_core::{
+    parameter_cache::{
+        parameter_cache_dir, parameter_cache_dir_name, ParameterData, ParameterMap,
+        GROTH_PARAMETER_EXT, SRS_PARAMETERS,
+    },
+    parameter_ledger::{hash_file, VerificationLedger},
 };
 use structopt::StructOpt;
 use tar::Archive;
 
 lazy_static! {
     static ref CLI_ABOUT: String = format!(
-        "Downloads missing or outdated Groth parameter files from ipfs using ipget.\n\
+        "Downloads missing or outdated Groth parameter files from ipfs using ipget, or from \
+        HTTP(S) gateways with --http.\n\
         \n\
         Set the $FIL_PROOFS_PARAMETER_CACHE env-var to specify the path to the parameter cache
         directory (location where params are written), otherwise params will be written to '{}'.",
@@ -33,6 +43,7 @@ lazy_static! {
 
 const DEFAULT_JSON: &str = include_str!("../../parameters.json");
 const DEFAULT_IPGET_VERSION: &str = "v0.6.0";
+const DEFAULT_GATEWAYS: &[&str] = &["https://proofs.filecoin.io/ipfs/", "https://ipfs.io/ipfs/"];
 
 #[inline]
 fn get_ipget_dir(version: &str) -> String {
@@ -162,6 +173,13 @@ fn get_filenames_requiring_download(
                 return true;
             };
             trace!("params file found");
+            let expected_digest = &parameter_map[filename].digest;
+            let ledger = VerificationLedger::new(parameter_cache_dir());
+            if ledger.is_verified(&path, expected_digest).unwrap_or(false) {
+                trace!("file has a valid verification stamp");
+                trace!("file is up to date");
+                return false;
+            }
             let calculated_digest = match get_digest_for_file_within_cache(filename) {
                 Ok(digest) => digest,
                 Err(e) => {
@@ -169,7 +187,6 @@ fn get_filenames_requiring_download(
                     return true;
                 }
             };
-            let expected_digest = &parameter_map[filename].digest;
             if &calculated_digest == expected_digest {
                 trace!("file is up to date");
                 false
@@ -217,6 +234,377 @@ fn download_file_with_ipget(
     Ok(())
 }
 
+fn http_client() -> Result<Client> {
+    let client = Client::builder()
+        .proxy(Proxy::custom(move |url| env_proxy::for_url(url).to_url()))
+        // Parameter files are up to tens of GiB, only limit the time to connect.
+        .timeout(None)
+        .connect_timeout(Duration::from_secs(30))
+        .build()?;
+
+    Ok(client)
+}
+
+/// Parses the gateway base URLs, the file's cid is appended to them.
+fn parse_gateways(gateways: &[String]) -> Result<Vec<Url>> {
+    let gateways: Vec<&str> = if gateways.is_empty() {
+        DEFAULT_GATEWAYS.to_vec()
+    } else {
+        gateways.iter().map(String::as_str).collect()
+    };
+
+    gateways
+        .into_iter()
+        .map(|gateway| {
+            // Without a trailing slash, `Url::join` would replace the last path segment.
+            let gateway = if gateway.ends_with('/') {
+                gateway.to_string()
+            } else {
+                format!("{}/", gateway)
+            };
+            Url::parse(&gateway).with_context(|| format!("invalid gateway url: {}", gateway))
+        })
+        .collect()
+}
+
+/// Downloads a file from the first gateway which serves it. The data is written to a `.partial`
+/// file next to `path`, which is resumed if it already exists. It is only moved to `path` once
+/// its digest matches the manifest.
+fn download_file_with_http(
+    client: &Client,
+    gateways: &[Url],
+    filename: &str,
+    data: &ParameterData,
+    path: &Path,
+    verbose: bool,
+) -> Result<()> {
+    let partial_path = path.with_file_name(format!("{}.partial", filename));
+
+    let fetched = gateways.iter().any(|gateway| {
+        let fetched = gateway
+            .join(&data.cid)
+            .context("invalid cid")
+            .and_then(|url| fetch_url(client, &url, &partial_path, verbose));
+        match fetched {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("failed to download from gateway {}: {:?}", gateway, e);
+                false
+            }
+        }
+    });
+    ensure!(fetched, "no gateway could serve {}", filename);
+
+    trace!("verifying digest of {}", partial_path.display());
+    let digest = hash_file(&partial_path, None)
+        .with_context(|| format!("failed to hash {}", partial_path.display()))?;
+    if digest != data.digest {
+        // Don't resume from corrupt data, the next attempt starts from scratch.
+        remove_file(&partial_path)?;
+        bail!(
+            "digest mismatch for {}: expected {}, got {}",
+            filename,
+            data.digest,
+            digest
+        );
+    }
+
+    trace!("moving {} into place", partial_path.display());
+    rename(&partial_path, path)?;
+    if let Some(dir) = path.parent() {
+        if let Err(e) = VerificationLedger::new(dir).record(path, &data.digest) {
+            warn!("failed to record verification stamp: {:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads `url` into `partial_path`, continuing after the data already in that file.
+fn fetch_url(client: &Client, url: &Url, partial_path: &Path, verbose: bool) -> Result<()> {
+    let offset = metadata(partial_path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url.clone());
+    if offset > 0 {
+        trace!("resuming download at byte {}", offset);
+        request = request.header(header::RANGE, format!("bytes={}-", offset));
+    }
+    trace!("making GET request: {}", url.as_str());
+    let mut resp = request.send()?;
+    let status = resp.status();
+    trace!("received GET response: {}", status);
+
+    let append = match status {
+        StatusCode::PARTIAL_CONTENT => {
+            let content_range = resp
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|val| val.to_str().ok())
+                .unwrap_or_default();
+            ensure!(
+                content_range.starts_with(&format!("bytes {}-", offset)),
+                "unexpected content-range: {:?}",
+                content_range
+            );
+            true
+        }
+        // The server doesn't support ranges, start over.
+        StatusCode::OK => false,
+        // Nothing left to download, the digest check decides if the file is complete.
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => return Ok(()),
+        _ => bail!("non-success response status: {}", status),
+    };
+
+    let start = if append { offset } else { 0 };
+    let mut writer = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(partial_path)
+        .with_context(|| format!("failed to open {}", partial_path.display()))?;
+    match resp.content_length() {
+        Some(size) if verbose => {
+            let mut resp_with_progress = FetchProgress::new(resp, start + size);
+            resp_with_progress.progress_bar.set(start);
+            copy(&mut resp_with_progress, &mut writer)?;
+        }
+        _ => {
+            copy(&mut resp, &mut writer)?;
+        }
+    }
+    writer.sync_all()?;
+
+    Ok(())
+}
+
+/// Exports the selected parameter and SRS files from the cache into a bundle at `output`.
+fn export(output: &Path, filter: &BundleFilter, parameter_map: &ParameterMap) -> Result<()> {
+    let files = filter.select(&[parameter_map, &*SRS_PARAMETERS]);
+    info!(
+        "exporting {} files to bundle: {}",
+        files.len(),
+        output.display()
+    );
+
+    // Write to a temporary file first, a failed export must not leave a partial bundle behind.
+    let partial_path = PathBuf::from(format!("{}.partial", output.display()));
+    let file = File::create(&partial_path)
+        .with_context(|| format!("failed to create {}", partial_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let exported = export_bundle(&mut writer, &parameter_cache_dir(), &files).and_then(|_| {
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        Ok(())
+    });
+    if let Err(e) = exported {
+        let _ = remove_file(&partial_path);
+        return Err(e);
+    }
+    rename(&partial_path, output)?;
+
+    Ok(())
+}
+
+/// Imports a bundle into the cache, its files have to be listed in `parameter_map` or the SRS
+/// manifest.
+fn import(bundle: &Path, parameter_map: &ParameterMap) -> Result<()> {
+    info!("importing bundle: {}", bundle.display());
+    let file =
+        File::open(bundle).with_context(|| format!("failed to open {}", bundle.display()))?;
+    let manifest = import_bundle(
+        BufReader::new(file),
+        &parameter_cache_dir(),
+        &[parameter_map, &*SRS_PARAMETERS],
+    )?;
+    info!(
+        "imported {} files: {:?}",
+        manifest.files.len(),
+        manifest.files.keys().collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
+
+/// Audits the parameter cache against `known` and prints the report, as JSON if `json` is set.
+/// Stale files are deleted if `prune` is set, corrupt files if `remove_corrupt` is set.
+fn audit(
+    known: &ParameterMap,
+    force: bool,
+    prune: bool,
+    remove_corrupt: bool,
+    json: bool,
+) -> Result<AuditReport> {
+    let cache_dir = parameter_cache_dir();
+    info!("auditing parameter cache: {}", cache_dir.display());
+    let mut report = audit_cache(&cache_dir, &[known], force)?;
+    if prune {
+        report.prune()?;
+    }
+    if remove_corrupt {
+        report.remove_corrupt()?;
+    }
+
+    if json {
+        let stdout = stdout();
+        let mut stdout = stdout.lock();
+        serde_json::to_writer_pretty(&mut stdout, &report)?;
+        writeln!(stdout)?;
+    } else {
+        for entry in &report.entries {
+            let size = match entry.size {
+                Some(size) => size.file_size(file_size_opts::BINARY).unwrap(),
+                None => "-".to_string(),
+            };
+            println!("{:<8} {:>10}  {}", entry.status, size, entry.filename);
+        }
+        for (status, count) in report.summary() {
+            info!("{} files: {}", status, count);
+        }
+        for filename in &report.pruned {
+            info!("pruned: {}", filename);
+        }
+        for filename in &report.removed_corrupt {
+            info!("removed corrupt file, fetching it again: {}", filename);
+        }
+    }
+
+    Ok(report)
+}
+
+/// How parameter files are downloaded.
+enum Fetcher {
+    Ipget(PathBuf),
+    Http { client: Client, gateways: Vec<Url> },
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    #[structopt(
+        about = "Export parameter files from the cache into a bundle, for importing on \
+        hosts without network access."
+    )]
+    Export {
+        #[structopt(
+            long,
+            short = "o",
+            value_name = "PATH",
+            help = "Path of the bundle to write."
+        )]
+        output: PathBuf,
+        #[structopt(
+            long = "sector-sizes",
+            short = "z",
+            value_name = "SECTOR SIZES",
+            value_delimiter = ",",
+            require_delimiter = true,
+            multiple = false,
+            help = "A comma-separated list of sector sizes (in bytes) to export parameters for. \
+                The SRS key is exported for all sector sizes."
+        )]
+        sector_sizes: Option<Vec<u64>>,
+        #[structopt(
+            long = "proof-kinds",
+            short = "k",
+            value_name = "KINDS",
+            value_delimiter = ",",
+            require_delimiter = true,
+            multiple = false,
+            help = "A comma-separated list of proof kinds to export parameters for: porep, post, \
+                update, srs."
+        )]
+        proof_kinds: Option<Vec<ProofKind>>,
+    },
+    #[structopt(
+        about = "Import a bundle into the parameter cache. Nothing is imported if the \
+        bundle is incomplete or any file does not match its digest."
+    )]
+    Import {
+        #[structopt(value_name = "BUNDLE", help = "Path of the bundle to import.")]
+        bundle: PathBuf,
+    },
+    #[structopt(
+        about = "Report the state of the parameter cache: every file of the manifest is valid, \
+        corrupt or missing. Files of older parameter versions and leftovers of interrupted \
+        downloads are stale, all other files are unknown."
+    )]
+    Audit {
+        #[structopt(long, help = "Print the report as JSON.")]
+        json: bool,
+        #[structopt(
+            long,
+            help = "Hash all files, even those with a valid verification stamp."
+        )]
+        rehash: bool,
+        #[structopt(long, help = "Delete stale files. Unknown files are kept.")]
+        prune: bool,
+        #[structopt(
+            long,
+            long_help = "Delete corrupt files and fetch them again, using the same options as \
+                downloading missing files. Parameters which are not in the manifest, e.g. for \
+                unpublished sector sizes, are reported as unknown and have to be regenerated \
+                with paramcache."
+        )]
+        repair: bool,
+    },
+}
+
+/// Returns the files of `parameter_map` which are out of date, restricted to the sector sizes
+/// given on the command line or selected interactively.
+fn select_filenames(cli: &Cli, parameter_map: &ParameterMap) -> Vec<String> {
+    let mut filenames: Vec<String> = parameter_map.keys().cloned().collect();
+    trace!("json contains {} files", filenames.len());
+
+    // Filter out unwanted sector sizes from params files (.params files only, leave verifying-key
+    // files).
+    if let Some(ref sector_sizes) = cli.sector_sizes {
+        filenames.retain(|filename| {
+            let remove = has_extension(filename, GROTH_PARAMETER_EXT)
+                && !sector_sizes.contains(&parameter_map[filename].sector_size);
+            if remove {
+                let human_size = parameter_map[filename]
+                    .sector_size
+                    .file_size(file_size_opts::BINARY)
+                    .unwrap();
+                trace!("ignoring file: {} ({})", filename, human_size);
+            }
+            !remove
+        });
+    }
+
+    // Determine which files are outdated.
+    filenames = get_filenames_requiring_download(parameter_map, filenames);
+    if filenames.is_empty() {
+        info!("no outdated files");
+        return filenames;
+    }
+
+    // If no sector size CLI argument was provided, prompt the user to select which files to
+    // download.
+    if cli.sector_sizes.is_none() && !cli.all {
+        let filename_strings: Vec<String> = filenames
+            .iter()
+            .map(|filename| {
+                let human_size = parameter_map[filename]
+                    .sector_size
+                    .file_size(file_size_opts::BINARY)
+                    .unwrap();
+                format!("{} ({})", filename, human_size)
+            })
+            .collect();
+        filenames = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Select files to be downloaded (press space key to select)")
+            .items(&filename_strings)
+            .interact()
+            .expect("MultiSelect interaction failed")
+            .into_iter()
+            .map(|i| filenames[i].clone())
+            .collect();
+    }
+
+    filenames
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "paramfetch", version = "1.1", about = CLI_ABOUT.as_str())]
 struct Cli {
@@ -273,6 +661,24 @@ struct Cli {
         help = "Specify additional arguments for ipget."
     )]
     ipget_args: Option<String>,
+    #[structopt(
+        long,
+        help = "Download files over HTTP(S) from IPFS gateways, instead of using ipget."
+    )]
+    http: bool,
+    #[structopt(
+        long = "gateway",
+        short = "g",
+        value_name = "URL",
+        number_of_values = 1,
+        requires = "http",
+        long_help = "Base URL of an IPFS gateway, the cid of a file is appended to it. Can be \
+            given multiple times, gateways are tried in order. Defaults to \
+            https://proofs.filecoin.io/ipfs/ and https://ipfs.io/ipfs/."
+    )]
+    gateways: Vec<String>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
 }
 
 pub fn main() {
@@ -280,7 +686,7 @@ pub fn main() {
     env::set_var("RUST_LOG", "paramfetch");
     fil_logger::init();
 
-    let cli = Cli::from_args();
+    let mut cli = Cli::from_args();
 
     // Parse parameters.json file.
     let parameter_map: ParameterMap = match cli.json {
@@ -310,55 +716,67 @@ pub fn main() {
         }
     };
 
-    let mut filenames: Vec<String> = parameter_map.keys().cloned().collect();
-    trace!("json contains {} files", filenames.len());
-
-    // Filter out unwanted sector sizes from params files (.params files only, leave verifying-key
-    // files).
-    if let Some(ref sector_sizes) = cli.sector_sizes {
-        filenames.retain(|filename| {
-            let remove = has_extension(filename, GROTH_PARAMETER_EXT)
-                && !sector_sizes.contains(&parameter_map[filename].sector_size);
-            if remove {
-                let human_size = parameter_map[filename]
-                    .sector_size
-                    .file_size(file_size_opts::BINARY)
-                    .unwrap();
-                trace!("ignoring file: {} ({})", filename, human_size);
+    let mut repair = None;
+    match cli.command.take() {
+        Some(Command::Export {
+            output,
+            sector_sizes,
+            proof_kinds,
+        }) => {
+            let filter = BundleFilter {
+                sector_sizes,
+                proof_kinds,
+            };
+            match export(&output, &filter, &parameter_map) {
+                Ok(_) => info!("finished exporting bundle"),
+                Err(e) => {
+                    error!("failed to export bundle: {:?}", e);
+                    exit(1);
+                }
             }
-            !remove
-        });
-    }
-
-    // Determine which files are outdated.
-    filenames = get_filenames_requiring_download(&parameter_map, filenames);
-    if filenames.is_empty() {
-        info!("no outdated files, exiting");
-        return;
+            return;
+        }
+        Some(Command::Import { bundle }) => {
+            match import(&bundle, &parameter_map) {
+                Ok(_) => info!("finished importing bundle"),
+                Err(e) => {
+                    error!("failed to import bundle: {:?}", e);
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Audit {
+            json,
+            rehash,
+            prune,
+            repair: remove_corrupt,
+        }) => {
+            // The SRS key is not part of parameters.json, but it can be fetched all the same.
+            let mut known = parameter_map.clone();
+            known.extend(SRS_PARAMETERS.clone());
+            let report = match audit(&known, rehash, prune, remove_corrupt, json) {
+                Ok(report) => report,
+                Err(e) => {
+                    error!("failed to audit parameter cache: {:?}", e);
+                    exit(1);
+                }
+            };
+            if report.removed_corrupt.is_empty() {
+                return;
+            }
+            repair = Some((known, report.removed_corrupt));
+        }
+        None => {}
     }
 
-    // If no sector size CLI argument was provided, prompt the user to select which files to
-    // download.
-    if cli.sector_sizes.is_none() && !cli.all {
-        let filename_strings: Vec<String> = filenames
-            .iter()
-            .map(|filename| {
-                let human_size = parameter_map[filename]
-                    .sector_size
-                    .file_size(file_size_opts::BINARY)
-                    .unwrap();
-                format!("{} ({})", filename, human_size)
-            })
-            .collect();
-        filenames = MultiSelect::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select files to be downloaded (press space key to select)")
-            .items(&filename_strings)
-            .interact()
-            .expect("MultiSelect interaction failed")
-            .into_iter()
-            .map(|i| filenames[i].clone())
-            .collect();
-    }
+    let (parameter_map, mut filenames) = match repair {
+        Some((parameter_map, filenames)) => (parameter_map, filenames),
+        None => {
+            let filenames = select_filenames(&cli, &parameter_map);
+            (parameter_map, filenames)
+        }
+    };
 
     info!(
         "{} files to be downloaded: {:?}",
@@ -370,7 +788,18 @@ pub fn main() {
         return;
     }
 
-    let ipget_path = if let Some(path_str) = cli.ipget_bin {
+    let fetcher = if cli.http {
+        let gateways = parse_gateways(&cli.gateways)
+            .map_err(|e| {
+                error!("{:?}, exiting", e);
+                exit(1);
+            })
+            .unwrap();
+        trace!("using gateways: {:?}", gateways);
+        let client = http_client().expect("failed to create http client");
+
+        Fetcher::Http { client, gateways }
+    } else if let Some(path_str) = cli.ipget_bin {
         let path = PathBuf::from(path_str);
         if !path.exists() {
             error!(
@@ -379,8 +808,9 @@ pub fn main() {
             );
             exit(1);
         }
+        trace!("using ipget binary: {}", path.display());
 
-        path
+        Fetcher::Ipget(path)
     } else {
         let ipget_version = cli
             .ipget_version
@@ -391,25 +821,44 @@ pub fn main() {
             info!("ipget binary not found: {}", path.display());
             download_ipget(&ipget_version, cli.verbose).expect("ipget download failed");
         }
+        trace!("using ipget binary: {}", path.display());
 
-        path
+        Fetcher::Ipget(path)
     };
-    trace!("using ipget binary: {}", ipget_path.display());
 
     trace!("creating param cache dir(s) if they don't exist");
     create_dir_all(parameter_cache_dir()).expect("failed to create param cache dir");
 
     loop {
         for filename in &filenames {
-            info!("downloading params file with ipget: {}", filename);
             let path = get_full_path_for_file_within_cache(filename);
-            match download_file_with_ipget(
-                &parameter_map[filename].cid,
-                &path,
-                &ipget_path,
-                &cli.ipget_args,
-                cli.verbose,
-            ) {
+            let downloaded = match fetcher {
+                Fetcher::Ipget(ref ipget_path) => {
+                    info!("downloading params file with ipget: {}", filename);
+                    download_file_with_ipget(
+                        &parameter_map[filename].cid,
+                        &path,
+                        ipget_path,
+                        &cli.ipget_args,
+                        cli.verbose,
+                    )
+                }
+                Fetcher::Http {
+                    ref client,
+                    ref gateways,
+                } => {
+                    info!("downloading params file over http: {}", filename);
+                    download_file_with_http(
+                        client,
+                        gateways,
+                        filename,
+                        &parameter_map[filename],
+                        &path,
+                        cli.verbose,
+                    )
+                }
+            };
+            match downloaded {
                 Ok(_) => info!("finished downloading params file"),
                 Err(e) => warn!("failed to download params file: {}", e),
             };
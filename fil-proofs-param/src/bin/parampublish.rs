@@ -12,8 +12,7 @@ use filecoin_proofs::{
         add_extension, filename_to_parameter_id, get_digest_for_file_within_cache,
         get_full_path_for_file_within_cache, has_extension, parameter_id_to_metadata_map,
     },
-    SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB, SECTOR_SIZE_512_MIB, SECTOR_SIZE_64_GIB,
-    SECTOR_SIZE_8_MIB,
+    published_sector_sizes,
 };
 use humansize::{file_size_opts, FileSize};
 use itertools::Itertools;
@@ -33,15 +32,6 @@ lazy_static! {
     );
 }
 
-// Default sector-sizes to publish.
-const DEFAULT_SECTOR_SIZES: [u64; 5] = [
-    SECTOR_SIZE_2_KIB,
-    SECTOR_SIZE_8_MIB,
-    SECTOR_SIZE_512_MIB,
-    SECTOR_SIZE_32_GIB,
-    SECTOR_SIZE_64_GIB,
-];
-
 #[derive(Clone, Debug, PartialEq)]
 struct FileInfo {
     id: String,
@@ -283,6 +273,8 @@ pub fn main() {
         let mut ids_sorted = Vec::<String>::with_capacity(infos.len() / 2);
         let mut items = Vec::<String>::with_capacity(infos.len() / 2);
         let mut default_items: Vec<bool> = vec![];
+        // The published sector sizes are selected by default.
+        let default_sector_sizes = published_sector_sizes();
         infos
             .iter()
             .sorted_by_key(|info| info.sector_size)
@@ -291,7 +283,7 @@ pub fn main() {
                     let item = format!("{} ({})", human_size(info.sector_size), info.id);
                     items.push(item);
                     ids_sorted.push(info.id.clone());
-                    default_items.push(DEFAULT_SECTOR_SIZES.contains(&info.sector_size));
+                    default_items.push(default_sector_sizes.contains(&info.sector_size));
                 }
             });
 
@@ -0,0 +1,378 @@
+//! Parameter bundles, for moving parameters to hosts without network access.
+//!
+//! A bundle is an uncompressed tar archive (parameter files don't compress). Its first entry is
+//! a manifest (`MANIFEST.json`) listing the cid, digest, sector size and length of every file in
+//! the bundle, followed by the files themselves.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{create_dir_all, metadata, remove_file, rename, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use blake2b_simd::State as Blake2b;
+use log::{info, trace, warn};
+use serde::{Deserialize, Serialize};
+use storage_proofs_core::{
+    parameter_cache::{ParameterData, ParameterMap, SRS_KEY_EXT},
+    parameter_ledger::{log_progress, verify_file, VerificationLedger},
+};
+use tar::{Archive, Builder, EntryType, Header};
+
+/// Name of the manifest entry, which is always the first entry of a bundle.
+pub const MANIFEST_NAME: &str = "MANIFEST.json";
+/// Bump this when the bundle layout changes.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Extension of the files an import is staged in, before they are moved into place.
+pub(crate) const STAGING_EXT: &str = "import";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub cid: String,
+    pub digest: String,
+    pub sector_size: u64,
+    /// Length of the file in bytes.
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub version: u32,
+    pub files: BTreeMap<String, BundleEntry>,
+}
+
+/// The kinds of proofs parameter files can be selected by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofKind {
+    PoRep,
+    PoSt,
+    EmptySectorUpdate,
+    /// The SRS key used for proof aggregation.
+    Srs,
+}
+
+impl ProofKind {
+    /// Returns the kind of proof the given parameter file belongs to.
+    pub fn of_file(filename: &str) -> Option<ProofKind> {
+        if Path::new(filename).extension().and_then(|ext| ext.to_str()) == Some(SRS_KEY_EXT) {
+            return Some(ProofKind::Srs);
+        }
+
+        // Strip the `v<VERSION>-` prefix.
+        let id = filename.splitn(2, '-').nth(1)?;
+        if id.starts_with("stacked-proof-of-replication-") {
+            Some(ProofKind::PoRep)
+        } else if id.starts_with("proof-of-spacetime-fallback-") {
+            Some(ProofKind::PoSt)
+        } else if id.starts_with("empty-sector-update-") {
+            Some(ProofKind::EmptySectorUpdate)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for ProofKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "porep" => Ok(ProofKind::PoRep),
+            "post" => Ok(ProofKind::PoSt),
+            "update" => Ok(ProofKind::EmptySectorUpdate),
+            "srs" => Ok(ProofKind::Srs),
+            _ => bail!(
+                "unknown proof kind {:?}, expected one of porep, post, update, srs",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ProofKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProofKind::PoRep => "porep",
+            ProofKind::PoSt => "post",
+            ProofKind::EmptySectorUpdate => "update",
+            ProofKind::Srs => "srs",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Selects the files to export. Filters which are not set match all files.
+#[derive(Clone, Debug, Default)]
+pub struct BundleFilter {
+    pub sector_sizes: Option<Vec<u64>>,
+    pub proof_kinds: Option<Vec<ProofKind>>,
+}
+
+impl BundleFilter {
+    pub fn matches(&self, filename: &str, data: &ParameterData) -> bool {
+        let kind = ProofKind::of_file(filename);
+        if let Some(ref proof_kinds) = self.proof_kinds {
+            if !kind
+                .map(|kind| proof_kinds.contains(&kind))
+                .unwrap_or(false)
+            {
+                return false;
+            }
+        }
+        // The SRS key is shared by all sector sizes.
+        if let Some(ref sector_sizes) = self.sector_sizes {
+            if kind != Some(ProofKind::Srs) && !sector_sizes.contains(&data.sector_size) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Returns the files of the given manifests matching the filter.
+    pub fn select<'a>(&self, manifests: &[&'a ParameterMap]) -> Vec<(String, &'a ParameterData)> {
+        manifests
+            .iter()
+            .copied()
+            .flat_map(|manifest| manifest.iter())
+            .filter(|(filename, data)| self.matches(filename, data))
+            .map(|(filename, data)| (filename.clone(), data))
+            .collect()
+    }
+}
+
+/// Writes a bundle of the given files from `cache_dir` to `writer`. Each file is checked against
+/// its digest first, a bundle never contains files which don't match their manifest.
+pub fn export_bundle<W: Write>(
+    writer: W,
+    cache_dir: &Path,
+    files: &[(String, &ParameterData)],
+) -> Result<BundleManifest> {
+    ensure!(!files.is_empty(), "no parameter files selected");
+
+    let ledger = VerificationLedger::new(cache_dir);
+    let mut manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        files: BTreeMap::new(),
+    };
+    for (filename, data) in files {
+        let path = cache_dir.join(filename);
+        let size = metadata(&path)
+            .with_context(|| format!("missing parameter file {}", path.display()))?
+            .len();
+        ensure!(
+            verify_file(&ledger, &path, &data.digest, false, Some(&log_progress))?,
+            "parameter file {} does not match its digest",
+            path.display()
+        );
+
+        manifest.files.insert(
+            filename.clone(),
+            BundleEntry {
+                cid: data.cid.clone(),
+                digest: data.digest.clone(),
+                sector_size: data.sector_size,
+                size,
+            },
+        );
+    }
+
+    let mut builder = Builder::new(writer);
+    let manifest_data = serde_json::to_vec_pretty(&manifest)?;
+    builder.append_data(
+        &mut file_header(manifest_data.len() as u64),
+        MANIFEST_NAME,
+        &manifest_data[..],
+    )?;
+    for (filename, entry) in &manifest.files {
+        info!("adding {} to bundle", filename);
+        let file = File::open(cache_dir.join(filename))?;
+        // The header holds the length from the manifest, never copy more than that.
+        builder.append_data(
+            &mut file_header(entry.size),
+            filename,
+            file.take(entry.size),
+        )?;
+    }
+    builder.into_inner()?.flush()?;
+
+    Ok(manifest)
+}
+
+fn file_header(size: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header
+}
+
+/// Unpacks a bundle into `cache_dir`. Every file in the bundle has to be listed in one of the
+/// `known` manifests (`parameters.json`, `srs-inner-product.json`) with the same digest.
+///
+/// The files are only moved into place once the whole bundle was read and all files matched
+/// their digest. If the bundle is incomplete or corrupt, nothing is imported.
+pub fn import_bundle<R: Read>(
+    reader: R,
+    cache_dir: &Path,
+    known: &[&ParameterMap],
+) -> Result<BundleManifest> {
+    create_dir_all(cache_dir)
+        .with_context(|| format!("failed to create {}", cache_dir.display()))?;
+
+    let mut staged = Vec::new();
+    let manifest = match stage_bundle(reader, cache_dir, known, &mut staged) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            for (staging_path, _) in &staged {
+                let _ = remove_file(staging_path);
+            }
+            return Err(err);
+        }
+    };
+
+    let ledger = VerificationLedger::new(cache_dir);
+    for (staging_path, path) in &staged {
+        trace!("moving {} into place", path.display());
+        rename(staging_path, path)?;
+        let digest = &manifest.files[&file_name(path)?].digest;
+        if let Err(err) = ledger.record(path, digest) {
+            warn!("failed to record verification stamp: {:?}", err);
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Writes the files of the bundle to staging files, `staged` holds the staging file and final
+/// path of every file written so far.
+fn stage_bundle<R: Read>(
+    reader: R,
+    cache_dir: &Path,
+    known: &[&ParameterMap],
+    staged: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<BundleManifest> {
+    let mut archive = Archive::new(reader);
+    let mut entries = archive.entries().context("invalid bundle")?;
+
+    let manifest: BundleManifest = match entries.next() {
+        Some(entry) => {
+            let entry = entry.context("invalid bundle")?;
+            ensure!(
+                &*entry.path()? == Path::new(MANIFEST_NAME),
+                "bundle does not start with a manifest"
+            );
+            serde_json::from_reader(entry).context("invalid bundle manifest")?
+        }
+        None => bail!("bundle is empty"),
+    };
+    ensure!(
+        manifest.version == BUNDLE_VERSION,
+        "unsupported bundle version {}, expected {}",
+        manifest.version,
+        BUNDLE_VERSION
+    );
+
+    for (filename, entry) in &manifest.files {
+        ensure!(
+            is_plain_file_name(filename),
+            "invalid file name {:?}",
+            filename
+        );
+        match known.iter().find_map(|manifest| manifest.get(filename)) {
+            Some(data) if data.digest == entry.digest => {}
+            Some(_) => bail!("digest of {} does not match the local manifest", filename),
+            None => bail!("{} is not a known parameter file", filename),
+        }
+    }
+
+    let mut imported = BTreeSet::new();
+    for entry in entries {
+        let mut entry = entry.context("invalid bundle")?;
+        let filename = entry
+            .path()?
+            .to_str()
+            .map(ToString::to_string)
+            .context("invalid file name in bundle")?;
+        let expected = manifest
+            .files
+            .get(&filename)
+            .with_context(|| format!("{} is not listed in the bundle manifest", filename))?;
+        ensure!(
+            imported.insert(filename.clone()),
+            "{} is contained twice",
+            filename
+        );
+
+        info!("importing {}", filename);
+        let staging_path = cache_dir.join(format!("{}.{}", filename, STAGING_EXT));
+        staged.push((staging_path.clone(), cache_dir.join(&filename)));
+        let (size, digest) = copy_with_digest(&mut entry, &staging_path)?;
+        ensure!(
+            size == expected.size,
+            "{} is truncated: expected {} bytes, got {}",
+            filename,
+            expected.size,
+            size
+        );
+        ensure!(
+            digest == expected.digest,
+            "digest mismatch for {}: expected {}, got {}",
+            filename,
+            expected.digest,
+            digest
+        );
+    }
+
+    let missing: Vec<_> = manifest
+        .files
+        .keys()
+        .filter(|filename| !imported.contains(*filename))
+        .collect();
+    ensure!(
+        missing.is_empty(),
+        "bundle is incomplete, missing {:?}",
+        missing
+    );
+
+    Ok(manifest)
+}
+
+/// Copies `reader` to a new file at `path`, returns the number of bytes copied and their digest.
+fn copy_with_digest<R: Read>(reader: &mut R, path: &Path) -> Result<(u64, String)> {
+    let mut file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut hasher = Blake2b::new();
+    let mut buf = vec![0u8; 1 << 20];
+    let mut size = 0u64;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    file.sync_all()?;
+
+    Ok((size, hasher.finalize().to_hex()[..32].to_string()))
+}
+
+fn is_plain_file_name(filename: &str) -> bool {
+    let mut components = Path::new(filename).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) && filename != MANIFEST_NAME
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(ToString::to_string)
+        .with_context(|| format!("invalid file name {}", path.display()))
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use blake2b_simd::State as Blake2b;
+use fil_proofs_param::audit::{audit_cache, EntryStatus};
+use storage_proofs_core::parameter_cache::{ParameterData, ParameterMap};
+use tempfile::tempdir;
+
+const VALID_PARAMS: &str =
+    "v28-stacked-proof-of-replication-merkletree-poseidon_hasher-8-0-0-sha256_hasher-aaa.params";
+const CORRUPT_VK: &str = "v28-proof-of-spacetime-fallback-merkletree-poseidon_hasher-8-0-0-bbb.vk";
+const MISSING_META: &str =
+    "v28-proof-of-spacetime-fallback-merkletree-poseidon_hasher-8-0-0-bbb.meta";
+const SRS_KEY: &str = "v28-fil-inner-product-v1.srs";
+const OLD_PARAMS: &str =
+    "v27-stacked-proof-of-replication-merkletree-poseidon_hasher-8-0-0-sha256_hasher-aaa.params";
+const PARTIAL_DOWNLOAD: &str = "v28-proof-of-spacetime-fallback-ccc.params.partial";
+const GENERATED_PARAMS: &str =
+    "v28-stacked-proof-of-replication-merkletree-poseidon_hasher-8-0-0-sha256_hasher-ddd.params";
+
+fn parameter_data(bytes: &[u8]) -> ParameterData {
+    let mut hasher = Blake2b::new();
+    hasher.update(bytes);
+
+    ParameterData {
+        cid: "Qm".to_string(),
+        digest: hasher.finalize().to_hex()[..32].to_string(),
+        sector_size: 2048,
+    }
+}
+
+/// Creates a cache with a file of every status and returns the manifests describing it.
+fn populated_cache(dir: &Path) -> (ParameterMap, ParameterMap) {
+    let mut parameters = BTreeMap::new();
+    fs::write(dir.join(VALID_PARAMS), b"params").expect("failed to write file");
+    parameters.insert(VALID_PARAMS.to_string(), parameter_data(b"params"));
+    fs::write(dir.join(CORRUPT_VK), b"corrupt").expect("failed to write file");
+    parameters.insert(CORRUPT_VK.to_string(), parameter_data(b"vk"));
+    parameters.insert(MISSING_META.to_string(), parameter_data(b"meta"));
+
+    let mut srs_parameters = BTreeMap::new();
+    fs::write(dir.join(SRS_KEY), b"srs").expect("failed to write file");
+    srs_parameters.insert(SRS_KEY.to_string(), parameter_data(b"srs"));
+
+    for filename in &[OLD_PARAMS, PARTIAL_DOWNLOAD, GENERATED_PARAMS] {
+        fs::write(dir.join(filename), b"other").expect("failed to write file");
+    }
+
+    (parameters, srs_parameters)
+}
+
+#[test]
+fn reports_status_of_every_file() -> Result<()> {
+    let cache = tempdir()?;
+    let (parameters, srs_parameters) = populated_cache(cache.path());
+
+    let report = audit_cache(cache.path(), &[&parameters, &srs_parameters], false)?;
+    let statuses: Vec<_> = report
+        .entries
+        .iter()
+        .map(|entry| (entry.filename.as_str(), entry.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            (OLD_PARAMS, EntryStatus::Stale),
+            (SRS_KEY, EntryStatus::Valid),
+            (PARTIAL_DOWNLOAD, EntryStatus::Stale),
+            (MISSING_META, EntryStatus::Missing),
+            (CORRUPT_VK, EntryStatus::Corrupt),
+            (VALID_PARAMS, EntryStatus::Valid),
+            (GENERATED_PARAMS, EntryStatus::Unknown),
+        ]
+    );
+    assert_eq!(report.summary()[&EntryStatus::Stale], 2);
+
+    let json = serde_json::to_value(&report)?;
+    assert_eq!(json["entries"][3]["status"], "missing");
+    assert_eq!(json["entries"][3]["size"], serde_json::Value::Null);
+
+    // The verification stamps written by the first audit are not mistaken for unknown files.
+    let again = audit_cache(cache.path(), &[&parameters, &srs_parameters], false)?;
+    assert_eq!(again.entries, report.entries);
+
+    Ok(())
+}
+
+#[test]
+fn prunes_stale_and_removes_corrupt_files() -> Result<()> {
+    let cache = tempdir()?;
+    let (parameters, srs_parameters) = populated_cache(cache.path());
+
+    let mut report = audit_cache(cache.path(), &[&parameters, &srs_parameters], false)?;
+    report.prune()?;
+    report.remove_corrupt()?;
+    assert_eq!(
+        report.pruned,
+        vec![OLD_PARAMS.to_string(), PARTIAL_DOWNLOAD.to_string()]
+    );
+    assert_eq!(report.removed_corrupt, vec![CORRUPT_VK.to_string()]);
+
+    for filename in &[OLD_PARAMS, PARTIAL_DOWNLOAD, CORRUPT_VK] {
+        assert!(!cache.path().join(filename).exists());
+    }
+    for filename in &[VALID_PARAMS, SRS_KEY, GENERATED_PARAMS] {
+        assert!(cache.path().join(filename).exists());
+    }
+
+    let report = audit_cache(cache.path(), &[&parameters, &srs_parameters], true)?;
+    assert_eq!(report.with_status(EntryStatus::Missing).count(), 2);
+    assert_eq!(report.with_status(EntryStatus::Stale).count(), 0);
+    assert_eq!(report.with_status(EntryStatus::Unknown).count(), 1);
+
+    Ok(())
+}
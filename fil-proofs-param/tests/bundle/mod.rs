@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use blake2b_simd::State as Blake2b;
+use fil_proofs_param::bundle::{
+    export_bundle, import_bundle, BundleFilter, ProofKind, BUNDLE_VERSION,
+};
+use rand::{thread_rng, Rng};
+use storage_proofs_core::parameter_cache::{ParameterData, ParameterMap};
+use tempfile::tempdir;
+
+const POREP_PARAMS: &str =
+    "v28-stacked-proof-of-replication-merkletree-poseidon_hasher-8-0-0-sha256_hasher-aaa.params";
+const POST_VK: &str = "v28-proof-of-spacetime-fallback-merkletree-poseidon_hasher-8-0-0-bbb.vk";
+const SRS_KEY: &str = "v28-fil-inner-product-v1.srs";
+
+/// Writes a file with random contents to `dir` and returns its manifest entry.
+fn random_file(dir: &Path, filename: &str, sector_size: u64) -> ParameterData {
+    let bytes: Vec<u8> = (0..4096).map(|_| thread_rng().gen()).collect();
+    fs::write(dir.join(filename), &bytes).expect("failed to write file");
+
+    let mut hasher = Blake2b::new();
+    hasher.update(&bytes);
+
+    ParameterData {
+        cid: format!("Qm{}", filename.len()),
+        digest: hasher.finalize().to_hex()[..32].to_string(),
+        sector_size,
+    }
+}
+
+/// Creates a cache with a PoRep, a PoSt and an SRS file and returns their manifests.
+fn populated_cache(dir: &Path) -> (ParameterMap, ParameterMap) {
+    let mut parameters = BTreeMap::new();
+    parameters.insert(
+        POREP_PARAMS.to_string(),
+        random_file(dir, POREP_PARAMS, 2048),
+    );
+    parameters.insert(POST_VK.to_string(), random_file(dir, POST_VK, 4096));
+
+    let mut srs_parameters = BTreeMap::new();
+    srs_parameters.insert(SRS_KEY.to_string(), random_file(dir, SRS_KEY, 0));
+
+    (parameters, srs_parameters)
+}
+
+fn cache_files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .expect("failed to read dir")
+        .map(|entry| {
+            entry
+                .expect("failed to read dir entry")
+                .file_name()
+                .into_string()
+                .expect("invalid file name")
+        })
+        .filter(|filename| !filename.starts_with("verified-parameters"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn selects_files_by_sector_size_and_proof_kind() {
+    assert_eq!(ProofKind::of_file(POREP_PARAMS), Some(ProofKind::PoRep));
+    assert_eq!(ProofKind::of_file(POST_VK), Some(ProofKind::PoSt));
+    assert_eq!(ProofKind::of_file(SRS_KEY), Some(ProofKind::Srs));
+    assert_eq!(ProofKind::of_file("unknown.vk"), None);
+
+    let dir = tempdir().expect("failed to create temp dir");
+    let (parameters, srs_parameters) = populated_cache(dir.path());
+    let manifests = [&parameters, &srs_parameters];
+
+    let names = |filter: BundleFilter| -> Vec<String> {
+        filter
+            .select(&manifests)
+            .into_iter()
+            .map(|(filename, _)| filename)
+            .collect()
+    };
+
+    assert_eq!(names(BundleFilter::default()).len(), 3);
+    // The SRS key is not specific to a sector size.
+    assert_eq!(
+        names(BundleFilter {
+            sector_sizes: Some(vec![2048]),
+            proof_kinds: None,
+        }),
+        vec![POREP_PARAMS.to_string(), SRS_KEY.to_string()]
+    );
+    assert_eq!(
+        names(BundleFilter {
+            sector_sizes: None,
+            proof_kinds: Some(vec!["post".parse().expect("invalid proof kind")]),
+        }),
+        vec![POST_VK.to_string()]
+    );
+    assert!("winning".parse::<ProofKind>().is_err());
+}
+
+#[test]
+fn imports_exported_bundle() -> Result<()> {
+    let source = tempdir()?;
+    let (parameters, srs_parameters) = populated_cache(source.path());
+
+    let filter = BundleFilter {
+        sector_sizes: Some(vec![2048]),
+        proof_kinds: None,
+    };
+    let files = filter.select(&[&parameters, &srs_parameters]);
+    let mut bundle = Vec::new();
+    let exported = export_bundle(&mut bundle, source.path(), &files)?;
+    assert_eq!(exported.version, BUNDLE_VERSION);
+    assert_eq!(exported.files.len(), 2);
+    assert_eq!(exported.files[POREP_PARAMS].size, 4096);
+
+    let target = tempdir()?;
+    let imported = import_bundle(&bundle[..], target.path(), &[&parameters, &srs_parameters])?;
+    assert_eq!(imported, exported);
+
+    assert_eq!(
+        cache_files(target.path()),
+        vec![POREP_PARAMS.to_string(), SRS_KEY.to_string()]
+    );
+    for filename in &[POREP_PARAMS, SRS_KEY] {
+        assert_eq!(
+            fs::read(target.path().join(filename))?,
+            fs::read(source.path().join(filename))?
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn refuses_corrupt_or_partial_bundles() -> Result<()> {
+    let source = tempdir()?;
+    let (parameters, srs_parameters) = populated_cache(source.path());
+    let known = [&parameters, &srs_parameters];
+
+    let files = BundleFilter::default().select(&known);
+    let mut bundle = Vec::new();
+    export_bundle(&mut bundle, source.path(), &files)?;
+
+    // Flip a byte in the data of the last file.
+    let mut corrupt = bundle.clone();
+    let last_data_byte = corrupt.len() - 1024 - 512 - 1;
+    corrupt[last_data_byte] ^= 0xff;
+    let target = tempdir()?;
+    let err = import_bundle(&corrupt[..], target.path(), &known).expect_err("must fail");
+    assert!(
+        format!("{:?}", err).contains("digest mismatch"),
+        "{:?}",
+        err
+    );
+    assert!(cache_files(target.path()).is_empty());
+
+    // Cut off the bundle within the second file.
+    let truncated = &bundle[..bundle.len() / 2];
+    let target = tempdir()?;
+    assert!(import_bundle(truncated, target.path(), &known).is_err());
+    assert!(cache_files(target.path()).is_empty());
+
+    // Files which don't match the local manifest are refused, even if the bundle is intact.
+    let mut other_parameters = BTreeMap::new();
+    other_parameters.insert(
+        POREP_PARAMS.to_string(),
+        random_file(source.path(), "other", 2048),
+    );
+    let target = tempdir()?;
+    let err = import_bundle(
+        &bundle[..],
+        target.path(),
+        &[&other_parameters, &srs_parameters],
+    )
+    .expect_err("must fail");
+    assert!(
+        format!("{:?}", err).contains("not a known parameter file"),
+        "{:?}",
+        err
+    );
+    assert!(cache_files(target.path()).is_empty());
+
+    Ok(())
+}
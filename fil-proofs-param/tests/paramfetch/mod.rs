@@ -10,8 +10,10 @@ use storage_proofs_core::parameter_cache::{ParameterData, ParameterMap};
 
 use crate::support::tmp_manifest;
 
+mod server;
 mod session;
 
+use server::GatewayServer;
 use session::ParamFetchSessionBuilder;
 
 /// Produce a random sequence of bytes and first 32 characters of hex encoded
@@ -205,3 +207,116 @@ fn no_json_path_uses_default_manifest() -> Result<(), FailureError> {
 
     Ok(())
 }
+
+#[test]
+fn fetches_missing_file_over_http() -> Result<(), FailureError> {
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "QmAAA".to_string(),
+            digest: aaa_checksum,
+            sector_size: 1024,
+        },
+    );
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    let mut files = BTreeMap::new();
+    files.insert("QmAAA".to_string(), aaa_bytes);
+    let server = GatewayServer::start(files);
+
+    // The first gateway is unreachable, paramfetch falls back to the second one.
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(5000)
+        .without_prompt()
+        .with_http_gateway("http://127.0.0.1:1/ipfs/")
+        .with_http_gateway(&server.url())
+        .build();
+
+    session.exp_string("file not found, marking for download")?;
+    session.exp_string("downloading params file over http: aaa.vk")?;
+    session.exp_string("failed to download from gateway http://127.0.0.1:1/ipfs/")?;
+    session.exp_string("finished downloading params file")?;
+    session.exp_string("succesfully updated all files, exiting")?;
+
+    assert_eq!(server.requested_ranges(), vec![None]);
+
+    Ok(())
+}
+
+#[test]
+fn resumes_partial_http_download() -> Result<(), FailureError> {
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+    let mut partial_bytes: &[u8] = &aaa_bytes[..12];
+
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "QmAAA".to_string(),
+            digest: aaa_checksum,
+            sector_size: 1024,
+        },
+    );
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    let mut files = BTreeMap::new();
+    files.insert("QmAAA".to_string(), aaa_bytes.clone());
+    let server = GatewayServer::start(files);
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(5000)
+        .without_prompt()
+        .with_http_gateway(&server.url())
+        .with_file_and_bytes("aaa.vk.partial", &mut partial_bytes)
+        .build();
+
+    session.exp_string("resuming download at byte 12")?;
+    session.exp_string("finished downloading params file")?;
+    session.exp_string("succesfully updated all files, exiting")?;
+
+    assert_eq!(server.requested_ranges(), vec![Some(12)]);
+
+    Ok(())
+}
+
+#[test]
+fn rejects_http_download_with_wrong_digest() -> Result<(), FailureError> {
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+
+    let (aaa_bytes, _) = rand_bytes_with_blake2b()?;
+    let (_, other_checksum) = rand_bytes_with_blake2b()?;
+
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "QmAAA".to_string(),
+            digest: other_checksum,
+            sector_size: 1024,
+        },
+    );
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    let mut files = BTreeMap::new();
+    files.insert("QmAAA".to_string(), aaa_bytes);
+    let server = GatewayServer::start(files);
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(5000)
+        .without_prompt()
+        .with_http_gateway(&server.url())
+        .build();
+
+    session.exp_string("digest mismatch for aaa.vk")?;
+    session.exp_string("1 files failed to be fetched")?;
+    session.exp_string("Retry failed downloads?")?;
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A minimal HTTP server standing in for an IPFS gateway. It serves
+/// `GET /ipfs/<cid>` requests, honoring `Range: bytes=<start>-` headers, and
+/// records the start of every requested range.
+pub struct GatewayServer {
+    addr: SocketAddr,
+    ranges: Arc<Mutex<Vec<Option<u64>>>>,
+}
+
+impl GatewayServer {
+    /// Serve the given files, keyed by cid, until the test process exits.
+    pub fn start(files: BTreeMap<String, Vec<u8>>) -> GatewayServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind listener");
+        let addr = listener.local_addr().expect("failed to get local address");
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+
+        let thread_ranges = ranges.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle(stream, &files, &thread_ranges);
+            }
+        });
+
+        GatewayServer { addr, ranges }
+    }
+
+    /// Base URL to pass to paramfetch with `--gateway`.
+    pub fn url(&self) -> String {
+        format!("http://{}/ipfs/", self.addr)
+    }
+
+    /// The start of the range of every request, `None` for requests without a range.
+    pub fn requested_ranges(&self) -> Vec<Option<u64>> {
+        self.ranges.lock().expect("ranges lock poisoned").clone()
+    }
+}
+
+fn handle(
+    mut stream: TcpStream,
+    files: &BTreeMap<String, Vec<u8>>,
+    ranges: &Mutex<Vec<Option<u64>>>,
+) {
+    let mut reader = BufReader::new(stream.try_clone().expect("failed to clone stream"));
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut range_start = None;
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.trim().is_empty() => break,
+            Ok(_) => {}
+        }
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or_default().trim();
+        let value = parts.next().unwrap_or_default().trim();
+        if name.eq_ignore_ascii_case("range") {
+            range_start = value
+                .strip_prefix("bytes=")
+                .and_then(|range| range.strip_suffix('-'))
+                .and_then(|start| start.parse::<u64>().ok());
+        }
+    }
+    ranges
+        .lock()
+        .expect("ranges lock poisoned")
+        .push(range_start);
+
+    let data = match path.strip_prefix("/ipfs/").and_then(|cid| files.get(cid)) {
+        Some(data) => data,
+        None => {
+            let _ = stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+            return;
+        }
+    };
+    let len = data.len() as u64;
+
+    let response = match range_start {
+        Some(start) if start >= len => {
+            format!(
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                len
+            )
+            .into_bytes()
+        }
+        Some(start) => {
+            let mut response = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                start,
+                len - 1,
+                len,
+                len - start
+            )
+            .into_bytes();
+            response.extend_from_slice(&data[start as usize..]);
+            response
+        }
+        None => {
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                len
+            )
+            .into_bytes();
+            response.extend_from_slice(data);
+            response
+        }
+    };
+    let _ = stream.write_all(&response);
+}
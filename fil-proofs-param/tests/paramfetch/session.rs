@@ -15,6 +15,7 @@ pub struct ParamFetchSessionBuilder {
     whitelisted_sector_sizes: Option<Vec<String>>,
     manifest: Option<PathBuf>,
     prompt_enabled: bool,
+    http_gateways: Option<Vec<String>>,
 }
 
 impl ParamFetchSessionBuilder {
@@ -27,6 +28,7 @@ impl ParamFetchSessionBuilder {
             manifest,
             prompt_enabled: true,
             whitelisted_sector_sizes: None,
+            http_gateways: None,
         }
     }
 
@@ -45,6 +47,20 @@ impl ParamFetchSessionBuilder {
         self
     }
 
+    /// Download all files without prompting for a selection (`--all`).
+    pub fn without_prompt(mut self) -> ParamFetchSessionBuilder {
+        self.prompt_enabled = false;
+        self
+    }
+
+    /// Download over HTTP from the provided gateway; can be called multiple times.
+    pub fn with_http_gateway(mut self, url: &str) -> ParamFetchSessionBuilder {
+        self.http_gateways
+            .get_or_insert_with(Vec::new)
+            .push(url.to_string());
+        self
+    }
+
     /// Create a file with the provided bytes in the cache directory.
     pub fn with_file_and_bytes<P: AsRef<Path>, R: Read>(
         self,
@@ -85,14 +101,24 @@ impl ParamFetchSessionBuilder {
                     "".to_string()
                 };
 
+                let http_arguments = self
+                    .http_gateways
+                    .map(|gateways| {
+                        gateways.iter().fold("--http".to_string(), |s, gateway| {
+                            format!("{} --gateway={}", s, gateway)
+                        })
+                    })
+                    .unwrap_or_else(|| "".to_string());
+
                 let cmd = format!(
-                    "{}={} {:?} {} {} {}",
+                    "{}={} {:?} {} {} {} {}",
                     "FIL_PROOFS_PARAMETER_CACHE", // related to var name in core/src/settings.rs
                     cache_dir_path,
                     paramfetch_path,
                     if self.prompt_enabled { "" } else { "--all" },
                     json_argument,
                     whitelist,
+                    http_arguments,
                 );
 
                 session
@@ -1,3 +1,5 @@
+mod audit;
+mod bundle;
 mod paramfetch;
 mod parampublish;
 mod support;
@@ -10,10 +10,10 @@ use fil_proofs_tooling::{
 };
 use filecoin_hashers::sha256::Sha256Hasher;
 use filecoin_proofs::{
-    clear_cache, parameters::public_params, seal_commit_phase1, seal_commit_phase2,
-    validate_cache_for_commit, DefaultOctLCTree, DefaultOctTree, PaddedBytesAmount, PoRepConfig,
-    PoRepProofPartitions, SectorSize, DRG_DEGREE, EXP_DEGREE, LAYERS, POREP_MINIMUM_CHALLENGES,
-    POREP_PARTITIONS,
+    clear_cache, parameters::public_params, replace_sector_size, seal_commit_phase1,
+    seal_commit_phase2, sector_size_info, validate_cache_for_commit, DefaultOctLCTree,
+    DefaultOctTree, PaddedBytesAmount, PoRepConfig, PoRepProofPartitions, SectorSize,
+    SectorSizeInfo, DRG_DEGREE, EXP_DEGREE,
 };
 use log::info;
 use rand::SeedableRng;
@@ -159,18 +159,14 @@ fn augment_with_op_measurements(mut output: &mut ProdbenchOutputs) {
 }
 
 fn configure_global_config(inputs: &ProdbenchInputs) {
-    LAYERS
-        .write()
-        .expect("LAYERS poisoned")
-        .insert(inputs.sector_size_bytes(), inputs.stacked_layers as usize);
-    POREP_PARTITIONS
-        .write()
-        .expect("POREP_PARTITIONS poisoned")
-        .insert(inputs.sector_size_bytes(), inputs.porep_partitions);
-    POREP_MINIMUM_CHALLENGES
-        .write()
-        .expect("POREP_MINIMUM_CHALLENGES poisoned")
-        .insert(inputs.sector_size_bytes(), inputs.porep_challenges);
+    let registered = sector_size_info(inputs.sector_size_bytes()).expect("unknown sector size");
+    replace_sector_size(SectorSizeInfo {
+        layers: inputs.stacked_layers as usize,
+        porep_partitions: inputs.porep_partitions,
+        porep_minimum_challenges: inputs.porep_challenges,
+        ..registered
+    })
+    .expect("invalid prodbench configuration");
 }
 
 pub fn run(
@@ -302,11 +298,9 @@ fn measure_porep_circuit(i: &ProdbenchInputs) -> usize {
 fn generate_params(i: &ProdbenchInputs) {
     let sector_size = SectorSize(i.sector_size_bytes());
     let partitions = PoRepProofPartitions(
-        *POREP_PARTITIONS
-            .read()
-            .expect("POREP_PARTITIONS poisoned")
-            .get(&i.sector_size_bytes())
-            .expect("unknown sector size"),
+        sector_size_info(i.sector_size_bytes())
+            .expect("unknown sector size")
+            .porep_partitions,
     );
     info!(
         "generating params: porep: (size: {:?}, partitions: {:?})",
@@ -9,9 +9,7 @@ use bincode::{deserialize, serialize};
 use fil_proofs_tooling::measure::FuncMeasurement;
 use fil_proofs_tooling::shared::{PROVER_ID, RANDOMNESS, TICKET_BYTES};
 use fil_proofs_tooling::{measure, Metadata};
-use filecoin_proofs::constants::{
-    POREP_PARTITIONS, WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT,
-};
+use filecoin_proofs::constants::{sector_size_info, WINDOW_POST_CHALLENGE_COUNT};
 use filecoin_proofs::types::{
     PaddedBytesAmount, PieceInfo, PoRepConfig, PoRepProofPartitions, PoStConfig,
     SealCommitPhase1Output, SealPreCommitOutput, SealPreCommitPhase1Output, SectorSize,
@@ -85,11 +83,9 @@ fn get_porep_config(sector_size: u64, api_version: ApiVersion) -> PoRepConfig {
     PoRepConfig {
         sector_size: SectorSize(sector_size),
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("POREP_PARTITONS poisoned")
-                .get(&(sector_size))
-                .expect("unknown sector size"),
+            sector_size_info(sector_size)
+                .expect("unknown sector size")
+                .porep_partitions,
         ),
         porep_id: arbitrary_porep_id,
         api_version,
@@ -509,11 +505,9 @@ pub fn run_window_post_bench<Tree: 'static + MerkleTreeTrait>(
     let post_config = PoStConfig {
         sector_size: SectorSize(sector_size),
         challenge_count: WINDOW_POST_CHALLENGE_COUNT,
-        sector_count: *WINDOW_POST_SECTOR_COUNT
-            .read()
-            .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-            .get(&sector_size)
-            .expect("unknown sector size"),
+        sector_count: sector_size_info(sector_size)
+            .expect("unknown sector size")
+            .window_post_sector_count,
         typ: PoStType::Window,
         priority: true,
         api_version,
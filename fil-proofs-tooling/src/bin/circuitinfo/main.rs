@@ -5,10 +5,9 @@ use blstrs::Scalar as Fr;
 use dialoguer::{theme::ColorfulTheme, MultiSelect};
 use filecoin_proofs::{
     parameters::{public_params, window_post_public_params, winning_post_public_params},
-    with_shape, DefaultPieceHasher, PaddedBytesAmount, PoRepConfig, PoRepProofPartitions,
-    PoStConfig, PoStType, SectorSize, POREP_PARTITIONS, PUBLISHED_SECTOR_SIZES,
-    WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT, WINNING_POST_CHALLENGE_COUNT,
-    WINNING_POST_SECTOR_COUNT,
+    registered_sector_sizes, sector_size_info, with_shape, DefaultPieceHasher, PaddedBytesAmount,
+    PoRepConfig, PoRepProofPartitions, PoStConfig, PoStType, SectorSize,
+    WINDOW_POST_CHALLENGE_COUNT, WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
 };
 use humansize::{file_size_opts, FileSize};
 use log::{info, warn};
@@ -120,11 +119,9 @@ fn window_post_info(sector_size: u64, api_version: ApiVersion) -> CircuitInfo {
         &PoStConfig {
             sector_size: SectorSize(sector_size),
             challenge_count: WINDOW_POST_CHALLENGE_COUNT,
-            sector_count: *WINDOW_POST_SECTOR_COUNT
-                .read()
-                .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-                .get(&sector_size)
-                .expect("unknown sector size"),
+            sector_count: sector_size_info(sector_size)
+                .expect("unknown sector size")
+                .window_post_sector_count,
             typ: PoStType::Window,
             priority: true,
             api_version,
@@ -134,11 +131,10 @@ fn window_post_info(sector_size: u64, api_version: ApiVersion) -> CircuitInfo {
 
 fn porep_info(sector_size: u64, api_version: ApiVersion) -> (CircuitInfo, usize) {
     let partitions = PoRepProofPartitions(
-        *POREP_PARTITIONS
-            .read()
-            .expect("POREP_PARTITIONS poisoned")
-            .get(&sector_size)
-            .expect("unknown sector size"),
+        sector_size_info(sector_size)
+            .expect("unknown sector size")
+            .porep_partitions_for(api_version)
+            .expect("unsupported api version"),
     );
     let info = with_shape!(
         sector_size,
@@ -162,9 +158,14 @@ pub fn main() {
 
     let opts = Opt::from_args();
 
+    let registered_sizes: Vec<u64> = registered_sector_sizes()
+        .iter()
+        .map(|info| info.sector_size)
+        .collect();
+
     // Display interactive menu if no sizes are given
     let sizes: Vec<u64> = if opts.constraints_for_sector_sizes.is_empty() {
-        let sector_sizes = PUBLISHED_SECTOR_SIZES
+        let sector_sizes = registered_sizes
             .iter()
             .map(|sector_size| {
                 // Right aligning the numbers makes them easier to read
@@ -184,7 +185,7 @@ pub fn main() {
             .expect("interaction failed");
 
         // Extract the selected sizes
-        PUBLISHED_SECTOR_SIZES
+        registered_sizes
             .iter()
             .enumerate()
             .filter_map(|(index, size)| {
@@ -199,7 +200,7 @@ pub fn main() {
         opts.constraints_for_sector_sizes
             .into_iter()
             .filter(|size| {
-                if PUBLISHED_SECTOR_SIZES.contains(size) {
+                if registered_sizes.contains(size) {
                     return true;
                 }
 
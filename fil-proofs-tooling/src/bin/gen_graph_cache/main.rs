@@ -7,8 +7,7 @@ use anyhow::Result;
 use clap::{Arg, Command};
 use filecoin_hashers::sha256::Sha256Hasher;
 use filecoin_proofs::{
-    with_shape, DRG_DEGREE, EXP_DEGREE, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB, SECTOR_SIZE_512_MIB,
-    SECTOR_SIZE_64_GIB, SECTOR_SIZE_8_MIB,
+    published_sector_sizes, with_shape, RegisteredSealProof, DRG_DEGREE, EXP_DEGREE,
 };
 use serde::{Deserialize, Serialize};
 use storage_proofs_core::{api_version::ApiVersion, merkle::MerkleTreeTrait, proof::ProofScheme};
@@ -88,95 +87,30 @@ fn main() -> Result<()> {
         )
         .get_matches();
 
-    // NOTE: The porep_ids below are tied to the versioned values provided in
-    // filecoin-proofs-api:src/registry [porep_id()] that matches the specified
-    // sector size and must be updated when that value is updated for the proper
-    // graph cache generation/validation.
+    // The parent caches are generated for the V1 and V1_1 seal proofs of the published sector
+    // sizes, using their porep_ids. These must match the values provided in
+    // filecoin-proofs-api:src/registry [porep_id()] for the proper graph cache
+    // generation/validation.
     //
-    // If this value changes, previously existing cache files will no longer be
+    // If these values change, previously existing cache files will no longer be
     // used and new cache files will be generated.
-    let sector_sizes_and_porep_ids: Vec<(u64, [u8; 32], ApiVersion)> = vec![
-        (
-            SECTOR_SIZE_2_KIB,
-            [
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_0_0,
-        ),
-        (
-            SECTOR_SIZE_8_MIB,
-            [
-                1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_0_0,
-        ),
-        (
-            SECTOR_SIZE_512_MIB,
-            [
-                2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_0_0,
-        ),
-        (
-            SECTOR_SIZE_32_GIB,
-            [
-                3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_0_0,
-        ),
-        (
-            SECTOR_SIZE_64_GIB,
-            [
-                4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_0_0,
-        ),
-        (
-            SECTOR_SIZE_2_KIB, // v1.1.0
-            [
-                5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_1_0,
-        ),
-        (
-            SECTOR_SIZE_8_MIB, // v1.1.0
-            [
-                6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_1_0,
-        ),
-        (
-            SECTOR_SIZE_512_MIB, // v1.1.0
-            [
-                7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_1_0,
-        ),
-        (
-            SECTOR_SIZE_32_GIB, // v1.1.0
-            [
-                8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_1_0,
-        ),
-        (
-            SECTOR_SIZE_64_GIB, // v1.1.0
-            [
-                9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0,
-            ],
-            ApiVersion::V1_1_0,
-        ),
-    ];
+    let sector_sizes_and_porep_ids: Vec<(u64, [u8; 32], ApiVersion)> =
+        [ApiVersion::V1_0_0, ApiVersion::V1_1_0]
+            .iter()
+            .flat_map(|&api_version| {
+                published_sector_sizes()
+                    .into_iter()
+                    .filter_map(move |sector_size| {
+                        RegisteredSealProof::ALL
+                            .iter()
+                            .find(|proof| {
+                                u64::from(proof.sector_size()) == sector_size
+                                    && proof.api_version() == api_version
+                            })
+                            .map(|proof| (sector_size, proof.porep_id(), api_version))
+                    })
+            })
+            .collect();
 
     let supported_sector_sizes = sector_sizes_and_porep_ids
         .iter()
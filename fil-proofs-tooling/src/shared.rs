@@ -3,10 +3,10 @@ use std::fs::File;
 use std::io::{BufWriter, Seek, SeekFrom, Write};
 
 use filecoin_proofs::{
-    add_piece, fauxrep_aux, seal_pre_commit_phase1, seal_pre_commit_phase2,
+    add_piece, fauxrep_aux, seal_pre_commit_phase1, seal_pre_commit_phase2, sector_size_info,
     validate_cache_for_precommit_phase2, MerkleTreeTrait, PaddedBytesAmount, PieceInfo,
     PoRepConfig, PoRepProofPartitions, PrivateReplicaInfo, PublicReplicaInfo, SealPreCommitOutput,
-    SealPreCommitPhase1Output, SectorSize, UnpaddedBytesAmount, POREP_PARTITIONS,
+    SealPreCommitPhase1Output, SectorSize, UnpaddedBytesAmount,
 };
 use generic_array::typenum::Unsigned;
 use log::info;
@@ -121,11 +121,10 @@ pub fn create_replicas<Tree: 'static + MerkleTreeTrait>(
     let porep_config = PoRepConfig {
         sector_size,
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("poisoned read access")
-                .get(&u64::from(sector_size))
-                .expect("unknown sector size"),
+            sector_size_info(u64::from(sector_size))
+                .expect("unknown sector size")
+                .porep_partitions_for(api_version)
+                .expect("unsupported api version"),
         ),
         porep_id,
         api_version,
@@ -216,6 +215,7 @@ pub fn create_replicas<Tree: 'static + MerkleTreeTrait>(
                         labels: Labels::new(vec![tmp_store_config.clone(); cache_dirs.len()]),
                         config: tmp_store_config,
                         comm_d: [0; 32],
+                        replica_id: None,
                     }
                 })
                 .collect::<Vec<_>>();
@@ -4,8 +4,8 @@ use std::time::Duration;
 use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
 use filecoin_proofs::{
     caches::{get_stacked_srs_key, get_stacked_srs_verifier_key},
-    get_seal_inputs, PoRepConfig, PoRepProofPartitions, SectorShape2KiB, SectorShape32GiB,
-    SectorSize, POREP_PARTITIONS, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
+    get_seal_inputs, sector_size_info, PoRepConfig, PoRepProofPartitions, SectorShape2KiB,
+    SectorShape32GiB, SectorSize, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
 };
 use rand::{thread_rng, Rng};
 use storage_proofs_core::{api_version::ApiVersion, is_legacy_porep_id};
@@ -31,11 +31,9 @@ fn bench_seal_inputs(c: &mut Criterion) {
     let config = PoRepConfig {
         sector_size: SectorSize(SECTOR_SIZE_2_KIB),
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("POREP_PARTITIONS poisoned")
-                .get(&SECTOR_SIZE_2_KIB)
-                .expect("unknown sector size"),
+            sector_size_info(SECTOR_SIZE_2_KIB)
+                .expect("unknown sector size")
+                .porep_partitions,
         ),
         porep_id,
         api_version: ApiVersion::V1_1_0,
@@ -82,11 +80,9 @@ fn bench_stacked_srs_key(c: &mut Criterion) {
     let config = PoRepConfig {
         sector_size: SectorSize(SECTOR_SIZE_32_GIB),
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("POREP_PARTITIONS poisoned")
-                .get(&SECTOR_SIZE_32_GIB)
-                .expect("unknown sector size"),
+            sector_size_info(SECTOR_SIZE_32_GIB)
+                .expect("unknown sector size")
+                .porep_partitions,
         ),
         porep_id,
         api_version: ApiVersion::V1_1_0,
@@ -123,11 +119,9 @@ fn bench_stacked_srs_verifier_key(c: &mut Criterion) {
     let config = PoRepConfig {
         sector_size: SectorSize(SECTOR_SIZE_32_GIB),
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("POREP_PARTITIONS poisoned")
-                .get(&SECTOR_SIZE_32_GIB)
-                .expect("unknown sector size"),
+            sector_size_info(SECTOR_SIZE_32_GIB)
+                .expect("unknown sector size")
+                .porep_partitions,
         ),
         porep_id,
         api_version: ApiVersion::V1_1_0,
@@ -3,8 +3,8 @@ use std::time::Duration;
 
 use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 use filecoin_proofs::{
-    add_piece, get_seal_inputs, PaddedBytesAmount, PoRepConfig, PoRepProofPartitions,
-    SectorShape2KiB, SectorSize, UnpaddedBytesAmount, POREP_PARTITIONS, SECTOR_SIZE_2_KIB,
+    add_piece, get_seal_inputs, sector_size_info, PaddedBytesAmount, PoRepConfig,
+    PoRepProofPartitions, SectorShape2KiB, SectorSize, UnpaddedBytesAmount, SECTOR_SIZE_2_KIB,
 };
 use fr32::Fr32Reader;
 use rand::{thread_rng, Rng};
@@ -117,11 +117,9 @@ fn get_seal_inputs_benchmark(c: &mut Criterion) {
     let config = PoRepConfig {
         sector_size: SectorSize(SECTOR_SIZE_2_KIB),
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("POREP_PARTITIONS poisoned")
-                .get(&SECTOR_SIZE_2_KIB)
-                .expect("unknown sector size"),
+            sector_size_info(SECTOR_SIZE_2_KIB)
+                .expect("unknown sector size")
+                .porep_partitions,
         ),
         porep_id,
         api_version: ApiVersion::V1_1_0,
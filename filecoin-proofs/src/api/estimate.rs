@@ -0,0 +1,511 @@
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use blstrs::{G1Affine, G2Affine, Scalar as Fr};
+use filecoin_hashers::Hasher;
+use log::{info, trace};
+use merkletree::merkle::{get_merkle_tree_cache_size, get_merkle_tree_len};
+use merkletree::store::StoreConfig;
+use storage_proofs_core::{
+    cache_key::CacheKey,
+    merkle::{get_base_tree_count, MerkleTreeTrait},
+    parameter_cache::{parameter_cache_params_path, CacheableParameters},
+    settings,
+    util::{default_rows_to_discard, NODE_SIZE},
+};
+use storage_proofs_porep::stacked::{
+    LayerChallenges, PersistentAux, StackedDrg, SYNTHETIC_PROOFS_BATCH_SIZE,
+};
+use storage_proofs_update::{
+    constants::TreeRHasher, EmptySectorUpdateCircuit, EmptySectorUpdateCompound, PublicParams,
+};
+use typenum::Unsigned;
+
+use crate::{
+    api::get_num_partition_for_fallback_post,
+    constants::{DefaultPieceHasher, DRG_DEGREE, EXP_DEGREE},
+    parameters::setup_params,
+    types::{
+        PaddedBytesAmount, PhaseEstimate, PoRepConfig, PoStConfig, PoStResourceEstimate,
+        ProvingDevice, ProvingEstimate, SealResourceEstimate, SectorUpdateConfig,
+        SectorUpdateResourceEstimate,
+    },
+};
+
+/// Number of bytes in a single parent cache entry (one u32 per parent).
+const PARENT_BYTES: u64 = ((DRG_DEGREE + EXP_DEGREE) * std::mem::size_of::<u32>()) as u64;
+
+/// Estimates the memory, scratch disk and proving work required to seal a
+/// single sector with the given config, based on the current settings.
+///
+/// Disk estimates are exact for the artifacts written to the cache
+/// directory (with the exception of `t_aux`). Memory estimates cover the
+/// large buffers allocated by each phase and the pages of the files it maps.
+/// Commit phase 2 accounts for the groth parameters and the synthesized
+/// circuits of all partitions, which are only known if the parameters are in
+/// the parameter cache, otherwise its memory estimate is `0`.
+pub fn estimate_seal_resources<Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+) -> Result<SealResourceEstimate> {
+    info!("estimate_seal_resources:start");
+
+    let sector_bytes = u64::from(PaddedBytesAmount::from(porep_config));
+    let nodes_count = sector_bytes as usize / NODE_SIZE;
+    let tree_count = get_base_tree_count::<Tree>();
+    let base_tree_leafs = nodes_count / tree_count;
+    let arity = Tree::Arity::to_usize();
+    let partitions = usize::from(porep_config.partitions);
+
+    let layer_challenges = layer_challenges(porep_config)?;
+    let layers = layer_challenges.layers();
+
+    let layer_size = sector_bytes;
+    let tree_d_size = tree_d_size(nodes_count)?;
+    let tree_c_size = (get_merkle_tree_len(base_tree_leafs, arity)? * NODE_SIZE) as u64;
+    let tree_r_last_size = tree_r_last_size::<Tree>(nodes_count)?;
+    let p_aux_size = p_aux_size::<Tree>()?;
+    let synthetic_proofs_size = StackedDrg::<Tree, DefaultPieceHasher>::synthetic_proofs_size(
+        nodes_count,
+        &layer_challenges,
+    );
+
+    let mut cache_files = BTreeMap::new();
+    for layer in 1..=layers {
+        cache_files.insert(data_file_name(&CacheKey::label_layer(layer)), layer_size);
+    }
+    cache_files.insert(
+        data_file_name(&CacheKey::CommDTree.to_string()),
+        tree_d_size,
+    );
+
+    let mut final_cache_files = BTreeMap::new();
+    for id in base_tree_ids(&CacheKey::CommCTree.to_string(), tree_count) {
+        cache_files.insert(data_file_name(&id), tree_c_size);
+    }
+    for id in base_tree_ids(&CacheKey::CommRLastTree.to_string(), tree_count) {
+        cache_files.insert(data_file_name(&id), tree_r_last_size);
+        final_cache_files.insert(data_file_name(&id), tree_r_last_size);
+    }
+    cache_files.insert(CacheKey::PAux.to_string(), p_aux_size);
+    final_cache_files.insert(CacheKey::PAux.to_string(), p_aux_size);
+
+    // The layers and trees the proofs of the challenges are read from.
+    let replica_files_size: u64 = cache_files.values().sum();
+
+    // The synthetic proofs survive `clear_cache`, commit phase 1 reads them instead of the layers.
+    if let Some(size) = synthetic_proofs_size {
+        cache_files.insert(CacheKey::SyntheticPoRepProofs.to_string(), size);
+        final_cache_files.insert(CacheKey::SyntheticPoRepProofs.to_string(), size);
+    }
+
+    let pre_commit_phase1 = pre_commit_phase1_estimate(sector_bytes, layers, 1)?;
+    trace!("pre_commit_phase1 estimate {:?}", pre_commit_phase1);
+
+    // Tree c and tree r last are built one after the other, one base tree at a time.
+    let tree_c_ram = if StackedDrg::<Tree, DefaultPieceHasher>::use_gpu_column_builder() {
+        let batch_nodes = min(
+            base_tree_leafs,
+            settings::current().max_gpu_column_batch_size as usize,
+        );
+        (batch_nodes * layers * NODE_SIZE) as u64 + tree_c_size
+    } else {
+        StackedDrg::<Tree, DefaultPieceHasher>::tree_c_cpu_memory(
+            layers,
+            base_tree_leafs,
+            tree_count,
+        ) + tree_c_size
+    };
+    let tree_r_last_ram = tree_r_last_ram::<Tree>(base_tree_leafs, tree_r_last_size);
+    let proof_size = StackedDrg::<Tree, DefaultPieceHasher>::proof_size(nodes_count, layers);
+    // With synthetic PoRep, the synthetic challenges are proven in batches from the mapped layers
+    // and trees at the end of pre-commit phase 2.
+    let synthetic_ram = layer_challenges.synthetic_count().map_or(0, |count| {
+        replica_files_size + min(count, SYNTHETIC_PROOFS_BATCH_SIZE) as u64 * proof_size
+    });
+    let pre_commit_phase2 = PhaseEstimate {
+        peak_ram: max(max(tree_c_ram, tree_r_last_ram), synthetic_ram),
+        disk_written: (tree_c_size + tree_r_last_size) * tree_count as u64
+            + p_aux_size
+            + synthetic_proofs_size.unwrap_or(0),
+    };
+    trace!("pre_commit_phase2 estimate {:?}", pre_commit_phase2);
+
+    // Commit phase 1 only reads the challenged nodes from memory mapped
+    // cache files, so in the worst case all of them end up in the page cache.
+    // With synthetic PoRep it only reads the proofs of the selected challenges.
+    let commit_phase1 = PhaseEstimate {
+        peak_ram: if synthetic_proofs_size.is_some() {
+            (partitions * layer_challenges.challenges_count_all()) as u64 * proof_size
+        } else {
+            replica_files_size
+        },
+        disk_written: 0,
+    };
+
+    let params_path = porep_config.get_cache_params_path::<Tree>()?;
+    let params_size = file_size(&params_path);
+    let commit_phase2 = PhaseEstimate {
+        peak_ram: proving_ram(&params_path, partitions)?,
+        disk_written: 0,
+    };
+
+    let estimate = SealResourceEstimate {
+        sector_size: porep_config.sector_size,
+        pre_commit_phase1,
+        pre_commit_phase2,
+        commit_phase1,
+        commit_phase2,
+        cache_files,
+        final_cache_files,
+        replica_size: sector_bytes,
+        parent_cache_size: nodes_count as u64 * PARENT_BYTES,
+        proving: ProvingEstimate {
+            partitions,
+            device: ProvingDevice::current(),
+            params_size,
+        },
+    };
+
+    info!("estimate_seal_resources:finish");
+
+    Ok(estimate)
+}
+
+/// Estimates the memory and scratch disk required to label `sectors` sectors
+/// together with
+/// [`seal_pre_commit_phase1_lockstep`](crate::seal_pre_commit_phase1_lockstep),
+/// based on the current settings. The disk estimate covers the cache
+/// directories of all sectors.
+pub fn estimate_seal_pre_commit_phase1_lockstep<Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+    sectors: usize,
+) -> Result<PhaseEstimate> {
+    ensure!(sectors > 0, "no sectors to seal");
+
+    let sector_bytes = u64::from(PaddedBytesAmount::from(porep_config));
+    let layers = layer_challenges(porep_config)?.layers();
+
+    pre_commit_phase1_estimate(sector_bytes, layers, sectors)
+}
+
+/// Estimates the memory, scratch disk and proving work required to encode
+/// and prove an empty sector update, based on the current settings.
+pub fn estimate_empty_sector_update_resources<
+    Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>,
+>(
+    config: &SectorUpdateConfig,
+) -> Result<SectorUpdateResourceEstimate> {
+    info!("estimate_empty_sector_update_resources:start");
+
+    let sector_bytes = u64::from(config.sector_size);
+    let nodes_count = config.nodes_count;
+    let tree_count = get_base_tree_count::<Tree>();
+    let base_tree_leafs = nodes_count / tree_count;
+
+    let tree_d_size = tree_d_size(nodes_count)?;
+    let tree_r_last_size = tree_r_last_size::<Tree>(nodes_count)?;
+    let p_aux_size = p_aux_size::<Tree>()?;
+
+    let mut cache_files = BTreeMap::new();
+    cache_files.insert(
+        data_file_name(&CacheKey::CommDTree.to_string()),
+        tree_d_size,
+    );
+    for id in base_tree_ids(&CacheKey::CommRLastTree.to_string(), tree_count) {
+        cache_files.insert(data_file_name(&id), tree_r_last_size);
+    }
+    cache_files.insert(CacheKey::PAux.to_string(), p_aux_size);
+
+    let encode = PhaseEstimate {
+        peak_ram: tree_r_last_ram::<Tree>(base_tree_leafs, tree_r_last_size),
+        disk_written: cache_files.values().sum(),
+    };
+
+    let public_params = PublicParams::from_sector_size(sector_bytes);
+    let params_id = <EmptySectorUpdateCompound<Tree> as CacheableParameters<
+        EmptySectorUpdateCircuit<Tree>,
+        _,
+    >>::cache_identifier(&public_params);
+    let params_path = parameter_cache_params_path(&params_id);
+    let params_size = file_size(&params_path);
+    let prove = PhaseEstimate {
+        peak_ram: proving_ram(&params_path, usize::from(config.update_partitions))?,
+        disk_written: 0,
+    };
+
+    let estimate = SectorUpdateResourceEstimate {
+        sector_size: config.sector_size,
+        encode,
+        prove,
+        cache_files,
+        replica_size: sector_bytes,
+        proving: ProvingEstimate {
+            partitions: usize::from(config.update_partitions),
+            device: ProvingDevice::current(),
+            params_size,
+        },
+    };
+
+    info!("estimate_empty_sector_update_resources:finish");
+
+    Ok(estimate)
+}
+
+/// Estimates the resources required to generate a Winning or Window PoSt
+/// over `num_sectors` sectors, based on the current settings.
+pub fn estimate_post_resources<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    num_sectors: usize,
+) -> Result<PoStResourceEstimate> {
+    info!("estimate_post_resources:start");
+
+    let nodes_count = u64::from(post_config.padded_sector_size()) as usize / NODE_SIZE;
+    let tree_count = get_base_tree_count::<Tree>() as u64;
+    let params_path = post_config.get_cache_params_path::<Tree>()?;
+    let params_size = file_size(&params_path);
+    let partitions = get_num_partition_for_fallback_post(post_config, num_sectors);
+
+    let estimate = PoStResourceEstimate {
+        sector_size: post_config.sector_size,
+        sectors_per_partition: post_config.sector_count,
+        tree_r_last_size: tree_r_last_size::<Tree>(nodes_count)? * tree_count,
+        prove: PhaseEstimate {
+            peak_ram: proving_ram(&params_path, partitions)?,
+            disk_written: 0,
+        },
+        proving: ProvingEstimate {
+            partitions,
+            device: ProvingDevice::current(),
+            params_size,
+        },
+    };
+
+    info!("estimate_post_resources:finish");
+
+    Ok(estimate)
+}
+
+fn layer_challenges(porep_config: PoRepConfig) -> Result<LayerChallenges> {
+    Ok(setup_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(porep_config.partitions),
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?
+    .layer_challenges)
+}
+
+// Pre-commit phase 1 of `sectors` sectors: building tree d from the mapped
+// data of each sector, then labeling all of them.
+fn pre_commit_phase1_estimate(
+    sector_bytes: u64,
+    layers: usize,
+    sectors: usize,
+) -> Result<PhaseEstimate> {
+    let tree_d_size = tree_d_size(sector_bytes as usize / NODE_SIZE)?;
+
+    Ok(PhaseEstimate {
+        peak_ram: max(
+            sector_bytes + tree_d_size,
+            labeling_ram(sector_bytes, sectors),
+        ),
+        disk_written: (sector_bytes * layers as u64 + tree_d_size) * sectors as u64,
+    })
+}
+
+// Memory used while labeling `sectors` sectors: the layer buffers of the
+// sectors labeled at once, the regions of their layers queued for writing,
+// plus the window(s) of the parent cache that are kept in memory.
+fn labeling_ram(sector_bytes: u64, sectors: usize) -> u64 {
+    let settings = settings::current();
+    let nodes_count = sector_bytes / NODE_SIZE as u64;
+    let cache_window = min(nodes_count, u64::from(settings.sdr_parents_cache_size)) * PARENT_BYTES;
+
+    // Within a memory budget the sectors are labeled one after the other,
+    // keeping a window of the labels of the current layer in memory.
+    if settings.sdr_memory_budget != 0 {
+        let window_nodes = min(
+            settings.sdr_memory_budget / NODE_SIZE as u64,
+            nodes_count + 1,
+        ) & !1;
+        return window_nodes * NODE_SIZE as u64 + cache_window;
+    }
+
+    // Every completed region of a layer is copied for the writer, up to four
+    // regions per sector at once: two queued, one being written and the one
+    // being copied.
+    let region_size = settings.sdr_write_behind_region_size;
+    let write_behind = sector_bytes
+        .checked_div(region_size)
+        .map_or(0, |regions| min(4, regions) * region_size);
+    let sector_ram = 2 * sector_bytes + write_behind;
+
+    // Without multicore SDR the sectors are labeled one after the other.
+    if !(cfg!(feature = "multicore-sdr") && settings.use_multicore_sdr) {
+        return sector_ram + cache_window;
+    }
+
+    // The sectors are labeled in lockstep. The cache reader maps two windows
+    // at once, and the producers fill a ring buffer of `lookahead` nodes with
+    // the parents of every sector.
+    let lookahead = settings.multicore_sdr_lookahead as u64;
+    let ring_buf = lookahead * (PARENT_BYTES / 4 * NODE_SIZE as u64 + 64);
+
+    sectors as u64 * (sector_ram + ring_buf) + 2 * cache_window
+}
+
+// Memory used to generate the groth proofs of `partitions` partitions with
+// the parameters at `params_path`: the mapped parameters, the bases read from
+// them, and the circuits of all partitions, which are synthesized at once.
+// Without the parameters nothing is known about the circuit, so `0`.
+fn proving_ram(params_path: &Path, partitions: usize) -> Result<u64> {
+    let (constraints, variables) = match circuit_size(params_path)? {
+        Some(size) => size,
+        None => return Ok(0),
+    };
+    let params_size = file_size(params_path).unwrap_or(0);
+    // The evaluations of a, b and c over the FFT domain, plus the assignment
+    // of every variable, once by the prover and once as multiexp scalars.
+    let scalar_size = mem::size_of::<Fr>() as u64;
+    let circuit_ram = (3 * constraints + 2 * variables) * scalar_size;
+
+    Ok(2 * params_size + partitions as u64 * circuit_ram)
+}
+
+// Reads the size of the FFT domain of the circuit and its number of
+// variables from the header of the groth parameters at `params_path`, if
+// present.
+fn circuit_size(params_path: &Path) -> Result<Option<(u64, u64)>> {
+    let mut file = match File::open(params_path) {
+        Ok(file) => file,
+        Err(_) => return Ok(None),
+    };
+    let g1_size = G1Affine::uncompressed_size() as u64;
+    let g2_size = G2Affine::uncompressed_size() as u64;
+
+    // The verifying key: alpha, beta and delta in g1, beta, gamma and delta
+    // in g2, then the bases of the inputs, of the h query (one less than the
+    // domain size) and of the l query (one per auxiliary variable).
+    let inputs = read_len(&mut file, params_path, 3 * g1_size + 3 * g2_size)?;
+    let h = read_len(&mut file, params_path, inputs * g1_size)?;
+    let aux = read_len(&mut file, params_path, h * g1_size)?;
+
+    Ok(Some((h + 1, inputs + aux)))
+}
+
+// Skips `skip` bytes of `file` and reads a big endian `u32` length.
+fn read_len(file: &mut File, path: &Path, skip: u64) -> Result<u64> {
+    let mut len = [0u8; 4];
+    file.seek(SeekFrom::Current(skip as i64))?;
+    file.read_exact(&mut len)
+        .with_context(|| format!("could not read header of {:?}", path))?;
+
+    Ok(u64::from(u32::from_be_bytes(len)))
+}
+
+fn tree_r_last_ram<Tree: 'static + MerkleTreeTrait>(
+    base_tree_leafs: usize,
+    tree_r_last_size: u64,
+) -> u64 {
+    if StackedDrg::<Tree, DefaultPieceHasher>::use_gpu_tree_builder() {
+        // The cached tree data is returned by the builder and then
+        // flattened into bytes before being written.
+        let batch_nodes = min(
+            base_tree_leafs,
+            settings::current().max_gpu_tree_batch_size as usize,
+        );
+        (batch_nodes * NODE_SIZE) as u64 + 2 * tree_r_last_size
+    } else {
+        (base_tree_leafs * NODE_SIZE) as u64
+    }
+}
+
+// Size of the tree_d file, which keeps all rows.
+fn tree_d_size(nodes_count: usize) -> Result<u64> {
+    Ok((get_merkle_tree_len(nodes_count, 2)? * NODE_SIZE) as u64)
+}
+
+// Size of a single base tree_r_last file, taking the discarded rows into account.
+fn tree_r_last_size<Tree: 'static + MerkleTreeTrait>(nodes_count: usize) -> Result<u64> {
+    let arity = Tree::Arity::to_usize();
+    let base_tree_leafs = nodes_count / get_base_tree_count::<Tree>();
+    let rows_to_discard = default_rows_to_discard(nodes_count, arity);
+    let cache_size = get_merkle_tree_cache_size(base_tree_leafs, arity, rows_to_discard)?;
+
+    Ok((cache_size * NODE_SIZE) as u64)
+}
+
+fn p_aux_size<Tree: 'static + MerkleTreeTrait>() -> Result<u64> {
+    let p_aux = PersistentAux::<<Tree::Hasher as Hasher>::Domain>::default();
+    bincode::serialized_size(&p_aux).context("failed to compute p_aux size")
+}
+
+fn base_tree_ids(id: &str, tree_count: usize) -> Vec<String> {
+    if tree_count == 1 {
+        vec![id.to_string()]
+    } else {
+        (0..tree_count).map(|i| format!("{}-{}", id, i)).collect()
+    }
+}
+
+fn data_file_name(id: &str) -> String {
+    StoreConfig::data_path(&PathBuf::new(), id)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().map(|m| m.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::{groth16, Circuit, ConstraintSystem, SynthesisError};
+    use blstrs::Bls12;
+    use ff::Field;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use tempfile::NamedTempFile;
+
+    // Knowledge of `x` such that `x^3 = y`.
+    struct Cube;
+
+    impl Circuit<Fr> for Cube {
+        fn synthesize<CS: ConstraintSystem<Fr>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || Ok(Fr::one()))?;
+            let x2 = cs.alloc(|| "x2", || Ok(Fr::one()))?;
+            let y = cs.alloc_input(|| "y", || Ok(Fr::one()))?;
+            cs.enforce(|| "x2 = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            cs.enforce(|| "y = x2 * x", |lc| lc + x2, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_circuit_size() {
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let params = groth16::generate_random_parameters::<Bls12, _, _>(Cube, &mut rng)
+            .expect("failed to generate params");
+        let mut file = NamedTempFile::new().expect("failed to create params file");
+        params.write(&mut file).expect("failed to write params");
+
+        let (domain, variables) = circuit_size(file.path())
+            .expect("failed to read params header")
+            .expect("params are present");
+        assert!(domain.is_power_of_two());
+        assert_eq!(domain, params.h.len() as u64 + 1);
+        assert_eq!(variables, (params.vk.ic.len() + params.l.len()) as u64);
+
+        let missing = file.path().with_extension("missing");
+        assert_eq!(circuit_size(&missing).expect("missing params"), None);
+        assert_eq!(proving_ram(&missing, 2).expect("missing params"), 0);
+    }
+}
@@ -1,9 +1,9 @@
-use std::fs::{self, File, OpenOptions};
+use std::fs::{File, OpenOptions};
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::{ensure, Context, Result};
-use bincode::deserialize;
 use filecoin_hashers::Hasher;
 use fr32::{write_unpadded, Fr32Reader};
 use log::{info, trace};
@@ -11,10 +11,13 @@ use memmap::MmapOptions;
 use merkletree::store::{DiskStore, LevelCacheStore, StoreConfig};
 use storage_proofs_core::{
     cache_key::CacheKey,
+    enter_span,
     measurements::{measure_op, Operation},
     merkle::get_base_tree_count,
+    metrics,
     pieces::generate_piece_commitment_bytes_from_source,
     sector::SectorId,
+    settings,
     util::default_rows_to_discard,
 };
 use storage_proofs_porep::{
@@ -30,6 +33,7 @@ use crate::{
         DefaultBinaryTree, DefaultOctTree, DefaultPieceDomain, DefaultPieceHasher,
         MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR as MINIMUM_PIECE_SIZE,
     },
+    error::{read_cache_file, Error},
     parameters::public_params,
     pieces::{get_piece_alignment, sum_piece_bytes_with_alignment},
     types::{
@@ -39,24 +43,45 @@ use crate::{
     },
 };
 
+mod estimate;
 mod fake_seal;
 mod post_util;
+mod preload;
 mod seal;
 mod update;
 mod util;
 mod window_post;
 mod winning_post;
 
+pub use estimate::*;
 pub use fake_seal::*;
 pub use post_util::*;
+pub use preload::*;
 pub use seal::*;
 pub use update::*;
 pub use util::*;
 pub use window_post::*;
 pub use winning_post::*;
 
+pub use storage_proofs_core::settings::ProofsConfig;
 pub use storage_proofs_update::constants::{hs, partition_count};
 
+/// Runs `f` with `config` in place of the global `SETTINGS` for every API call made from within
+/// it on the current thread. This allows e.g. sealing one sector with multicore SDR and the GPU
+/// tree builders, while another thread of the same process uses the CPU builders.
+///
+/// Settings which size process-wide state, like the memory cache limits and the multicore SDR
+/// core groups, are always taken from the global `SETTINGS`, see [`ProofsConfig`].
+pub fn with_config<T, F: FnOnce() -> T>(config: &ProofsConfig, f: F) -> T {
+    settings::with_settings(Arc::new(config.clone()), f)
+}
+
+/// Renders the metrics collected by this process (phase durations, bytes read and written,
+/// parameter cache hits and misses, faulty sectors) in the Prometheus text exposition format.
+pub fn render_metrics() -> String {
+    metrics::REGISTRY.render()
+}
+
 /// Unseals the sector at `sealed_path` and returns the bytes for a piece
 /// whose first (unpadded) byte begins at `offset` and ends at `offset` plus
 /// `num_bytes`, inclusive. Note that the entire sector is unsealed each time
@@ -88,6 +113,7 @@ pub fn get_unsealed_range<T: Into<PathBuf> + AsRef<Path>, Tree: 'static + Merkle
     num_bytes: UnpaddedBytesAmount,
 ) -> Result<UnpaddedBytesAmount> {
     info!("get_unsealed_range:start");
+    let _timer = metrics::time_phase("get_unsealed_range");
 
     let f_out = File::create(&output_path)
         .with_context(|| format!("could not create output_path={:?}", output_path.as_ref()))?;
@@ -148,7 +174,14 @@ where
     Tree: 'static + MerkleTreeTrait,
 {
     info!("unseal_range:start");
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+    let _timer = metrics::time_phase("unseal_range");
+    let _span = enter_span!(
+        "unseal_range",
+        sector_id = %sector_id,
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
 
     let comm_d =
         as_safe_commitment::<<DefaultPieceHasher as Hasher>::Domain, _>(&comm_d, "comm_d")?;
@@ -215,7 +248,14 @@ where
     Tree: 'static + MerkleTreeTrait,
 {
     info!("unseal_range_mapped:start");
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+    let _timer = metrics::time_phase("unseal_range_mapped");
+    let _span = enter_span!(
+        "unseal_range_mapped",
+        sector_id = %sector_id,
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
 
     let comm_d =
         as_safe_commitment::<<DefaultPieceHasher as Hasher>::Domain, _>(&comm_d, "comm_d")?;
@@ -312,6 +352,7 @@ where
     // byte will be the the byte at index `offset_padded` in the sealed sector.
     let written = write_unpadded(unsealed, &mut unsealed_output, 0, num_bytes.into())
         .context("write_unpadded failed")?;
+    metrics::record_bytes_written("unsealed", written as u64);
 
     let amount = UnpaddedBytesAmount(written as u64);
 
@@ -418,6 +459,7 @@ where
         comm.copy_from_slice(commitment.as_ref());
 
         let written = piece_alignment.left_bytes + piece_alignment.right_bytes + piece_size;
+        metrics::record_bytes_written("piece", u64::from(PaddedBytesAmount::from(written)));
 
         Ok((PieceInfo::new(comm, n)?, written))
     });
@@ -632,6 +674,7 @@ where
     T: AsRef<Path>,
 {
     info!("validate_cache_for_precommit_phase2:start");
+    let _timer = metrics::time_phase("validate_cache_for_precommit_phase2");
 
     ensure!(
         replica_path.as_ref().exists(),
@@ -676,6 +719,7 @@ where
     T: AsRef<Path>,
 {
     info!("validate_cache_for_commit:start");
+    let _timer = metrics::time_phase("validate_cache_for_commit");
 
     // Verify that the replica exists and is not empty.
     ensure!(
@@ -695,40 +739,42 @@ where
 
     // Make sure p_aux exists and is valid.
     let p_aux_path = cache.join(CacheKey::PAux.to_string());
-    let p_aux_bytes = fs::read(&p_aux_path)
+    let _: PersistentAux<<Tree::Hasher as Hasher>::Domain> = read_cache_file(&p_aux_path)
         .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
 
-    let _: PersistentAux<<Tree::Hasher as Hasher>::Domain> = deserialize(&p_aux_bytes)?;
-    drop(p_aux_bytes);
-
     // Make sure t_aux exists and is valid.
     let t_aux = {
         let t_aux_path = cache.join(CacheKey::TAux.to_string());
-        let t_aux_bytes = fs::read(&t_aux_path)
+        let mut res: TemporaryAux<Tree, DefaultPieceHasher> = read_cache_file(&t_aux_path)
             .with_context(|| format!("could not read file t_aux={:?}", t_aux_path))?;
 
-        let mut res: TemporaryAux<Tree, DefaultPieceHasher> = deserialize(&t_aux_bytes)?;
-
         // Switch t_aux to the passed in cache_path
         res.set_cache_path(&cache_path);
         res
     };
 
-    // Verify all stores/labels within the Labels object.
+    // With synthetic PoRep, commit phase 1 only reads the persisted synthetic proofs, so the
+    // layers and trees other than tree_r_last may have been cleared already.
     let cache = cache_path.as_ref().to_path_buf();
-    t_aux.labels.verify_stores(verify_store, &cache)?;
-
-    // Verify each tree disk store.
-    verify_store(
-        &t_aux.tree_d_config,
-        <DefaultBinaryTree as MerkleTreeTrait>::Arity::to_usize(),
-        get_base_tree_count::<Tree>(),
-    )?;
-    verify_store(
-        &t_aux.tree_c_config,
-        <DefaultOctTree as MerkleTreeTrait>::Arity::to_usize(),
-        get_base_tree_count::<Tree>(),
-    )?;
+    if !cache
+        .join(CacheKey::SyntheticPoRepProofs.to_string())
+        .exists()
+    {
+        // Verify all stores/labels within the Labels object.
+        t_aux.labels.verify_stores(verify_store, &cache)?;
+
+        // Verify each tree disk store.
+        verify_store(
+            &t_aux.tree_d_config,
+            <DefaultBinaryTree as MerkleTreeTrait>::Arity::to_usize(),
+            get_base_tree_count::<Tree>(),
+        )?;
+        verify_store(
+            &t_aux.tree_c_config,
+            <DefaultOctTree as MerkleTreeTrait>::Arity::to_usize(),
+            get_base_tree_count::<Tree>(),
+        )?;
+    }
     verify_level_cache_store::<DefaultOctTree>(&t_aux.tree_r_last_config)?;
 
     info!("validate_cache_for_commit:finish");
@@ -1,19 +1,20 @@
 use std::collections::BTreeMap;
-use std::fs;
+use std::fs::remove_file;
 use std::path::Path;
 
 use anyhow::{anyhow, ensure, Context, Result};
-use bincode::deserialize;
 use filecoin_hashers::{sha256::Sha256Hasher, Hasher};
 use log::{debug, info};
 use storage_proofs_core::{
-    cache_key::CacheKey, merkle::MerkleTreeTrait, proof::ProofScheme, sector::SectorId,
+    cache_key::CacheKey, enter_span, merkle::MerkleTreeTrait, metrics, proof::ProofScheme,
+    sector::SectorId, settings,
 };
 use storage_proofs_post::fallback::{self, generate_leaf_challenge, FallbackPoSt, SectorProof};
 
 use crate::{
     api::as_safe_commitment,
     constants::DefaultPieceHasher,
+    error::{read_cache_file, Error},
     types::{
         ChallengeSeed, FallbackPoStSectorProof, PoStConfig, PrivateReplicaInfo, ProverId,
         TemporaryAux, VanillaProof,
@@ -24,14 +25,13 @@ use crate::{
 // Ensure that any associated cached data persisted is discarded.
 pub fn clear_cache<Tree: MerkleTreeTrait>(cache_dir: &Path) -> Result<()> {
     info!("clear_cache:start");
+    let _timer = metrics::time_phase("clear_cache");
 
     let mut t_aux: TemporaryAux<Tree, Sha256Hasher> = {
         let f_aux_path = cache_dir.to_path_buf().join(CacheKey::TAux.to_string());
-        let aux_bytes = fs::read(&f_aux_path)
-            .with_context(|| format!("could not read from path={:?}", f_aux_path))?;
-
-        deserialize(&aux_bytes)
-    }?;
+        read_cache_file(&f_aux_path)
+            .with_context(|| format!("could not read from path={:?}", f_aux_path))?
+    };
 
     t_aux.set_cache_path(cache_dir);
     let result = TemporaryAux::<Tree, DefaultPieceHasher>::clear_temp(t_aux);
@@ -41,11 +41,27 @@ pub fn clear_cache<Tree: MerkleTreeTrait>(cache_dir: &Path) -> Result<()> {
     result
 }
 
+/// Removes the synthetic PoRep proofs persisted by pre-commit phase 2, which are no longer needed
+/// once commit phase 1 finished. Succeeds if there are none.
+pub fn clear_synthetic_proofs(cache_dir: &Path) -> Result<()> {
+    info!("clear_synthetic_proofs:start");
+
+    let synthetic_path = cache_dir.join(CacheKey::SyntheticPoRepProofs.to_string());
+    if synthetic_path.exists() {
+        remove_file(&synthetic_path)
+            .with_context(|| format!("Failed to delete {:?}", synthetic_path))?;
+    }
+
+    info!("clear_synthetic_proofs:finish");
+    Ok(())
+}
+
 // Ensure that any associated cached data persisted is discarded.
 pub fn clear_caches<Tree: MerkleTreeTrait>(
     replicas: &BTreeMap<SectorId, PrivateReplicaInfo<Tree>>,
 ) -> Result<()> {
     info!("clear_caches:start");
+    let _timer = metrics::time_phase("clear_caches");
 
     for replica in replicas.values() {
         clear_cache::<Tree>(replica.cache_dir.as_path())?;
@@ -65,9 +81,10 @@ pub fn generate_fallback_sector_challenges<Tree: 'static + MerkleTreeTrait>(
     _prover_id: ProverId,
 ) -> Result<BTreeMap<SectorId, Vec<u64>>> {
     info!("generate_sector_challenges:start");
+    let _timer = metrics::time_phase("generate_sector_challenges");
     ensure!(
         post_config.typ == PoStType::Window || post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
@@ -78,6 +95,7 @@ pub fn generate_fallback_sector_challenges<Tree: 'static + MerkleTreeTrait>(
         challenge_count: post_config.challenge_count,
         sector_count: post_config.sector_count,
         api_version: post_config.api_version,
+        synthesis_num_cpus: settings::current().window_post_synthesis_num_cpus as usize,
     };
 
     let mut sector_challenges: BTreeMap<SectorId, Vec<u64>> = BTreeMap::new();
@@ -130,9 +148,17 @@ pub fn generate_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
     challenges: &[u64],
 ) -> Result<FallbackPoStSectorProof<Tree>> {
     info!("generate_single_vanilla_proof:start: {:?}", sector_id);
+    let _timer = metrics::time_phase("generate_single_vanilla_proof");
+    let _span = enter_span!(
+        "generate_single_vanilla_proof",
+        sector_id = %sector_id,
+        sector_size = u64::from(post_config.sector_size),
+        api_version = %post_config.api_version,
+    );
 
     let tree = &replica
         .merkle_tree(post_config.sector_size)
+        .map_err(|err| faulty_sector(err, sector_id))
         .with_context(|| {
             format!(
                 "generate_single_vanilla_proof: merkle_tree failed: {:?}",
@@ -158,8 +184,9 @@ pub fn generate_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
         sectors: &priv_sectors,
     };
 
-    let vanilla_proof =
-        fallback::vanilla_proof(sector_id, &priv_inputs, challenges).with_context(|| {
+    let vanilla_proof = fallback::vanilla_proof(sector_id, &priv_inputs, challenges)
+        .map_err(|err| faulty_sector(err, sector_id))
+        .with_context(|| {
             format!(
                 "generate_single_vanilla_proof: vanilla_proof failed: {:?}",
                 sector_id
@@ -175,6 +202,12 @@ pub fn generate_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
     })
 }
 
+// Marks the failure to generate a sector's vanilla proof as a fault.
+fn faulty_sector(err: anyhow::Error, sector_id: SectorId) -> anyhow::Error {
+    metrics::record_faulty_sectors(1);
+    err.context(Error::FaultySectors(vec![sector_id]))
+}
+
 // Partition a flat vector of vanilla sector proofs.  The post_config
 // (PoSt) type is required in order to determine the proper shape of
 // the returned partitioned proofs.
@@ -186,9 +219,10 @@ pub fn partition_vanilla_proofs<Tree: MerkleTreeTrait>(
     vanilla_proofs: &[FallbackPoStSectorProof<Tree>],
 ) -> Result<Vec<VanillaProof<Tree>>> {
     info!("partition_vanilla_proofs:start");
+    let _timer = metrics::time_phase("partition_vanilla_proofs");
     ensure!(
         post_config.typ == PoStType::Window || post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let num_sectors_per_chunk = pub_params.sector_count;
@@ -266,13 +300,14 @@ pub fn single_partition_vanilla_proofs<Tree: MerkleTreeTrait>(
     vanilla_proofs: &[FallbackPoStSectorProof<Tree>],
 ) -> Result<VanillaProof<Tree>> {
     info!("single_partition_vanilla_proofs:start");
+    let _timer = metrics::time_phase("single_partition_vanilla_proofs");
     ensure!(pub_inputs.k.is_some(), "must have a partition index");
     let partition_index = pub_inputs.k.expect("prechecked");
 
     debug!("processing partition: {}", partition_index);
     ensure!(
         post_config.typ == PoStType::Window || post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let num_sectors_per_chunk = pub_params.sector_count;
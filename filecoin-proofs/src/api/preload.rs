@@ -0,0 +1,334 @@
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use bellperson::groth16::MappedParameters;
+use blstrs::Bls12;
+use log::{info, warn};
+use storage_proofs_core::{merkle::MerkleTreeTrait, parameter_store, settings};
+use storage_proofs_update::constants::TreeRHasher;
+
+use crate::{
+    caches::{
+        get_empty_sector_update_params, get_empty_sector_update_verifying_key, get_post_params,
+        get_post_verifying_key, get_stacked_params, get_stacked_srs_key,
+        get_stacked_srs_verifier_key, get_stacked_verifying_key,
+    },
+    types::{PoRepConfig, PoStConfig},
+    with_shape,
+};
+
+/// Size of the pages touched when pre-faulting mapped parameters.
+const PAGE_SIZE: usize = 4096;
+
+/// A proof whose parameters are loaded by [`preload_parameters`].
+#[derive(Clone, Debug)]
+pub enum ProofKind {
+    /// Groth parameters and verifying key for sealing.
+    PoRep(PoRepConfig),
+    /// SRS prover and verifier keys for aggregating `num_proofs` seal proofs. `num_proofs` is
+    /// the padded number of proofs, a power of two.
+    PoRepAggregation {
+        porep_config: PoRepConfig,
+        num_proofs: usize,
+    },
+    /// Groth parameters and verifying key for Winning or Window PoSt, depending on the type of
+    /// the config.
+    PoSt(PoStConfig),
+    /// Groth parameters and verifying key for empty sector updates.
+    EmptySectorUpdate(PoRepConfig),
+}
+
+impl fmt::Display for ProofKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofKind::PoRep(config) => write!(f, "PoRep[{}]", u64::from(config.sector_size)),
+            ProofKind::PoRepAggregation {
+                porep_config,
+                num_proofs,
+            } => write!(
+                f,
+                "PoRepAggregation[{}-{}]",
+                u64::from(porep_config.sector_size),
+                num_proofs
+            ),
+            ProofKind::PoSt(config) => {
+                write!(f, "{:?}PoSt[{}]", config.typ, u64::from(config.sector_size))
+            }
+            ProofKind::EmptySectorUpdate(config) => {
+                write!(f, "EmptySectorUpdate[{}]", u64::from(config.sector_size))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PreloadOptions {
+    /// Touch every page of the mapped Groth parameters, so proving does not fault them in.
+    pub prefault: bool,
+    /// Lock the mapped Groth parameters into memory. Implies `prefault`. If locking fails, e.g.
+    /// because of `RLIMIT_MEMLOCK`, the pages are only pre-faulted.
+    pub mlock: bool,
+}
+
+#[derive(Debug, Default)]
+struct PreloadState {
+    loaded: usize,
+    failed: Vec<(String, anyhow::Error)>,
+    finished: bool,
+}
+
+/// Reports the progress of a [`preload_parameters`] call.
+#[derive(Debug)]
+pub struct PreloadHandle {
+    total: usize,
+    state: Arc<(Mutex<PreloadState>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PreloadHandle {
+    /// Returns `true` once all parameters were loaded, or failed to load.
+    pub fn is_ready(&self) -> bool {
+        self.lock().finished
+    }
+
+    /// Returns the number of proof kinds processed so far and the total number.
+    pub fn progress(&self) -> (usize, usize) {
+        let state = self.lock();
+        (state.loaded + state.failed.len(), self.total)
+    }
+
+    /// Waits for at most `timeout` for the preload to finish, returns whether it did.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (lock, ready) = &*self.state;
+        let state = lock.lock().expect("preload state poisoned");
+        let (state, _) = ready
+            .wait_timeout_while(state, timeout, |state| !state.finished)
+            .expect("preload state poisoned");
+        state.finished
+    }
+
+    /// Waits for the preload to finish. Fails if the parameters of any proof kind could not be
+    /// loaded, the remaining ones are loaded nonetheless.
+    pub fn wait(mut self) -> Result<()> {
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .map_err(|_| anyhow!("preload thread panicked"))?;
+        }
+
+        let failed = std::mem::take(&mut self.lock().failed);
+        let count = failed.len();
+        match failed.into_iter().next() {
+            Some((kind, err)) => Err(err.context(format!(
+                "failed to preload parameters for {} ({} of {} failed)",
+                kind, count, self.total
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PreloadState> {
+        self.state.0.lock().expect("preload state poisoned")
+    }
+}
+
+/// Loads the Groth parameters, verifying keys and SRS keys of `kinds` into the in-memory caches
+/// on a background thread, so the first proof does not pay for mapping and validating them.
+///
+/// The settings and parameter store in effect on the calling thread are used.
+pub fn preload_parameters(kinds: &[ProofKind]) -> Result<PreloadHandle> {
+    preload_parameters_with_options(kinds, PreloadOptions::default())
+}
+
+/// Like [`preload_parameters`], optionally pre-faulting or locking the mapped parameters.
+pub fn preload_parameters_with_options(
+    kinds: &[ProofKind],
+    options: PreloadOptions,
+) -> Result<PreloadHandle> {
+    let kinds = kinds.to_vec();
+    let state = Arc::new((Mutex::new(PreloadState::default()), Condvar::new()));
+    let settings = settings::current();
+    let store = parameter_store::current();
+
+    let thread_state = state.clone();
+    let total = kinds.len();
+    let thread = thread::Builder::new()
+        .name("preload-parameters".to_string())
+        .spawn(move || {
+            settings::with_settings(settings, || {
+                parameter_store::with_parameter_store(store, || {
+                    preload_all(&kinds, options, &thread_state)
+                })
+            })
+        })
+        .context("failed to spawn preload thread")?;
+
+    Ok(PreloadHandle {
+        total,
+        state,
+        thread: Some(thread),
+    })
+}
+
+fn preload_all(
+    kinds: &[ProofKind],
+    options: PreloadOptions,
+    state: &(Mutex<PreloadState>, Condvar),
+) {
+    info!("preload_parameters:start");
+    for kind in kinds {
+        info!("preloading parameters for {}", kind);
+        // Unsupported sector sizes panic in `with_shape!`.
+        let result = catch_unwind(AssertUnwindSafe(|| preload(kind, options)))
+            .unwrap_or_else(|_| Err(anyhow!("panicked while loading parameters")));
+
+        let mut state = state.0.lock().expect("preload state poisoned");
+        match result {
+            Ok(()) => state.loaded += 1,
+            Err(err) => {
+                warn!("failed to preload parameters for {}: {:?}", kind, err);
+                state.failed.push((kind.to_string(), err));
+            }
+        }
+    }
+
+    let (lock, ready) = state;
+    lock.lock().expect("preload state poisoned").finished = true;
+    ready.notify_all();
+    info!("preload_parameters:finish");
+}
+
+fn preload(kind: &ProofKind, options: PreloadOptions) -> Result<()> {
+    match kind {
+        ProofKind::PoRep(porep_config) => with_shape!(
+            u64::from(porep_config.sector_size),
+            preload_porep,
+            *porep_config,
+            options
+        ),
+        ProofKind::PoRepAggregation {
+            porep_config,
+            num_proofs,
+        } => with_shape!(
+            u64::from(porep_config.sector_size),
+            preload_porep_aggregation,
+            *porep_config,
+            *num_proofs
+        ),
+        ProofKind::PoSt(post_config) => with_shape!(
+            u64::from(post_config.sector_size),
+            preload_post,
+            post_config,
+            options
+        ),
+        ProofKind::EmptySectorUpdate(porep_config) => with_shape!(
+            u64::from(porep_config.sector_size),
+            preload_empty_sector_update,
+            *porep_config,
+            options
+        ),
+    }
+}
+
+fn preload_porep<Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+    options: PreloadOptions,
+) -> Result<()> {
+    let params = get_stacked_params::<Tree>(porep_config)?;
+    get_stacked_verifying_key::<Tree>(porep_config)?;
+    fault_in(&params, options);
+
+    Ok(())
+}
+
+fn preload_porep_aggregation<Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+    num_proofs: usize,
+) -> Result<()> {
+    get_stacked_srs_key::<Tree>(porep_config, num_proofs)?;
+    get_stacked_srs_verifier_key::<Tree>(porep_config, num_proofs)?;
+
+    Ok(())
+}
+
+fn preload_post<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    options: PreloadOptions,
+) -> Result<()> {
+    let params = get_post_params::<Tree>(post_config)?;
+    get_post_verifying_key::<Tree>(post_config)?;
+    fault_in(&params, options);
+
+    Ok(())
+}
+
+fn preload_empty_sector_update<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    porep_config: PoRepConfig,
+    options: PreloadOptions,
+) -> Result<()> {
+    let params = get_empty_sector_update_params::<Tree>(porep_config)?;
+    get_empty_sector_update_verifying_key::<Tree>(porep_config)?;
+    fault_in(&params, options);
+
+    Ok(())
+}
+
+/// Pre-faults and optionally locks the pages of the mapped parameter file.
+fn fault_in(params: &MappedParameters<Bls12>, options: PreloadOptions) {
+    let data: &[u8] = &params.params;
+    if options.mlock {
+        match lock_pages(data) {
+            Ok(()) => {
+                info!(
+                    "locked {} bytes of {} into memory",
+                    data.len(),
+                    params.param_file_path.display()
+                );
+                return;
+            }
+            Err(err) => warn!(
+                "failed to lock {} into memory, pre-faulting only: {}",
+                params.param_file_path.display(),
+                err
+            ),
+        }
+    }
+    if options.prefault || options.mlock {
+        for offset in (0..data.len()).step_by(PAGE_SIZE) {
+            // Volatile, so the reads are not optimized away.
+            unsafe { std::ptr::read_volatile(data.as_ptr().add(offset)) };
+        }
+        info!(
+            "pre-faulted {} bytes of {}",
+            data.len(),
+            params.param_file_path.display()
+        );
+    }
+}
+
+/// Locks the pages into memory, which also faults them in. They are unlocked once the mapping
+/// is dropped, i.e. when the parameters are evicted from the cache and no longer in use.
+#[cfg(unix)]
+fn lock_pages(data: &[u8]) -> std::io::Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    let res = unsafe { libc::mlock(data.as_ptr() as *const libc::c_void, data.len()) };
+    if res != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lock_pages(_data: &[u8]) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "locking memory is not supported on this platform",
+    ))
+}
@@ -4,7 +4,7 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{ensure, Context, Result};
 use bellperson::groth16;
-use bincode::{deserialize, serialize};
+use bincode::serialize;
 use blstrs::{Bls12, Scalar as Fr};
 use filecoin_hashers::{Domain, Hasher};
 use log::{info, trace};
@@ -16,8 +16,10 @@ use storage_proofs_core::{
     cache_key::CacheKey,
     compound_proof::{self, CompoundProof},
     drgraph::Graph,
+    enter_span,
     measurements::{measure_op, Operation},
     merkle::{create_base_merkle_tree, BinaryMerkleTree, MerkleTreeTrait},
+    metrics,
     multi_proof::MultiProof,
     parameter_cache::SRS_MAX_PROOFS_TO_AGGREGATE,
     proof::ProofScheme,
@@ -26,8 +28,8 @@ use storage_proofs_core::{
     Data,
 };
 use storage_proofs_porep::stacked::{
-    self, generate_replica_id, ChallengeRequirements, StackedCompound, StackedDrg, Tau,
-    TemporaryAux, TemporaryAuxCache,
+    self, generate_replica_id, ChallengeRequirements, PersistentAux, StackedCompound, StackedDrg,
+    Tau, TemporaryAux, TemporaryAuxCache,
 };
 
 use crate::{
@@ -37,15 +39,17 @@ use crate::{
         get_stacked_verifying_key,
     },
     constants::{
-        DefaultBinaryTree, DefaultPieceDomain, DefaultPieceHasher, POREP_MINIMUM_CHALLENGES,
+        sector_size_info, DefaultBinaryTree, DefaultPieceDomain, DefaultPieceHasher,
         SINGLE_PARTITION_PROOF_LEN,
     },
+    error::{read_cache_file, Error},
     parameters::setup_params,
     pieces::{self, verify_pieces},
     types::{
         AggregateSnarkProof, Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig,
         PoRepProofPartitions, ProverId, SealCommitOutput, SealCommitPhase1Output,
-        SealPreCommitOutput, SealPreCommitPhase1Output, SectorSize, Ticket, BINARY_ARITY,
+        SealPreCommitOutput, SealPreCommitPhase1Input, SealPreCommitPhase1Output, SectorSize,
+        Ticket, BINARY_ARITY,
     },
 };
 
@@ -66,7 +70,164 @@ where
     T: AsRef<Path>,
 {
     info!("seal_pre_commit_phase1:start: {:?}", sector_id);
+    let _timer = metrics::time_phase("seal_pre_commit_phase1");
+    let _span = enter_span!(
+        "seal_pre_commit_phase1",
+        sector_id = %sector_id,
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
+
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            PaddedBytesAmount::from(porep_config),
+            usize::from(PoRepProofPartitions::from(porep_config)),
+            porep_config.porep_id,
+            porep_config.api_version,
+        )?,
+        partitions: Some(usize::from(PoRepProofPartitions::from(porep_config))),
+        priority: false,
+    };
+
+    let compound_public_params = <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
+        StackedDrg<'_, Tree, DefaultPieceHasher>,
+        _,
+    >>::setup(&compound_setup_params)?;
+
+    let (config, comm_d, replica_id) = prepare_pre_commit_phase1::<_, _, _, Tree>(
+        porep_config,
+        &compound_public_params.vanilla_params,
+        cache_path,
+        in_path,
+        out_path,
+        prover_id,
+        sector_id,
+        ticket,
+        piece_infos,
+    )?;
+
+    let labels = StackedDrg::<Tree, DefaultPieceHasher>::replicate_phase1(
+        &compound_public_params.vanilla_params,
+        &replica_id,
+        config.clone(),
+    )?;
+
+    let out = SealPreCommitPhase1Output {
+        labels,
+        config,
+        comm_d,
+        replica_id: Some(commitment_from_fr(replica_id.into())),
+    };
+
+    info!("seal_pre_commit_phase1:finish: {:?}", sector_id);
+    Ok(out)
+}
+
+/// Labels several sectors in pre-commit phase 1 together, the outputs are identical to calling
+/// [`seal_pre_commit_phase1`] for each sector. With multicore SDR the sectors are labeled in
+/// lockstep, so that they share the reads of the parents cache and are hashed together, which
+/// lets more sectors be sealed per core group.
+pub fn seal_pre_commit_phase1_lockstep<Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+    sectors: &[SealPreCommitPhase1Input],
+) -> Result<Vec<SealPreCommitPhase1Output<Tree>>> {
+    info!(
+        "seal_pre_commit_phase1_lockstep:start: {} sectors",
+        sectors.len()
+    );
+    let _timer = metrics::time_phase("seal_pre_commit_phase1");
+    let _span = enter_span!(
+        "seal_pre_commit_phase1_lockstep",
+        sectors = sectors.len(),
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
+    ensure!(!sectors.is_empty(), "no sectors to seal");
+
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            PaddedBytesAmount::from(porep_config),
+            usize::from(PoRepProofPartitions::from(porep_config)),
+            porep_config.porep_id,
+            porep_config.api_version,
+        )?,
+        partitions: Some(usize::from(PoRepProofPartitions::from(porep_config))),
+        priority: false,
+    };
+
+    let compound_public_params = <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
+        StackedDrg<'_, Tree, DefaultPieceHasher>,
+        _,
+    >>::setup(&compound_setup_params)?;
+
+    let prepared = sectors
+        .iter()
+        .map(|sector| {
+            prepare_pre_commit_phase1::<_, _, _, Tree>(
+                porep_config,
+                &compound_public_params.vanilla_params,
+                &sector.cache_path,
+                &sector.in_path,
+                &sector.out_path,
+                sector.prover_id,
+                sector.sector_id,
+                sector.ticket,
+                &sector.piece_infos,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let replica_ids = prepared
+        .iter()
+        .map(|(_, _, replica_id)| *replica_id)
+        .collect::<Vec<_>>();
+    let configs = prepared
+        .iter()
+        .map(|(config, _, _)| config.clone())
+        .collect();
 
+    let labels = StackedDrg::<Tree, DefaultPieceHasher>::replicate_phase1_lockstep(
+        &compound_public_params.vanilla_params,
+        &replica_ids,
+        configs,
+    )?;
+
+    let out = labels
+        .into_iter()
+        .zip(prepared)
+        .map(
+            |(labels, (config, comm_d, replica_id))| SealPreCommitPhase1Output {
+                labels,
+                config,
+                comm_d,
+                replica_id: Some(commitment_from_fr(replica_id.into())),
+            },
+        )
+        .collect();
+
+    info!("seal_pre_commit_phase1_lockstep:finish");
+    Ok(out)
+}
+
+/// Copies the unsealed data of a sector to its output location, builds tree-d and derives the
+/// replica id, everything pre-commit phase 1 does before labeling.
+#[allow(clippy::too_many_arguments)]
+fn prepare_pre_commit_phase1<R, S, T, Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+    vanilla_params: &stacked::PublicParams<Tree>,
+    cache_path: R,
+    in_path: S,
+    out_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+) -> Result<(StoreConfig, Commitment, <Tree::Hasher as Hasher>::Domain)>
+where
+    R: AsRef<Path>,
+    S: AsRef<Path>,
+    T: AsRef<Path>,
+{
     // Sanity check all input path types.
     ensure!(
         metadata(in_path.as_ref())?.is_file(),
@@ -112,29 +273,13 @@ where
             .with_context(|| format!("could not mmap out_path={:?}", out_path.as_ref().display()))?
     };
 
-    let compound_setup_params = compound_proof::SetupParams {
-        vanilla_params: setup_params(
-            PaddedBytesAmount::from(porep_config),
-            usize::from(PoRepProofPartitions::from(porep_config)),
-            porep_config.porep_id,
-            porep_config.api_version,
-        )?,
-        partitions: Some(usize::from(PoRepProofPartitions::from(porep_config))),
-        priority: false,
-    };
-
-    let compound_public_params = <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
-        StackedDrg<'_, Tree, DefaultPieceHasher>,
-        _,
-    >>::setup(&compound_setup_params)?;
-
     trace!("building merkle tree for the original data");
     let (config, comm_d) = measure_op(Operation::CommD, || -> Result<_> {
         let base_tree_size = get_base_tree_size::<DefaultBinaryTree>(porep_config.sector_size)?;
         let base_tree_leafs = get_base_tree_leafs::<DefaultBinaryTree>(base_tree_size)?;
         ensure!(
-            compound_public_params.vanilla_params.graph.size() == base_tree_leafs,
-            "graph size and leaf size don't match"
+            vanilla_params.graph.size() == base_tree_leafs,
+            Error::ConfigMismatch("graph size and leaf size don't match".to_string())
         );
 
         trace!(
@@ -170,7 +315,7 @@ where
 
     ensure!(
         verify_pieces(&comm_d, piece_infos, porep_config.into())?,
-        "pieces and comm_d do not match"
+        Error::PieceCommitmentMismatch
     );
 
     let replica_id = generate_replica_id::<Tree::Hasher, _>(
@@ -181,20 +326,7 @@ where
         &porep_config.porep_id,
     );
 
-    let labels = StackedDrg::<Tree, DefaultPieceHasher>::replicate_phase1(
-        &compound_public_params.vanilla_params,
-        &replica_id,
-        config.clone(),
-    )?;
-
-    let out = SealPreCommitPhase1Output {
-        labels,
-        config,
-        comm_d,
-    };
-
-    info!("seal_pre_commit_phase1:finish: {:?}", sector_id);
-    Ok(out)
+    Ok((config, comm_d, replica_id))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -209,6 +341,12 @@ where
     S: AsRef<Path>,
 {
     info!("seal_pre_commit_phase2:start");
+    let _timer = metrics::time_phase("seal_pre_commit_phase2");
+    let _span = enter_span!(
+        "seal_pre_commit_phase2",
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
 
     // Sanity check all input path types.
     ensure!(
@@ -224,7 +362,7 @@ where
         mut labels,
         mut config,
         comm_d,
-        ..
+        replica_id,
     } = phase1_output;
 
     labels.update_root(cache_path.as_ref());
@@ -263,7 +401,7 @@ where
         );
         ensure!(
             config.rows_to_discard == default_rows_to_discard(base_tree_leafs, BINARY_ARITY),
-            "Invalid cache size specified"
+            Error::ConfigMismatch("Invalid cache size specified".to_string())
         );
 
         let store: DiskStore<DefaultPieceDomain> =
@@ -315,12 +453,59 @@ where
         .write_all(&t_aux_bytes)
         .with_context(|| format!("could not write to file t_aux={:?}", t_aux_path))?;
 
+    if porep_config.api_version.supports_synthetic_porep() {
+        let replica_id = replica_id.context("synthetic PoRep requires the replica_id")?;
+        prove_synthetic_challenges(
+            &compound_public_params.vanilla_params,
+            as_safe_commitment(&replica_id, "replica_id")?,
+            &tau,
+            &p_aux,
+            &t_aux,
+            cache_path.as_ref(),
+            replica_path.as_ref(),
+        )?;
+    }
+
     let out = SealPreCommitOutput { comm_r, comm_d };
 
     info!("seal_pre_commit_phase2:finish");
     Ok(out)
 }
 
+/// Proves all synthetic challenges of a replica, so that its layers are no longer needed once
+/// pre-commit phase 2 finished.
+fn prove_synthetic_challenges<Tree: 'static + MerkleTreeTrait>(
+    pub_params: &stacked::PublicParams<Tree>,
+    replica_id: <Tree::Hasher as Hasher>::Domain,
+    tau: &Tau<<Tree::Hasher as Hasher>::Domain, DefaultPieceDomain>,
+    p_aux: &PersistentAux<<Tree::Hasher as Hasher>::Domain>,
+    t_aux: &TemporaryAux<Tree, DefaultPieceHasher>,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<()> {
+    info!("prove_synthetic_challenges:start");
+    let _timer = metrics::time_phase("prove_synthetic_challenges");
+
+    let t_aux_cache: TemporaryAuxCache<Tree, DefaultPieceHasher> =
+        TemporaryAuxCache::new(t_aux, replica_path.to_path_buf())
+            .context("failed to restore contents of t_aux")?;
+
+    let synthetic_path = cache_path.join(CacheKey::SyntheticPoRepProofs.to_string());
+    StackedDrg::<Tree, DefaultPieceHasher>::prove_synthetic_layers(
+        &pub_params.graph,
+        &replica_id,
+        tau,
+        p_aux,
+        &t_aux_cache,
+        &pub_params.layer_challenges,
+        &synthetic_path,
+    )
+    .with_context(|| format!("could not write synthetic proofs={:?}", synthetic_path))?;
+
+    info!("prove_synthetic_challenges:finish");
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     porep_config: PoRepConfig,
@@ -334,6 +519,13 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     piece_infos: &[PieceInfo],
 ) -> Result<SealCommitPhase1Output<Tree>> {
     info!("seal_commit_phase1:start: {:?}", sector_id);
+    let _timer = metrics::time_phase("seal_commit_phase1");
+    let _span = enter_span!(
+        "seal_commit_phase1",
+        sector_id = %sector_id,
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
 
     // Sanity check all input path types.
     ensure!(
@@ -347,39 +539,13 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
 
     let SealPreCommitOutput { comm_d, comm_r } = pre_commit;
 
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
+    ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
     ensure!(
         verify_pieces(&comm_d, piece_infos, porep_config.into())?,
-        "pieces and comm_d do not match"
+        Error::PieceCommitmentMismatch
     );
 
-    let p_aux = {
-        let p_aux_path = cache_path.as_ref().join(CacheKey::PAux.to_string());
-        let p_aux_bytes = fs::read(&p_aux_path)
-            .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
-
-        deserialize(&p_aux_bytes)
-    }?;
-
-    let t_aux = {
-        let t_aux_path = cache_path.as_ref().join(CacheKey::TAux.to_string());
-        let t_aux_bytes = fs::read(&t_aux_path)
-            .with_context(|| format!("could not read file t_aux={:?}", t_aux_path))?;
-
-        let mut res: TemporaryAux<_, _> = deserialize(&t_aux_bytes)?;
-
-        // Switch t_aux to the passed in cache_path
-        res.set_cache_path(cache_path);
-        res
-    };
-
-    // Convert TemporaryAux to TemporaryAuxCache, which instantiates all
-    // elements based on the configs stored in TemporaryAux.
-    let t_aux_cache: TemporaryAuxCache<Tree, DefaultPieceHasher> =
-        TemporaryAuxCache::new(&t_aux, replica_path.as_ref().to_path_buf())
-            .context("failed to restore contents of t_aux")?;
-
     let comm_r_safe = as_safe_commitment(&comm_r, "comm_r")?;
     let comm_d_safe = DefaultPieceDomain::try_from_bytes(&comm_d)?;
 
@@ -391,6 +557,8 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
         &porep_config.porep_id,
     );
 
+    // Non-interactive challenges are derived from comm_r and ignore the seed, so this phase can
+    // run right after pre-commit phase 2.
     let public_inputs = stacked::PublicInputs {
         replica_id,
         tau: Some(stacked::Tau {
@@ -401,11 +569,6 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
         seed,
     };
 
-    let private_inputs = stacked::PrivateInputs::<Tree, DefaultPieceHasher> {
-        p_aux,
-        t_aux: t_aux_cache,
-    };
-
     let compound_setup_params = compound_proof::SetupParams {
         vanilla_params: setup_params(
             PaddedBytesAmount::from(porep_config),
@@ -422,12 +585,55 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
         _,
     >>::setup(&compound_setup_params)?;
 
-    let vanilla_proofs = StackedDrg::prove_all_partitions(
-        &compound_public_params.vanilla_params,
-        &public_inputs,
-        &private_inputs,
-        StackedCompound::partition_count(&compound_public_params),
-    )?;
+    let layer_challenges = &compound_public_params.vanilla_params.layer_challenges;
+    let vanilla_proofs = if layer_challenges.synthetic_count().is_some() {
+        // The proofs of the challenges selected by the seed were generated in pre-commit phase 2,
+        // the layers may be gone by now.
+        let synthetic_path = cache_path
+            .as_ref()
+            .join(CacheKey::SyntheticPoRepProofs.to_string());
+        StackedDrg::<Tree, DefaultPieceHasher>::read_synthetic_proofs(
+            &public_inputs,
+            layer_challenges,
+            StackedCompound::partition_count(&compound_public_params),
+            &synthetic_path,
+        )
+        .with_context(|| format!("could not read synthetic proofs={:?}", synthetic_path))?
+    } else {
+        let p_aux = {
+            let p_aux_path = cache_path.as_ref().join(CacheKey::PAux.to_string());
+            read_cache_file(&p_aux_path)
+                .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?
+        };
+
+        let t_aux = {
+            let t_aux_path = cache_path.as_ref().join(CacheKey::TAux.to_string());
+            let mut res: TemporaryAux<_, _> = read_cache_file(&t_aux_path)
+                .with_context(|| format!("could not read file t_aux={:?}", t_aux_path))?;
+
+            // Switch t_aux to the passed in cache_path
+            res.set_cache_path(cache_path);
+            res
+        };
+
+        // Convert TemporaryAux to TemporaryAuxCache, which instantiates all
+        // elements based on the configs stored in TemporaryAux.
+        let t_aux_cache: TemporaryAuxCache<Tree, DefaultPieceHasher> =
+            TemporaryAuxCache::new(&t_aux, replica_path.as_ref().to_path_buf())
+                .context("failed to restore contents of t_aux")?;
+
+        let private_inputs = stacked::PrivateInputs::<Tree, DefaultPieceHasher> {
+            p_aux,
+            t_aux: t_aux_cache,
+        };
+
+        StackedDrg::prove_all_partitions(
+            &compound_public_params.vanilla_params,
+            &public_inputs,
+            &private_inputs,
+            StackedCompound::partition_count(&compound_public_params),
+        )?
+    };
 
     let sanity_check = StackedDrg::<Tree, DefaultPieceHasher>::verify_all_partitions(
         &compound_public_params.vanilla_params,
@@ -457,6 +663,13 @@ pub fn seal_commit_phase2<Tree: 'static + MerkleTreeTrait>(
     sector_id: SectorId,
 ) -> Result<SealCommitOutput> {
     info!("seal_commit_phase2:start: {:?}", sector_id);
+    let _timer = metrics::time_phase("seal_commit_phase2");
+    let _span = enter_span!(
+        "seal_commit_phase2",
+        sector_id = %sector_id,
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
 
     let SealCommitPhase1Output {
         vanilla_proofs,
@@ -467,8 +680,8 @@ pub fn seal_commit_phase2<Tree: 'static + MerkleTreeTrait>(
         ticket,
     } = phase1_output;
 
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
+    ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
 
     let comm_r_safe = as_safe_commitment(&comm_r, "comm_r")?;
     let comm_d_safe = DefaultPieceDomain::try_from_bytes(&comm_d)?;
@@ -571,8 +784,8 @@ pub fn get_seal_inputs<Tree: 'static + MerkleTreeTrait>(
 ) -> Result<Vec<Vec<Fr>>> {
     trace!("get_seal_inputs:start");
 
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
+    ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
 
     let replica_id = generate_replica_id::<Tree::Hasher, _>(
         &prover_id,
@@ -726,6 +939,7 @@ pub fn aggregate_seal_commit_proofs<Tree: 'static + MerkleTreeTrait>(
     commit_outputs: &[SealCommitOutput],
 ) -> Result<AggregateSnarkProof> {
     info!("aggregate_seal_commit_proofs:start");
+    let _timer = metrics::time_phase("aggregate_seal_commit_proofs");
 
     ensure!(
         !commit_outputs.is_empty(),
@@ -811,6 +1025,7 @@ pub fn verify_aggregate_seal_commit_proofs<Tree: 'static + MerkleTreeTrait>(
     commit_inputs: Vec<Vec<Fr>>,
 ) -> Result<bool> {
     info!("verify_aggregate_seal_commit_proofs:start");
+    let _timer = metrics::time_phase("verify_aggregate_seal_commit_proofs");
 
     let aggregate_proof =
         groth16::aggregate::AggregateProof::read(std::io::Cursor::new(&aggregate_proof_bytes))?;
@@ -925,9 +1140,16 @@ pub fn verify_seal<Tree: 'static + MerkleTreeTrait>(
     proof_vec: &[u8],
 ) -> Result<bool> {
     info!("verify_seal:start: {:?}", sector_id);
+    let _timer = metrics::time_phase("verify_seal");
+    let _span = enter_span!(
+        "verify_seal",
+        sector_id = %sector_id,
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
 
-    ensure!(comm_d_in != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r_in != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(comm_d_in != [0; 32], Error::ZeroCommitment("comm_d"));
+    ensure!(comm_r_in != [0; 32], Error::ZeroCommitment("comm_r"));
     ensure!(!proof_vec.is_empty(), "Invalid proof bytes (empty vector)");
 
     let comm_r: <Tree::Hasher as Hasher>::Domain = as_safe_commitment(&comm_r_in, "comm_r")?;
@@ -985,11 +1207,9 @@ pub fn verify_seal<Tree: 'static + MerkleTreeTrait>(
             &public_inputs,
             &proof,
             &ChallengeRequirements {
-                minimum_challenges: *POREP_MINIMUM_CHALLENGES
-                    .read()
-                    .expect("POREP_MINIMUM_CHALLENGES poisoned")
-                    .get(&u64::from(SectorSize::from(porep_config)))
-                    .expect("unknown sector size") as usize,
+                minimum_challenges: sector_size_info(u64::from(SectorSize::from(porep_config)))?
+                    .porep_minimum_challenges_for(porep_config.api_version)?
+                    as usize,
             },
         )
     };
@@ -1022,6 +1242,7 @@ pub fn verify_batch_seal<Tree: 'static + MerkleTreeTrait>(
     proof_vecs: &[&[u8]],
 ) -> Result<bool> {
     info!("verify_batch_seal:start");
+    let _timer = metrics::time_phase("verify_batch_seal");
     ensure!(!comm_r_ins.is_empty(), "Cannot prove empty batch");
     let l = comm_r_ins.len();
     ensure!(l == comm_d_ins.len(), "Inconsistent inputs");
@@ -1033,16 +1254,10 @@ pub fn verify_batch_seal<Tree: 'static + MerkleTreeTrait>(
     ensure!(l == proof_vecs.len(), "Inconsistent inputs");
 
     for comm_d_in in comm_d_ins {
-        ensure!(
-            comm_d_in != &[0; 32],
-            "Invalid all zero commitment (comm_d)"
-        );
+        ensure!(comm_d_in != &[0; 32], Error::ZeroCommitment("comm_d"));
     }
     for comm_r_in in comm_r_ins {
-        ensure!(
-            comm_r_in != &[0; 32],
-            "Invalid all zero commitment (comm_r)"
-        );
+        ensure!(comm_r_in != &[0; 32], Error::ZeroCommitment("comm_r"));
     }
     for proofs in proof_vecs {
         ensure!(!proofs.is_empty(), "Invalid proof (empty bytes) found");
@@ -1108,11 +1323,9 @@ pub fn verify_batch_seal<Tree: 'static + MerkleTreeTrait>(
         &public_inputs,
         &proofs,
         &ChallengeRequirements {
-            minimum_challenges: *POREP_MINIMUM_CHALLENGES
-                .read()
-                .expect("POREP_MINIMUM_CHALLENGES poisoned")
-                .get(&u64::from(SectorSize::from(porep_config)))
-                .expect("unknown sector size") as usize,
+            minimum_challenges: sector_size_info(u64::from(SectorSize::from(porep_config)))?
+                .porep_minimum_challenges_for(porep_config.api_version)?
+                as usize,
         },
     )
     .map_err(Into::into);
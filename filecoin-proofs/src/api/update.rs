@@ -1,9 +1,9 @@
-use std::fs::{self, File};
+use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 use anyhow::{ensure, Context, Result};
-use bincode::{deserialize, serialize};
+use bincode::serialize;
 use filecoin_hashers::{Domain, Hasher};
 use generic_array::typenum::Unsigned;
 use log::{info, trace};
@@ -12,7 +12,9 @@ use merkletree::store::StoreConfig;
 use storage_proofs_core::{
     cache_key::CacheKey,
     compound_proof::{self, CompoundProof},
+    enter_span,
     merkle::{get_base_tree_count, MerkleTreeTrait},
+    metrics,
     multi_proof::MultiProof,
     proof::ProofScheme,
 };
@@ -25,6 +27,7 @@ use storage_proofs_update::{
 use crate::{
     caches::{get_empty_sector_update_params, get_empty_sector_update_verifying_key},
     constants::{DefaultPieceDomain, DefaultPieceHasher},
+    error::{read_cache_file, Error},
     pieces::verify_pieces,
     types::{
         Commitment, EmptySectorUpdateEncoded, EmptySectorUpdateProof, PieceInfo, PoRepConfig,
@@ -37,11 +40,9 @@ fn get_p_aux<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     cache_path: &Path,
 ) -> Result<PersistentAux<<Tree::Hasher as Hasher>::Domain>> {
     let p_aux_path = cache_path.join(CacheKey::PAux.to_string());
-    let p_aux_bytes = fs::read(&p_aux_path)
+    let p_aux = read_cache_file(&p_aux_path)
         .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
 
-    let p_aux = deserialize(&p_aux_bytes)?;
-
     Ok(p_aux)
 }
 
@@ -67,10 +68,8 @@ fn get_t_aux<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
 ) -> Result<TemporaryAux<Tree, DefaultPieceHasher>> {
     let t_aux_path = cache_path.join(CacheKey::TAux.to_string());
     trace!("Instantiating TemporaryAux from {:?}", cache_path);
-    let t_aux_bytes = fs::read(&t_aux_path)
+    let mut res: TemporaryAux<Tree, DefaultPieceHasher> = read_cache_file(&t_aux_path)
         .with_context(|| format!("could not read file t_aux={:?}", t_aux_path))?;
-
-    let mut res: TemporaryAux<Tree, DefaultPieceHasher> = deserialize(&t_aux_bytes)?;
     res.set_cache_path(cache_path);
     trace!("Set TemporaryAux cache_path to {:?}", cache_path);
 
@@ -149,6 +148,12 @@ pub fn encode_into<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     piece_infos: &[PieceInfo],
 ) -> Result<EmptySectorUpdateEncoded> {
     info!("encode_into:start");
+    let _timer = metrics::time_phase("encode_into");
+    let _span = enter_span!(
+        "encode_into",
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
     let config = SectorUpdateConfig::from_porep_config(porep_config);
 
     let p_aux = get_p_aux::<Tree>(sector_key_cache_path)?;
@@ -180,15 +185,12 @@ pub fn encode_into<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     comm_r_domain.write_bytes(&mut comm_r)?;
     comm_r_last_domain.write_bytes(&mut comm_r_last)?;
 
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
-    ensure!(
-        comm_r_last != [0; 32],
-        "Invalid all zero commitment (comm_r)"
-    );
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
+    ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
+    ensure!(comm_r_last != [0; 32], Error::ZeroCommitment("comm_r_last"));
     ensure!(
         verify_pieces(&comm_d, piece_infos, porep_config.into())?,
-        "pieces and comm_d do not match"
+        Error::PieceCommitmentMismatch
     );
 
     // Persist p_aux and t_aux into the new_cache_path here
@@ -217,6 +219,8 @@ pub fn decode_from<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     comm_d_new: Commitment,
 ) -> Result<()> {
     info!("decode_from:start");
+    let _timer = metrics::time_phase("decode_from");
+    let _span = enter_span!("decode_from", sector_size = u64::from(config.sector_size),);
 
     let p_aux = get_p_aux::<Tree>(sector_key_cache_path)?;
 
@@ -249,6 +253,8 @@ pub fn remove_encoded_data<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>
     comm_d_new: Commitment,
 ) -> Result<()> {
     info!("remove_data:start");
+    let _timer = metrics::time_phase("remove_data");
+    let _span = enter_span!("remove_data", sector_size = u64::from(config.sector_size),);
 
     let p_aux = get_p_aux::<Tree>(replica_cache_path)?;
     let t_aux = get_t_aux::<Tree>(replica_cache_path)?;
@@ -295,6 +301,11 @@ pub fn generate_single_partition_proof<Tree: 'static + MerkleTreeTrait<Hasher =
     replica_cache_path: &Path,
 ) -> Result<PartitionProof<Tree>> {
     info!("generate_single_partition_proof:start");
+    let _timer = metrics::time_phase("generate_single_partition_proof");
+    let _span = enter_span!(
+        "generate_single_partition_proof",
+        sector_size = u64::from(config.sector_size),
+    );
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -350,6 +361,7 @@ pub fn verify_single_partition_proof<Tree: 'static + MerkleTreeTrait<Hasher = Tr
     comm_d_new: Commitment,
 ) -> Result<bool> {
     info!("verify_single_partition_proof:start");
+    let _timer = metrics::time_phase("verify_single_partition_proof");
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -390,6 +402,11 @@ pub fn generate_partition_proofs<Tree: 'static + MerkleTreeTrait<Hasher = TreeRH
     replica_cache_path: &Path,
 ) -> Result<Vec<PartitionProof<Tree>>> {
     info!("generate_partition_proofs:start");
+    let _timer = metrics::time_phase("generate_partition_proofs");
+    let _span = enter_span!(
+        "generate_partition_proofs",
+        sector_size = u64::from(config.sector_size),
+    );
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -444,6 +461,7 @@ pub fn verify_partition_proofs<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHas
     comm_d_new: Commitment,
 ) -> Result<bool> {
     info!("verify_partition_proofs:start");
+    let _timer = metrics::time_phase("verify_partition_proofs");
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -480,6 +498,12 @@ pub fn generate_empty_sector_update_proof_with_vanilla<
     comm_d_new: Commitment,
 ) -> Result<EmptySectorUpdateProof> {
     info!("generate_empty_sector_update_proof_with_vanilla:start");
+    let _timer = metrics::time_phase("generate_empty_sector_update_proof_with_vanilla");
+    let _span = enter_span!(
+        "generate_empty_sector_update_proof_with_vanilla",
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -531,6 +555,12 @@ pub fn generate_empty_sector_update_proof<Tree: 'static + MerkleTreeTrait<Hasher
     replica_cache_path: &Path,
 ) -> Result<EmptySectorUpdateProof> {
     info!("generate_empty_sector_update_proof:start");
+    let _timer = metrics::time_phase("generate_empty_sector_update_proof");
+    let _span = enter_span!(
+        "generate_empty_sector_update_proof",
+        sector_size = u64::from(porep_config.sector_size),
+        api_version = %porep_config.api_version,
+    );
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -594,6 +624,7 @@ pub fn verify_empty_sector_update_proof<Tree: 'static + MerkleTreeTrait<Hasher =
     comm_d_new: Commitment,
 ) -> Result<bool> {
     info!("verify_empty_sector_update_proof:start");
+    let _timer = metrics::time_phase("verify_empty_sector_update_proof");
 
     let comm_r_old_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_old)?;
     let comm_r_new_safe = <TreeRHasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
@@ -8,7 +8,10 @@ use merkletree::merkle::{get_merkle_tree_leafs, get_merkle_tree_len};
 use storage_proofs_core::merkle::{get_base_tree_count, MerkleTreeTrait};
 use typenum::Unsigned;
 
-use crate::types::{Commitment, SectorSize};
+use crate::{
+    error::Error,
+    types::{Commitment, SectorSize},
+};
 
 pub fn as_safe_commitment<H: Domain, T: AsRef<str>>(
     comm: &[u8; 32],
@@ -16,7 +19,7 @@ pub fn as_safe_commitment<H: Domain, T: AsRef<str>>(
 ) -> Result<H> {
     bytes_into_fr(comm)
         .map(Into::into)
-        .with_context(|| format!("Invalid commitment ({})", commitment_name.as_ref(),))
+        .with_context(|| Error::InvalidCommitment(commitment_name.as_ref().to_string()))
 }
 
 pub fn commitment_from_fr(fr: Fr) -> Commitment {
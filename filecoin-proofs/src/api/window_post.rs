@@ -6,7 +6,9 @@ use log::info;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use storage_proofs_core::{
     compound_proof::{self, CompoundProof},
+    enter_span,
     merkle::MerkleTreeTrait,
+    metrics,
     multi_proof::MultiProof,
     sector::SectorId,
 };
@@ -20,6 +22,7 @@ use crate::{
         single_partition_vanilla_proofs,
     },
     caches::{get_post_params, get_post_verifying_key},
+    error::Error,
     parameters::window_post_setup_params,
     types::{
         ChallengeSeed, FallbackPoStSectorProof, PoStConfig, PrivateReplicaInfo, ProverId,
@@ -36,9 +39,15 @@ pub fn generate_window_post_with_vanilla<Tree: 'static + MerkleTreeTrait>(
     vanilla_proofs: Vec<FallbackPoStSectorProof<Tree>>,
 ) -> Result<SnarkProof> {
     info!("generate_window_post_with_vanilla:start");
+    let _timer = metrics::time_phase("generate_window_post_with_vanilla");
+    let _span = enter_span!(
+        "generate_window_post_with_vanilla",
+        sector_size = u64::from(post_config.sector_size),
+        api_version = %post_config.api_version,
+    );
     ensure!(
         post_config.typ == PoStType::Window,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
@@ -104,9 +113,15 @@ pub fn generate_window_post<Tree: 'static + MerkleTreeTrait>(
     prover_id: ProverId,
 ) -> Result<SnarkProof> {
     info!("generate_window_post:start");
+    let _timer = metrics::time_phase("generate_window_post");
+    let _span = enter_span!(
+        "generate_window_post",
+        sector_size = u64::from(post_config.sector_size),
+        api_version = %post_config.api_version,
+    );
     ensure!(
         post_config.typ == PoStType::Window,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let randomness_safe = as_safe_commitment(randomness, "randomness")?;
@@ -185,10 +200,11 @@ pub fn verify_window_post<Tree: 'static + MerkleTreeTrait>(
     proof: &[u8],
 ) -> Result<bool> {
     info!("verify_window_post:start");
+    let _timer = metrics::time_phase("verify_window_post");
 
     ensure!(
         post_config.typ == PoStType::Window,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let randomness_safe = as_safe_commitment(randomness, "randomness")?;
@@ -256,9 +272,15 @@ pub fn generate_single_window_post_with_vanilla<Tree: 'static + MerkleTreeTrait>
     partition_index: usize,
 ) -> Result<PartitionSnarkProof> {
     info!("generate_single_window_post_with_vanilla:start");
+    let _timer = metrics::time_phase("generate_single_window_post_with_vanilla");
+    let _span = enter_span!(
+        "generate_single_window_post_with_vanilla",
+        sector_size = u64::from(post_config.sector_size),
+        api_version = %post_config.api_version,
+    );
     ensure!(
         post_config.typ == PoStType::Window,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
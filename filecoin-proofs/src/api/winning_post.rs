@@ -3,7 +3,9 @@ use filecoin_hashers::Hasher;
 use log::info;
 use storage_proofs_core::{
     compound_proof::{self, CompoundProof},
+    enter_span,
     merkle::MerkleTreeTrait,
+    metrics,
     multi_proof::MultiProof,
     sector::SectorId,
 };
@@ -15,6 +17,7 @@ use storage_proofs_post::fallback::{
 use crate::{
     api::{as_safe_commitment, partition_vanilla_proofs},
     caches::{get_post_params, get_post_verifying_key},
+    error::Error,
     parameters::winning_post_setup_params,
     types::{
         ChallengeSeed, Commitment, FallbackPoStSectorProof, PoStConfig, PrivateReplicaInfo,
@@ -31,9 +34,15 @@ pub fn generate_winning_post_with_vanilla<Tree: 'static + MerkleTreeTrait>(
     vanilla_proofs: Vec<FallbackPoStSectorProof<Tree>>,
 ) -> Result<SnarkProof> {
     info!("generate_winning_post_with_vanilla:start");
+    let _timer = metrics::time_phase("generate_winning_post_with_vanilla");
+    let _span = enter_span!(
+        "generate_winning_post_with_vanilla",
+        sector_size = u64::from(post_config.sector_size),
+        api_version = %post_config.api_version,
+    );
     ensure!(
         post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     ensure!(
@@ -102,9 +111,15 @@ pub fn generate_winning_post<Tree: 'static + MerkleTreeTrait>(
     prover_id: ProverId,
 ) -> Result<SnarkProof> {
     info!("generate_winning_post:start");
+    let _timer = metrics::time_phase("generate_winning_post");
+    let _span = enter_span!(
+        "generate_winning_post",
+        sector_size = u64::from(post_config.sector_size),
+        api_version = %post_config.api_version,
+    );
     ensure!(
         post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     ensure!(
@@ -194,10 +209,11 @@ pub fn generate_winning_post_sector_challenge<Tree: MerkleTreeTrait>(
     prover_id: Commitment,
 ) -> Result<Vec<u64>> {
     info!("generate_winning_post_sector_challenge:start");
+    let _timer = metrics::time_phase("generate_winning_post_sector_challenge");
     ensure!(sector_set_size != 0, "empty sector set is invalid");
     ensure!(
         post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
 
     let prover_id_safe: <Tree::Hasher as Hasher>::Domain =
@@ -230,10 +246,11 @@ pub fn verify_winning_post<Tree: 'static + MerkleTreeTrait>(
     proof: &[u8],
 ) -> Result<bool> {
     info!("verify_winning_post:start");
+    let _timer = metrics::time_phase("verify_winning_post");
 
     ensure!(
         post_config.typ == PoStType::Winning,
-        "invalid post config type"
+        Error::ConfigMismatch("invalid post config type".to_string())
     );
     ensure!(
         post_config.sector_count == replicas.len(),
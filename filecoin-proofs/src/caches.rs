@@ -1,14 +1,17 @@
 use std::collections::HashMap;
+use std::mem;
 use std::sync::{Arc, Mutex};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use bellperson::groth16::{self, prepare_verifying_key};
-use blstrs::Bls12;
+use blstrs::{Bls12, G1Affine, G2Affine};
 use lazy_static::lazy_static;
 use log::{info, trace};
 use once_cell::sync::OnceCell;
 use rand::rngs::OsRng;
-use storage_proofs_core::{compound_proof::CompoundProof, merkle::MerkleTreeTrait};
+use storage_proofs_core::{
+    compound_proof::CompoundProof, merkle::MerkleTreeTrait, metrics, settings::SETTINGS,
+};
 use storage_proofs_porep::stacked::{StackedCompound, StackedDrg};
 use storage_proofs_post::fallback::{FallbackPoSt, FallbackPoStCircuit, FallbackPoStCompound};
 use storage_proofs_update::{
@@ -17,7 +20,8 @@ use storage_proofs_update::{
 };
 
 use crate::{
-    constants::{DefaultPieceHasher, PUBLISHED_SECTOR_SIZES},
+    constants::DefaultPieceHasher,
+    error::parameter_error,
     parameters::{public_params, window_post_public_params, winning_post_public_params},
     types::{PaddedBytesAmount, PoRepConfig, PoRepProofPartitions, PoStConfig, PoStType},
 };
@@ -27,127 +31,369 @@ pub type Bls12PreparedVerifyingKey = groth16::PreparedVerifyingKey<Bls12>;
 type Bls12ProverSRSKey = groth16::aggregate::ProverSRS<Bls12>;
 type Bls12VerifierSRSKey = groth16::aggregate::VerifierSRS<Bls12>;
 
-type Cache<G> = HashMap<String, Arc<G>>;
-type GrothMemCache = Cache<Bls12GrothParams>;
-type VerifyingKeyMemCache = Cache<Bls12PreparedVerifyingKey>;
-
-const FIP0013_MIN_SNARKS: usize = 64;
-const FIP0013_MAX_SNARKS: usize = 8192;
-
-// Note that proofs testing will use values under and over the FIP13
-// min and max, respectively.
-const PROOFS_TESTS_MIN_SNARKS: usize = FIP0013_MIN_SNARKS >> 5;
-const PROOFS_TESTS_MAX_SNARKS: usize = FIP0013_MAX_SNARKS << 1;
+type GrothMemCache = MemoryCache<Bls12GrothParams>;
+type VerifyingKeyMemCache = MemoryCache<Bls12PreparedVerifyingKey>;
 
 const SRS_IDENTIFIER: &str = "srs-key";
 const SRS_VERIFIER_IDENTIFIER: &str = "srs-verifying-key";
+const VERIFYING_KEY_IDENTIFIER: &str = "verifying-key";
+
+/// Window size of the multiscalar tables bellperson precomputes for a prover SRS key.
+const SRS_TABLE_WINDOW_SIZE: usize = 8;
+
+const GROTH_PARAMS_CACHE_NAME: &str = "groth_params";
+const VERIFYING_KEY_CACHE_NAME: &str = "verifying_key";
+const SRS_KEY_CACHE_NAME: &str = "srs_key";
+const SRS_VERIFIER_KEY_CACHE_NAME: &str = "srs_verifier_key";
 
 lazy_static! {
-    static ref GROTH_PARAM_MEMORY_CACHE: Mutex<GrothMemCache> = Default::default();
-    static ref VERIFYING_KEY_MEMORY_CACHE: Mutex<VerifyingKeyMemCache> = Default::default();
-    static ref SRS_KEY_MEMORY_CACHE: SRSCache<Bls12ProverSRSKey> =
-        SRSCache::with_defaults(SRS_IDENTIFIER);
+    static ref GROTH_PARAM_MEMORY_CACHE: GrothMemCache = MemoryCache::new(CacheLimits {
+        max_entries: SETTINGS.groth_param_memory_cache_max_entries,
+        max_bytes: SETTINGS.groth_param_memory_cache_max_bytes,
+    });
+    static ref VERIFYING_KEY_MEMORY_CACHE: VerifyingKeyMemCache = MemoryCache::new(CacheLimits {
+        max_entries: SETTINGS.verifying_key_memory_cache_max_entries,
+        max_bytes: 0,
+    });
+    static ref SRS_KEY_MEMORY_CACHE: SRSCache<Bls12ProverSRSKey> = SRSCache::new(CacheLimits {
+        max_entries: SETTINGS.srs_memory_cache_max_entries,
+        max_bytes: 0,
+    });
     static ref SRS_VERIFIER_KEY_MEMORY_CACHE: SRSCache<Bls12VerifierSRSKey> =
-        SRSCache::with_defaults(SRS_VERIFIER_IDENTIFIER);
+        SRSCache::new(CacheLimits {
+            max_entries: SETTINGS.srs_memory_cache_max_entries,
+            max_bytes: 0,
+        });
 }
 
-/// We have a separate SRSCache type for srs keys since they are
-/// cached differently (as a hashmap per type, keyed by identifier
-/// consisting of sector size and pow2 num proofs to aggregate).
-#[derive(Debug, Default)]
-pub struct SRSCache<G> {
-    data: HashMap<String, OnceCell<Arc<G>>>,
-}
-
-impl<G> SRSCache<G> {
-    /// Initializes the cache by pre-populating the internal map with
-    /// all supported keys that could be looked up at a later time.
-    pub fn with_defaults(identifier: &str) -> Self {
-        let mut data = HashMap::new();
-        let mut num_proofs_to_aggregate = PROOFS_TESTS_MIN_SNARKS;
-
-        loop {
-            for sector_size in &PUBLISHED_SECTOR_SIZES {
-                let key = format!(
-                    "STACKED[{}-{}]-{}",
-                    sector_size, num_proofs_to_aggregate, identifier,
-                );
-                trace!("inserting placeholder srs key with hash key {}", key);
-                data.insert(key, OnceCell::new());
+/// The in-memory caches, for adjusting their limits at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryCacheKind {
+    GrothParams,
+    VerifyingKeys,
+    SrsKeys,
+    SrsVerifierKeys,
+}
+
+/// Limits of a [`MemoryCache`], a limit of `0` means unbounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheLimits {
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// The memory accounted for an entry of a [`MemoryCache`].
+pub trait CacheWeight {
+    fn cache_weight(&self) -> u64;
+}
+
+impl CacheWeight for Bls12GrothParams {
+    fn cache_weight(&self) -> u64 {
+        // The mapped parameter file dominates, everything else is small in comparison.
+        (self.params.len() + mem::size_of::<Self>()) as u64
+    }
+}
+
+impl CacheWeight for Bls12PreparedVerifyingKey {
+    fn cache_weight(&self) -> u64 {
+        // The contents of prepared verifying keys are private to bellperson, so they are not
+        // weighed and the verifying key cache is only bounded by its number of entries.
+        0
+    }
+}
+
+impl CacheWeight for Bls12ProverSRSKey {
+    fn cache_weight(&self) -> u64 {
+        // The powers of alpha and beta are held in precomputed multiscalar tables, `2n` points
+        // each in G1 and `n` points each in G2, and once more in the commitment keys.
+        let table_entries = (1 << SRS_TABLE_WINDOW_SIZE) - 1;
+        let g1_points = 2 * 2 * self.n * table_entries + self.wkey.a.len() + self.wkey.b.len();
+        let g2_points = 2 * self.n * table_entries + self.vkey.a.len() + self.vkey.b.len();
+
+        (g1_points * mem::size_of::<G1Affine>()
+            + g2_points * mem::size_of::<G2Affine>()
+            + mem::size_of::<Self>()) as u64
+    }
+}
+
+impl CacheWeight for Bls12VerifierSRSKey {
+    fn cache_weight(&self) -> u64 {
+        // A handful of points, nothing on the heap.
+        mem::size_of::<Self>() as u64
+    }
+}
+
+struct CacheEntry<G> {
+    cell: Arc<OnceCell<Arc<G>>>,
+    weight: u64,
+    last_used: u64,
+}
+
+struct CacheInner<G> {
+    entries: HashMap<String, CacheEntry<G>>,
+    limits: CacheLimits,
+    bytes: u64,
+    clock: u64,
+}
+
+impl<G> CacheInner<G> {
+    fn over_limits(&self) -> bool {
+        (self.limits.max_entries != 0 && self.entries.len() > self.limits.max_entries)
+            || (self.limits.max_bytes != 0 && self.bytes > self.limits.max_bytes)
+    }
+
+    /// Evicts the least recently used entries until the cache is within its limits. Entries
+    /// still being generated and `keep` are never evicted.
+    fn evict_to_limits(&mut self, cache_name: &'static str, keep: &str) {
+        while self.over_limits() {
+            let victim = self
+                .entries
+                .iter()
+                .filter(|(key, entry)| key.as_str() != keep && entry.cell.get().is_some())
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match victim {
+                Some(key) => {
+                    info!("evicting {} from {} memory cache", key, cache_name);
+                    metrics::record_parameter_cache_eviction(cache_name);
+                    self.remove(&key);
+                }
+                None => break,
             }
+        }
+    }
 
-            num_proofs_to_aggregate <<= 1;
-            if num_proofs_to_aggregate > PROOFS_TESTS_MAX_SNARKS {
-                break;
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.bytes -= entry.weight;
+                true
             }
+            None => false,
         }
+    }
+}
 
-        Self { data }
+/// A least recently used cache of parameters, keyed by identifier.
+///
+/// Once the cache holds more than `max_entries` entries or more than `max_bytes`, the least
+/// recently used entries are evicted. Evicted entries stay alive for as long as a caller holds
+/// on to them. Concurrent lookups of the same identifier wait for a single generator.
+pub struct MemoryCache<G> {
+    inner: Mutex<CacheInner<G>>,
+}
+
+impl<G: CacheWeight> MemoryCache<G> {
+    pub fn new(limits: CacheLimits) -> Self {
+        MemoryCache {
+            inner: Mutex::new(CacheInner {
+                entries: HashMap::new(),
+                limits,
+                bytes: 0,
+                clock: 0,
+            }),
+        }
     }
 
-    /// Returns `None` for non existent entries, `Some(v)` for existing ones, where `v` is either
-    /// the result of running `generator` or already existing one.
-    pub fn get_or_init<F>(&self, key: &str, generator: F) -> Result<Option<&Arc<G>>>
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner<G>> {
+        self.inner.lock().expect("poisoned cache")
+    }
+
+    /// Returns the entry for `identifier`, running `generator` if there is none. The returned
+    /// flag is `true` if the entry was found in the cache.
+    pub fn get_or_insert<F>(
+        &self,
+        cache_name: &'static str,
+        identifier: &str,
+        generator: F,
+    ) -> Result<(Arc<G>, bool)>
     where
         F: FnOnce() -> Result<G>,
     {
-        if let Some(cell) = self.data.get(key) {
-            trace!("generating or waiting on specialize for {}", key);
-            let result =
-                cell.get_or_try_init(|| -> Result<Arc<G>> { Ok(Arc::new(generator()?)) })?;
-            return Ok(Some(result));
+        let cell = {
+            let mut inner = self.lock();
+            inner.clock += 1;
+            let clock = inner.clock;
+            let entry = inner
+                .entries
+                .entry(identifier.to_string())
+                .or_insert_with(|| CacheEntry {
+                    cell: Arc::new(OnceCell::new()),
+                    weight: 0,
+                    last_used: clock,
+                });
+            entry.last_used = clock;
+            entry.cell.clone()
+        };
+
+        let mut generated = false;
+        let value = cell
+            .get_or_try_init(|| -> Result<Arc<G>> {
+                generated = true;
+                Ok(Arc::new(generator().map_err(parameter_error)?))
+            })
+            .map(Clone::clone);
+
+        if value.is_err() || generated {
+            let mut inner = self.lock();
+            // The entry may have been evicted or replaced in the meantime.
+            let current = inner
+                .entries
+                .get(identifier)
+                .map(|entry| Arc::ptr_eq(&entry.cell, &cell))
+                .unwrap_or(false);
+            if current {
+                match value {
+                    Ok(ref value) => {
+                        let weight = value.cache_weight();
+                        if let Some(entry) = inner.entries.get_mut(identifier) {
+                            entry.weight = weight;
+                        }
+                        inner.bytes += weight;
+                        inner.evict_to_limits(cache_name, identifier);
+                    }
+                    Err(_) if cell.get().is_none() => {
+                        inner.entries.remove(identifier);
+                    }
+                    Err(_) => {}
+                }
+            }
         }
 
-        Ok(None)
+        Ok((value?, !generated))
+    }
+
+    /// Removes the entry for `identifier`, returns whether it was cached.
+    pub fn evict(&self, identifier: &str) -> bool {
+        self.lock().remove(identifier)
+    }
+
+    /// Removes all entries whose identifier matches `predicate`, returns how many were removed.
+    pub fn evict_matching<P: Fn(&str) -> bool>(&self, predicate: P) -> usize {
+        let mut inner = self.lock();
+        let keys: Vec<String> = inner
+            .entries
+            .keys()
+            .filter(|key| predicate(key))
+            .cloned()
+            .collect();
+        keys.iter().filter(|key| inner.remove(key)).count()
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.bytes = 0;
+    }
+
+    pub fn contains(&self, identifier: &str) -> bool {
+        self.lock()
+            .entries
+            .get(identifier)
+            .map(|entry| entry.cell.get().is_some())
+            .unwrap_or(false)
+    }
+
+    pub fn limits(&self) -> CacheLimits {
+        self.lock().limits
+    }
+
+    /// Changes the limits, evicting entries if the cache is over the new limits.
+    pub fn set_limits(&self, cache_name: &'static str, limits: CacheLimits) {
+        let mut inner = self.lock();
+        inner.limits = limits;
+        inner.evict_to_limits(cache_name, "");
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.lock();
+        CacheStats {
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+        }
+    }
+}
+
+/// We have a separate SRSCache type for srs keys since they are
+/// cached differently (as a map per type, keyed by identifier
+/// consisting of sector size and pow2 num proofs to aggregate).
+/// Entries are created on first use, for any sector size and
+/// number of proofs.
+pub struct SRSCache<G> {
+    cache: MemoryCache<G>,
+}
+
+impl<G: CacheWeight> SRSCache<G> {
+    pub fn new(limits: CacheLimits) -> Self {
+        SRSCache {
+            cache: MemoryCache::new(limits),
+        }
+    }
+
+    /// Returns the entry for `key`, which is either the result of running `generator` or the
+    /// already existing one.
+    pub fn get_or_init<F>(
+        &self,
+        cache_name: &'static str,
+        key: &str,
+        generator: F,
+    ) -> Result<Arc<G>>
+    where
+        F: FnOnce() -> Result<G>,
+    {
+        trace!("generating or waiting on specialize for {}", key);
+        let (entry, hit) = self.cache.get_or_insert(cache_name, key, generator)?;
+        metrics::record_parameter_cache_lookup(cache_name, hit);
+
+        Ok(entry)
+    }
+
+    pub fn cache(&self) -> &MemoryCache<G> {
+        &self.cache
     }
 }
 
 pub fn cache_lookup<F, G>(
-    cache_ref: &Mutex<Cache<G>>,
+    cache_name: &'static str,
+    cache_ref: &MemoryCache<G>,
     identifier: String,
     generator: F,
 ) -> Result<Arc<G>>
 where
     F: FnOnce() -> Result<G>,
-    G: Send + Sync,
+    G: CacheWeight + Send + Sync,
 {
     info!("trying parameters memory cache for: {}", &identifier);
-    {
-        let cache = (*cache_ref).lock().expect("poisoned cache");
-
-        if let Some(entry) = cache.get(&identifier) {
-            info!("found params in memory cache for {}", &identifier);
-            return Ok(entry.clone());
-        }
-    }
-
-    info!("no params in memory cache for {}", &identifier);
-
-    let new_entry = Arc::new(generator()?);
-    let res = new_entry.clone();
-    {
-        let cache = &mut (*cache_ref).lock().expect("poisoned cache");
-        cache.insert(identifier, new_entry);
+    let (entry, hit) = cache_ref.get_or_insert(cache_name, &identifier, || {
+        info!("no params in memory cache for {}", &identifier);
+        generator()
+    })?;
+    if hit {
+        info!("found params in memory cache for {}", &identifier);
     }
+    metrics::record_parameter_cache_lookup(cache_name, hit);
 
-    Ok(res)
+    Ok(entry)
 }
 
 pub fn srs_cache_lookup<F, G>(
+    cache_name: &'static str,
     cache_ref: &SRSCache<G>,
     identifier: String,
     generator: F,
 ) -> Result<Arc<G>>
 where
     F: FnOnce() -> Result<G>,
-    G: Send + Sync,
+    G: CacheWeight + Send + Sync,
 {
     trace!("srs_cache_lookup looking up {}", identifier);
-    if let Some(entry) = cache_ref.get_or_init(&identifier, generator)? {
-        return Ok(entry.clone());
-    }
-
-    panic!("unknown identifier {}", identifier);
+    cache_ref.get_or_init(cache_name, &identifier, generator)
 }
 
 #[inline]
@@ -155,7 +401,12 @@ pub fn lookup_groth_params<F>(identifier: String, generator: F) -> Result<Arc<Bl
 where
     F: FnOnce() -> Result<Bls12GrothParams>,
 {
-    cache_lookup(&*GROTH_PARAM_MEMORY_CACHE, identifier, generator)
+    cache_lookup(
+        GROTH_PARAMS_CACHE_NAME,
+        &*GROTH_PARAM_MEMORY_CACHE,
+        identifier,
+        generator,
+    )
 }
 
 #[inline]
@@ -166,8 +417,13 @@ pub fn lookup_verifying_key<F>(
 where
     F: FnOnce() -> Result<Bls12PreparedVerifyingKey>,
 {
-    let vk_identifier = format!("{}-verifying-key", &identifier);
-    cache_lookup(&*VERIFYING_KEY_MEMORY_CACHE, vk_identifier, generator)
+    let vk_identifier = format!("{}-{}", &identifier, VERIFYING_KEY_IDENTIFIER);
+    cache_lookup(
+        VERIFYING_KEY_CACHE_NAME,
+        &*VERIFYING_KEY_MEMORY_CACHE,
+        vk_identifier,
+        generator,
+    )
 }
 
 #[inline]
@@ -176,7 +432,12 @@ where
     F: FnOnce() -> Result<Bls12ProverSRSKey>,
 {
     let srs_identifier = format!("{}-{}", &identifier, SRS_IDENTIFIER);
-    srs_cache_lookup::<_, Bls12ProverSRSKey>(&*SRS_KEY_MEMORY_CACHE, srs_identifier, generator)
+    srs_cache_lookup::<_, Bls12ProverSRSKey>(
+        SRS_KEY_CACHE_NAME,
+        &*SRS_KEY_MEMORY_CACHE,
+        srs_identifier,
+        generator,
+    )
 }
 
 #[inline]
@@ -189,12 +450,47 @@ where
 {
     let srs_identifier = format!("{}-{}", &identifier, SRS_VERIFIER_IDENTIFIER);
     srs_cache_lookup::<_, Bls12VerifierSRSKey>(
+        SRS_VERIFIER_KEY_CACHE_NAME,
         &*SRS_VERIFIER_KEY_MEMORY_CACHE,
         srs_identifier,
         generator,
     )
 }
 
+fn stacked_cache_key(porep_config: PoRepConfig) -> String {
+    format!(
+        "STACKED[{}]",
+        usize::from(PaddedBytesAmount::from(porep_config))
+    )
+}
+
+fn post_cache_key(post_config: &PoStConfig) -> String {
+    let name = match post_config.typ {
+        PoStType::Winning => "WINNING_POST",
+        PoStType::Window => "WINDOW_POST",
+    };
+    format!(
+        "{}[{}]",
+        name,
+        usize::from(post_config.padded_sector_size())
+    )
+}
+
+fn empty_sector_update_cache_key(porep_config: PoRepConfig) -> String {
+    format!(
+        "SECTOR-UPDATE[{}]",
+        usize::from(PaddedBytesAmount::from(porep_config))
+    )
+}
+
+fn srs_cache_key(porep_config: PoRepConfig, num_proofs_to_aggregate: usize) -> String {
+    format!(
+        "STACKED[{}-{}]",
+        usize::from(PaddedBytesAmount::from(porep_config)),
+        num_proofs_to_aggregate,
+    )
+}
+
 pub fn get_stacked_params<Tree: 'static + MerkleTreeTrait>(
     porep_config: PoRepConfig,
 ) -> Result<Arc<Bls12GrothParams>> {
@@ -213,13 +509,7 @@ pub fn get_stacked_params<Tree: 'static + MerkleTreeTrait>(
         .map_err(Into::into)
     };
 
-    lookup_groth_params(
-        format!(
-            "STACKED[{}]",
-            usize::from(PaddedBytesAmount::from(porep_config))
-        ),
-        parameters_generator,
-    )
+    lookup_groth_params(stacked_cache_key(porep_config), parameters_generator)
 }
 
 pub fn get_post_params<Tree: 'static + MerkleTreeTrait>(
@@ -237,13 +527,7 @@ pub fn get_post_params<Tree: 'static + MerkleTreeTrait>(
                 .map_err(Into::into)
             };
 
-            Ok(lookup_groth_params(
-                format!(
-                    "WINNING_POST[{}]",
-                    usize::from(post_config.padded_sector_size())
-                ),
-                parameters_generator,
-            )?)
+            lookup_groth_params(post_cache_key(post_config), parameters_generator)
         }
         PoStType::Window => {
             let post_public_params = window_post_public_params::<Tree>(post_config)?;
@@ -256,13 +540,7 @@ pub fn get_post_params<Tree: 'static + MerkleTreeTrait>(
                 .map_err(Into::into)
             };
 
-            Ok(lookup_groth_params(
-                format!(
-                    "Window_POST[{}]",
-                    usize::from(post_config.padded_sector_size())
-                ),
-                parameters_generator,
-            )?)
+            lookup_groth_params(post_cache_key(post_config), parameters_generator)
         }
     }
 }
@@ -282,10 +560,7 @@ pub fn get_empty_sector_update_params<Tree: 'static + MerkleTreeTrait<Hasher = T
     };
 
     lookup_groth_params(
-        format!(
-            "SECTOR-UPDATE[{}]",
-            usize::from(PaddedBytesAmount::from(porep_config))
-        ),
+        empty_sector_update_cache_key(porep_config),
         parameters_generator,
     )
 }
@@ -308,13 +583,7 @@ pub fn get_stacked_verifying_key<Tree: 'static + MerkleTreeTrait>(
         Ok(prepare_verifying_key(&vk))
     };
 
-    lookup_verifying_key(
-        format!(
-            "STACKED[{}]",
-            usize::from(PaddedBytesAmount::from(porep_config))
-        ),
-        vk_generator,
-    )
+    lookup_verifying_key(stacked_cache_key(porep_config), vk_generator)
 }
 
 pub fn get_post_verifying_key<Tree: 'static + MerkleTreeTrait>(
@@ -332,13 +601,7 @@ pub fn get_post_verifying_key<Tree: 'static + MerkleTreeTrait>(
                 Ok(prepare_verifying_key(&vk))
             };
 
-            Ok(lookup_verifying_key(
-                format!(
-                    "WINNING_POST[{}]",
-                    usize::from(post_config.padded_sector_size())
-                ),
-                vk_generator,
-            )?)
+            lookup_verifying_key(post_cache_key(post_config), vk_generator)
         }
         PoStType::Window => {
             let post_public_params = window_post_public_params::<Tree>(post_config)?;
@@ -351,13 +614,7 @@ pub fn get_post_verifying_key<Tree: 'static + MerkleTreeTrait>(
                 Ok(prepare_verifying_key(&vk))
             };
 
-            Ok(lookup_verifying_key(
-                format!(
-                    "WINDOW_POST[{}]",
-                    usize::from(post_config.padded_sector_size())
-                ),
-                vk_generator,
-            )?)
+            lookup_verifying_key(post_cache_key(post_config), vk_generator)
         }
     }
 }
@@ -386,11 +643,7 @@ pub fn get_stacked_srs_key<Tree: 'static + MerkleTreeTrait>(
     };
 
     lookup_srs_key(
-        format!(
-            "STACKED[{}-{}]",
-            usize::from(PaddedBytesAmount::from(porep_config)),
-            num_proofs_to_aggregate,
-        ),
+        srs_cache_key(porep_config, num_proofs_to_aggregate),
         srs_generator,
     )
 }
@@ -421,11 +674,7 @@ pub fn get_stacked_srs_verifier_key<Tree: 'static + MerkleTreeTrait>(
     };
 
     lookup_srs_verifier_key(
-        format!(
-            "STACKED[{}-{}]",
-            usize::from(PaddedBytesAmount::from(porep_config)),
-            num_proofs_to_aggregate,
-        ),
+        srs_cache_key(porep_config, num_proofs_to_aggregate),
         srs_verifier_generator,
     )
 }
@@ -446,11 +695,264 @@ pub fn get_empty_sector_update_verifying_key<
         Ok(prepare_verifying_key(&vk))
     };
 
-    lookup_verifying_key(
-        format!(
-            "SECTOR-UPDATE[{}]",
-            usize::from(PaddedBytesAmount::from(porep_config))
-        ),
-        vk_generator,
-    )
+    lookup_verifying_key(empty_sector_update_cache_key(porep_config), vk_generator)
+}
+
+/// Changes the limits of one of the in-memory caches. The initial limits are taken from the
+/// `*_memory_cache_max_*` settings. Verifying keys are not weighed, so their cache can't be
+/// bounded by bytes.
+pub fn set_memory_cache_limits(kind: MemoryCacheKind, limits: CacheLimits) -> Result<()> {
+    match kind {
+        MemoryCacheKind::GrothParams => {
+            GROTH_PARAM_MEMORY_CACHE.set_limits(GROTH_PARAMS_CACHE_NAME, limits)
+        }
+        MemoryCacheKind::VerifyingKeys => {
+            ensure!(
+                limits.max_bytes == 0,
+                "the verifying key cache can't be bounded by bytes"
+            );
+            VERIFYING_KEY_MEMORY_CACHE.set_limits(VERIFYING_KEY_CACHE_NAME, limits)
+        }
+        MemoryCacheKind::SrsKeys => SRS_KEY_MEMORY_CACHE
+            .cache()
+            .set_limits(SRS_KEY_CACHE_NAME, limits),
+        MemoryCacheKind::SrsVerifierKeys => SRS_VERIFIER_KEY_MEMORY_CACHE
+            .cache()
+            .set_limits(SRS_VERIFIER_KEY_CACHE_NAME, limits),
+    }
+
+    Ok(())
+}
+
+pub fn memory_cache_stats(kind: MemoryCacheKind) -> CacheStats {
+    match kind {
+        MemoryCacheKind::GrothParams => GROTH_PARAM_MEMORY_CACHE.stats(),
+        MemoryCacheKind::VerifyingKeys => VERIFYING_KEY_MEMORY_CACHE.stats(),
+        MemoryCacheKind::SrsKeys => SRS_KEY_MEMORY_CACHE.cache().stats(),
+        MemoryCacheKind::SrsVerifierKeys => SRS_VERIFIER_KEY_MEMORY_CACHE.cache().stats(),
+    }
+}
+
+/// Empties all in-memory caches.
+pub fn clear_memory_caches() {
+    GROTH_PARAM_MEMORY_CACHE.clear();
+    VERIFYING_KEY_MEMORY_CACHE.clear();
+    SRS_KEY_MEMORY_CACHE.cache().clear();
+    SRS_VERIFIER_KEY_MEMORY_CACHE.cache().clear();
+}
+
+fn evict_verifying_key(identifier: &str) -> bool {
+    VERIFYING_KEY_MEMORY_CACHE.evict(&format!("{}-{}", identifier, VERIFYING_KEY_IDENTIFIER))
+}
+
+/// Evicts the PoRep Groth parameters, verifying key and SRS keys for the sector size of
+/// `porep_config` from memory, returns the number of evicted entries.
+pub fn evict_porep_params(porep_config: PoRepConfig) -> usize {
+    let key = stacked_cache_key(porep_config);
+    let srs_prefix = format!(
+        "STACKED[{}-",
+        usize::from(PaddedBytesAmount::from(porep_config))
+    );
+
+    GROTH_PARAM_MEMORY_CACHE.evict(&key) as usize
+        + evict_verifying_key(&key) as usize
+        + SRS_KEY_MEMORY_CACHE
+            .cache()
+            .evict_matching(|key| key.starts_with(&srs_prefix))
+        + SRS_VERIFIER_KEY_MEMORY_CACHE
+            .cache()
+            .evict_matching(|key| key.starts_with(&srs_prefix))
+}
+
+/// Evicts the PoSt Groth parameters and verifying key for `post_config` from memory, returns the
+/// number of evicted entries.
+pub fn evict_post_params(post_config: &PoStConfig) -> usize {
+    let key = post_cache_key(post_config);
+
+    GROTH_PARAM_MEMORY_CACHE.evict(&key) as usize + evict_verifying_key(&key) as usize
+}
+
+/// Evicts the empty sector update Groth parameters and verifying key for the sector size of
+/// `porep_config` from memory, returns the number of evicted entries.
+pub fn evict_empty_sector_update_params(porep_config: PoRepConfig) -> usize {
+    let key = empty_sector_update_cache_key(porep_config);
+
+    GROTH_PARAM_MEMORY_CACHE.evict(&key) as usize + evict_verifying_key(&key) as usize
+}
+
+/// Evicts all Groth parameters, verifying keys and SRS keys for `sector_size` from memory,
+/// returns the number of evicted entries.
+pub fn evict_sector_size(sector_size: u64) -> usize {
+    let key = format!("[{}]", sector_size);
+    let srs_prefix = format!("[{}-", sector_size);
+    let matches = |identifier: &str| identifier.contains(&key) || identifier.contains(&srs_prefix);
+
+    GROTH_PARAM_MEMORY_CACHE.evict_matching(matches)
+        + VERIFYING_KEY_MEMORY_CACHE.evict_matching(matches)
+        + SRS_KEY_MEMORY_CACHE.cache().evict_matching(matches)
+        + SRS_VERIFIER_KEY_MEMORY_CACHE
+            .cache()
+            .evict_matching(matches)
+}
+
+/// Loads the PoRep Groth parameters and verifying key for `porep_config` into memory.
+pub fn preload_porep_params<Tree: 'static + MerkleTreeTrait>(
+    porep_config: PoRepConfig,
+) -> Result<()> {
+    get_stacked_params::<Tree>(porep_config)?;
+    get_stacked_verifying_key::<Tree>(porep_config)?;
+
+    Ok(())
+}
+
+/// Loads the PoSt Groth parameters and verifying key for `post_config` into memory.
+pub fn preload_post_params<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+) -> Result<()> {
+    get_post_params::<Tree>(post_config)?;
+    get_post_verifying_key::<Tree>(post_config)?;
+
+    Ok(())
+}
+
+/// Loads the empty sector update Groth parameters and verifying key for `porep_config` into
+/// memory.
+pub fn preload_empty_sector_update_params<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    porep_config: PoRepConfig,
+) -> Result<()> {
+    get_empty_sector_update_params::<Tree>(porep_config)?;
+    get_empty_sector_update_verifying_key::<Tree>(porep_config)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::anyhow;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::TEST_SEED;
+
+    struct Blob(u64);
+
+    impl CacheWeight for Blob {
+        fn cache_weight(&self) -> u64 {
+            self.0
+        }
+    }
+
+    fn insert(cache: &MemoryCache<Blob>, key: &str, weight: u64) -> bool {
+        let (_, hit) = cache
+            .get_or_insert("test", key, || Ok(Blob(weight)))
+            .expect("failed to insert");
+        hit
+    }
+
+    #[test]
+    fn test_memory_cache_evicts_least_recently_used() {
+        let cache = MemoryCache::new(CacheLimits {
+            max_entries: 2,
+            max_bytes: 0,
+        });
+        assert!(!insert(&cache, "a", 1));
+        assert!(!insert(&cache, "b", 1));
+        // Touch `a`, so `b` is the least recently used entry.
+        assert!(insert(&cache, "a", 1));
+        assert!(!insert(&cache, "c", 1));
+
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("c"));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                entries: 2,
+                bytes: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_memory_cache_byte_limit() {
+        let cache = MemoryCache::new(CacheLimits {
+            max_entries: 0,
+            max_bytes: 100,
+        });
+        insert(&cache, "a", 40);
+        insert(&cache, "b", 40);
+        insert(&cache, "c", 40);
+        assert!(!cache.contains("a"));
+        assert_eq!(cache.stats().bytes, 80);
+
+        // An entry larger than the limit is kept until the next insertion.
+        insert(&cache, "d", 200);
+        assert!(cache.contains("d"));
+        assert_eq!(cache.stats().entries, 1);
+
+        cache.set_limits(
+            "test",
+            CacheLimits {
+                max_entries: 0,
+                max_bytes: 10,
+            },
+        );
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_memory_cache_evict_and_errors() {
+        let cache = MemoryCache::new(CacheLimits::default());
+        let err = cache
+            .get_or_insert("test", "a", || Err(anyhow!("no parameters")))
+            .map(|_| ())
+            .expect_err("generator error must be returned");
+        assert!(err.to_string().contains("no parameters"));
+        // Failed lookups leave no entry behind.
+        assert_eq!(cache.stats().entries, 0);
+
+        insert(&cache, "STACKED[2048-64]", 1);
+        insert(&cache, "STACKED[2048-128]", 1);
+        insert(&cache, "STACKED[4096-64]", 1);
+        assert_eq!(
+            cache.evict_matching(|key| key.starts_with("STACKED[2048-")),
+            2
+        );
+        assert!(cache.evict("STACKED[4096-64]"));
+        assert!(!cache.evict("STACKED[4096-64]"));
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_srs_key_weight() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let srs = groth16::aggregate::setup_fake_srs::<Bls12, _>(rng, 16);
+        let (prover_4, verifier_4) = srs.specialize(4);
+        let (prover_8, _) = srs.specialize(8);
+
+        // The multiscalar tables dominate the prover keys and grow with the number of proofs.
+        let tables_4 = (2 * 8 * mem::size_of::<G1Affine>() + 2 * 4 * mem::size_of::<G2Affine>())
+            * ((1 << SRS_TABLE_WINDOW_SIZE) - 1);
+        assert!(prover_4.cache_weight() > tables_4 as u64);
+        assert!(prover_8.cache_weight() > 2 * tables_4 as u64);
+        assert!(prover_8.cache_weight() < 3 * prover_4.cache_weight());
+        assert_eq!(
+            verifier_4.cache_weight(),
+            mem::size_of::<Bls12VerifierSRSKey>() as u64
+        );
+    }
+
+    #[test]
+    fn test_verifying_key_cache_has_no_byte_limit() {
+        let limits = CacheLimits {
+            max_entries: 0,
+            max_bytes: 1024,
+        };
+        assert!(set_memory_cache_limits(MemoryCacheKind::VerifyingKeys, limits).is_err());
+        assert!(set_memory_cache_limits(MemoryCacheKind::SrsKeys, limits).is_ok());
+        set_memory_cache_limits(MemoryCacheKind::SrsKeys, CacheLimits::default())
+            .expect("failed to reset limits");
+    }
 }
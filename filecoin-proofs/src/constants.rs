@@ -1,19 +1,21 @@
-use std::collections::HashMap;
-use std::sync::RwLock;
-
 pub use storage_proofs_core::drgraph::BASE_DEGREE as DRG_DEGREE;
+pub use storage_proofs_core::sector_registry::{
+    is_registered_sector_size, published_sector_sizes, registered_sector_sizes, sector_size_info,
+    SectorSizeInfo, TreeShape,
+};
 pub use storage_proofs_porep::stacked::EXP_DEGREE;
 
+use anyhow::Result;
 use filecoin_hashers::{poseidon::PoseidonHasher, sha256::Sha256Hasher, Hasher};
-use lazy_static::lazy_static;
 use storage_proofs_core::{
     merkle::{BinaryMerkleTree, LCTree, OctLCMerkleTree, OctMerkleTree},
+    sector_registry,
     util::NODE_SIZE,
     MAX_LEGACY_POREP_REGISTERED_PROOF_ID,
 };
-use typenum::{U0, U2, U8};
+use typenum::{U0, U2, U4, U8};
 
-use crate::types::UnpaddedBytesAmount;
+use crate::{caches, types::UnpaddedBytesAmount};
 
 pub const SECTOR_SIZE_2_KIB: u64 = 1 << 11;
 pub const SECTOR_SIZE_4_KIB: u64 = 1 << 12;
@@ -33,92 +35,30 @@ pub const WINDOW_POST_CHALLENGE_COUNT: usize = 10;
 
 pub const MAX_LEGACY_REGISTERED_SEAL_PROOF_ID: u64 = MAX_LEGACY_POREP_REGISTERED_PROOF_ID;
 
-/// Sector sizes for which parameters have been published.
-pub const PUBLISHED_SECTOR_SIZES: [u64; 10] = [
-    SECTOR_SIZE_2_KIB,
-    SECTOR_SIZE_4_KIB,
-    SECTOR_SIZE_16_KIB,
-    SECTOR_SIZE_32_KIB,
-    SECTOR_SIZE_8_MIB,
-    SECTOR_SIZE_16_MIB,
-    SECTOR_SIZE_512_MIB,
-    SECTOR_SIZE_1_GIB,
-    SECTOR_SIZE_32_GIB,
-    SECTOR_SIZE_64_GIB,
-];
+/// Registers a sector size, e.g. for a devnet, see [`sector_registry`]. Parameters of the sector
+/// size held in memory are evicted, as they do not match a changed registration.
+pub fn register_sector_size(info: SectorSizeInfo) -> Result<()> {
+    sector_registry::register_sector_size(info)?;
+    caches::evict_sector_size(info.sector_size);
+
+    Ok(())
+}
 
-lazy_static! {
-    pub static ref POREP_MINIMUM_CHALLENGES: RwLock<HashMap<u64, u64>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 2),
-            (SECTOR_SIZE_4_KIB, 2),
-            (SECTOR_SIZE_16_KIB, 2),
-            (SECTOR_SIZE_32_KIB, 2),
-            (SECTOR_SIZE_8_MIB, 2),
-            (SECTOR_SIZE_16_MIB, 2),
-            (SECTOR_SIZE_512_MIB, 2),
-            (SECTOR_SIZE_1_GIB, 2),
-            (SECTOR_SIZE_32_GIB, 176),
-            (SECTOR_SIZE_64_GIB, 176),
-        ]
-        .iter()
-        .copied()
-        .collect()
-    );
-    pub static ref POREP_PARTITIONS: RwLock<HashMap<u64, u8>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 1),
-            (SECTOR_SIZE_4_KIB, 1),
-            (SECTOR_SIZE_16_KIB, 1),
-            (SECTOR_SIZE_32_KIB, 1),
-            (SECTOR_SIZE_8_MIB, 1),
-            (SECTOR_SIZE_16_MIB, 1),
-            (SECTOR_SIZE_512_MIB, 1),
-            (SECTOR_SIZE_1_GIB, 1),
-            (SECTOR_SIZE_32_GIB, 10),
-            (SECTOR_SIZE_64_GIB, 10),
-        ]
-        .iter()
-        .copied()
-        .collect()
-    );
-    pub static ref LAYERS: RwLock<HashMap<u64, usize>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 2),
-            (SECTOR_SIZE_4_KIB, 2),
-            (SECTOR_SIZE_16_KIB, 2),
-            (SECTOR_SIZE_32_KIB, 2),
-            (SECTOR_SIZE_8_MIB, 2),
-            (SECTOR_SIZE_16_MIB, 2),
-            (SECTOR_SIZE_512_MIB, 2),
-            (SECTOR_SIZE_1_GIB, 2),
-            (SECTOR_SIZE_32_GIB, 11),
-            (SECTOR_SIZE_64_GIB, 11),
-        ]
-        .iter()
-        .copied()
-        .collect()
-    );
-    // These numbers must match those used for Window PoSt scheduling in the miner actor.
-    // Please coordinate changes with actor code.
-    // https://github.com/filecoin-project/specs-actors/blob/master/actors/abi/sector.go
-    pub static ref WINDOW_POST_SECTOR_COUNT: RwLock<HashMap<u64, usize>> = RwLock::new(
-        [
-            (SECTOR_SIZE_2_KIB, 2),
-            (SECTOR_SIZE_4_KIB, 2),
-            (SECTOR_SIZE_16_KIB, 2),
-            (SECTOR_SIZE_32_KIB, 2),
-            (SECTOR_SIZE_8_MIB, 2),
-            (SECTOR_SIZE_16_MIB, 2),
-            (SECTOR_SIZE_512_MIB, 2),
-            (SECTOR_SIZE_1_GIB, 2),
-            (SECTOR_SIZE_32_GIB, 2349), // this gives 125,279,217 constraints, fitting in a single partition
-            (SECTOR_SIZE_64_GIB, 2300), // this gives 129,887,900 constraints, fitting in a single partition
-        ]
-        .iter()
-        .copied()
-        .collect()
-    );
+/// Registers a sector size like [`register_sector_size`], but also replaces the registration of
+/// a published sector size, e.g. to benchmark other proof parameters.
+pub fn replace_sector_size(info: SectorSizeInfo) -> Result<()> {
+    sector_registry::replace_sector_size(info)?;
+    caches::evict_sector_size(info.sector_size);
+
+    Ok(())
+}
+
+/// Returns the tree shape of a registered sector size.
+/// Panics if provided with an unknown sector size.
+pub fn sector_shape(sector_size: u64) -> TreeShape {
+    sector_size_info(sector_size)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .shape
 }
 
 /// The size of a single snark proof.
@@ -148,6 +88,7 @@ pub type DefaultOctLCTree = OctLCMerkleTree<DefaultTreeHasher>;
 // Generic shapes
 pub type SectorShapeBase = LCTree<DefaultTreeHasher, U8, U0, U0>;
 pub type SectorShapeSub2 = LCTree<DefaultTreeHasher, U8, U2, U0>;
+pub type SectorShapeSub4 = LCTree<DefaultTreeHasher, U8, U4, U0>;
 pub type SectorShapeSub8 = LCTree<DefaultTreeHasher, U8, U8, U0>;
 pub type SectorShapeTop2 = LCTree<DefaultTreeHasher, U8, U8, U2>;
 
@@ -166,70 +107,61 @@ pub type SectorShape32GiB = SectorShapeSub8;
 pub type SectorShape32KiB = SectorShapeTop2;
 pub type SectorShape64GiB = SectorShapeTop2;
 
+fn has_sector_shape(sector_size: u64, shape: TreeShape) -> bool {
+    sector_size_info(sector_size)
+        .map(|info| info.shape == shape)
+        .unwrap_or(false)
+}
+
 pub fn is_sector_shape_base(sector_size: u64) -> bool {
-    matches!(
-        sector_size,
-        SECTOR_SIZE_2_KIB | SECTOR_SIZE_8_MIB | SECTOR_SIZE_512_MIB
-    )
+    has_sector_shape(sector_size, TreeShape::BASE)
 }
 
 pub fn is_sector_shape_sub2(sector_size: u64) -> bool {
-    matches!(
-        sector_size,
-        SECTOR_SIZE_4_KIB | SECTOR_SIZE_16_MIB | SECTOR_SIZE_1_GIB
-    )
+    has_sector_shape(sector_size, TreeShape::SUB2)
+}
+
+pub fn is_sector_shape_sub4(sector_size: u64) -> bool {
+    has_sector_shape(sector_size, TreeShape::SUB4)
 }
 
 pub fn is_sector_shape_sub8(sector_size: u64) -> bool {
-    matches!(sector_size, SECTOR_SIZE_16_KIB | SECTOR_SIZE_32_GIB)
+    has_sector_shape(sector_size, TreeShape::SUB8)
 }
 
 pub fn is_sector_shape_top2(sector_size: u64) -> bool {
-    matches!(sector_size, SECTOR_SIZE_32_KIB | SECTOR_SIZE_64_GIB)
+    has_sector_shape(sector_size, TreeShape::TOP2)
 }
 
-/// Calls a function with the type hint of the sector shape matching the provided sector.
+/// Calls a function with the type hint of the sector shape the provided sector size is
+/// registered with.
 /// Panics if provided with an unknown sector size.
 #[macro_export]
 macro_rules! with_shape {
     ($size:expr, $f:ident) => {
         with_shape!($size, $f,)
     };
-    ($size:expr, $f:ident, $($args:expr,)*) => {
-        match $size {
-            _x if $size == $crate::constants::SECTOR_SIZE_2_KIB => {
-              $f::<$crate::constants::SectorShape2KiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_4_KIB => {
-              $f::<$crate::constants::SectorShape4KiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_16_KIB => {
-              $f::<$crate::constants::SectorShape16KiB>($($args),*)
+    ($size:expr, $f:ident, $($args:expr,)*) => {{
+        let size: u64 = $size;
+        match $crate::constants::sector_shape(size) {
+            $crate::constants::TreeShape::BASE => {
+              $f::<$crate::constants::SectorShapeBase>($($args),*)
             },
-            _x if $size == $crate::constants::SECTOR_SIZE_32_KIB => {
-              $f::<$crate::constants::SectorShape32KiB>($($args),*)
+            $crate::constants::TreeShape::SUB2 => {
+              $f::<$crate::constants::SectorShapeSub2>($($args),*)
             },
-            _xx if $size == $crate::constants::SECTOR_SIZE_8_MIB => {
-              $f::<$crate::constants::SectorShape8MiB>($($args),*)
+            $crate::constants::TreeShape::SUB4 => {
+              $f::<$crate::constants::SectorShapeSub4>($($args),*)
             },
-            _xx if $size == $crate::constants::SECTOR_SIZE_16_MIB => {
-              $f::<$crate::constants::SectorShape16MiB>($($args),*)
+            $crate::constants::TreeShape::SUB8 => {
+              $f::<$crate::constants::SectorShapeSub8>($($args),*)
             },
-            _x if $size == $crate::constants::SECTOR_SIZE_512_MIB => {
-              $f::<$crate::constants::SectorShape512MiB>($($args),*)
+            $crate::constants::TreeShape::TOP2 => {
+              $f::<$crate::constants::SectorShapeTop2>($($args),*)
             },
-            _x if $size == $crate::constants::SECTOR_SIZE_1_GIB => {
-              $f::<$crate::constants::SectorShape1GiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_32_GIB => {
-              $f::<$crate::constants::SectorShape32GiB>($($args),*)
-            },
-            _x if $size == $crate::constants::SECTOR_SIZE_64_GIB => {
-              $f::<$crate::constants::SectorShape64GiB>($($args),*)
-            },
-            _ => panic!("unsupported sector size: {}", $size),
+            shape => panic!("unsupported tree shape {} of sector size {}", shape, size),
         }
-    };
+    }};
     ($size:expr, $f:ident, $($args:expr),*) => {
         with_shape!($size, $f, $($args,)*)
     };
@@ -0,0 +1,219 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use bincode::deserialize;
+use serde::de::DeserializeOwned;
+use storage_proofs_core::{error::Error as CoreError, metrics, sector::SectorId};
+
+/// Errors returned by the public API.
+///
+/// API functions return `anyhow::Result`, with these errors attached to the
+/// error chain (either as the root error or as context). Use [`Error::find`]
+/// to retrieve them, or [`recovery`] to decide how to handle a failure.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Missing cache file {}", _0.display())]
+    MissingCacheFile(PathBuf),
+    #[error("Corrupt cache file {}", _0.display())]
+    CorruptCacheFile(PathBuf),
+    #[error("Parameter file not found: {}", _0.display())]
+    ParameterNotFound(PathBuf),
+    #[error("Invalid parameter file (digest mismatch): {}", _0.display())]
+    ParameterDigestMismatch(PathBuf),
+    #[error("Invalid commitment ({0})")]
+    InvalidCommitment(String),
+    #[error("Invalid all zero commitment ({0})")]
+    ZeroCommitment(&'static str),
+    #[error("pieces and comm_d do not match")]
+    PieceCommitmentMismatch,
+    #[error("faulty sectors {:?}", _0)]
+    FaultySectors(Vec<SectorId>),
+    #[error("Config mismatch: {0}")]
+    ConfigMismatch(String),
+    #[error("IO error on {}: {}", path.display(), source)]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Core(#[from] CoreError),
+}
+
+/// How a caller should handle a failed API call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// The failure is likely transient, the call can be retried as is.
+    Retry,
+    /// The outputs of a previous phase are missing or invalid, that phase
+    /// needs to be run again.
+    RedoPhase,
+    /// The sector data can't be recovered, the sector(s) should be marked
+    /// as faulty.
+    MarkFaulty,
+    /// The failure requires operator intervention (e.g. missing parameters
+    /// or a misconfiguration), retrying won't help.
+    Abort,
+}
+
+impl Error {
+    /// Returns the first `Error` attached to the chain of `err`.
+    pub fn find(err: &anyhow::Error) -> Option<&Error> {
+        err.downcast_ref::<Error>()
+    }
+
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            Error::MissingCacheFile(_)
+            | Error::CorruptCacheFile(_)
+            | Error::InvalidCommitment(_)
+            | Error::ZeroCommitment(_)
+            | Error::PieceCommitmentMismatch => Recovery::RedoPhase,
+            Error::ParameterNotFound(_)
+            | Error::ParameterDigestMismatch(_)
+            | Error::ConfigMismatch(_) => Recovery::Abort,
+            Error::FaultySectors(_) => Recovery::MarkFaulty,
+            Error::Io { .. } => Recovery::Retry,
+            Error::Core(err) => core_recovery(err),
+        }
+    }
+
+    /// Maps errors of a cache file read to either `MissingCacheFile` or `Io`.
+    pub(crate) fn cache_file(path: &Path, err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            Error::MissingCacheFile(path.to_path_buf())
+        } else {
+            Error::Io {
+                path: path.to_path_buf(),
+                source: err,
+            }
+        }
+    }
+}
+
+fn core_recovery(err: &CoreError) -> Recovery {
+    match err {
+        CoreError::FaultySectors(_) => Recovery::MarkFaulty,
+        CoreError::Io(_) => Recovery::Retry,
+        CoreError::BadPieceCommitment
+        | CoreError::InvalidCommitment
+        | CoreError::MalformedMerkleTree
+        | CoreError::MerkleTreeGenerationError(_) => Recovery::RedoPhase,
+        _ => Recovery::Abort,
+    }
+}
+
+/// Classifies an error returned by the API, based on the typed errors found
+/// in its chain. Returns `None` if the error could not be classified.
+pub fn recovery(err: &anyhow::Error) -> Option<Recovery> {
+    if let Some(err) = err.downcast_ref::<Error>() {
+        return Some(err.recovery());
+    }
+    if let Some(err) = err.downcast_ref::<CoreError>() {
+        return Some(core_recovery(err));
+    }
+    if err.downcast_ref::<io::Error>().is_some() {
+        return Some(Recovery::Retry);
+    }
+
+    None
+}
+
+/// Returns the faulty sectors reported in the chain of `err`, if any.
+pub fn faulty_sectors(err: &anyhow::Error) -> Option<&[SectorId]> {
+    match err.downcast_ref::<Error>() {
+        Some(Error::FaultySectors(sectors)) => return Some(sectors),
+        Some(Error::Core(CoreError::FaultySectors(sectors))) => return Some(sectors),
+        _ => {}
+    }
+    match err.downcast_ref::<CoreError>() {
+        Some(CoreError::FaultySectors(sectors)) => Some(sectors),
+        _ => None,
+    }
+}
+
+/// Reads and deserializes a file from a sector's cache directory (e.g. `p_aux`
+/// or `t_aux`).
+pub(crate) fn read_cache_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let bytes = fs::read(path).map_err(|err| Error::cache_file(path, err))?;
+    metrics::record_bytes_read("cache", bytes.len() as u64);
+
+    deserialize(&bytes).map_err(|err| {
+        anyhow::Error::from(err).context(Error::CorruptCacheFile(path.to_path_buf()))
+    })
+}
+
+/// Attaches the matching typed error to a failed parameter lookup.
+pub(crate) fn parameter_error(err: anyhow::Error) -> anyhow::Error {
+    let typed = match err.downcast_ref::<CoreError>() {
+        Some(CoreError::MissingParameters(path)) => Error::ParameterNotFound(path.clone()),
+        Some(CoreError::InvalidParameters(path)) => {
+            Error::ParameterDigestMismatch(PathBuf::from(path))
+        }
+        _ => return err,
+    };
+
+    err.context(typed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Context;
+
+    #[test]
+    fn test_recovery_through_context() {
+        let err = Err::<(), _>(Error::MissingCacheFile(PathBuf::from("p_aux")))
+            .context("seal_commit_phase1 failed")
+            .context("outer context")
+            .expect_err("must fail");
+        assert_eq!(recovery(&err), Some(Recovery::RedoPhase));
+        assert!(matches!(
+            Error::find(&err),
+            Some(Error::MissingCacheFile(_))
+        ));
+
+        let sectors = vec![SectorId::from(1), SectorId::from(3)];
+        let err = anyhow::Error::from(CoreError::FaultySectors(sectors.clone()))
+            .context("generate_window_post failed");
+        assert_eq!(recovery(&err), Some(Recovery::MarkFaulty));
+        assert_eq!(faulty_sectors(&err), Some(&sectors[..]));
+
+        let err = parameter_error(
+            anyhow::Error::from(CoreError::MissingParameters(PathBuf::from("v28-x.params")))
+                .context("no cached parameters"),
+        );
+        assert_eq!(recovery(&err), Some(Recovery::Abort));
+        assert!(matches!(
+            Error::find(&err),
+            Some(Error::ParameterNotFound(_))
+        ));
+
+        let err = anyhow::anyhow!("unclassified failure");
+        assert_eq!(recovery(&err), None);
+        assert_eq!(faulty_sectors(&err), None);
+    }
+
+    #[test]
+    fn test_read_cache_file() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+
+        let path = dir.path().join("p_aux");
+        let err = read_cache_file::<u64>(&path).expect_err("must fail");
+        assert!(matches!(
+            Error::find(&err),
+            Some(Error::MissingCacheFile(_))
+        ));
+
+        fs::write(&path, [1u8]).expect("failed to write file");
+        let err = read_cache_file::<u64>(&path).expect_err("must fail");
+        assert!(matches!(
+            Error::find(&err),
+            Some(Error::CorruptCacheFile(_))
+        ));
+        assert_eq!(recovery(&err), Some(Recovery::RedoPhase));
+    }
+}
@@ -5,9 +5,11 @@
 
 pub mod caches;
 pub mod constants;
+pub mod error;
 pub mod param;
 pub mod parameters;
 pub mod pieces;
+pub mod registered;
 pub mod types;
 
 mod api;
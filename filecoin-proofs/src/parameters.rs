@@ -4,7 +4,7 @@ use storage_proofs_porep::stacked::{self, LayerChallenges, StackedDrg};
 use storage_proofs_post::fallback::{self, FallbackPoSt};
 
 use crate::{
-    constants::{DefaultPieceHasher, DRG_DEGREE, EXP_DEGREE, LAYERS, POREP_MINIMUM_CHALLENGES},
+    constants::{sector_size_info, DefaultPieceHasher, DRG_DEGREE, EXP_DEGREE},
     types::{MerkleTreeTrait, PaddedBytesAmount, PoStConfig},
 };
 
@@ -80,19 +80,24 @@ pub fn setup_params(
     porep_id: [u8; 32],
     api_version: ApiVersion,
 ) -> Result<stacked::SetupParams> {
-    let layer_challenges = select_challenges(
+    let sector_info = sector_size_info(u64::from(sector_bytes))?;
+    let mut layer_challenges = select_challenges(
         partitions,
-        *POREP_MINIMUM_CHALLENGES
-            .read()
-            .expect("POREP_MINIMUM_CHALLENGES poisoned")
-            .get(&u64::from(sector_bytes))
-            .expect("unknown sector size") as usize,
-        *LAYERS
-            .read()
-            .expect("LAYERS poisoned")
-            .get(&u64::from(sector_bytes))
-            .expect("unknown sector size"),
+        sector_info.porep_minimum_challenges_for(api_version)? as usize,
+        sector_info.layers,
     );
+    if api_version.supports_non_interactive_porep() {
+        layer_challenges = LayerChallenges::new_non_interactive(
+            layer_challenges.layers(),
+            layer_challenges.challenges_count_all(),
+        );
+    } else if api_version.supports_synthetic_porep() {
+        layer_challenges = LayerChallenges::new_synthetic(
+            layer_challenges.layers(),
+            layer_challenges.challenges_count_all(),
+            sector_info.synthetic_porep_challenges,
+        );
+    }
     let sector_bytes = u64::from(sector_bytes);
 
     ensure!(
@@ -134,7 +139,7 @@ fn select_challenges(
 mod tests {
     use super::*;
 
-    use crate::{DefaultOctLCTree, PoRepProofPartitions, PoStType};
+    use crate::{DefaultOctLCTree, PoRepProofPartitions, PoStType, SECTOR_SIZE_2_KIB};
 
     #[test]
     fn partition_layer_challenges_test() {
@@ -147,6 +152,53 @@ mod tests {
         assert_eq!(3, f(4));
     }
 
+    #[test]
+    fn test_synthetic_challenges_setup() {
+        let sector_bytes = PaddedBytesAmount(SECTOR_SIZE_2_KIB);
+        let challenges = |api_version| {
+            setup_params(sector_bytes, 1, [0; 32], api_version)
+                .expect("failed to setup params")
+                .layer_challenges
+        };
+
+        assert_eq!(challenges(ApiVersion::V1_1_0).synthetic_count(), None);
+        let synthetic = challenges(ApiVersion::V1_2_0);
+        assert_eq!(synthetic.synthetic_count(), Some(16));
+        assert_eq!(
+            synthetic.challenges_count_all(),
+            challenges(ApiVersion::V1_1_0).challenges_count_all()
+        );
+    }
+
+    #[test]
+    fn test_non_interactive_challenges_setup() {
+        let sector_bytes = PaddedBytesAmount(SECTOR_SIZE_2_KIB);
+        let info = sector_size_info(SECTOR_SIZE_2_KIB).expect("2KiB is not registered");
+        let partitions = info
+            .porep_partitions_for(ApiVersion::V1_3_0)
+            .expect("non-interactive porep is not registered");
+        assert_eq!(partitions, 2);
+
+        let interactive = setup_params(sector_bytes, 1, [0; 32], ApiVersion::V1_1_0)
+            .expect("failed to setup params")
+            .layer_challenges;
+        let non_interactive = setup_params(
+            sector_bytes,
+            partitions as usize,
+            [0; 32],
+            ApiVersion::V1_3_0,
+        )
+        .expect("failed to setup params")
+        .layer_challenges;
+        assert!(non_interactive.is_non_interactive());
+        assert_eq!(non_interactive.synthetic_count(), None);
+        // The additional partitions keep the circuit of interactive PoRep.
+        assert_eq!(
+            non_interactive.challenges_count_all(),
+            interactive.challenges_count_all()
+        );
+    }
+
     #[test]
     fn test_winning_post_params() {
         let config = PoStConfig {
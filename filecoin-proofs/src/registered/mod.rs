@@ -0,0 +1,147 @@
+//! Non-generic entry points, dispatching on a registered proof.
+//!
+//! The functions of the crate root are generic over the replica tree type. The functions of this
+//! module instead take a [`RegisteredSealProof`], [`RegisteredPoStProof`] or
+//! [`RegisteredUpdateProof`], derive the proof config from it and call the generic function for
+//! the tree shape registered for its sector size. Outputs which depend on the tree type are
+//! returned as enums over the supported shapes, and can be serialized for the next phase.
+//!
+//! [`RegisteredSealProof`]: crate::RegisteredSealProof
+//! [`RegisteredPoStProof`]: crate::RegisteredPoStProof
+//! [`RegisteredUpdateProof`]: crate::RegisteredUpdateProof
+
+use std::any::{type_name, Any};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{
+        SectorShapeBase, SectorShapeSub2, SectorShapeSub4, SectorShapeSub8, SectorShapeTop2,
+        TreeShape,
+    },
+    types::{self, Commitment},
+    MerkleTreeTrait, TreeRHasher,
+};
+
+mod post;
+mod seal;
+mod update;
+
+pub use post::*;
+pub use seal::*;
+pub use update::*;
+
+/// Declares an enum holding a `Generic<Tree>` for each supported tree shape.
+macro_rules! shaped {
+    ($(#[$attr:meta])* $name:ident, $($generic:ident)::+, $bound:path) => {
+        $(#[$attr])*
+        #[derive(Serialize, Deserialize)]
+        pub enum $name {
+            Base($($generic)::+<SectorShapeBase>),
+            Sub2($($generic)::+<SectorShapeSub2>),
+            Sub4($($generic)::+<SectorShapeSub4>),
+            Sub8($($generic)::+<SectorShapeSub8>),
+            Top2($($generic)::+<SectorShapeTop2>),
+        }
+
+        impl $name {
+            pub fn from_tree<Tree: 'static + $bound>(value: $($generic)::+<Tree>) -> Result<Self> {
+                let value: Box<dyn Any> = Box::new(value);
+                let shaped = match TreeShape::of::<Tree>() {
+                    TreeShape::BASE => value.downcast().map(|value| $name::Base(*value)),
+                    TreeShape::SUB2 => value.downcast().map(|value| $name::Sub2(*value)),
+                    TreeShape::SUB4 => value.downcast().map(|value| $name::Sub4(*value)),
+                    TreeShape::SUB8 => value.downcast().map(|value| $name::Sub8(*value)),
+                    TreeShape::TOP2 => value.downcast().map(|value| $name::Top2(*value)),
+                    _ => Err(value),
+                };
+                shaped.map_err(|_| anyhow!("unsupported tree type {}", type_name::<Tree>()))
+            }
+
+            pub fn into_tree<Tree: 'static + $bound>(self) -> Result<$($generic)::+<Tree>> {
+                let shape = self.shape();
+                let value: Box<dyn Any> = match self {
+                    $name::Base(value) => Box::new(value),
+                    $name::Sub2(value) => Box::new(value),
+                    $name::Sub4(value) => Box::new(value),
+                    $name::Sub8(value) => Box::new(value),
+                    $name::Top2(value) => Box::new(value),
+                };
+                value.downcast().map(|value| *value).map_err(|_| {
+                    anyhow!(
+                        "{} of tree shape {} used for tree type {}",
+                        stringify!($name),
+                        shape,
+                        type_name::<Tree>()
+                    )
+                })
+            }
+
+            pub fn shape(&self) -> TreeShape {
+                match self {
+                    $name::Base(_) => TreeShape::BASE,
+                    $name::Sub2(_) => TreeShape::SUB2,
+                    $name::Sub4(_) => TreeShape::SUB4,
+                    $name::Sub8(_) => TreeShape::SUB8,
+                    $name::Top2(_) => TreeShape::TOP2,
+                }
+            }
+        }
+    };
+}
+
+shaped!(
+    /// The output of [`seal_pre_commit_phase1`], for any tree shape.
+    #[derive(Debug)]
+    SealPreCommitPhase1Output,
+    types::SealPreCommitPhase1Output,
+    MerkleTreeTrait
+);
+
+shaped!(
+    /// The output of [`seal_commit_phase1`], for any tree shape.
+    #[derive(Debug)]
+    SealCommitPhase1Output,
+    types::SealCommitPhase1Output,
+    MerkleTreeTrait
+);
+
+shaped!(
+    /// A vanilla PoSt proof of a single sector, for any tree shape.
+    #[derive(Debug)]
+    FallbackPoStSectorProof,
+    types::FallbackPoStSectorProof,
+    MerkleTreeTrait
+);
+
+shaped!(
+    /// A vanilla empty sector update proof of a single partition, for any tree shape.
+    PartitionProof,
+    types::PartitionProof,
+    MerkleTreeTrait<Hasher = TreeRHasher>
+);
+
+/// The location of a replica to generate a PoSt over. The persistent aux of the replica is only
+/// read once the tree shape is known.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PrivateReplicaInfo {
+    pub replica: PathBuf,
+    pub comm_r: Commitment,
+    pub cache_dir: PathBuf,
+}
+
+impl PrivateReplicaInfo {
+    pub fn new(replica: PathBuf, comm_r: Commitment, cache_dir: PathBuf) -> Self {
+        PrivateReplicaInfo {
+            replica,
+            comm_r,
+            cache_dir,
+        }
+    }
+
+    fn to_tree<Tree: 'static + MerkleTreeTrait>(&self) -> Result<types::PrivateReplicaInfo<Tree>> {
+        types::PrivateReplicaInfo::new(self.replica.clone(), self.comm_r, self.cache_dir.clone())
+    }
+}
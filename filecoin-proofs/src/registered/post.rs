@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use storage_proofs_core::sector::SectorId;
+
+use crate::{
+    api,
+    registered::{FallbackPoStSectorProof, PrivateReplicaInfo},
+    types::{
+        self, ChallengeSeed, Commitment, MerkleTreeTrait, PartitionSnarkProof, ProverId,
+        PublicReplicaInfo, RegisteredPoStProof, SnarkProof,
+    },
+    with_shape,
+};
+
+/// Non-generic
+/// [`generate_winning_post_sector_challenge`](crate::generate_winning_post_sector_challenge).
+pub fn generate_winning_post_sector_challenge(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    sector_set_size: u64,
+    prover_id: Commitment,
+) -> Result<Vec<u64>> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_winning_post_sector_challenge_inner,
+        registered_proof,
+        randomness,
+        sector_set_size,
+        prover_id,
+    )
+}
+
+fn generate_winning_post_sector_challenge_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    sector_set_size: u64,
+    prover_id: Commitment,
+) -> Result<Vec<u64>> {
+    api::generate_winning_post_sector_challenge::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        sector_set_size,
+        prover_id,
+    )
+}
+
+/// Non-generic
+/// [`generate_fallback_sector_challenges`](crate::generate_fallback_sector_challenges).
+pub fn generate_fallback_sector_challenges(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    pub_sectors: &[SectorId],
+    prover_id: ProverId,
+) -> Result<BTreeMap<SectorId, Vec<u64>>> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_fallback_sector_challenges_inner,
+        registered_proof,
+        randomness,
+        pub_sectors,
+        prover_id,
+    )
+}
+
+fn generate_fallback_sector_challenges_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    pub_sectors: &[SectorId],
+    prover_id: ProverId,
+) -> Result<BTreeMap<SectorId, Vec<u64>>> {
+    api::generate_fallback_sector_challenges::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        pub_sectors,
+        prover_id,
+    )
+}
+
+/// Non-generic [`generate_single_vanilla_proof`](crate::generate_single_vanilla_proof).
+pub fn generate_single_vanilla_proof(
+    registered_proof: RegisteredPoStProof,
+    sector_id: SectorId,
+    replica: &PrivateReplicaInfo,
+    challenges: &[u64],
+) -> Result<FallbackPoStSectorProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_single_vanilla_proof_inner,
+        registered_proof,
+        sector_id,
+        replica,
+        challenges,
+    )
+}
+
+fn generate_single_vanilla_proof_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    sector_id: SectorId,
+    replica: &PrivateReplicaInfo,
+    challenges: &[u64],
+) -> Result<FallbackPoStSectorProof> {
+    let proof = api::generate_single_vanilla_proof::<Tree>(
+        &registered_proof.as_v1_config(),
+        sector_id,
+        &replica.to_tree()?,
+        challenges,
+    )?;
+    FallbackPoStSectorProof::from_tree(proof)
+}
+
+/// Non-generic
+/// [`generate_winning_post_with_vanilla`](crate::generate_winning_post_with_vanilla).
+pub fn generate_winning_post_with_vanilla(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+) -> Result<SnarkProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_winning_post_with_vanilla_inner,
+        registered_proof,
+        randomness,
+        prover_id,
+        vanilla_proofs,
+    )
+}
+
+fn generate_winning_post_with_vanilla_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+) -> Result<SnarkProof> {
+    api::generate_winning_post_with_vanilla::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        prover_id,
+        vanilla_proofs_to_tree(vanilla_proofs)?,
+    )
+}
+
+/// Non-generic [`generate_winning_post`](crate::generate_winning_post).
+pub fn generate_winning_post(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &[(SectorId, PrivateReplicaInfo)],
+    prover_id: ProverId,
+) -> Result<SnarkProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_winning_post_inner,
+        registered_proof,
+        randomness,
+        replicas,
+        prover_id,
+    )
+}
+
+fn generate_winning_post_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &[(SectorId, PrivateReplicaInfo)],
+    prover_id: ProverId,
+) -> Result<SnarkProof> {
+    let replicas = replicas
+        .iter()
+        .map(|(sector_id, replica)| Ok((*sector_id, replica.to_tree::<Tree>()?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    api::generate_winning_post::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        &replicas,
+        prover_id,
+    )
+}
+
+/// Non-generic [`verify_winning_post`](crate::verify_winning_post).
+pub fn verify_winning_post(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &[(SectorId, PublicReplicaInfo)],
+    prover_id: ProverId,
+    proof: &[u8],
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_winning_post_inner,
+        registered_proof,
+        randomness,
+        replicas,
+        prover_id,
+        proof,
+    )
+}
+
+fn verify_winning_post_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &[(SectorId, PublicReplicaInfo)],
+    prover_id: ProverId,
+    proof: &[u8],
+) -> Result<bool> {
+    api::verify_winning_post::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        replicas,
+        prover_id,
+        proof,
+    )
+}
+
+/// Non-generic
+/// [`generate_window_post_with_vanilla`](crate::generate_window_post_with_vanilla).
+pub fn generate_window_post_with_vanilla(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+) -> Result<SnarkProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_window_post_with_vanilla_inner,
+        registered_proof,
+        randomness,
+        prover_id,
+        vanilla_proofs,
+    )
+}
+
+fn generate_window_post_with_vanilla_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+) -> Result<SnarkProof> {
+    api::generate_window_post_with_vanilla::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        prover_id,
+        vanilla_proofs_to_tree(vanilla_proofs)?,
+    )
+}
+
+/// Non-generic [`generate_window_post`](crate::generate_window_post).
+pub fn generate_window_post(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    prover_id: ProverId,
+) -> Result<SnarkProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_window_post_inner,
+        registered_proof,
+        randomness,
+        replicas,
+        prover_id,
+    )
+}
+
+fn generate_window_post_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    prover_id: ProverId,
+) -> Result<SnarkProof> {
+    let replicas = replicas
+        .iter()
+        .map(|(sector_id, replica)| Ok((*sector_id, replica.to_tree::<Tree>()?)))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    api::generate_window_post::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        &replicas,
+        prover_id,
+    )
+}
+
+/// Non-generic [`verify_window_post`](crate::verify_window_post).
+pub fn verify_window_post(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    prover_id: ProverId,
+    proof: &[u8],
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_window_post_inner,
+        registered_proof,
+        randomness,
+        replicas,
+        prover_id,
+        proof,
+    )
+}
+
+fn verify_window_post_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    prover_id: ProverId,
+    proof: &[u8],
+) -> Result<bool> {
+    api::verify_window_post::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        replicas,
+        prover_id,
+        proof,
+    )
+}
+
+/// Non-generic
+/// [`generate_single_window_post_with_vanilla`](crate::generate_single_window_post_with_vanilla).
+pub fn generate_single_window_post_with_vanilla(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+    partition_index: usize,
+) -> Result<PartitionSnarkProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_single_window_post_with_vanilla_inner,
+        registered_proof,
+        randomness,
+        prover_id,
+        vanilla_proofs,
+        partition_index,
+    )
+}
+
+fn generate_single_window_post_with_vanilla_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredPoStProof,
+    randomness: &ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+    partition_index: usize,
+) -> Result<PartitionSnarkProof> {
+    api::generate_single_window_post_with_vanilla::<Tree>(
+        &registered_proof.as_v1_config(),
+        randomness,
+        prover_id,
+        vanilla_proofs_to_tree(vanilla_proofs)?,
+        partition_index,
+    )
+}
+
+fn vanilla_proofs_to_tree<Tree: 'static + MerkleTreeTrait>(
+    vanilla_proofs: Vec<FallbackPoStSectorProof>,
+) -> Result<Vec<types::FallbackPoStSectorProof<Tree>>> {
+    vanilla_proofs
+        .into_iter()
+        .map(FallbackPoStSectorProof::into_tree)
+        .collect()
+}
@@ -0,0 +1,483 @@
+use std::path::Path;
+
+use anyhow::Result;
+use blstrs::Scalar as Fr;
+use storage_proofs_core::sector::SectorId;
+
+use crate::{
+    api,
+    registered::{SealCommitPhase1Output, SealPreCommitPhase1Output},
+    types::{
+        AggregateSnarkProof, Commitment, MerkleTreeTrait, PieceInfo, ProverId, RegisteredSealProof,
+        SealCommitOutput, SealPreCommitOutput, SealPreCommitPhase1Input, Ticket, UnpaddedByteIndex,
+        UnpaddedBytesAmount,
+    },
+    with_shape,
+};
+
+/// Non-generic [`seal_pre_commit_phase1`](crate::seal_pre_commit_phase1).
+#[allow(clippy::too_many_arguments)]
+pub fn seal_pre_commit_phase1(
+    registered_proof: RegisteredSealProof,
+    cache_path: &Path,
+    in_path: &Path,
+    out_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+) -> Result<SealPreCommitPhase1Output> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        seal_pre_commit_phase1_inner,
+        registered_proof,
+        cache_path,
+        in_path,
+        out_path,
+        prover_id,
+        sector_id,
+        ticket,
+        piece_infos,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn seal_pre_commit_phase1_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    cache_path: &Path,
+    in_path: &Path,
+    out_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+) -> Result<SealPreCommitPhase1Output> {
+    let output = api::seal_pre_commit_phase1::<_, _, _, Tree>(
+        registered_proof.as_v1_config(),
+        cache_path,
+        in_path,
+        out_path,
+        prover_id,
+        sector_id,
+        ticket,
+        piece_infos,
+    )?;
+    SealPreCommitPhase1Output::from_tree(output)
+}
+
+/// Non-generic [`seal_pre_commit_phase1_lockstep`](crate::seal_pre_commit_phase1_lockstep).
+pub fn seal_pre_commit_phase1_lockstep(
+    registered_proof: RegisteredSealProof,
+    sectors: &[SealPreCommitPhase1Input],
+) -> Result<Vec<SealPreCommitPhase1Output>> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        seal_pre_commit_phase1_lockstep_inner,
+        registered_proof,
+        sectors,
+    )
+}
+
+fn seal_pre_commit_phase1_lockstep_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    sectors: &[SealPreCommitPhase1Input],
+) -> Result<Vec<SealPreCommitPhase1Output>> {
+    api::seal_pre_commit_phase1_lockstep::<Tree>(registered_proof.as_v1_config(), sectors)?
+        .into_iter()
+        .map(SealPreCommitPhase1Output::from_tree)
+        .collect()
+}
+
+/// Non-generic [`seal_pre_commit_phase2`](crate::seal_pre_commit_phase2).
+pub fn seal_pre_commit_phase2(
+    registered_proof: RegisteredSealProof,
+    phase1_output: SealPreCommitPhase1Output,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<SealPreCommitOutput> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        seal_pre_commit_phase2_inner,
+        registered_proof,
+        phase1_output,
+        cache_path,
+        replica_path,
+    )
+}
+
+fn seal_pre_commit_phase2_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    phase1_output: SealPreCommitPhase1Output,
+    cache_path: &Path,
+    replica_path: &Path,
+) -> Result<SealPreCommitOutput> {
+    api::seal_pre_commit_phase2::<_, _, Tree>(
+        registered_proof.as_v1_config(),
+        phase1_output.into_tree()?,
+        cache_path,
+        replica_path,
+    )
+}
+
+/// Non-generic [`seal_commit_phase1`](crate::seal_commit_phase1).
+#[allow(clippy::too_many_arguments)]
+pub fn seal_commit_phase1(
+    registered_proof: RegisteredSealProof,
+    cache_path: &Path,
+    replica_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    pre_commit: SealPreCommitOutput,
+    piece_infos: &[PieceInfo],
+) -> Result<SealCommitPhase1Output> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        seal_commit_phase1_inner,
+        registered_proof,
+        cache_path,
+        replica_path,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        pre_commit,
+        piece_infos,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn seal_commit_phase1_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    cache_path: &Path,
+    replica_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    pre_commit: SealPreCommitOutput,
+    piece_infos: &[PieceInfo],
+) -> Result<SealCommitPhase1Output> {
+    let output = api::seal_commit_phase1::<_, Tree>(
+        registered_proof.as_v1_config(),
+        cache_path,
+        replica_path,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        pre_commit,
+        piece_infos,
+    )?;
+    SealCommitPhase1Output::from_tree(output)
+}
+
+/// Non-generic [`seal_commit_phase2`](crate::seal_commit_phase2).
+pub fn seal_commit_phase2(
+    registered_proof: RegisteredSealProof,
+    phase1_output: SealCommitPhase1Output,
+    prover_id: ProverId,
+    sector_id: SectorId,
+) -> Result<SealCommitOutput> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        seal_commit_phase2_inner,
+        registered_proof,
+        phase1_output,
+        prover_id,
+        sector_id,
+    )
+}
+
+fn seal_commit_phase2_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    phase1_output: SealCommitPhase1Output,
+    prover_id: ProverId,
+    sector_id: SectorId,
+) -> Result<SealCommitOutput> {
+    api::seal_commit_phase2::<Tree>(
+        registered_proof.as_v1_config(),
+        phase1_output.into_tree()?,
+        prover_id,
+        sector_id,
+    )
+}
+
+/// Non-generic [`get_seal_inputs`](crate::get_seal_inputs).
+pub fn get_seal_inputs(
+    registered_proof: RegisteredSealProof,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<Vec<Fr>>> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        get_seal_inputs_inner,
+        registered_proof,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )
+}
+
+fn get_seal_inputs_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<Vec<Fr>>> {
+    api::get_seal_inputs::<Tree>(
+        registered_proof.as_v1_config(),
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )
+}
+
+/// Non-generic [`aggregate_seal_commit_proofs`](crate::aggregate_seal_commit_proofs).
+pub fn aggregate_seal_commit_proofs(
+    registered_proof: RegisteredSealProof,
+    comm_rs: &[[u8; 32]],
+    seeds: &[[u8; 32]],
+    commit_outputs: &[SealCommitOutput],
+) -> Result<AggregateSnarkProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        aggregate_seal_commit_proofs_inner,
+        registered_proof,
+        comm_rs,
+        seeds,
+        commit_outputs,
+    )
+}
+
+fn aggregate_seal_commit_proofs_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    comm_rs: &[[u8; 32]],
+    seeds: &[[u8; 32]],
+    commit_outputs: &[SealCommitOutput],
+) -> Result<AggregateSnarkProof> {
+    api::aggregate_seal_commit_proofs::<Tree>(
+        registered_proof.as_v1_config(),
+        comm_rs,
+        seeds,
+        commit_outputs,
+    )
+}
+
+/// Non-generic
+/// [`verify_aggregate_seal_commit_proofs`](crate::verify_aggregate_seal_commit_proofs).
+pub fn verify_aggregate_seal_commit_proofs(
+    registered_proof: RegisteredSealProof,
+    aggregate_proof_bytes: AggregateSnarkProof,
+    comm_rs: &[[u8; 32]],
+    seeds: &[[u8; 32]],
+    commit_inputs: Vec<Vec<Fr>>,
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_aggregate_seal_commit_proofs_inner,
+        registered_proof,
+        aggregate_proof_bytes,
+        comm_rs,
+        seeds,
+        commit_inputs,
+    )
+}
+
+fn verify_aggregate_seal_commit_proofs_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    aggregate_proof_bytes: AggregateSnarkProof,
+    comm_rs: &[[u8; 32]],
+    seeds: &[[u8; 32]],
+    commit_inputs: Vec<Vec<Fr>>,
+) -> Result<bool> {
+    api::verify_aggregate_seal_commit_proofs::<Tree>(
+        registered_proof.as_v1_config(),
+        aggregate_proof_bytes,
+        comm_rs,
+        seeds,
+        commit_inputs,
+    )
+}
+
+/// Non-generic [`verify_seal`](crate::verify_seal).
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal(
+    registered_proof: RegisteredSealProof,
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    proof_vec: &[u8],
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_seal_inner,
+        registered_proof,
+        comm_r_in,
+        comm_d_in,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        proof_vec,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn verify_seal_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    proof_vec: &[u8],
+) -> Result<bool> {
+    api::verify_seal::<Tree>(
+        registered_proof.as_v1_config(),
+        comm_r_in,
+        comm_d_in,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        proof_vec,
+    )
+}
+
+/// Non-generic [`verify_batch_seal`](crate::verify_batch_seal).
+#[allow(clippy::too_many_arguments)]
+pub fn verify_batch_seal(
+    registered_proof: RegisteredSealProof,
+    comm_r_ins: &[Commitment],
+    comm_d_ins: &[Commitment],
+    prover_ids: &[ProverId],
+    sector_ids: &[SectorId],
+    tickets: &[Ticket],
+    seeds: &[Ticket],
+    proof_vecs: &[&[u8]],
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_batch_seal_inner,
+        registered_proof,
+        comm_r_ins,
+        comm_d_ins,
+        prover_ids,
+        sector_ids,
+        tickets,
+        seeds,
+        proof_vecs,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn verify_batch_seal_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    comm_r_ins: &[Commitment],
+    comm_d_ins: &[Commitment],
+    prover_ids: &[ProverId],
+    sector_ids: &[SectorId],
+    tickets: &[Ticket],
+    seeds: &[Ticket],
+    proof_vecs: &[&[u8]],
+) -> Result<bool> {
+    api::verify_batch_seal::<Tree>(
+        registered_proof.as_v1_config(),
+        comm_r_ins,
+        comm_d_ins,
+        prover_ids,
+        sector_ids,
+        tickets,
+        seeds,
+        proof_vecs,
+    )
+}
+
+/// Non-generic [`get_unsealed_range`](crate::get_unsealed_range).
+#[allow(clippy::too_many_arguments)]
+pub fn get_unsealed_range(
+    registered_proof: RegisteredSealProof,
+    cache_path: &Path,
+    sealed_path: &Path,
+    output_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    comm_d: Commitment,
+    ticket: Ticket,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<UnpaddedBytesAmount> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        get_unsealed_range_inner,
+        registered_proof,
+        cache_path,
+        sealed_path,
+        output_path,
+        prover_id,
+        sector_id,
+        comm_d,
+        ticket,
+        offset,
+        num_bytes,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn get_unsealed_range_inner<Tree: 'static + MerkleTreeTrait>(
+    registered_proof: RegisteredSealProof,
+    cache_path: &Path,
+    sealed_path: &Path,
+    output_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    comm_d: Commitment,
+    ticket: Ticket,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<UnpaddedBytesAmount> {
+    api::get_unsealed_range::<_, Tree>(
+        registered_proof.as_v1_config(),
+        cache_path,
+        sealed_path,
+        output_path,
+        prover_id,
+        sector_id,
+        comm_d,
+        ticket,
+        offset,
+        num_bytes,
+    )
+}
+
+/// Non-generic [`clear_cache`](crate::clear_cache).
+pub fn clear_cache(registered_proof: RegisteredSealProof, cache_dir: &Path) -> Result<()> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        clear_cache_inner,
+        cache_dir
+    )
+}
+
+fn clear_cache_inner<Tree: MerkleTreeTrait>(cache_dir: &Path) -> Result<()> {
+    api::clear_cache::<Tree>(cache_dir)
+}
@@ -0,0 +1,363 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{
+    api::{self, TreeRHasher},
+    registered::PartitionProof,
+    types::{
+        self, Commitment, EmptySectorUpdateEncoded, EmptySectorUpdateProof, MerkleTreeTrait,
+        PieceInfo, RegisteredUpdateProof, SectorUpdateConfig,
+    },
+    with_shape,
+};
+
+/// Non-generic [`encode_into`](crate::encode_into).
+pub fn encode_into(
+    registered_proof: RegisteredUpdateProof,
+    new_replica_path: &Path,
+    new_cache_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    staged_data_path: &Path,
+    piece_infos: &[PieceInfo],
+) -> Result<EmptySectorUpdateEncoded> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        encode_into_inner,
+        registered_proof,
+        new_replica_path,
+        new_cache_path,
+        sector_key_path,
+        sector_key_cache_path,
+        staged_data_path,
+        piece_infos,
+    )
+}
+
+fn encode_into_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    registered_proof: RegisteredUpdateProof,
+    new_replica_path: &Path,
+    new_cache_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    staged_data_path: &Path,
+    piece_infos: &[PieceInfo],
+) -> Result<EmptySectorUpdateEncoded> {
+    api::encode_into::<Tree>(
+        registered_proof.as_v1_config(),
+        new_replica_path,
+        new_cache_path,
+        sector_key_path,
+        sector_key_cache_path,
+        staged_data_path,
+        piece_infos,
+    )
+}
+
+/// Non-generic [`decode_from`](crate::decode_from).
+pub fn decode_from(
+    registered_proof: RegisteredUpdateProof,
+    out_data_path: &Path,
+    replica_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    comm_d_new: Commitment,
+) -> Result<()> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        decode_from_inner,
+        registered_proof,
+        out_data_path,
+        replica_path,
+        sector_key_path,
+        sector_key_cache_path,
+        comm_d_new,
+    )
+}
+
+fn decode_from_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    registered_proof: RegisteredUpdateProof,
+    out_data_path: &Path,
+    replica_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    comm_d_new: Commitment,
+) -> Result<()> {
+    api::decode_from::<Tree>(
+        update_config(registered_proof),
+        out_data_path,
+        replica_path,
+        sector_key_path,
+        sector_key_cache_path,
+        comm_d_new,
+    )
+}
+
+/// Non-generic [`remove_encoded_data`](crate::remove_encoded_data).
+#[allow(clippy::too_many_arguments)]
+pub fn remove_encoded_data(
+    registered_proof: RegisteredUpdateProof,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+    data_path: &Path,
+    comm_d_new: Commitment,
+) -> Result<()> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        remove_encoded_data_inner,
+        registered_proof,
+        sector_key_path,
+        sector_key_cache_path,
+        replica_path,
+        replica_cache_path,
+        data_path,
+        comm_d_new,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn remove_encoded_data_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    registered_proof: RegisteredUpdateProof,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+    data_path: &Path,
+    comm_d_new: Commitment,
+) -> Result<()> {
+    api::remove_encoded_data::<Tree>(
+        update_config(registered_proof),
+        sector_key_path,
+        sector_key_cache_path,
+        replica_path,
+        replica_cache_path,
+        data_path,
+        comm_d_new,
+    )
+}
+
+/// Non-generic [`generate_partition_proofs`](crate::generate_partition_proofs).
+#[allow(clippy::too_many_arguments)]
+pub fn generate_partition_proofs(
+    registered_proof: RegisteredUpdateProof,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+) -> Result<Vec<PartitionProof>> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_partition_proofs_inner,
+        registered_proof,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+        sector_key_path,
+        sector_key_cache_path,
+        replica_path,
+        replica_cache_path,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_partition_proofs_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    registered_proof: RegisteredUpdateProof,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+) -> Result<Vec<PartitionProof>> {
+    api::generate_partition_proofs::<Tree>(
+        update_config(registered_proof),
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+        sector_key_path,
+        sector_key_cache_path,
+        replica_path,
+        replica_cache_path,
+    )?
+    .into_iter()
+    .map(PartitionProof::from_tree)
+    .collect()
+}
+
+/// Non-generic [`verify_partition_proofs`](crate::verify_partition_proofs).
+pub fn verify_partition_proofs(
+    registered_proof: RegisteredUpdateProof,
+    proofs: Vec<PartitionProof>,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_partition_proofs_inner,
+        registered_proof,
+        proofs,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+fn verify_partition_proofs_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    registered_proof: RegisteredUpdateProof,
+    proofs: Vec<PartitionProof>,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    api::verify_partition_proofs::<Tree>(
+        update_config(registered_proof),
+        &partition_proofs_to_tree(proofs)?,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+/// Non-generic
+/// [`generate_empty_sector_update_proof_with_vanilla`](crate::generate_empty_sector_update_proof_with_vanilla).
+pub fn generate_empty_sector_update_proof_with_vanilla(
+    registered_proof: RegisteredUpdateProof,
+    vanilla_proofs: Vec<PartitionProof>,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<EmptySectorUpdateProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_empty_sector_update_proof_with_vanilla_inner,
+        registered_proof,
+        vanilla_proofs,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+fn generate_empty_sector_update_proof_with_vanilla_inner<
+    Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>,
+>(
+    registered_proof: RegisteredUpdateProof,
+    vanilla_proofs: Vec<PartitionProof>,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<EmptySectorUpdateProof> {
+    api::generate_empty_sector_update_proof_with_vanilla::<Tree>(
+        registered_proof.as_v1_config(),
+        partition_proofs_to_tree(vanilla_proofs)?,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+/// Non-generic
+/// [`generate_empty_sector_update_proof`](crate::generate_empty_sector_update_proof).
+#[allow(clippy::too_many_arguments)]
+pub fn generate_empty_sector_update_proof(
+    registered_proof: RegisteredUpdateProof,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+) -> Result<EmptySectorUpdateProof> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        generate_empty_sector_update_proof_inner,
+        registered_proof,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+        sector_key_path,
+        sector_key_cache_path,
+        replica_path,
+        replica_cache_path,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_empty_sector_update_proof_inner<
+    Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>,
+>(
+    registered_proof: RegisteredUpdateProof,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+) -> Result<EmptySectorUpdateProof> {
+    api::generate_empty_sector_update_proof::<Tree>(
+        registered_proof.as_v1_config(),
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+        sector_key_path,
+        sector_key_cache_path,
+        replica_path,
+        replica_cache_path,
+    )
+}
+
+/// Non-generic
+/// [`verify_empty_sector_update_proof`](crate::verify_empty_sector_update_proof).
+pub fn verify_empty_sector_update_proof(
+    registered_proof: RegisteredUpdateProof,
+    proof_bytes: &[u8],
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    with_shape!(
+        u64::from(registered_proof.sector_size()),
+        verify_empty_sector_update_proof_inner,
+        registered_proof,
+        proof_bytes,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+fn verify_empty_sector_update_proof_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    registered_proof: RegisteredUpdateProof,
+    proof_bytes: &[u8],
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    api::verify_empty_sector_update_proof::<Tree>(
+        registered_proof.as_v1_config(),
+        proof_bytes,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+fn update_config(registered_proof: RegisteredUpdateProof) -> SectorUpdateConfig {
+    SectorUpdateConfig::from_porep_config(registered_proof.as_v1_config())
+}
+
+fn partition_proofs_to_tree<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    proofs: Vec<PartitionProof>,
+) -> Result<Vec<types::PartitionProof<Tree>>> {
+    proofs.into_iter().map(PartitionProof::into_tree).collect()
+}
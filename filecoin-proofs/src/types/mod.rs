@@ -2,6 +2,8 @@ pub use merkletree::store::StoreConfig;
 pub use storage_proofs_core::merkle::{MerkleProof, MerkleTreeTrait};
 pub use storage_proofs_porep::stacked::{Labels, PersistentAux, TemporaryAux};
 
+use std::path::PathBuf;
+
 use filecoin_hashers::Hasher;
 use serde::{Deserialize, Serialize};
 use storage_proofs_core::{merkle::BinaryMerkleTree, sector::SectorId};
@@ -19,6 +21,8 @@ mod post_config;
 mod post_proof_partitions;
 mod private_replica_info;
 mod public_replica_info;
+mod registered_proof;
+mod resource_estimate;
 mod sector_class;
 mod sector_size;
 mod sector_update_config;
@@ -33,6 +37,8 @@ pub use post_config::*;
 pub use post_proof_partitions::*;
 pub use private_replica_info::*;
 pub use public_replica_info::*;
+pub use registered_proof::*;
+pub use resource_estimate::*;
 pub use sector_class::*;
 pub use sector_size::*;
 pub use sector_update_config::*;
@@ -56,6 +62,18 @@ pub struct SealPreCommitOutput {
     pub comm_d: Commitment,
 }
 
+/// The inputs of pre-commit phase 1 of one sector, see `seal_pre_commit_phase1_lockstep`.
+#[derive(Debug, Clone)]
+pub struct SealPreCommitPhase1Input {
+    pub cache_path: PathBuf,
+    pub in_path: PathBuf,
+    pub out_path: PathBuf,
+    pub prover_id: ProverId,
+    pub sector_id: SectorId,
+    pub ticket: Ticket,
+    pub piece_infos: Vec<PieceInfo>,
+}
+
 pub type VanillaSealProof<Tree> = stacked::Proof<Tree, DefaultPieceHasher>;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -86,6 +104,9 @@ pub struct SealPreCommitPhase1Output<Tree: MerkleTreeTrait> {
     pub labels: Labels<Tree>,
     pub config: StoreConfig,
     pub comm_d: Commitment,
+    /// The replica id, which phase 2 needs to prove synthetic challenges.
+    #[serde(default)]
+    pub replica_id: Option<Commitment>,
 }
 
 #[repr(transparent)]
@@ -0,0 +1,411 @@
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, Error, Result};
+use serde::{Deserialize, Serialize};
+use storage_proofs_core::api_version::ApiVersion;
+
+use crate::{
+    constants::{
+        sector_shape, sector_size_info, TreeShape, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
+        SECTOR_SIZE_512_MIB, SECTOR_SIZE_64_GIB, SECTOR_SIZE_8_MIB, WINDOW_POST_CHALLENGE_COUNT,
+        WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
+    },
+    types::{PoRepConfig, PoRepProofPartitions, PoStConfig, PoStType, SectorSize},
+};
+
+/// The seal proofs registered on chain. The discriminants are the on-chain proof ids, which are
+/// also the first eight bytes (little-endian) of the `porep_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RegisteredSealProof {
+    StackedDrg2KiBV1 = 0,
+    StackedDrg8MiBV1 = 1,
+    StackedDrg512MiBV1 = 2,
+    StackedDrg32GiBV1 = 3,
+    StackedDrg64GiBV1 = 4,
+
+    StackedDrg2KiBV1_1 = 5,
+    StackedDrg8MiBV1_1 = 6,
+    StackedDrg512MiBV1_1 = 7,
+    StackedDrg32GiBV1_1 = 8,
+    StackedDrg64GiBV1_1 = 9,
+
+    // Synthetic PoRep.
+    StackedDrg2KiBV1_2 = 10,
+    StackedDrg8MiBV1_2 = 11,
+    StackedDrg512MiBV1_2 = 12,
+    StackedDrg32GiBV1_2 = 13,
+    StackedDrg64GiBV1_2 = 14,
+
+    // Non-interactive PoRep.
+    StackedDrg2KiBV1_3 = 15,
+    StackedDrg8MiBV1_3 = 16,
+    StackedDrg512MiBV1_3 = 17,
+    StackedDrg32GiBV1_3 = 18,
+    StackedDrg64GiBV1_3 = 19,
+}
+
+impl RegisteredSealProof {
+    pub const ALL: [RegisteredSealProof; 20] = [
+        RegisteredSealProof::StackedDrg2KiBV1,
+        RegisteredSealProof::StackedDrg8MiBV1,
+        RegisteredSealProof::StackedDrg512MiBV1,
+        RegisteredSealProof::StackedDrg32GiBV1,
+        RegisteredSealProof::StackedDrg64GiBV1,
+        RegisteredSealProof::StackedDrg2KiBV1_1,
+        RegisteredSealProof::StackedDrg8MiBV1_1,
+        RegisteredSealProof::StackedDrg512MiBV1_1,
+        RegisteredSealProof::StackedDrg32GiBV1_1,
+        RegisteredSealProof::StackedDrg64GiBV1_1,
+        RegisteredSealProof::StackedDrg2KiBV1_2,
+        RegisteredSealProof::StackedDrg8MiBV1_2,
+        RegisteredSealProof::StackedDrg512MiBV1_2,
+        RegisteredSealProof::StackedDrg32GiBV1_2,
+        RegisteredSealProof::StackedDrg64GiBV1_2,
+        RegisteredSealProof::StackedDrg2KiBV1_3,
+        RegisteredSealProof::StackedDrg8MiBV1_3,
+        RegisteredSealProof::StackedDrg512MiBV1_3,
+        RegisteredSealProof::StackedDrg32GiBV1_3,
+        RegisteredSealProof::StackedDrg64GiBV1_3,
+    ];
+
+    pub fn sector_size(self) -> SectorSize {
+        use RegisteredSealProof::*;
+
+        let size =
+            match self {
+                StackedDrg2KiBV1 | StackedDrg2KiBV1_1 | StackedDrg2KiBV1_2 | StackedDrg2KiBV1_3 => {
+                    SECTOR_SIZE_2_KIB
+                }
+                StackedDrg8MiBV1 | StackedDrg8MiBV1_1 | StackedDrg8MiBV1_2 | StackedDrg8MiBV1_3 => {
+                    SECTOR_SIZE_8_MIB
+                }
+                StackedDrg512MiBV1 | StackedDrg512MiBV1_1 | StackedDrg512MiBV1_2
+                | StackedDrg512MiBV1_3 => SECTOR_SIZE_512_MIB,
+                StackedDrg32GiBV1 | StackedDrg32GiBV1_1 | StackedDrg32GiBV1_2
+                | StackedDrg32GiBV1_3 => SECTOR_SIZE_32_GIB,
+                StackedDrg64GiBV1 | StackedDrg64GiBV1_1 | StackedDrg64GiBV1_2
+                | StackedDrg64GiBV1_3 => SECTOR_SIZE_64_GIB,
+            };
+        SectorSize(size)
+    }
+
+    pub fn api_version(self) -> ApiVersion {
+        use RegisteredSealProof::*;
+
+        match self {
+            StackedDrg2KiBV1 | StackedDrg8MiBV1 | StackedDrg512MiBV1 | StackedDrg32GiBV1
+            | StackedDrg64GiBV1 => ApiVersion::V1_0_0,
+            StackedDrg2KiBV1_1 | StackedDrg8MiBV1_1 | StackedDrg512MiBV1_1
+            | StackedDrg32GiBV1_1 | StackedDrg64GiBV1_1 => ApiVersion::V1_1_0,
+            StackedDrg2KiBV1_2 | StackedDrg8MiBV1_2 | StackedDrg512MiBV1_2
+            | StackedDrg32GiBV1_2 | StackedDrg64GiBV1_2 => ApiVersion::V1_2_0,
+            StackedDrg2KiBV1_3 | StackedDrg8MiBV1_3 | StackedDrg512MiBV1_3
+            | StackedDrg32GiBV1_3 | StackedDrg64GiBV1_3 => ApiVersion::V1_3_0,
+        }
+    }
+
+    /// The proof id followed by a zero nonce.
+    pub fn porep_id(self) -> [u8; 32] {
+        let mut porep_id = [0u8; 32];
+        porep_id[..8].copy_from_slice(&u64::from(self).to_le_bytes());
+        porep_id
+    }
+
+    /// The shape of the replica tree, as registered for the sector size.
+    pub fn shape(self) -> TreeShape {
+        sector_shape(self.sector_size().into())
+    }
+
+    /// The number of PoRep partitions, as registered for the sector size.
+    pub fn partitions(self) -> PoRepProofPartitions {
+        let info = sector_size_info(self.sector_size().into())
+            .expect("sector size of registered proof is not registered");
+        PoRepProofPartitions(
+            info.porep_partitions_for(self.api_version())
+                .expect("registered proof is not supported for its sector size"),
+        )
+    }
+
+    pub fn as_v1_config(self) -> PoRepConfig {
+        PoRepConfig {
+            sector_size: self.sector_size(),
+            partitions: self.partitions(),
+            porep_id: self.porep_id(),
+            api_version: self.api_version(),
+        }
+    }
+
+    pub fn registered_winning_post_proof(self) -> RegisteredPoStProof {
+        use RegisteredPoStProof::*;
+        use RegisteredSealProof::*;
+
+        match self {
+            StackedDrg2KiBV1 | StackedDrg2KiBV1_1 | StackedDrg2KiBV1_2 | StackedDrg2KiBV1_3 => {
+                StackedDrgWinning2KiBV1
+            }
+            StackedDrg8MiBV1 | StackedDrg8MiBV1_1 | StackedDrg8MiBV1_2 | StackedDrg8MiBV1_3 => {
+                StackedDrgWinning8MiBV1
+            }
+            StackedDrg512MiBV1 | StackedDrg512MiBV1_1 | StackedDrg512MiBV1_2
+            | StackedDrg512MiBV1_3 => StackedDrgWinning512MiBV1,
+            StackedDrg32GiBV1 | StackedDrg32GiBV1_1 | StackedDrg32GiBV1_2 | StackedDrg32GiBV1_3 => {
+                StackedDrgWinning32GiBV1
+            }
+            StackedDrg64GiBV1 | StackedDrg64GiBV1_1 | StackedDrg64GiBV1_2 | StackedDrg64GiBV1_3 => {
+                StackedDrgWinning64GiBV1
+            }
+        }
+    }
+
+    pub fn registered_window_post_proof(self) -> RegisteredPoStProof {
+        use RegisteredPoStProof::*;
+        use RegisteredSealProof::*;
+
+        match self {
+            StackedDrg2KiBV1 | StackedDrg2KiBV1_1 | StackedDrg2KiBV1_2 | StackedDrg2KiBV1_3 => {
+                StackedDrgWindow2KiBV1
+            }
+            StackedDrg8MiBV1 | StackedDrg8MiBV1_1 | StackedDrg8MiBV1_2 | StackedDrg8MiBV1_3 => {
+                StackedDrgWindow8MiBV1
+            }
+            StackedDrg512MiBV1 | StackedDrg512MiBV1_1 | StackedDrg512MiBV1_2
+            | StackedDrg512MiBV1_3 => StackedDrgWindow512MiBV1,
+            StackedDrg32GiBV1 | StackedDrg32GiBV1_1 | StackedDrg32GiBV1_2 | StackedDrg32GiBV1_3 => {
+                StackedDrgWindow32GiBV1
+            }
+            StackedDrg64GiBV1 | StackedDrg64GiBV1_1 | StackedDrg64GiBV1_2 | StackedDrg64GiBV1_3 => {
+                StackedDrgWindow64GiBV1
+            }
+        }
+    }
+
+    /// Empty sector updates are only supported for sectors sealed with a V1_1, V1_2 or V1_3 proof.
+    pub fn registered_update_proof(self) -> Result<RegisteredUpdateProof> {
+        use RegisteredSealProof::*;
+
+        match self {
+            StackedDrg2KiBV1_1 | StackedDrg2KiBV1_2 | StackedDrg2KiBV1_3 => {
+                Ok(RegisteredUpdateProof::StackedDrg2KiBV1)
+            }
+            StackedDrg8MiBV1_1 | StackedDrg8MiBV1_2 | StackedDrg8MiBV1_3 => {
+                Ok(RegisteredUpdateProof::StackedDrg8MiBV1)
+            }
+            StackedDrg512MiBV1_1 | StackedDrg512MiBV1_2 | StackedDrg512MiBV1_3 => {
+                Ok(RegisteredUpdateProof::StackedDrg512MiBV1)
+            }
+            StackedDrg32GiBV1_1 | StackedDrg32GiBV1_2 | StackedDrg32GiBV1_3 => {
+                Ok(RegisteredUpdateProof::StackedDrg32GiBV1)
+            }
+            StackedDrg64GiBV1_1 | StackedDrg64GiBV1_2 | StackedDrg64GiBV1_3 => {
+                Ok(RegisteredUpdateProof::StackedDrg64GiBV1)
+            }
+            proof => Err(anyhow!("{:?} does not support empty sector updates", proof)),
+        }
+    }
+}
+
+impl From<RegisteredSealProof> for u64 {
+    fn from(proof: RegisteredSealProof) -> Self {
+        proof as u64
+    }
+}
+
+impl TryFrom<u64> for RegisteredSealProof {
+    type Error = Error;
+
+    fn try_from(id: u64) -> Result<Self> {
+        RegisteredSealProof::ALL
+            .iter()
+            .copied()
+            .find(|proof| u64::from(*proof) == id)
+            .ok_or_else(|| anyhow!("unknown registered seal proof {}", id))
+    }
+}
+
+/// The Winning and Window PoSt proofs registered on chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RegisteredPoStProof {
+    StackedDrgWinning2KiBV1 = 0,
+    StackedDrgWinning8MiBV1 = 1,
+    StackedDrgWinning512MiBV1 = 2,
+    StackedDrgWinning32GiBV1 = 3,
+    StackedDrgWinning64GiBV1 = 4,
+
+    StackedDrgWindow2KiBV1 = 5,
+    StackedDrgWindow8MiBV1 = 6,
+    StackedDrgWindow512MiBV1 = 7,
+    StackedDrgWindow32GiBV1 = 8,
+    StackedDrgWindow64GiBV1 = 9,
+}
+
+impl RegisteredPoStProof {
+    pub const ALL: [RegisteredPoStProof; 10] = [
+        RegisteredPoStProof::StackedDrgWinning2KiBV1,
+        RegisteredPoStProof::StackedDrgWinning8MiBV1,
+        RegisteredPoStProof::StackedDrgWinning512MiBV1,
+        RegisteredPoStProof::StackedDrgWinning32GiBV1,
+        RegisteredPoStProof::StackedDrgWinning64GiBV1,
+        RegisteredPoStProof::StackedDrgWindow2KiBV1,
+        RegisteredPoStProof::StackedDrgWindow8MiBV1,
+        RegisteredPoStProof::StackedDrgWindow512MiBV1,
+        RegisteredPoStProof::StackedDrgWindow32GiBV1,
+        RegisteredPoStProof::StackedDrgWindow64GiBV1,
+    ];
+
+    pub fn sector_size(self) -> SectorSize {
+        use RegisteredPoStProof::*;
+
+        let size = match self {
+            StackedDrgWinning2KiBV1 | StackedDrgWindow2KiBV1 => SECTOR_SIZE_2_KIB,
+            StackedDrgWinning8MiBV1 | StackedDrgWindow8MiBV1 => SECTOR_SIZE_8_MIB,
+            StackedDrgWinning512MiBV1 | StackedDrgWindow512MiBV1 => SECTOR_SIZE_512_MIB,
+            StackedDrgWinning32GiBV1 | StackedDrgWindow32GiBV1 => SECTOR_SIZE_32_GIB,
+            StackedDrgWinning64GiBV1 | StackedDrgWindow64GiBV1 => SECTOR_SIZE_64_GIB,
+        };
+        SectorSize(size)
+    }
+
+    pub fn api_version(self) -> ApiVersion {
+        ApiVersion::V1_0_0
+    }
+
+    pub fn typ(self) -> PoStType {
+        use RegisteredPoStProof::*;
+
+        match self {
+            StackedDrgWinning2KiBV1
+            | StackedDrgWinning8MiBV1
+            | StackedDrgWinning512MiBV1
+            | StackedDrgWinning32GiBV1
+            | StackedDrgWinning64GiBV1 => PoStType::Winning,
+            StackedDrgWindow2KiBV1
+            | StackedDrgWindow8MiBV1
+            | StackedDrgWindow512MiBV1
+            | StackedDrgWindow32GiBV1
+            | StackedDrgWindow64GiBV1 => PoStType::Window,
+        }
+    }
+
+    /// The shape of the replica tree, as registered for the sector size.
+    pub fn shape(self) -> TreeShape {
+        sector_shape(self.sector_size().into())
+    }
+
+    /// The number of sectors per partition. For Window PoSt this is registered for the sector
+    /// size.
+    pub fn sector_count(self) -> usize {
+        match self.typ() {
+            PoStType::Winning => WINNING_POST_SECTOR_COUNT,
+            PoStType::Window => {
+                sector_size_info(self.sector_size().into())
+                    .expect("sector size of registered proof is not registered")
+                    .window_post_sector_count
+            }
+        }
+    }
+
+    pub fn challenge_count(self) -> usize {
+        match self.typ() {
+            PoStType::Winning => WINNING_POST_CHALLENGE_COUNT,
+            PoStType::Window => WINDOW_POST_CHALLENGE_COUNT,
+        }
+    }
+
+    /// Winning PoSt must be generated within an epoch, so it always runs on the GPU.
+    pub fn as_v1_config(self) -> PoStConfig {
+        PoStConfig {
+            sector_size: self.sector_size(),
+            challenge_count: self.challenge_count(),
+            sector_count: self.sector_count(),
+            typ: self.typ(),
+            priority: self.typ() == PoStType::Winning,
+            api_version: self.api_version(),
+        }
+    }
+}
+
+impl From<RegisteredPoStProof> for u64 {
+    fn from(proof: RegisteredPoStProof) -> Self {
+        proof as u64
+    }
+}
+
+impl TryFrom<u64> for RegisteredPoStProof {
+    type Error = Error;
+
+    fn try_from(id: u64) -> Result<Self> {
+        RegisteredPoStProof::ALL
+            .iter()
+            .copied()
+            .find(|proof| u64::from(*proof) == id)
+            .ok_or_else(|| anyhow!("unknown registered post proof {}", id))
+    }
+}
+
+/// The empty sector update proofs registered on chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RegisteredUpdateProof {
+    StackedDrg2KiBV1 = 0,
+    StackedDrg8MiBV1 = 1,
+    StackedDrg512MiBV1 = 2,
+    StackedDrg32GiBV1 = 3,
+    StackedDrg64GiBV1 = 4,
+}
+
+impl RegisteredUpdateProof {
+    pub const ALL: [RegisteredUpdateProof; 5] = [
+        RegisteredUpdateProof::StackedDrg2KiBV1,
+        RegisteredUpdateProof::StackedDrg8MiBV1,
+        RegisteredUpdateProof::StackedDrg512MiBV1,
+        RegisteredUpdateProof::StackedDrg32GiBV1,
+        RegisteredUpdateProof::StackedDrg64GiBV1,
+    ];
+
+    /// The seal proof of the sectors this proof updates.
+    pub fn registered_seal_proof(self) -> RegisteredSealProof {
+        use RegisteredSealProof::*;
+
+        match self {
+            RegisteredUpdateProof::StackedDrg2KiBV1 => StackedDrg2KiBV1_1,
+            RegisteredUpdateProof::StackedDrg8MiBV1 => StackedDrg8MiBV1_1,
+            RegisteredUpdateProof::StackedDrg512MiBV1 => StackedDrg512MiBV1_1,
+            RegisteredUpdateProof::StackedDrg32GiBV1 => StackedDrg32GiBV1_1,
+            RegisteredUpdateProof::StackedDrg64GiBV1 => StackedDrg64GiBV1_1,
+        }
+    }
+
+    pub fn sector_size(self) -> SectorSize {
+        self.registered_seal_proof().sector_size()
+    }
+
+    pub fn api_version(self) -> ApiVersion {
+        self.registered_seal_proof().api_version()
+    }
+
+    pub fn shape(self) -> TreeShape {
+        self.registered_seal_proof().shape()
+    }
+
+    /// The config of the updated sector's seal proof, which the update proof is generated for.
+    pub fn as_v1_config(self) -> PoRepConfig {
+        self.registered_seal_proof().as_v1_config()
+    }
+}
+
+impl From<RegisteredUpdateProof> for u64 {
+    fn from(proof: RegisteredUpdateProof) -> Self {
+        proof as u64
+    }
+}
+
+impl TryFrom<u64> for RegisteredUpdateProof {
+    type Error = Error;
+
+    fn try_from(id: u64) -> Result<Self> {
+        RegisteredUpdateProof::ALL
+            .iter()
+            .copied()
+            .find(|proof| u64::from(*proof) == id)
+            .ok_or_else(|| anyhow!("unknown registered update proof {}", id))
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use crate::types::SectorSize;
+
+/// Expected resource usage of a single API phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhaseEstimate {
+    /// Expected peak memory usage in bytes, including the pages of the files
+    /// mapped by the phase. Files read at random are assumed to end up fully
+    /// resident, so this is an upper bound for phases reading cache files.
+    pub peak_ram: u64,
+    /// Bytes written to the cache directory by this phase, in the files it
+    /// creates, not counting `t_aux`.
+    pub disk_written: u64,
+}
+
+/// The device the groth proofs will be generated on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvingDevice {
+    Cpu,
+    Gpu,
+}
+
+impl ProvingDevice {
+    /// Returns the device used by this build for proving.
+    pub fn current() -> Self {
+        if cfg!(any(feature = "cuda", feature = "opencl")) {
+            ProvingDevice::Gpu
+        } else {
+            ProvingDevice::Cpu
+        }
+    }
+}
+
+/// Expected work required to generate the snark proof(s).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProvingEstimate {
+    /// Number of partition proofs that will be synthesized and proven.
+    pub partitions: usize,
+    /// Device used to generate the partition proofs.
+    pub device: ProvingDevice,
+    /// Size of the groth parameters file, if present in the parameter cache.
+    pub params_size: Option<u64>,
+}
+
+/// Expected resource usage for sealing a single sector.
+///
+/// Sizes of the cache artifacts are keyed by their file name within the
+/// sector's cache directory. `t_aux` is not included, as its size depends
+/// on the length of the cache path (it is a few hundred bytes).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SealResourceEstimate {
+    pub sector_size: SectorSize,
+    pub pre_commit_phase1: PhaseEstimate,
+    pub pre_commit_phase2: PhaseEstimate,
+    pub commit_phase1: PhaseEstimate,
+    pub commit_phase2: PhaseEstimate,
+    /// Every artifact written to the cache directory during sealing.
+    pub cache_files: BTreeMap<String, u64>,
+    /// The artifacts remaining in the cache directory after `clear_cache`.
+    pub final_cache_files: BTreeMap<String, u64>,
+    /// Size of the sealed replica.
+    pub replica_size: u64,
+    /// Size of the shared parent cache file for this sector size.
+    pub parent_cache_size: u64,
+    pub proving: ProvingEstimate,
+}
+
+impl SealResourceEstimate {
+    /// Maximum scratch space used in the cache directory (before `clear_cache`).
+    pub fn peak_cache_size(&self) -> u64 {
+        self.cache_files.values().sum()
+    }
+
+    /// Size of the cache directory after `clear_cache`.
+    pub fn final_cache_size(&self) -> u64 {
+        self.final_cache_files.values().sum()
+    }
+}
+
+/// Expected resource usage for encoding and proving an empty sector update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectorUpdateResourceEstimate {
+    pub sector_size: SectorSize,
+    pub encode: PhaseEstimate,
+    pub prove: PhaseEstimate,
+    /// Every artifact written to the new cache directory during encoding.
+    pub cache_files: BTreeMap<String, u64>,
+    /// Size of the new replica.
+    pub replica_size: u64,
+    pub proving: ProvingEstimate,
+}
+
+impl SectorUpdateResourceEstimate {
+    pub fn cache_size(&self) -> u64 {
+        self.cache_files.values().sum()
+    }
+}
+
+/// Expected resource usage for generating a Winning or Window PoSt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoStResourceEstimate {
+    pub sector_size: SectorSize,
+    /// Number of sectors proven per partition.
+    pub sectors_per_partition: usize,
+    /// Bytes of `tree-r-last` read from each sector's cache directory.
+    pub tree_r_last_size: u64,
+    pub prove: PhaseEstimate,
+    pub proving: ProvingEstimate,
+}
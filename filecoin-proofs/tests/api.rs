@@ -1,7 +1,12 @@
 use std::collections::BTreeMap;
-use std::fs::{metadata, read_dir, remove_file, OpenOptions};
+use std::convert::TryFrom;
+use std::env;
+use std::fs::{metadata, read_dir, read_to_string, remove_file, write, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{ensure, Context, Error, Result};
 use bellperson::groth16;
@@ -10,33 +15,49 @@ use blstrs::{Bls12, Scalar as Fr};
 use ff::Field;
 use filecoin_hashers::Hasher;
 use filecoin_proofs::{
-    add_piece, aggregate_seal_commit_proofs, clear_cache, compute_comm_d, decode_from, encode_into,
-    fauxrep_aux, generate_empty_sector_update_proof,
-    generate_empty_sector_update_proof_with_vanilla, generate_fallback_sector_challenges,
-    generate_partition_proofs, generate_piece_commitment, generate_single_partition_proof,
-    generate_single_vanilla_proof, generate_single_window_post_with_vanilla, generate_window_post,
+    add_piece, aggregate_seal_commit_proofs,
+    caches::{evict_post_params, memory_cache_stats, MemoryCacheKind},
+    clear_cache, clear_synthetic_proofs, compute_comm_d, decode_from, encode_into, error,
+    estimate_seal_pre_commit_phase1_lockstep, estimate_seal_resources, fauxrep_aux,
+    generate_empty_sector_update_proof, generate_empty_sector_update_proof_with_vanilla,
+    generate_fallback_sector_challenges, generate_partition_proofs, generate_piece_commitment,
+    generate_single_partition_proof, generate_single_vanilla_proof,
+    generate_single_window_post_with_vanilla, generate_window_post,
     generate_window_post_with_vanilla, generate_winning_post,
     generate_winning_post_sector_challenge, generate_winning_post_with_vanilla,
     get_num_partition_for_fallback_post, get_seal_inputs, merge_window_post_partition_proofs,
-    remove_encoded_data, seal_commit_phase1, seal_commit_phase2, seal_pre_commit_phase1,
-    seal_pre_commit_phase2, unseal_range, validate_cache_for_commit,
-    validate_cache_for_precommit_phase2, verify_aggregate_seal_commit_proofs,
-    verify_empty_sector_update_proof, verify_partition_proofs, verify_seal,
-    verify_single_partition_proof, verify_window_post, verify_winning_post, Commitment,
-    DefaultTreeDomain, MerkleTreeTrait, PaddedBytesAmount, PieceInfo, PoRepConfig,
-    PoRepProofPartitions, PoStConfig, PoStType, PrivateReplicaInfo, ProverId, PublicReplicaInfo,
-    SealCommitOutput, SealPreCommitOutput, SealPreCommitPhase1Output, SectorShape16KiB,
-    SectorShape2KiB, SectorShape32KiB, SectorShape4KiB, SectorSize, SectorUpdateConfig,
-    UnpaddedByteIndex, UnpaddedBytesAmount, POREP_PARTITIONS, SECTOR_SIZE_16_KIB,
-    SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB, WINDOW_POST_CHALLENGE_COUNT,
-    WINDOW_POST_SECTOR_COUNT, WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
+    parameters::winning_post_public_params,
+    preload_parameters, preload_parameters_with_options, registered, remove_encoded_data,
+    render_metrics, seal_commit_phase1, seal_commit_phase2, seal_pre_commit_phase1,
+    seal_pre_commit_phase1_lockstep, seal_pre_commit_phase2, sector_size_info, unseal_range,
+    validate_cache_for_commit, validate_cache_for_precommit_phase2,
+    verify_aggregate_seal_commit_proofs, verify_empty_sector_update_proof, verify_partition_proofs,
+    verify_seal, verify_single_partition_proof, verify_window_post, verify_winning_post,
+    with_config, Commitment, DefaultTreeDomain, MerkleTreeTrait, PaddedBytesAmount, PhaseEstimate,
+    PieceInfo, PoRepConfig, PoRepProofPartitions, PoStConfig, PoStType, PreloadOptions,
+    PrivateReplicaInfo, ProofKind, ProofsConfig, ProverId, PublicReplicaInfo, RegisteredPoStProof,
+    RegisteredSealProof, RegisteredUpdateProof, SealCommitOutput, SealPreCommitOutput,
+    SealPreCommitPhase1Input, SealPreCommitPhase1Output, SectorShape16KiB, SectorShape2KiB,
+    SectorShape32KiB, SectorShape4KiB, SectorShape8MiB, SectorSize, SectorUpdateConfig,
+    StoreConfig, UnpaddedByteIndex, UnpaddedBytesAmount, SECTOR_SIZE_16_KIB, SECTOR_SIZE_2_KIB,
+    SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB, SECTOR_SIZE_8_MIB, WINDOW_POST_CHALLENGE_COUNT,
+    WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
 };
 use fr32::bytes_into_fr;
 use log::info;
 use memmap::MmapOptions;
 use rand::{random, Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
-use storage_proofs_core::{api_version::ApiVersion, is_legacy_porep_id, sector::SectorId};
+use storage_proofs_core::{
+    api_version::ApiVersion,
+    cache_key::CacheKey,
+    compound_proof::CompoundProof,
+    is_legacy_porep_id,
+    parameter_store::{with_parameter_store, MemoryParameterStore},
+    sector::SectorId,
+    settings::SETTINGS,
+};
+use storage_proofs_post::fallback::FallbackPoStCompound;
 use storage_proofs_update::constants::TreeRHasher;
 use tempfile::{tempdir, NamedTempFile, TempDir};
 
@@ -45,6 +66,8 @@ use filecoin_proofs::{
     SectorShape32GiB, SectorShape512MiB, SectorShape64GiB, SECTOR_SIZE_32_GIB, SECTOR_SIZE_512_MIB,
     SECTOR_SIZE_64_GIB,
 };
+#[cfg(feature = "multicore-sdr")]
+use storage_proofs_core::settings::{self, Settings};
 
 // Use a fixed PoRep ID, so that the parents cache can be re-used between some tests.
 // Note however, that parents caches cannot be shared when testing the differences
@@ -52,6 +75,8 @@ use filecoin_proofs::{
 // same porep_ids).
 const ARBITRARY_POREP_ID_V1_0_0: [u8; 32] = [127; 32];
 const ARBITRARY_POREP_ID_V1_1_0: [u8; 32] = [128; 32];
+const ARBITRARY_POREP_ID_V1_2_0: [u8; 32] = [129; 32];
+const ARBITRARY_POREP_ID_V1_3_0: [u8; 32] = [130; 32];
 
 const TEST_SEED: [u8; 16] = [
     0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
@@ -79,6 +104,28 @@ fn test_seal_lifecycle_2kib_porep_id_v1_1_base_8() -> Result<()> {
     seal_lifecycle::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, &porep_id, ApiVersion::V1_1_0)
 }
 
+#[test]
+#[ignore]
+fn test_seal_lifecycle_2kib_porep_id_v1_2_base_8() -> Result<()> {
+    let porep_id_v1_2: u64 = 10; // This is a RegisteredSealProof value
+
+    let mut porep_id = [0u8; 32];
+    porep_id[..8].copy_from_slice(&porep_id_v1_2.to_le_bytes());
+    assert!(!is_legacy_porep_id(porep_id));
+    seal_lifecycle::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, &porep_id, ApiVersion::V1_2_0)
+}
+
+#[test]
+#[ignore]
+fn test_seal_lifecycle_2kib_porep_id_v1_3_base_8() -> Result<()> {
+    let porep_id_v1_3: u64 = 15; // This is a RegisteredSealProof value
+
+    let mut porep_id = [0u8; 32];
+    porep_id[..8].copy_from_slice(&porep_id_v1_3.to_le_bytes());
+    assert!(!is_legacy_porep_id(porep_id));
+    seal_lifecycle::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, &porep_id, ApiVersion::V1_3_0)
+}
+
 #[test]
 #[ignore]
 fn test_seal_lifecycle_upgrade_2kib_porep_id_v1_1_base_8() -> Result<()> {
@@ -110,6 +157,16 @@ fn test_seal_lifecycle_4kib_sub_8_2_v1_1() -> Result<()> {
     )
 }
 
+#[test]
+#[ignore]
+fn test_seal_lifecycle_4kib_sub_8_2_v1_2() -> Result<()> {
+    seal_lifecycle::<SectorShape4KiB>(
+        SECTOR_SIZE_4_KIB,
+        &ARBITRARY_POREP_ID_V1_2_0,
+        ApiVersion::V1_2_0,
+    )
+}
+
 #[test]
 #[ignore]
 fn test_seal_lifecycle_upgrade_4kib_sub_8_2_v1_1() -> Result<()> {
@@ -346,6 +403,26 @@ fn test_seal_proof_aggregation_1_2kib_porep_id_v1_1_base_8() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[ignore]
+fn test_seal_proof_aggregation_3_2kib_porep_id_v1_3_base_8() -> Result<()> {
+    let proofs_to_aggregate = 3; // Requires auto-padding
+
+    let porep_id_v1_3: u64 = 15; // This is a RegisteredSealProof value
+
+    let mut porep_id = [0u8; 32];
+    porep_id[..8].copy_from_slice(&porep_id_v1_3.to_le_bytes());
+    let verified = aggregate_proofs::<SectorShape2KiB>(
+        SECTOR_SIZE_2_KIB,
+        &porep_id,
+        ApiVersion::V1_3_0,
+        proofs_to_aggregate,
+    )?;
+    assert!(verified);
+
+    Ok(())
+}
+
 #[test]
 #[ignore]
 fn test_seal_proof_aggregation_3_2kib_porep_id_v1_1_base_8() -> Result<()> {
@@ -736,6 +813,329 @@ fn run_resumable_seal<Tree: 'static + MerkleTreeTrait>(
     }
 }
 
+#[test]
+fn test_seal_resource_estimate_2kib_base_8() -> Result<()> {
+    run_seal_resource_estimate::<SectorShape2KiB>(
+        "test_seal_resource_estimate_2kib_base_8",
+        porep_config(
+            SECTOR_SIZE_2_KIB,
+            ARBITRARY_POREP_ID_V1_1_0,
+            ApiVersion::V1_1_0,
+        ),
+    )
+}
+
+#[test]
+fn test_seal_resource_estimate_synthetic_2kib_base_8() -> Result<()> {
+    run_seal_resource_estimate::<SectorShape2KiB>(
+        "test_seal_resource_estimate_synthetic_2kib_base_8",
+        porep_config(
+            SECTOR_SIZE_2_KIB,
+            ARBITRARY_POREP_ID_V1_2_0,
+            ApiVersion::V1_2_0,
+        ),
+    )
+}
+
+#[test]
+fn test_seal_resource_estimate_8mib_base_8() -> Result<()> {
+    run_seal_resource_estimate::<SectorShape8MiB>(
+        "test_seal_resource_estimate_8mib_base_8",
+        porep_config(
+            SECTOR_SIZE_8_MIB,
+            ARBITRARY_POREP_ID_V1_1_0,
+            ApiVersion::V1_1_0,
+        ),
+    )
+}
+
+/// Set in the process a resource estimate test is re-run in.
+const RESOURCE_ESTIMATE_PROCESS: &str = "FIL_PROOFS_TEST_RESOURCE_ESTIMATE_PROCESS";
+
+// Seals a sector phase by phase, checking the memory and disk usage of every
+// phase against the estimate. The peak memory is measured for the whole
+// process, so the test re-runs itself in a process of its own.
+fn run_seal_resource_estimate<Tree: 'static + MerkleTreeTrait>(
+    test_name: &str,
+    config: PoRepConfig,
+) -> Result<()> {
+    if env::var_os(RESOURCE_ESTIMATE_PROCESS).is_none() {
+        let status = Command::new(env::current_exe()?)
+            .args([test_name, "--exact", "--nocapture", "--test-threads=1"])
+            .env(RESOURCE_ESTIMATE_PROCESS, "1")
+            .status()?;
+        ensure!(status.success(), "{} failed: {}", test_name, status);
+        return Ok(());
+    }
+
+    fil_logger::maybe_init();
+
+    let sector_size = u64::from(config.sector_size);
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
+    let mut prover_id = [0u8; 32];
+    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+
+    let (staged_sector_file, piece_infos) = stage_sector(sector_size)?;
+    let sealed_sector_file = NamedTempFile::new()?;
+    let cache_dir = tempdir()?;
+
+    let estimate = estimate_seal_resources::<Tree>(config)?;
+    assert_eq!(estimate.replica_size, sector_size);
+    assert_eq!(
+        estimate.proving.partitions,
+        usize::from(config.partitions),
+        "wrong partition count"
+    );
+
+    let ticket = rng.gen();
+    let seed = rng.gen();
+    let sector_id = rng.gen::<u64>().into();
+
+    let phase1_output = check_phase_estimate(
+        "pre_commit_phase1",
+        &estimate.pre_commit_phase1,
+        &cache_dir,
+        || {
+            seal_pre_commit_phase1::<_, _, _, Tree>(
+                config,
+                cache_dir.path(),
+                staged_sector_file.path(),
+                sealed_sector_file.path(),
+                prover_id,
+                sector_id,
+                ticket,
+                &piece_infos,
+            )
+        },
+    )?;
+    let pre_commit_output = check_phase_estimate(
+        "pre_commit_phase2",
+        &estimate.pre_commit_phase2,
+        &cache_dir,
+        || {
+            seal_pre_commit_phase2(
+                config,
+                phase1_output,
+                cache_dir.path(),
+                sealed_sector_file.path(),
+            )
+        },
+    )?;
+
+    assert_eq!(metadata(sealed_sector_file.path())?.len(), sector_size);
+    assert_eq!(
+        get_cache_file_sizes(&cache_dir)?,
+        estimate.cache_files,
+        "cache artifacts don't match the estimate"
+    );
+
+    let commit_phase1_output =
+        check_phase_estimate("commit_phase1", &estimate.commit_phase1, &cache_dir, || {
+            seal_commit_phase1::<_, Tree>(
+                config,
+                cache_dir.path(),
+                sealed_sector_file.path(),
+                prover_id,
+                sector_id,
+                ticket,
+                seed,
+                pre_commit_output,
+                &piece_infos,
+            )
+        })?;
+    // Without the groth parameters, commit phase 2 would generate them.
+    if estimate.proving.params_size.is_some() {
+        check_phase_estimate("commit_phase2", &estimate.commit_phase2, &cache_dir, || {
+            seal_commit_phase2(config, commit_phase1_output, prover_id, sector_id)
+        })?;
+    }
+
+    clear_cache::<Tree>(cache_dir.path())?;
+    let final_cache_files = get_cache_file_sizes(&cache_dir)?;
+    assert_eq!(
+        final_cache_files, estimate.final_cache_files,
+        "cache artifacts after clear_cache don't match the estimate"
+    );
+    assert_eq!(
+        final_cache_files.values().sum::<u64>(),
+        estimate.final_cache_size()
+    );
+
+    Ok(())
+}
+
+// The measured peak memory of a phase may exceed its estimate by this
+// factor, plus `PEAK_RAM_SLACK` bytes for the allocator, the thread stacks
+// and the small buffers the estimate leaves out.
+const PEAK_RAM_TOLERANCE: f64 = 1.25;
+const PEAK_RAM_SLACK: u64 = 16 << 20;
+
+// Runs a phase, checking the bytes it writes to the cache dir and the growth
+// of the peak resident memory of the process against its estimate.
+fn check_phase_estimate<T>(
+    phase: &str,
+    estimate: &PhaseEstimate,
+    cache_dir: &TempDir,
+    run: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let files_before = get_cache_file_sizes(cache_dir)?;
+    let rss_before = reset_peak_rss();
+
+    let output = run()?;
+
+    let peak_ram =
+        rss_before.and_then(|before| Some(read_proc_status("VmHWM")?.saturating_sub(before)));
+    let disk_written: u64 = get_cache_file_sizes(cache_dir)?
+        .iter()
+        .filter(|(name, size)| files_before.get(*name) != Some(size))
+        .map(|(_, size)| size)
+        .sum();
+    info!(
+        "{}: peak ram {:?} (estimated {}), disk written {} (estimated {})",
+        phase, peak_ram, estimate.peak_ram, disk_written, estimate.disk_written
+    );
+
+    assert_eq!(
+        disk_written, estimate.disk_written,
+        "{} disk writes don't match the estimate",
+        phase
+    );
+    // The peak memory can't be measured without procfs.
+    if let Some(peak_ram) = peak_ram {
+        let limit = (estimate.peak_ram as f64 * PEAK_RAM_TOLERANCE) as u64 + PEAK_RAM_SLACK;
+        assert!(
+            peak_ram <= limit,
+            "{} used {} bytes of memory, estimated {}",
+            phase,
+            peak_ram,
+            estimate.peak_ram
+        );
+    }
+
+    Ok(output)
+}
+
+// Resets the peak resident memory of the process to its current resident
+// memory, which is returned.
+fn reset_peak_rss() -> Option<u64> {
+    write("/proc/self/clear_refs", "5").ok()?;
+    read_proc_status("VmRSS")
+}
+
+// Reads a memory size in bytes from `/proc/self/status`.
+fn read_proc_status(key: &str) -> Option<u64> {
+    read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .and_then(|value| value.trim().strip_suffix(" kB")?.parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+}
+
+// Returns the size of every file in the cache dir, except for t_aux
+// (which is not part of the estimate).
+fn get_cache_file_sizes(cache_dir: &TempDir) -> Result<BTreeMap<String, u64>> {
+    let mut sizes = BTreeMap::new();
+    for entry in read_dir(cache_dir.path())? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name != CacheKey::TAux.to_string() {
+            sizes.insert(name, entry.metadata()?.len());
+        }
+    }
+
+    Ok(sizes)
+}
+
+#[test]
+fn test_seal_pre_commit_phase1_with_config_2kib_base_8() -> Result<()> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
+    let mut prover_id = [0u8; 32];
+    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+
+    let (mut piece_file, _piece_bytes) = generate_piece_file(sector_size)?;
+    let sealed_sector_file = NamedTempFile::new()?;
+    let cache_dir = tempdir()?;
+
+    // Use a parent cache dir that is private to this call.
+    let parent_cache_dir = tempdir()?;
+    let proofs_config = ProofsConfig {
+        parent_cache: parent_cache_dir.path().to_string_lossy().into_owned(),
+        ..SETTINGS.clone()
+    };
+
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+    let ticket = rng.gen();
+    let sector_id = rng.gen::<u64>().into();
+    with_config(&proofs_config, || {
+        run_seal_pre_commit_phase1::<SectorShape2KiB>(
+            config,
+            prover_id,
+            sector_id,
+            ticket,
+            &cache_dir,
+            &mut piece_file,
+            &sealed_sector_file,
+        )
+    })?;
+
+    let parent_caches: Vec<_> = read_dir(parent_cache_dir.path())?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(parent_caches.len(), 1, "parent cache was not created");
+    assert_eq!(
+        parent_caches[0].extension().and_then(|ext| ext.to_str()),
+        Some("cache")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_clear_cache_missing_t_aux() -> Result<()> {
+    let cache_dir = tempdir()?;
+
+    let err = clear_cache::<SectorShape2KiB>(cache_dir.path())
+        .expect_err("clear_cache must fail without t_aux");
+    assert!(matches!(
+        error::Error::find(&err),
+        Some(error::Error::MissingCacheFile(_))
+    ));
+    assert_eq!(error::recovery(&err), Some(error::Recovery::RedoPhase));
+
+    Ok(())
+}
+
+#[test]
+fn test_render_metrics() -> Result<()> {
+    let number_of_bytes_in_piece = UnpaddedBytesAmount(127);
+    let piece_bytes: Vec<u8> = (0..127).map(|_| random::<u8>()).collect();
+    let mut staged_sector_file = NamedTempFile::new()?;
+    add_piece(
+        &piece_bytes[..],
+        &mut staged_sector_file,
+        number_of_bytes_in_piece,
+        &[],
+    )?;
+    let _ = clear_cache::<SectorShape2KiB>(tempdir()?.path());
+
+    let rendered = render_metrics();
+    assert!(rendered.contains("# TYPE fil_proofs_bytes_written_total counter\n"));
+    assert!(rendered.contains("fil_proofs_bytes_written_total{kind=\"piece\"} "));
+    assert!(rendered.contains("# TYPE fil_proofs_operation_duration_seconds histogram\n"));
+    assert!(rendered.contains(
+        "fil_proofs_operation_duration_seconds_bucket{operation=\"add-piece\",le=\"+Inf\"} "
+    ));
+    assert!(rendered.contains("fil_proofs_phase_duration_seconds_count{phase=\"clear_cache\"} "));
+
+    Ok(())
+}
+
 #[test]
 #[ignore]
 fn test_winning_post_2kib_base_8() -> Result<()> {
@@ -833,6 +1233,8 @@ fn winning_post<Tree: 'static + MerkleTreeTrait>(
     let porep_id = match api_version {
         ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_1_0 => ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_2_0 => ARBITRARY_POREP_ID_V1_2_0,
+        ApiVersion::V1_3_0 => ARBITRARY_POREP_ID_V1_3_0,
     };
 
     let (sector_id, replica, comm_r, cache_dir) = if fake {
@@ -922,11 +1324,9 @@ fn winning_post<Tree: 'static + MerkleTreeTrait>(
 #[ignore]
 fn test_window_post_single_partition_smaller_2kib_base_8() -> Result<()> {
     let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape2KiB>(
         sector_size,
@@ -962,11 +1362,9 @@ fn test_window_post_single_partition_smaller_2kib_base_8() -> Result<()> {
 #[ignore]
 fn test_window_post_two_partitions_matching_2kib_base_8() -> Result<()> {
     let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape2KiB>(
         sector_size,
@@ -1002,11 +1400,9 @@ fn test_window_post_two_partitions_matching_2kib_base_8() -> Result<()> {
 #[ignore]
 fn test_window_post_two_partitions_matching_4kib_sub_8_2() -> Result<()> {
     let sector_size = SECTOR_SIZE_4_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape4KiB>(
         sector_size,
@@ -1042,11 +1438,9 @@ fn test_window_post_two_partitions_matching_4kib_sub_8_2() -> Result<()> {
 #[ignore]
 fn test_window_post_two_partitions_matching_16kib_sub_8_8() -> Result<()> {
     let sector_size = SECTOR_SIZE_16_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape16KiB>(
         sector_size,
@@ -1082,11 +1476,9 @@ fn test_window_post_two_partitions_matching_16kib_sub_8_8() -> Result<()> {
 #[ignore]
 fn test_window_post_two_partitions_matching_32kib_top_8_8_2() -> Result<()> {
     let sector_size = SECTOR_SIZE_32_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape32KiB>(
         sector_size,
@@ -1122,11 +1514,9 @@ fn test_window_post_two_partitions_matching_32kib_top_8_8_2() -> Result<()> {
 #[ignore]
 fn test_window_post_two_partitions_smaller_2kib_base_8() -> Result<()> {
     let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape2KiB>(
         sector_size,
@@ -1162,11 +1552,9 @@ fn test_window_post_two_partitions_smaller_2kib_base_8() -> Result<()> {
 #[ignore]
 fn test_window_post_single_partition_matching_2kib_base_8() -> Result<()> {
     let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     window_post::<SectorShape2KiB>(
         sector_size,
@@ -1201,11 +1589,9 @@ fn test_window_post_single_partition_matching_2kib_base_8() -> Result<()> {
 #[test]
 fn test_window_post_partition_matching_2kib_base_8() -> Result<()> {
     let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+    let sector_count = sector_size_info(sector_size)
+        .expect("unknown sector size")
+        .window_post_sector_count;
 
     partition_window_post::<SectorShape2KiB>(
         sector_size,
@@ -1253,6 +1639,8 @@ fn partition_window_post<Tree: 'static + MerkleTreeTrait>(
     let porep_id = match api_version {
         ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_1_0 => ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_2_0 => ARBITRARY_POREP_ID_V1_2_0,
+        ApiVersion::V1_3_0 => ARBITRARY_POREP_ID_V1_3_0,
     };
 
     for _ in 0..total_sector_count {
@@ -1378,6 +1766,8 @@ fn window_post<Tree: 'static + MerkleTreeTrait>(
     let porep_id = match api_version {
         ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
         ApiVersion::V1_1_0 => ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_2_0 => ARBITRARY_POREP_ID_V1_2_0,
+        ApiVersion::V1_3_0 => ARBITRARY_POREP_ID_V1_3_0,
     };
 
     for _ in 0..total_sector_count {
@@ -1477,41 +1867,61 @@ fn porep_config(sector_size: u64, porep_id: [u8; 32], api_version: ApiVersion) -
     PoRepConfig {
         sector_size: SectorSize(sector_size),
         partitions: PoRepProofPartitions(
-            *POREP_PARTITIONS
-                .read()
-                .expect("POREP_PARTITIONS poisoned")
-                .get(&sector_size)
-                .expect("unknown sector size"),
+            sector_size_info(sector_size)
+                .expect("unknown sector size")
+                .porep_partitions_for(api_version)
+                .expect("unsupported api version"),
         ),
         porep_id,
         api_version,
     }
 }
 
-fn run_seal_pre_commit_phase1<Tree: 'static + MerkleTreeTrait>(
-    config: PoRepConfig,
-    prover_id: ProverId,
-    sector_id: SectorId,
-    ticket: [u8; 32],
-    cache_dir: &TempDir,
-    mut piece_file: &mut NamedTempFile,
-    sealed_sector_file: &NamedTempFile,
-) -> Result<(Vec<PieceInfo>, SealPreCommitPhase1Output<Tree>)> {
-    let number_of_bytes_in_piece =
-        UnpaddedBytesAmount::from(PaddedBytesAmount(config.sector_size.into()));
+fn generate_prover_id<R: Rng>(rng: &mut R) -> ProverId {
+    let prover_fr: DefaultTreeDomain = Fr::random(rng).into();
+    let mut prover_id = [0u8; 32];
+    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+
+    prover_id
+}
+
+/// Stages a sector holding the piece of `piece_file`, returns the staged sector and its pieces.
+fn stage_piece(
+    piece_file: &mut NamedTempFile,
+    sector_size: u64,
+) -> Result<(NamedTempFile, Vec<PieceInfo>)> {
+    let number_of_bytes_in_piece = UnpaddedBytesAmount::from(PaddedBytesAmount(sector_size));
 
     let piece_info = generate_piece_commitment(piece_file.as_file_mut(), number_of_bytes_in_piece)?;
     piece_file.as_file_mut().seek(SeekFrom::Start(0))?;
 
     let mut staged_sector_file = NamedTempFile::new()?;
     add_piece(
-        &mut piece_file,
+        piece_file,
         &mut staged_sector_file,
         number_of_bytes_in_piece,
         &[],
     )?;
 
-    let piece_infos = vec![piece_info];
+    Ok((staged_sector_file, vec![piece_info]))
+}
+
+/// Stages a sector holding a single piece of random data.
+fn stage_sector(sector_size: u64) -> Result<(NamedTempFile, Vec<PieceInfo>)> {
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    stage_piece(&mut piece_file, sector_size)
+}
+
+fn run_seal_pre_commit_phase1<Tree: 'static + MerkleTreeTrait>(
+    config: PoRepConfig,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: [u8; 32],
+    cache_dir: &TempDir,
+    piece_file: &mut NamedTempFile,
+    sealed_sector_file: &NamedTempFile,
+) -> Result<(Vec<PieceInfo>, SealPreCommitPhase1Output<Tree>)> {
+    let (staged_sector_file, piece_infos) = stage_piece(piece_file, config.sector_size.into())?;
 
     let phase1_output = seal_pre_commit_phase1::<_, _, _, Tree>(
         config,
@@ -2125,3 +2535,454 @@ fn test_aggregate_proof_encode_decode() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_preload_parameters_reports_failures() -> Result<()> {
+    let porep_config = PoRepConfig {
+        sector_size: SectorSize(12345),
+        partitions: PoRepProofPartitions(1),
+        porep_id: ARBITRARY_POREP_ID_V1_1_0,
+        api_version: ApiVersion::V1_1_0,
+    };
+
+    let handle = preload_parameters(&[ProofKind::PoRep(porep_config)])?;
+    assert!(handle.wait_timeout(Duration::from_secs(60)));
+    assert!(handle.is_ready());
+    assert_eq!(handle.progress(), (1, 1));
+
+    let err = handle
+        .wait()
+        .expect_err("unsupported sector size must fail");
+    assert!(format!("{:?}", err).contains("PoRep[12345]"));
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_preload_parameters_2kib_base_8() -> Result<()> {
+    let post_config = PoStConfig {
+        sector_size: SectorSize(SECTOR_SIZE_2_KIB),
+        sector_count: WINNING_POST_SECTOR_COUNT,
+        challenge_count: WINNING_POST_CHALLENGE_COUNT,
+        typ: PoStType::Winning,
+        priority: false,
+        api_version: ApiVersion::V1_1_0,
+    };
+
+    // Generate parameters into a store of their own, the production parameters may be missing.
+    let store = Arc::new(MemoryParameterStore::new());
+    with_parameter_store(store.clone(), || -> Result<()> {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let public_params = winning_post_public_params::<SectorShape2KiB>(&post_config)?;
+        FallbackPoStCompound::<SectorShape2KiB>::groth_params(Some(&mut rng), &public_params)?;
+        FallbackPoStCompound::<SectorShape2KiB>::verifying_key(Some(&mut rng), &public_params)?;
+
+        let handle = preload_parameters_with_options(
+            &[ProofKind::PoSt(post_config.clone())],
+            PreloadOptions {
+                prefault: true,
+                mlock: false,
+            },
+        )?;
+        handle.wait()
+    })?;
+
+    assert!(memory_cache_stats(MemoryCacheKind::GrothParams).entries >= 1);
+    assert!(memory_cache_stats(MemoryCacheKind::VerifyingKeys).entries >= 1);
+    assert_eq!(evict_post_params(&post_config), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_registered_proof_configs() -> Result<()> {
+    for proof in RegisteredSealProof::ALL.iter().copied() {
+        assert_eq!(RegisteredSealProof::try_from(u64::from(proof))?, proof);
+
+        let config = proof.as_v1_config();
+        let info = sector_size_info(config.sector_size.into())?;
+        assert_eq!(
+            config.partitions.0,
+            info.porep_partitions_for(config.api_version)?
+        );
+        assert_eq!(proof.shape(), info.shape);
+        assert_eq!(
+            is_legacy_porep_id(config.porep_id),
+            config.api_version == ApiVersion::V1_0_0
+        );
+
+        let winning = proof.registered_winning_post_proof().as_v1_config();
+        assert_eq!(winning.sector_size, config.sector_size);
+        assert_eq!(winning.typ, PoStType::Winning);
+        assert_eq!(winning.sector_count, WINNING_POST_SECTOR_COUNT);
+
+        let window = proof.registered_window_post_proof().as_v1_config();
+        assert_eq!(window.sector_size, config.sector_size);
+        assert_eq!(window.typ, PoStType::Window);
+        assert_eq!(window.sector_count, info.window_post_sector_count);
+        assert_eq!(window.challenge_count, WINDOW_POST_CHALLENGE_COUNT);
+
+        match proof.registered_update_proof() {
+            Ok(update) => {
+                // Sectors sealed with synthetic or non-interactive PoRep are updated like V1_1
+                // sectors.
+                let seal_proof = update.registered_seal_proof();
+                assert_eq!(seal_proof.sector_size(), proof.sector_size());
+                assert_eq!(seal_proof.api_version(), ApiVersion::V1_1_0);
+                if config.api_version == ApiVersion::V1_1_0 {
+                    assert_eq!(seal_proof, proof);
+                }
+                assert_eq!(RegisteredUpdateProof::try_from(u64::from(update))?, update);
+            }
+            Err(_) => assert_eq!(config.api_version, ApiVersion::V1_0_0),
+        }
+    }
+
+    for proof in RegisteredPoStProof::ALL.iter().copied() {
+        assert_eq!(RegisteredPoStProof::try_from(u64::from(proof))?, proof);
+    }
+
+    assert_eq!(
+        RegisteredSealProof::StackedDrg2KiBV1_1.porep_id()[..8],
+        5u64.to_le_bytes()
+    );
+    assert_eq!(
+        RegisteredSealProof::StackedDrg2KiBV1_2.api_version(),
+        ApiVersion::V1_2_0
+    );
+    assert_eq!(
+        RegisteredSealProof::StackedDrg32GiBV1_3.porep_id()[..8],
+        18u64.to_le_bytes()
+    );
+    assert_eq!(RegisteredSealProof::StackedDrg32GiBV1_3.partitions().0, 126);
+    assert!(RegisteredSealProof::try_from(20).is_err());
+    assert!(RegisteredUpdateProof::try_from(5).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_commit_phase1_synthetic_2kib() -> Result<()> {
+    let registered_proof = RegisteredSealProof::StackedDrg2KiBV1_2;
+    let sector_size = u64::from(registered_proof.sector_size());
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = generate_prover_id(&mut rng);
+    let sector_id = SectorId::from(rng.gen::<u64>());
+    let ticket = rng.gen();
+    let seed = rng.gen();
+
+    let (staged_sector_file, piece_infos) = stage_sector(sector_size)?;
+
+    let cache_dir = tempdir()?;
+    let sealed_sector_file = NamedTempFile::new()?;
+
+    let phase1_output = registered::seal_pre_commit_phase1(
+        registered_proof,
+        cache_dir.path(),
+        staged_sector_file.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        &piece_infos,
+    )?;
+    let pre_commit_output = registered::seal_pre_commit_phase2(
+        registered_proof,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    // The layers are not needed to prove the challenges selected by the seed.
+    let synthetic_path = cache_dir
+        .path()
+        .join(CacheKey::SyntheticPoRepProofs.to_string());
+    assert!(synthetic_path.exists());
+    registered::clear_cache(registered_proof, cache_dir.path())?;
+    let layer_path =
+        StoreConfig::data_path(&cache_dir.path().to_path_buf(), &CacheKey::label_layer(1));
+    assert!(!layer_path.exists());
+    validate_cache_for_commit::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    // Commit phase 1 checks the vanilla proofs before returning them.
+    let phase1_output = registered::seal_commit_phase1(
+        registered_proof,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        pre_commit_output,
+        &piece_infos,
+    )?;
+    let phase1_output = phase1_output.into_tree::<SectorShape2KiB>()?;
+    assert_eq!(
+        phase1_output.vanilla_proofs.len(),
+        usize::from(registered_proof.partitions())
+    );
+
+    clear_synthetic_proofs(cache_dir.path())?;
+    assert!(!synthetic_path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_commit_phase1_non_interactive_2kib() -> Result<()> {
+    let registered_proof = RegisteredSealProof::StackedDrg2KiBV1_3;
+    let sector_size = u64::from(registered_proof.sector_size());
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = generate_prover_id(&mut rng);
+    let sector_id = SectorId::from(rng.gen::<u64>());
+    let ticket = rng.gen();
+    let seeds: [[u8; 32]; 2] = [rng.gen(), rng.gen()];
+
+    let (staged_sector_file, piece_infos) = stage_sector(sector_size)?;
+
+    let cache_dir = tempdir()?;
+    let sealed_sector_file = NamedTempFile::new()?;
+
+    let phase1_output = registered::seal_pre_commit_phase1(
+        registered_proof,
+        cache_dir.path(),
+        staged_sector_file.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        &piece_infos,
+    )?;
+    let pre_commit_output = registered::seal_pre_commit_phase2(
+        registered_proof,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    // The challenges do not depend on the seed, so the commit phase does not wait for it.
+    let vanilla_proofs = seeds
+        .iter()
+        .map(|seed| {
+            let phase1_output = registered::seal_commit_phase1(
+                registered_proof,
+                cache_dir.path(),
+                sealed_sector_file.path(),
+                prover_id,
+                sector_id,
+                ticket,
+                *seed,
+                pre_commit_output.clone(),
+                &piece_infos,
+            )?;
+            let phase1_output = phase1_output.into_tree::<SectorShape2KiB>()?;
+            Ok(phase1_output.vanilla_proofs)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    assert_eq!(
+        vanilla_proofs[0].len(),
+        usize::from(registered_proof.partitions())
+    );
+    assert!(
+        registered_proof.partitions().0 > RegisteredSealProof::StackedDrg2KiBV1_1.partitions().0
+    );
+    assert_eq!(
+        serialize(&vanilla_proofs[0])?,
+        serialize(&vanilla_proofs[1])?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_pre_commit_phase1_lockstep_2kib() -> Result<()> {
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = generate_prover_id(&mut rng);
+
+    let (staged_sector_file, piece_infos) = stage_sector(sector_size)?;
+
+    // The same data sealed into three sectors with their own replica ids.
+    let sectors = (0..3)
+        .map(|_| (SectorId::from(rng.gen::<u64>()), rng.gen::<[u8; 32]>()))
+        .collect::<Vec<_>>();
+    let cache_dirs = (0..2 * sectors.len())
+        .map(|_| tempdir())
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let sealed_sector_files = (0..2 * sectors.len())
+        .map(|_| NamedTempFile::new())
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let inputs = sectors
+        .iter()
+        .zip(cache_dirs.iter().zip(sealed_sector_files.iter()))
+        .map(
+            |((sector_id, ticket), (cache_dir, sealed_sector_file))| SealPreCommitPhase1Input {
+                cache_path: cache_dir.path().to_path_buf(),
+                in_path: staged_sector_file.path().to_path_buf(),
+                out_path: sealed_sector_file.path().to_path_buf(),
+                prover_id,
+                sector_id: *sector_id,
+                ticket: *ticket,
+                piece_infos: piece_infos.clone(),
+            },
+        )
+        .collect::<Vec<_>>();
+    let lockstep = || seal_pre_commit_phase1_lockstep::<SectorShape2KiB>(config, &inputs);
+    // The sectors are labeled in lockstep by the multicore SDR, they are checked against
+    // sectors sealed one by one.
+    #[cfg(feature = "multicore-sdr")]
+    let lockstep_outputs = settings::with_settings(
+        Arc::new(Settings {
+            use_multicore_sdr: true,
+            ..(*SETTINGS).clone()
+        }),
+        lockstep,
+    )?;
+    #[cfg(not(feature = "multicore-sdr"))]
+    let lockstep_outputs = lockstep()?;
+    assert_eq!(lockstep_outputs.len(), sectors.len());
+
+    // The estimate covers the cache dirs of all sectors labeled together.
+    let estimate =
+        estimate_seal_pre_commit_phase1_lockstep::<SectorShape2KiB>(config, sectors.len())?;
+    let cache_size = cache_dirs[..sectors.len()]
+        .iter()
+        .map(|cache_dir| Ok(get_cache_file_sizes(cache_dir)?.values().sum::<u64>()))
+        .sum::<Result<u64>>()?;
+    assert_eq!(estimate.disk_written, cache_size);
+
+    for (i, (input, lockstep_output)) in inputs.iter().zip(lockstep_outputs).enumerate() {
+        let cache_dir = &cache_dirs[sectors.len() + i];
+        let sealed_sector_file = &sealed_sector_files[sectors.len() + i];
+        let output = seal_pre_commit_phase1::<_, _, _, SectorShape2KiB>(
+            config,
+            cache_dir.path(),
+            staged_sector_file.path(),
+            sealed_sector_file.path(),
+            prover_id,
+            input.sector_id,
+            input.ticket,
+            &input.piece_infos,
+        )?;
+        assert_eq!(lockstep_output.comm_d, output.comm_d);
+        assert_eq!(lockstep_output.replica_id, output.replica_id);
+
+        let lockstep_pre_commit =
+            seal_pre_commit_phase2(config, lockstep_output, &input.cache_path, &input.out_path)?;
+        let pre_commit =
+            seal_pre_commit_phase2(config, output, cache_dir.path(), sealed_sector_file.path())?;
+        assert_eq!(lockstep_pre_commit.comm_r, pre_commit.comm_r);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_registered_seal_lifecycle_2kib_v1_1() -> Result<()> {
+    let registered_proof = RegisteredSealProof::StackedDrg2KiBV1_1;
+    let sector_size = u64::from(registered_proof.sector_size());
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = generate_prover_id(&mut rng);
+    let sector_id = SectorId::from(rng.gen::<u64>());
+    let ticket = rng.gen();
+    let seed = rng.gen();
+
+    let (staged_sector_file, piece_infos) = stage_sector(sector_size)?;
+
+    let cache_dir = tempdir()?;
+    let sealed_sector_file = NamedTempFile::new()?;
+
+    let phase1_output = registered::seal_pre_commit_phase1(
+        registered_proof,
+        cache_dir.path(),
+        staged_sector_file.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        &piece_infos,
+    )?;
+    assert_eq!(phase1_output.shape(), registered_proof.shape());
+
+    // The outputs are passed between phases in serialized form.
+    let phase1_output = serde_json::from_slice(&serde_json::to_vec(&phase1_output)?)?;
+    let pre_commit_output = registered::seal_pre_commit_phase2(
+        registered_proof,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    let comm_r = pre_commit_output.comm_r;
+    let comm_d = pre_commit_output.comm_d;
+
+    let phase1_output = registered::seal_commit_phase1(
+        registered_proof,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        pre_commit_output,
+        &piece_infos,
+    )?;
+    registered::clear_cache(registered_proof, cache_dir.path())?;
+
+    let phase1_output = bincode::deserialize(&serialize(&phase1_output)?)?;
+    let commit_output =
+        registered::seal_commit_phase2(registered_proof, phase1_output, prover_id, sector_id)?;
+    assert!(registered::verify_seal(
+        registered_proof,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        &commit_output.proof,
+    )?);
+
+    let post_proof = registered_proof.registered_winning_post_proof();
+    let randomness = rng.gen();
+    let challenged_sectors = registered::generate_winning_post_sector_challenge(
+        post_proof,
+        &randomness,
+        WINNING_POST_SECTOR_COUNT as u64,
+        prover_id,
+    )?;
+    assert_eq!(challenged_sectors, vec![0]);
+
+    let priv_replicas = vec![(
+        sector_id,
+        registered::PrivateReplicaInfo::new(
+            sealed_sector_file.path().into(),
+            comm_r,
+            cache_dir.path().into(),
+        ),
+    )];
+    let proof =
+        registered::generate_winning_post(post_proof, &randomness, &priv_replicas, prover_id)?;
+
+    let pub_replicas = vec![(sector_id, PublicReplicaInfo::new(comm_r)?)];
+    assert!(registered::verify_winning_post(
+        post_proof,
+        &randomness,
+        &pub_replicas,
+        prover_id,
+        &proof,
+    )?);
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use filecoin_proofs::{
-    with_shape, SECTOR_SIZE_16_MIB, SECTOR_SIZE_1_GIB, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
+    register_sector_size, sector_size_info, with_shape, SectorSizeInfo, TreeShape,
+    SECTOR_SIZE_16_MIB, SECTOR_SIZE_1_GIB, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
     SECTOR_SIZE_4_KIB, SECTOR_SIZE_512_MIB, SECTOR_SIZE_64_GIB, SECTOR_SIZE_8_MIB,
 };
 use generic_array::typenum::Unsigned;
@@ -74,6 +75,34 @@ fn test_with_shape_macro() {
     test_with_shape_macro_aux(SECTOR_SIZE_64_GIB);
 }
 
+#[test]
+fn test_with_shape_macro_registered_sizes() {
+    // 128MiB and 4GiB, which are not published.
+    for sector_size in [1 << 27, 1 << 32].iter().copied() {
+        let (base, sub, top) = canonical_shape(sector_size);
+        register_sector_size(SectorSizeInfo {
+            sector_size,
+            shape: TreeShape::new(base, sub, top),
+            layers: 2,
+            porep_minimum_challenges: 2,
+            porep_partitions: 1,
+            synthetic_porep_challenges: 16,
+            ni_porep_minimum_challenges: Some(4),
+            window_post_sector_count: 2,
+            published: false,
+        })
+        .expect("failed to register sector size");
+        assert_eq!(
+            sector_size_info(sector_size)
+                .expect("sector size not registered")
+                .layers,
+            2
+        );
+
+        test_with_shape_macro_aux(sector_size);
+    }
+}
+
 fn test_with_shape_macro_aux(sector_size: u64) {
     let expected = canonical_shape(sector_size);
     let arities = with_shape!(sector_size, arities_to_usize);
@@ -3,8 +3,8 @@ use std::panic::panic_any;
 use blstrs::Scalar as Fr;
 use ff::Field;
 use filecoin_proofs::{
-    as_safe_commitment, verify_seal, DefaultOctLCTree, DefaultTreeDomain, PoRepConfig,
-    PoRepProofPartitions, SectorSize, POREP_PARTITIONS, SECTOR_SIZE_2_KIB, TEST_SEED,
+    as_safe_commitment, sector_size_info, verify_seal, DefaultOctLCTree, DefaultTreeDomain,
+    PoRepConfig, PoRepProofPartitions, SectorSize, SECTOR_SIZE_2_KIB, TEST_SEED,
 };
 use fr32::bytes_into_fr;
 use rand::SeedableRng;
@@ -29,11 +29,9 @@ fn test_verify_seal_fr32_validation() {
             PoRepConfig {
                 sector_size: SectorSize(SECTOR_SIZE_2_KIB),
                 partitions: PoRepProofPartitions(
-                    *POREP_PARTITIONS
-                        .read()
-                        .expect("POREP_PARTITIONS poisoned")
-                        .get(&SECTOR_SIZE_2_KIB)
-                        .expect("unknown sector size"),
+                    sector_size_info(SECTOR_SIZE_2_KIB)
+                        .expect("unknown sector size")
+                        .porep_partitions,
                 ),
                 porep_id: arbitrary_porep_id,
                 api_version: ApiVersion::V1_1_0,
@@ -68,11 +66,9 @@ fn test_verify_seal_fr32_validation() {
             PoRepConfig {
                 sector_size: SectorSize(SECTOR_SIZE_2_KIB),
                 partitions: PoRepProofPartitions(
-                    *POREP_PARTITIONS
-                        .read()
-                        .expect("POREP_PARTITIONS poisoned")
-                        .get(&SECTOR_SIZE_2_KIB)
-                        .expect("unknown sector size"),
+                    sector_size_info(SECTOR_SIZE_2_KIB)
+                        .expect("unknown sector size")
+                        .porep_partitions,
                 ),
                 porep_id: arbitrary_porep_id,
                 api_version: ApiVersion::V1_1_0,
@@ -111,11 +107,9 @@ fn test_verify_seal_fr32_validation() {
             PoRepConfig {
                 sector_size: SectorSize(SECTOR_SIZE_2_KIB),
                 partitions: PoRepProofPartitions(
-                    *POREP_PARTITIONS
-                        .read()
-                        .expect("POREP_PARTITIONS poisoned")
-                        .get(&SECTOR_SIZE_2_KIB)
-                        .expect("unknown sector size"),
+                    sector_size_info(SECTOR_SIZE_2_KIB)
+                        .expect("unknown sector size")
+                        .porep_partitions,
                 ),
                 porep_id: arbitrary_porep_id,
                 api_version: ApiVersion::V1_1_0,
@@ -0,0 +1,63 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rand::{thread_rng, RngCore};
+use sha2raw::Implementation;
+
+const H256: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+fn compress256_multi_benchmark(c: &mut Criterion) {
+    let messages_count = 16;
+    let params = vec![1, 7, 38];
+
+    let mut impls = vec![("single", Implementation::detect())];
+    #[cfg(target_arch = "x86_64")]
+    {
+        if let Some(imp) = Implementation::avx2_if_supported() {
+            impls.push(("avx2", imp));
+        }
+        if let Some(imp) = Implementation::avx512_if_supported() {
+            impls.push(("avx512", imp));
+        }
+    }
+
+    let mut group = c.benchmark_group("compress256-multi");
+    for blocks in params {
+        let mut rng = thread_rng();
+        let messages = (0..messages_count)
+            .map(|_| {
+                let mut message = vec![0u8; blocks * 64];
+                rng.fill_bytes(&mut message);
+                message
+            })
+            .collect::<Vec<_>>();
+        let messages = messages.iter().map(|m| &m[..]).collect::<Vec<_>>();
+        let halves = messages
+            .iter()
+            .map(|m| m.chunks(32).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        group.throughput(Throughput::Bytes((messages_count * blocks * 64) as u64));
+
+        for (name, imp) in &impls {
+            group.bench_function(format!("{}-{}x{}", name, messages_count, blocks), |b| {
+                let mut states = vec![H256; messages_count];
+                b.iter(|| {
+                    if *name == "single" {
+                        // One message after the other with the single message path.
+                        for (state, halves) in states.iter_mut().zip(halves.iter()) {
+                            imp.compress256(state, halves);
+                        }
+                    } else {
+                        imp.compress256_multi(&mut states, &messages);
+                    }
+                    black_box(&states);
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, compress256_multi_benchmark);
+criterion_main!(benches);
@@ -10,8 +10,13 @@ pub use digest::Digest;
 mod consts;
 mod platform;
 mod sha256;
+#[cfg(target_arch = "x86_64")]
+mod sha256_avx2;
+#[cfg(target_arch = "x86_64")]
+mod sha256_avx512;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod sha256_intrinsics;
 mod sha256_utils;
 
-pub use sha256::Sha256;
+pub use platform::Implementation;
+pub use sha256::{compress256_multi, Sha256};
@@ -1,6 +1,9 @@
+use crate::consts::STATE_LEN;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use crate::sha256_intrinsics;
 use crate::sha256_utils;
+#[cfg(target_arch = "x86_64")]
+use crate::{sha256_avx2, sha256_avx512};
 
 #[allow(dead_code)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -12,11 +15,39 @@ enum Platform {
     Sha,
 }
 
+/// How several independent messages are compressed.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MultiPlatform {
+    /// One message after the other, with the single message implementation.
+    Single,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+}
+
 #[derive(Clone, Copy, Debug)]
-pub struct Implementation(Platform);
+pub struct Implementation(Platform, MultiPlatform);
 
 impl Implementation {
     pub fn detect() -> Self {
+        let Implementation(platform, _) = Self::detect_single();
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(Implementation(_, multi)) = Self::avx512_if_supported() {
+                return Implementation(platform, multi);
+            }
+            if let Some(Implementation(_, multi)) = Self::avx2_if_supported() {
+                return Implementation(platform, multi);
+            }
+        }
+
+        Implementation(platform, MultiPlatform::Single)
+    }
+
+    fn detect_single() -> Self {
         // Try the different implementations in order of how fast/modern they are.
         #[cfg(target_arch = "x86_64")]
         {
@@ -35,7 +66,7 @@ impl Implementation {
     }
 
     pub fn portable() -> Self {
-        Implementation(Platform::Portable)
+        Implementation(Platform::Portable, MultiPlatform::Single)
     }
 
     #[cfg(target_arch = "x86_64")]
@@ -56,7 +87,7 @@ impl Implementation {
 
         // Make sure this computer actually supports it
         if is_runtime_ok {
-            return Some(Implementation(Platform::Sha));
+            return Some(Implementation(Platform::Sha, MultiPlatform::Single));
         }
 
         None
@@ -64,7 +95,78 @@ impl Implementation {
 
     #[cfg(feature = "asm")]
     pub fn asm_if_supported() -> Option<Self> {
-        Some(Implementation(Platform::Asm))
+        Some(Implementation(Platform::Asm, MultiPlatform::Single))
+    }
+
+    /// The fastest single message implementation, compressing several messages with AVX2.
+    #[cfg(target_arch = "x86_64")]
+    pub fn avx2_if_supported() -> Option<Self> {
+        cpufeatures::new!(cpuid_avx2, "avx2");
+
+        if cpuid_avx2::get() {
+            let Implementation(platform, _) = Self::detect_single();
+            return Some(Implementation(platform, MultiPlatform::Avx2));
+        }
+
+        None
+    }
+
+    /// The fastest single message implementation, compressing several messages with AVX-512.
+    #[cfg(target_arch = "x86_64")]
+    pub fn avx512_if_supported() -> Option<Self> {
+        cpufeatures::new!(cpuid_avx512, "avx512f");
+
+        if cpuid_avx512::get() {
+            let Implementation(platform, _) = Self::detect_single();
+            return Some(Implementation(platform, MultiPlatform::Avx512));
+        }
+
+        None
+    }
+
+    /// The number of messages [`Implementation::compress256_multi`] compresses at once.
+    pub fn lanes(self) -> usize {
+        match self.1 {
+            MultiPlatform::Single => 1,
+            #[cfg(target_arch = "x86_64")]
+            MultiPlatform::Avx2 => sha256_avx2::LANES,
+            #[cfg(target_arch = "x86_64")]
+            MultiPlatform::Avx512 => sha256_avx512::LANES,
+        }
+    }
+
+    /// Compresses the blocks of `messages[i]` into `states[i]`, hashing up to
+    /// [`Implementation::lanes`] messages in parallel. All messages must consist of the same
+    /// number of 64 byte blocks.
+    pub fn compress256_multi(self, states: &mut [[u32; STATE_LEN]], messages: &[&[u8]]) {
+        assert_eq!(states.len(), messages.len(), "one state per message");
+        let len = messages.first().map_or(0, |message| message.len());
+        assert_eq!(len % 64, 0, "messages must consist of whole blocks");
+        assert!(
+            messages.iter().all(|message| message.len() == len),
+            "messages must have the same length"
+        );
+
+        let lanes = self.lanes();
+        for (states, messages) in states.chunks_mut(lanes).zip(messages.chunks(lanes)) {
+            match self.1 {
+                #[cfg(target_arch = "x86_64")]
+                MultiPlatform::Avx2 if messages.len() > 1 => {
+                    unsafe { sha256_avx2::compress256(states, messages) };
+                }
+                #[cfg(target_arch = "x86_64")]
+                MultiPlatform::Avx512 if messages.len() > 1 => {
+                    unsafe { sha256_avx512::compress256(states, messages) };
+                }
+                _ => {
+                    for (state, message) in states.iter_mut().zip(messages) {
+                        for block in message.chunks(64) {
+                            self.compress256(state, &[&block[..32], &block[32..]]);
+                        }
+                    }
+                }
+            }
+        }
     }
 
     #[inline]
@@ -1,7 +1,10 @@
 use byteorder::{ByteOrder, BE};
 use lazy_static::lazy_static;
 
-use crate::{consts::H256, platform::Implementation};
+use crate::{
+    consts::{H256, STATE_LEN},
+    platform::Implementation,
+};
 
 lazy_static! {
     static ref IMPL: Implementation = Implementation::detect();
@@ -81,6 +84,12 @@ impl Sha256 {
 
 opaque_debug::implement!(Sha256);
 
+/// Compresses the blocks of `messages[i]` into `states[i]` with the detected implementation,
+/// see [`Implementation::compress256_multi`].
+pub fn compress256_multi(states: &mut [[u32; STATE_LEN]], messages: &[&[u8]]) {
+    IMPL.compress256_multi(states, messages);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,6 +109,57 @@ mod tests {
         fuzz(1_000);
     }
 
+    #[test]
+    fn test_compress256_multi() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let mut impls = vec![Implementation::portable(), Implementation::detect()];
+        #[cfg(target_arch = "x86_64")]
+        {
+            impls.extend(Implementation::avx2_if_supported());
+            impls.extend(Implementation::avx512_if_supported());
+        }
+
+        for count in 0..=35 {
+            for blocks in 1..=4 {
+                let messages = (0..count)
+                    .map(|_| {
+                        let mut message = vec![0u8; 64 * blocks];
+                        rng.fill_bytes(&mut message);
+                        message
+                    })
+                    .collect::<Vec<_>>();
+                let messages = messages.iter().map(|m| &m[..]).collect::<Vec<_>>();
+                let initial = (0..count)
+                    .map(|_| {
+                        let mut state = H256;
+                        state[0] = rng.next_u32();
+                        state
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut expected = initial.clone();
+                for (state, message) in expected.iter_mut().zip(messages.iter()) {
+                    let halves = message.chunks(32).collect::<Vec<_>>();
+                    Implementation::portable().compress256(state, &halves);
+                }
+
+                for imp in &impls {
+                    let mut states = initial.clone();
+                    imp.compress256_multi(&mut states, &messages);
+                    assert_eq!(states, expected, "{:?}, {} x {} blocks", imp, count, blocks);
+                }
+
+                let mut states = initial.clone();
+                compress256_multi(&mut states, &messages);
+                assert_eq!(states, expected);
+            }
+        }
+    }
+
     fn fuzz(n: usize) {
         let rng = &mut XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
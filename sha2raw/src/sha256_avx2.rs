@@ -0,0 +1,120 @@
+//! Multi-buffer SHA-256 compression with AVX2, hashing up to eight messages at once with one
+//! message per 32-bit lane.
+
+use std::arch::x86_64::*;
+
+use byteorder::{ByteOrder, BE};
+
+use crate::consts::{BLOCK_LEN, K32, STATE_LEN};
+
+/// The number of messages compressed at once.
+pub const LANES: usize = 8;
+
+macro_rules! rotr {
+    ($x:expr, $n:literal) => {
+        _mm256_or_si256(_mm256_srli_epi32($x, $n), _mm256_slli_epi32($x, 32 - $n))
+    };
+}
+
+macro_rules! xor3 {
+    ($a:expr, $b:expr, $c:expr) => {
+        _mm256_xor_si256(_mm256_xor_si256($a, $b), $c)
+    };
+}
+
+macro_rules! add {
+    ($a:expr, $($b:expr),+) => {{
+        let sum = $a;
+        $(let sum = _mm256_add_epi32(sum, $b);)+
+        sum
+    }};
+}
+
+/// Compresses the blocks of `messages[i]` into `states[i]`, for up to [`LANES`] messages of the
+/// same length. Unused lanes hash the first message and are discarded.
+///
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub unsafe fn compress256(states: &mut [[u32; STATE_LEN]], messages: &[&[u8]]) {
+    debug_assert!(!messages.is_empty() && messages.len() <= LANES);
+    debug_assert_eq!(states.len(), messages.len());
+
+    let mut words = [[0u32; LANES]; STATE_LEN];
+    for (lane, state) in states.iter().enumerate() {
+        for (word, value) in words.iter_mut().zip(state.iter()) {
+            word[lane] = *value;
+        }
+    }
+    let mut state = [_mm256_setzero_si256(); STATE_LEN];
+    for (vector, word) in state.iter_mut().zip(words.iter()) {
+        *vector = _mm256_loadu_si256(word.as_ptr() as *const __m256i);
+    }
+
+    let mut block = [0u32; BLOCK_LEN];
+    let mut schedule = [[0u32; LANES]; BLOCK_LEN];
+    let mut w = [_mm256_setzero_si256(); 64];
+    for offset in (0..messages[0].len()).step_by(64) {
+        // Transpose the block, so that each vector holds the same word of every message.
+        for lane in 0..LANES {
+            let message = messages.get(lane).unwrap_or(&messages[0]);
+            BE::read_u32_into(&message[offset..offset + 64], &mut block);
+            for (word, value) in schedule.iter_mut().zip(block.iter()) {
+                word[lane] = *value;
+            }
+        }
+        for (vector, word) in w.iter_mut().zip(schedule.iter()) {
+            *vector = _mm256_loadu_si256(word.as_ptr() as *const __m256i);
+        }
+
+        for t in 16..64 {
+            let s0 = xor3!(
+                rotr!(w[t - 15], 7),
+                rotr!(w[t - 15], 18),
+                _mm256_srli_epi32(w[t - 15], 3)
+            );
+            let s1 = xor3!(
+                rotr!(w[t - 2], 17),
+                rotr!(w[t - 2], 19),
+                _mm256_srli_epi32(w[t - 2], 10)
+            );
+            w[t] = add!(w[t - 16], s0, w[t - 7], s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for t in 0..64 {
+            let s1 = xor3!(rotr!(e, 6), rotr!(e, 11), rotr!(e, 25));
+            let ch = _mm256_xor_si256(_mm256_and_si256(e, f), _mm256_andnot_si256(e, g));
+            let t1 = add!(h, s1, ch, _mm256_set1_epi32(K32[t] as i32), w[t]);
+            let s0 = xor3!(rotr!(a, 2), rotr!(a, 13), rotr!(a, 22));
+            let maj = _mm256_or_si256(
+                _mm256_and_si256(a, b),
+                _mm256_and_si256(c, _mm256_or_si256(a, b)),
+            );
+            let t2 = _mm256_add_epi32(s0, maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = _mm256_add_epi32(d, t1);
+            d = c;
+            c = b;
+            b = a;
+            a = _mm256_add_epi32(t1, t2);
+        }
+
+        for (vector, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+            *vector = _mm256_add_epi32(*vector, *value);
+        }
+    }
+
+    for (vector, word) in state.iter().zip(words.iter_mut()) {
+        _mm256_storeu_si256(word.as_mut_ptr() as *mut __m256i, *vector);
+    }
+    for (lane, state) in states.iter_mut().enumerate() {
+        for (value, word) in state.iter_mut().zip(words.iter()) {
+            *value = word[lane];
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Multi-buffer SHA-256 compression with AVX-512, hashing up to sixteen messages at once with
+//! one message per 32-bit lane.
+//!
+//! The AVX-512 intrinsics are not stable on our toolchain, so the compression is written in
+//! inline assembly. Rust transposes the blocks into the message schedule, the assembly expands
+//! the schedule and runs the rounds.
+
+use std::arch::asm;
+
+use byteorder::{ByteOrder, BE};
+
+use crate::consts::{BLOCK_LEN, K32, STATE_LEN};
+
+/// The number of messages compressed at once.
+pub const LANES: usize = 16;
+
+static K: [u32; 64] = K32;
+
+#[repr(align(64))]
+struct Vectors<const N: usize>([[u32; LANES]; N]);
+
+/// Compresses the blocks of `messages[i]` into `states[i]`, for up to [`LANES`] messages of the
+/// same length. Unused lanes hash the first message and are discarded.
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F.
+pub unsafe fn compress256(states: &mut [[u32; STATE_LEN]], messages: &[&[u8]]) {
+    debug_assert!(!messages.is_empty() && messages.len() <= LANES);
+    debug_assert_eq!(states.len(), messages.len());
+
+    let mut state = Vectors([[0u32; LANES]; STATE_LEN]);
+    for (lane, values) in states.iter().enumerate() {
+        for (word, value) in state.0.iter_mut().zip(values.iter()) {
+            word[lane] = *value;
+        }
+    }
+
+    let mut block = [0u32; BLOCK_LEN];
+    let mut w = Vectors([[0u32; LANES]; 64]);
+    for offset in (0..messages[0].len()).step_by(64) {
+        // Transpose the block, so that each vector holds the same word of every message.
+        for lane in 0..LANES {
+            let message = messages.get(lane).unwrap_or(&messages[0]);
+            BE::read_u32_into(&message[offset..offset + 64], &mut block);
+            for (word, value) in w.0.iter_mut().zip(block.iter()) {
+                word[lane] = *value;
+            }
+        }
+
+        // zmm0-zmm7 hold the working variables a-h, zmm8-zmm12 are temporaries.
+        asm!(
+            // Expand the message schedule, with `w` pointing at W[t - 16].
+            "mov {n:e}, 48",
+            "2:",
+            "vmovdqu32 zmm8, [{w} + 14*64]",
+            "vprord zmm9, zmm8, 17",
+            "vprord zmm10, zmm8, 19",
+            "vpsrld zmm11, zmm8, 10",
+            "vpternlogd zmm9, zmm10, zmm11, 0x96",
+            "vmovdqu32 zmm8, [{w} + 1*64]",
+            "vprord zmm10, zmm8, 7",
+            "vprord zmm11, zmm8, 18",
+            "vpsrld zmm12, zmm8, 3",
+            "vpternlogd zmm10, zmm11, zmm12, 0x96",
+            "vpaddd zmm9, zmm9, zmm10",
+            "vpaddd zmm9, zmm9, [{w} + 9*64]",
+            "vpaddd zmm9, zmm9, [{w}]",
+            "vmovdqu32 [{w} + 16*64], zmm9",
+            "add {w}, 64",
+            "dec {n:e}",
+            "jnz 2b",
+            "sub {w}, 48*64",
+            // Load the state.
+            "vmovdqu32 zmm0, [{s}]",
+            "vmovdqu32 zmm1, [{s} + 1*64]",
+            "vmovdqu32 zmm2, [{s} + 2*64]",
+            "vmovdqu32 zmm3, [{s} + 3*64]",
+            "vmovdqu32 zmm4, [{s} + 4*64]",
+            "vmovdqu32 zmm5, [{s} + 5*64]",
+            "vmovdqu32 zmm6, [{s} + 6*64]",
+            "vmovdqu32 zmm7, [{s} + 7*64]",
+            // The rounds, with `w` pointing at W[t] and `k` at K[t].
+            "mov {n:e}, 64",
+            "3:",
+            // T1 = h + S1(e) + Ch(e, f, g) + K[t] + W[t]
+            "vprord zmm8, zmm4, 6",
+            "vprord zmm9, zmm4, 11",
+            "vprord zmm10, zmm4, 25",
+            "vpternlogd zmm8, zmm9, zmm10, 0x96",
+            "vmovdqa64 zmm9, zmm4",
+            "vpternlogd zmm9, zmm5, zmm6, 0xca",
+            "vpaddd zmm8, zmm8, zmm9",
+            "vpaddd zmm8, zmm8, zmm7",
+            "vpaddd zmm8, zmm8, [{w}]",
+            "vpaddd zmm8, zmm8, dword ptr [{k}]{{1to16}}",
+            // T2 = S0(a) + Maj(a, b, c)
+            "vprord zmm9, zmm0, 2",
+            "vprord zmm10, zmm0, 13",
+            "vprord zmm11, zmm0, 22",
+            "vpternlogd zmm9, zmm10, zmm11, 0x96",
+            "vmovdqa64 zmm10, zmm0",
+            "vpternlogd zmm10, zmm1, zmm2, 0xe8",
+            "vpaddd zmm9, zmm9, zmm10",
+            "vmovdqa64 zmm7, zmm6",
+            "vmovdqa64 zmm6, zmm5",
+            "vmovdqa64 zmm5, zmm4",
+            "vpaddd zmm4, zmm3, zmm8",
+            "vmovdqa64 zmm3, zmm2",
+            "vmovdqa64 zmm2, zmm1",
+            "vmovdqa64 zmm1, zmm0",
+            "vpaddd zmm0, zmm8, zmm9",
+            "add {w}, 64",
+            "add {k}, 4",
+            "dec {n:e}",
+            "jnz 3b",
+            // Add the working variables to the state.
+            "vpaddd zmm0, zmm0, [{s}]",
+            "vpaddd zmm1, zmm1, [{s} + 1*64]",
+            "vpaddd zmm2, zmm2, [{s} + 2*64]",
+            "vpaddd zmm3, zmm3, [{s} + 3*64]",
+            "vpaddd zmm4, zmm4, [{s} + 4*64]",
+            "vpaddd zmm5, zmm5, [{s} + 5*64]",
+            "vpaddd zmm6, zmm6, [{s} + 6*64]",
+            "vpaddd zmm7, zmm7, [{s} + 7*64]",
+            "vmovdqu32 [{s}], zmm0",
+            "vmovdqu32 [{s} + 1*64], zmm1",
+            "vmovdqu32 [{s} + 2*64], zmm2",
+            "vmovdqu32 [{s} + 3*64], zmm3",
+            "vmovdqu32 [{s} + 4*64], zmm4",
+            "vmovdqu32 [{s} + 5*64], zmm5",
+            "vmovdqu32 [{s} + 6*64], zmm6",
+            "vmovdqu32 [{s} + 7*64], zmm7",
+            s = in(reg) state.0.as_mut_ptr(),
+            w = inout(reg) w.0.as_mut_ptr() => _,
+            k = inout(reg) K.as_ptr() => _,
+            n = out(reg) _,
+            out("zmm0") _,
+            out("zmm1") _,
+            out("zmm2") _,
+            out("zmm3") _,
+            out("zmm4") _,
+            out("zmm5") _,
+            out("zmm6") _,
+            out("zmm7") _,
+            out("zmm8") _,
+            out("zmm9") _,
+            out("zmm10") _,
+            out("zmm11") _,
+            out("zmm12") _,
+            options(nostack),
+        );
+    }
+
+    for (lane, values) in states.iter_mut().enumerate() {
+        for (value, word) in values.iter_mut().zip(state.0.iter()) {
+            *value = word[lane];
+        }
+    }
+}
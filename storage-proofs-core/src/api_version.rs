@@ -8,6 +8,8 @@ use semver::Version;
 pub enum ApiVersion {
     V1_0_0,
     V1_1_0,
+    V1_2_0,
+    V1_3_0,
 }
 
 impl ApiVersion {
@@ -15,6 +17,26 @@ impl ApiVersion {
         match self {
             ApiVersion::V1_0_0 => Version::new(1, 0, 0),
             ApiVersion::V1_1_0 => Version::new(1, 1, 0),
+            ApiVersion::V1_2_0 => Version::new(1, 2, 0),
+            ApiVersion::V1_3_0 => Version::new(1, 3, 0),
+        }
+    }
+
+    /// Whether PoRep challenges are selected from synthetic challenges, which are proven before
+    /// the seed is known.
+    pub fn supports_synthetic_porep(&self) -> bool {
+        match self {
+            ApiVersion::V1_0_0 | ApiVersion::V1_1_0 | ApiVersion::V1_3_0 => false,
+            ApiVersion::V1_2_0 => true,
+        }
+    }
+
+    /// Whether PoRep challenges are derived from `comm_r` instead of an interactive seed, so that
+    /// the commit phase can run right after pre-commit phase 2.
+    pub fn supports_non_interactive_porep(&self) -> bool {
+        match self {
+            ApiVersion::V1_0_0 | ApiVersion::V1_1_0 | ApiVersion::V1_2_0 => false,
+            ApiVersion::V1_3_0 => true,
         }
     }
 }
@@ -40,7 +62,9 @@ impl FromStr for ApiVersion {
         match (api_version.major, api_version.minor, api_version.patch) {
             (1, 0, 0) => Ok(ApiVersion::V1_0_0),
             (1, 1, 0) => Ok(ApiVersion::V1_1_0),
-            (1, 1, _) | (1, 0, _) => Err(format_err!(
+            (1, 2, 0) => Ok(ApiVersion::V1_2_0),
+            (1, 3, 0) => Ok(ApiVersion::V1_3_0),
+            (1, 3, _) | (1, 2, _) | (1, 1, _) | (1, 0, _) => Err(format_err!(
                 "Could not parse API Version from string (patch)"
             )),
             (1, _, _) => Err(format_err!(
@@ -57,10 +81,32 @@ impl FromStr for ApiVersion {
 fn test_fmt() {
     assert_eq!(format!("{}", ApiVersion::V1_0_0), "1.0.0");
     assert_eq!(format!("{}", ApiVersion::V1_1_0), "1.1.0");
+    assert_eq!(format!("{}", ApiVersion::V1_2_0), "1.2.0");
+    assert_eq!(format!("{}", ApiVersion::V1_3_0), "1.3.0");
 }
 
 #[test]
 fn test_as_semver() {
     assert_eq!(ApiVersion::V1_0_0.as_semver().major, 1);
     assert_eq!(ApiVersion::V1_1_0.as_semver().major, 1);
+    assert_eq!(ApiVersion::V1_2_0.as_semver().minor, 2);
+    assert_eq!(ApiVersion::V1_3_0.as_semver().minor, 3);
+}
+
+#[test]
+fn test_from_str() {
+    assert_eq!(
+        "1.2.0".parse::<ApiVersion>().expect("failed to parse"),
+        ApiVersion::V1_2_0
+    );
+    assert!("1.2.1".parse::<ApiVersion>().is_err());
+    assert!(!ApiVersion::V1_1_0.supports_synthetic_porep());
+    assert!(ApiVersion::V1_2_0.supports_synthetic_porep());
+    assert_eq!(
+        "1.3.0".parse::<ApiVersion>().expect("failed to parse"),
+        ApiVersion::V1_3_0
+    );
+    assert!(!ApiVersion::V1_3_0.supports_synthetic_porep());
+    assert!(ApiVersion::V1_3_0.supports_non_interactive_porep());
+    assert!(!ApiVersion::V1_2_0.supports_non_interactive_porep());
 }
@@ -18,6 +18,7 @@ use rayon::prelude::{
 };
 
 use crate::{
+    enter_span,
     error::Result,
     multi_proof::MultiProof,
     parameter_cache::{CacheableParameters, ParameterSetMetadata},
@@ -90,8 +91,10 @@ where
         ensure!(partition_count > 0, "There must be partitions");
 
         info!("vanilla_proofs:start");
-        let vanilla_proofs =
-            S::prove_all_partitions(&pub_params.vanilla_params, pub_in, priv_in, partition_count)?;
+        let vanilla_proofs = {
+            let _span = enter_span!("vanilla_proofs", partitions = partition_count);
+            S::prove_all_partitions(&pub_params.vanilla_params, pub_in, priv_in, partition_count)?
+        };
 
         info!("vanilla_proofs:finish");
 
@@ -242,6 +245,9 @@ where
             "cannot create a circuit proof over missing vanilla proofs"
         );
 
+        let _span = enter_span!("circuit_proofs", partitions = vanilla_proofs.len());
+
+        let synthesis_span = enter_span!("synthesis");
         let circuits = vanilla_proofs
             .into_par_iter()
             .enumerate()
@@ -255,12 +261,17 @@ where
                 )
             })
             .collect::<Result<Vec<_>>>()?;
+        drop(synthesis_span);
 
+        // Note that bellperson synthesizes the constraint systems of the circuits as part of
+        // proving, so the `proving` span includes that work.
+        let proving_span = enter_span!("proving", priority);
         let groth_proofs = if priority {
             create_random_proof_batch_in_priority(circuits, groth_params, &mut rng)?
         } else {
             create_random_proof_batch(circuits, groth_params, &mut rng)?
         };
+        drop(proving_span);
 
         groth_proofs
             .into_iter()
@@ -165,7 +165,9 @@ impl<H: Hasher> Graph<H> for BucketGraph<H> {
 
                 let (predecessor_index, other_drg_parents) = match self.api_version {
                     ApiVersion::V1_0_0 => (m_prime, &mut parents[..]),
-                    ApiVersion::V1_1_0 => (0, &mut parents[1..]),
+                    ApiVersion::V1_1_0 | ApiVersion::V1_2_0 | ApiVersion::V1_3_0 => {
+                        (0, &mut parents[1..])
+                    }
                 };
 
                 for parent in other_drg_parents.iter_mut().take(m_prime) {
@@ -337,7 +339,7 @@ mod tests {
                             "immediate predecessor was not last DRG parent"
                         );
                     }
-                    ApiVersion::V1_1_0 => {
+                    ApiVersion::V1_1_0 | ApiVersion::V1_2_0 | ApiVersion::V1_3_0 => {
                         assert_eq!(
                             i - 1,
                             pa1[0] as usize,
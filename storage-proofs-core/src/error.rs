@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::path::PathBuf;
 
 pub use anyhow::Result;
 
@@ -41,6 +42,12 @@ pub enum Error {
     FaultySectors(Vec<SectorId>),
     #[error("Invalid parameters file: {}", _0)]
     InvalidParameters(String),
+    #[error("No cached parameters found at {}", _0.display())]
+    MissingParameters(PathBuf),
+    #[error("unsupported sector size: {0}")]
+    UnknownSectorSize(u64),
+    #[error("invalid sector size {0}: {1}")]
+    InvalidSectorSize(u64, String),
 }
 
 impl From<Box<dyn Any + Send>> for Error {
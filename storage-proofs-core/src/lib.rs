@@ -21,14 +21,19 @@ pub mod error;
 pub mod gadgets;
 pub mod measurements;
 pub mod merkle;
+pub mod metrics;
 pub mod multi_proof;
 pub mod parameter_cache;
+pub mod parameter_ledger;
+pub mod parameter_store;
 pub mod partitions;
 pub mod pieces;
 pub mod por;
 pub mod proof;
 pub mod sector;
+pub mod sector_registry;
 pub mod settings;
+pub mod spans;
 pub mod test_helper;
 pub mod util;
 
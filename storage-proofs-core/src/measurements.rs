@@ -3,12 +3,14 @@ use std::sync::{
     mpsc::{channel, Receiver, Sender},
     Mutex,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "measurements")]
 use lazy_static::lazy_static;
 use serde::Serialize;
 
+use crate::metrics;
+
 #[cfg(feature = "measurements")]
 lazy_static! {
     pub static ref OP_MEASUREMENTS: (
@@ -46,13 +48,31 @@ pub enum Operation {
     PostPartialTicketHash,
 }
 
+impl Operation {
+    /// Name of the operation, as used in metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::AddPiece => "add-piece",
+            Operation::GeneratePieceCommitment => "generate-piece-commitment",
+            Operation::GenerateTreeC => "generate-tree-c",
+            Operation::GenerateTreeRLast => "generate-tree-r-last",
+            Operation::CommD => "comm-d",
+            Operation::EncodeWindowTimeAll => "encode-window-time-all",
+            Operation::WindowCommLeavesTime => "window-comm-leaves-time",
+            Operation::PorepCommitTime => "porep-commit-time",
+            Operation::PostInclusionProofs => "post-inclusion-proofs",
+            Operation::PostFinalizeTicket => "post-finalize-ticket",
+            Operation::PostReadChallengedRange => "post-read-challenged-range",
+            Operation::PostPartialTicketHash => "post-partial-ticket-hash",
+        }
+    }
+}
+
 #[cfg(feature = "measurements")]
 pub fn measure_op<T, F>(op: Operation, f: F) -> T
 where
     F: FnOnce() -> T,
 {
-    use std::time::Instant;
-
     let cpu_time_start = cpu_time::ProcessTime::now();
     let wall_start_time = Instant::now();
 
@@ -70,6 +90,9 @@ where
         .stop()
         .unwrap();
 
+    let wall_time = wall_start_time.elapsed();
+    metrics::record_operation(op.as_str(), wall_time);
+
     let opt_tx = OP_MEASUREMENTS
         .0
         .lock()
@@ -80,7 +103,7 @@ where
             .send(OpMeasurement {
                 op,
                 cpu_time: cpu_time_start.elapsed(),
-                wall_time: wall_start_time.elapsed(),
+                wall_time,
             })
             .expect("failed to send to perf channel");
     }
@@ -89,9 +112,13 @@ where
 }
 
 #[cfg(not(feature = "measurements"))]
-pub fn measure_op<T, F>(_: Operation, f: F) -> T
+pub fn measure_op<T, F>(op: Operation, f: F) -> T
 where
     F: FnOnce() -> T,
 {
-    f()
+    let start = Instant::now();
+    let x = f();
+    metrics::record_operation(op.as_str(), start.elapsed());
+
+    x
 }
@@ -0,0 +1,495 @@
+//! Always-available, low overhead metrics.
+//!
+//! Metrics are registered in the global [`REGISTRY`] the first time they are
+//! used. The `record_*` functions keep the metrics they update in per-thread
+//! caches, so that once a thread has used a metric, updating it takes atomic
+//! operations only. The registry renders all metrics in the Prometheus text
+//! exposition format via [`Registry::render`].
+//!
+//! The following metrics are collected:
+//!
+//! * `fil_proofs_phase_duration_seconds{phase}`: duration of each API call.
+//! * `fil_proofs_operation_duration_seconds{operation}`: duration of each
+//!   [`Operation`](crate::measurements::Operation) (tree building, labeling, ...).
+//! * `fil_proofs_bytes_read_total{kind}` / `fil_proofs_bytes_written_total{kind}`:
+//!   bytes read from and written to the sector and cache files.
+//! * `fil_proofs_parameter_cache_requests_total{cache, result}`: hits and misses
+//!   of the in-memory parameter caches.
+//! * `fil_proofs_faulty_sectors_total`: sectors found to be faulty while
+//!   generating PoSt vanilla proofs.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::LocalKey;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The global registry, holding all metrics of this process.
+    pub static ref REGISTRY: Registry = Registry::default();
+}
+
+type Cache<K, M> = RefCell<HashMap<K, Arc<M>>>;
+
+thread_local! {
+    static PHASE_DURATIONS: Cache<&'static str, Histogram> = Default::default();
+    static OPERATION_DURATIONS: Cache<&'static str, Histogram> = Default::default();
+    static BYTES_READ: Cache<&'static str, Counter> = Default::default();
+    static BYTES_WRITTEN: Cache<&'static str, Counter> = Default::default();
+    static PARAMETER_CACHE_LOOKUPS: Cache<(&'static str, bool), Counter> = Default::default();
+    static PARAMETER_CACHE_EVICTIONS: Cache<&'static str, Counter> = Default::default();
+    static FAULTY_SECTORS: Cache<(), Counter> = Default::default();
+}
+
+/// Upper bounds (in seconds) of the buckets of duration histograms. They range
+/// from a millisecond (e.g. verification) to several hours (e.g. labeling).
+pub const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.01, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0,
+    14400.0, 28800.0,
+];
+
+/// A monotonically increasing counter.
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, value: u64) {
+        self.value.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// A histogram of durations, with fixed bucket bounds.
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Non-cumulative count of observations per bucket, the last one is `+Inf`.
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(self.bounds.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Number of observations.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Sum of all observations.
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// Records the elapsed time into a histogram when dropped.
+#[must_use = "the duration is recorded when the timer is dropped"]
+pub struct Timer {
+    histogram: Arc<Histogram>,
+    start: Instant,
+}
+
+impl Timer {
+    pub fn new(histogram: Arc<Histogram>) -> Self {
+        Timer {
+            histogram,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.histogram.observe(self.start.elapsed());
+    }
+}
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Debug)]
+enum Series {
+    Counter(BTreeMap<Labels, Arc<Counter>>),
+    Histogram(BTreeMap<Labels, Arc<Histogram>>),
+}
+
+#[derive(Debug)]
+struct Family {
+    help: &'static str,
+    series: Series,
+}
+
+/// A set of metrics, rendered in the Prometheus text format.
+///
+/// A metric is identified by its name and labels. Looking up a metric takes a
+/// lock, so hot code paths should keep the returned `Arc` around.
+#[derive(Debug, Default)]
+pub struct Registry {
+    families: Mutex<BTreeMap<&'static str, Family>>,
+}
+
+impl Registry {
+    /// Returns the counter with the given name and labels, registering it if needed.
+    ///
+    /// Panics if `name` is already registered as a different type of metric.
+    pub fn counter(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&'static str, &str)],
+    ) -> Arc<Counter> {
+        let mut families = self.families.lock().expect("metrics registry poisoned");
+        let family = families.entry(name).or_insert_with(|| Family {
+            help,
+            series: Series::Counter(BTreeMap::new()),
+        });
+        match family.series {
+            Series::Counter(ref mut series) => series
+                .entry(to_labels(labels))
+                .or_insert_with(Default::default)
+                .clone(),
+            Series::Histogram(_) => panic!("metric {} is registered as a histogram", name),
+        }
+    }
+
+    /// Returns the duration histogram with the given name and labels, registering
+    /// it with [`DURATION_BUCKETS`] if needed.
+    ///
+    /// Panics if `name` is already registered as a different type of metric.
+    pub fn histogram(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&'static str, &str)],
+    ) -> Arc<Histogram> {
+        let mut families = self.families.lock().expect("metrics registry poisoned");
+        let family = families.entry(name).or_insert_with(|| Family {
+            help,
+            series: Series::Histogram(BTreeMap::new()),
+        });
+        match family.series {
+            Series::Histogram(ref mut series) => series
+                .entry(to_labels(labels))
+                .or_insert_with(|| Arc::new(Histogram::new(DURATION_BUCKETS)))
+                .clone(),
+            Series::Counter(_) => panic!("metric {} is registered as a counter", name),
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let families = self.families.lock().expect("metrics registry poisoned");
+        let mut out = String::new();
+
+        // Writing to a `String` can't fail.
+        for (name, family) in families.iter() {
+            match family.series {
+                Series::Counter(ref series) => {
+                    let _ = writeln!(out, "# HELP {} {}", name, family.help);
+                    let _ = writeln!(out, "# TYPE {} counter", name);
+                    for (labels, counter) in series {
+                        let _ = writeln!(
+                            out,
+                            "{}{} {}",
+                            name,
+                            format_labels(labels, None),
+                            counter.get()
+                        );
+                    }
+                }
+                Series::Histogram(ref series) => {
+                    let _ = writeln!(out, "# HELP {} {}", name, family.help);
+                    let _ = writeln!(out, "# TYPE {} histogram", name);
+                    for (labels, histogram) in series {
+                        let mut cumulative = 0;
+                        for (i, bucket) in histogram.buckets.iter().enumerate() {
+                            cumulative += bucket.load(Ordering::Relaxed);
+                            let le = match histogram.bounds.get(i) {
+                                Some(bound) => bound.to_string(),
+                                None => "+Inf".to_string(),
+                            };
+                            let _ = writeln!(
+                                out,
+                                "{}_bucket{} {}",
+                                name,
+                                format_labels(labels, Some(&le)),
+                                cumulative
+                            );
+                        }
+                        let _ = writeln!(
+                            out,
+                            "{}_sum{} {}",
+                            name,
+                            format_labels(labels, None),
+                            histogram.sum().as_secs_f64()
+                        );
+                        let _ = writeln!(
+                            out,
+                            "{}_count{} {}",
+                            name,
+                            format_labels(labels, None),
+                            histogram.count()
+                        );
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+fn to_labels(labels: &[(&'static str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(name, value)| (*name, value.to_string()))
+        .collect()
+}
+
+fn format_labels(labels: &[(&'static str, String)], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Returns the metric cached for `key` by this thread, looking it up in the
+/// registry with `register` the first time. Threads being torn down, whose
+/// cache is gone, look it up every time.
+fn cached<K: Copy + Eq + Hash, M>(
+    cache: &'static LocalKey<Cache<K, M>>,
+    key: K,
+    register: impl FnOnce() -> Arc<M>,
+) -> Arc<M> {
+    let metric = cache.try_with(|cache| cache.borrow().get(&key).cloned());
+    metric.ok().flatten().unwrap_or_else(|| {
+        let metric = register();
+        let _ = cache.try_with(|cache| cache.borrow_mut().insert(key, metric.clone()));
+        metric
+    })
+}
+
+/// Starts timing an API call, the duration is recorded when the returned timer
+/// is dropped.
+pub fn time_phase(phase: &'static str) -> Timer {
+    Timer::new(cached(&PHASE_DURATIONS, phase, || {
+        REGISTRY.histogram(
+            "fil_proofs_phase_duration_seconds",
+            "Duration of API calls.",
+            &[("phase", phase)],
+        )
+    }))
+}
+
+/// Records the duration of an [`Operation`](crate::measurements::Operation).
+pub fn record_operation(operation: &'static str, duration: Duration) {
+    cached(&OPERATION_DURATIONS, operation, || {
+        REGISTRY.histogram(
+            "fil_proofs_operation_duration_seconds",
+            "Duration of proving operations.",
+            &[("operation", operation)],
+        )
+    })
+    .observe(duration);
+}
+
+/// Records bytes read from sector or cache files of the given kind (e.g. `layer`).
+pub fn record_bytes_read(kind: &'static str, bytes: u64) {
+    cached(&BYTES_READ, kind, || {
+        REGISTRY.counter(
+            "fil_proofs_bytes_read_total",
+            "Bytes read from sector and cache files.",
+            &[("kind", kind)],
+        )
+    })
+    .inc_by(bytes);
+}
+
+/// Records bytes written to sector or cache files of the given kind (e.g. `layer`).
+pub fn record_bytes_written(kind: &'static str, bytes: u64) {
+    cached(&BYTES_WRITTEN, kind, || {
+        REGISTRY.counter(
+            "fil_proofs_bytes_written_total",
+            "Bytes written to sector and cache files.",
+            &[("kind", kind)],
+        )
+    })
+    .inc_by(bytes);
+}
+
+/// Records a lookup in one of the in-memory parameter caches.
+pub fn record_parameter_cache_lookup(cache: &'static str, hit: bool) {
+    cached(&PARAMETER_CACHE_LOOKUPS, (cache, hit), || {
+        REGISTRY.counter(
+            "fil_proofs_parameter_cache_requests_total",
+            "Lookups in the in-memory parameter caches.",
+            &[
+                ("cache", cache),
+                ("result", if hit { "hit" } else { "miss" }),
+            ],
+        )
+    })
+    .inc();
+}
+
+/// Records an entry evicted from one of the in-memory parameter caches.
+pub fn record_parameter_cache_eviction(cache: &'static str) {
+    cached(&PARAMETER_CACHE_EVICTIONS, cache, || {
+        REGISTRY.counter(
+            "fil_proofs_parameter_cache_evictions_total",
+            "Entries evicted from the in-memory parameter caches.",
+            &[("cache", cache)],
+        )
+    })
+    .inc();
+}
+
+/// Records sectors found to be faulty.
+pub fn record_faulty_sectors(count: usize) {
+    cached(&FAULTY_SECTORS, (), || {
+        REGISTRY.counter(
+            "fil_proofs_faulty_sectors_total",
+            "Sectors found to be faulty while generating PoSt vanilla proofs.",
+            &[],
+        )
+    })
+    .inc_by(count as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counter() {
+        let registry = Registry::default();
+        let counter = registry.counter("requests_total", "Requests.", &[("cache", "groth")]);
+        counter.inc();
+        registry
+            .counter("requests_total", "Requests.", &[("cache", "groth")])
+            .inc_by(2);
+        registry
+            .counter("requests_total", "Requests.", &[("cache", "a\"b")])
+            .inc();
+
+        assert_eq!(counter.get(), 3);
+        assert_eq!(
+            registry.render(),
+            "# HELP requests_total Requests.\n\
+             # TYPE requests_total counter\n\
+             requests_total{cache=\"a\\\"b\"} 1\n\
+             requests_total{cache=\"groth\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn test_render_histogram() {
+        let registry = Registry::default();
+        let histogram = registry.histogram("duration_seconds", "Durations.", &[]);
+        histogram.observe(Duration::from_millis(200));
+        histogram.observe(Duration::from_secs(2));
+        histogram.observe(Duration::from_secs(100_000));
+
+        assert_eq!(histogram.count(), 3);
+        assert_eq!(histogram.sum(), Duration::from_millis(100_002_200));
+
+        let rendered = registry.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "# HELP duration_seconds Durations.");
+        assert_eq!(lines[1], "# TYPE duration_seconds histogram");
+        assert!(lines.contains(&"duration_seconds_bucket{le=\"0.1\"} 0"));
+        assert!(lines.contains(&"duration_seconds_bucket{le=\"0.5\"} 1"));
+        assert!(lines.contains(&"duration_seconds_bucket{le=\"5\"} 2"));
+        assert!(lines.contains(&"duration_seconds_bucket{le=\"28800\"} 2"));
+        assert!(lines.contains(&"duration_seconds_bucket{le=\"+Inf\"} 3"));
+        assert!(lines.contains(&"duration_seconds_sum 100002.2"));
+        assert!(lines.contains(&"duration_seconds_count 3"));
+    }
+
+    #[test]
+    #[should_panic(expected = "registered as a counter")]
+    fn test_type_mismatch() {
+        let registry = Registry::default();
+        registry.counter("metric", "A metric.", &[]);
+        registry.histogram("metric", "A metric.", &[]);
+    }
+
+    #[test]
+    fn test_cached() {
+        thread_local! {
+            static CACHE: Cache<&'static str, Counter> = Default::default();
+        }
+
+        let registry = Registry::default();
+        let register = || registry.counter("cached_total", "Cached.", &[("kind", "a")]);
+        cached(&CACHE, "a", register).inc();
+        // The cached counter is used, without looking it up in the registry.
+        cached(&CACHE, "a", || panic!("cached counter looked up again")).inc();
+
+        assert_eq!(register().get(), 2);
+        assert_eq!(CACHE.with(|cache| cache.borrow().len()), 1);
+    }
+
+    #[test]
+    fn test_timer() {
+        let registry = Registry::default();
+        {
+            let _timer = Timer::new(registry.histogram("timer_seconds", "Timer.", &[]));
+        }
+        assert_eq!(
+            registry.histogram("timer_seconds", "Timer.", &[]).count(),
+            1
+        );
+    }
+}
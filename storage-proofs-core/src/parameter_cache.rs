@@ -1,11 +1,16 @@
 use std::collections::{BTreeMap, HashSet};
-use std::fs::{create_dir_all, File, OpenOptions};
+use std::env;
+use std::fs::{self, create_dir_all, File, OpenOptions};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::process;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
 use std::time::Instant;
 
-use anyhow::bail;
+use anyhow::{ensure, Context};
 use bellperson::{groth16, Circuit};
 use blake2b_simd::Params as Blake2bParams;
 use blstrs::{Bls12, Scalar as Fr};
@@ -13,14 +18,17 @@ use fs2::FileExt;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use log::info;
-use memmap::MmapOptions;
+use memmap::{MmapMut, MmapOptions};
 use rand::RngCore;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 use crate::{
     error::{Error, Result},
-    settings::SETTINGS,
+    parameter_ledger::{log_progress, verify_file, ProgressCallback, VerificationLedger},
+    parameter_store::{self, ParameterEntry, ParameterStore},
+    settings,
 };
 
 /// Bump this when circuits change to invalidate the cache.
@@ -33,12 +41,18 @@ pub const VERIFYING_KEY_EXT: &str = "vk";
 pub const SRS_KEY_EXT: &str = "srs";
 pub const SRS_SHARED_KEY_NAME: &str = "fil-inner-product-v1";
 
+// NOTE: We do not currently support lengths higher than this,
+// even though the SRS file can handle up to (2 << 19) + 1
+// elements.  Specifying under that limit speeds up
+// performance quite a bit.
+const SRS_MAX_READ_LEN: usize = (2 << 14) + 1;
+
 #[derive(Debug)]
 pub struct LockedFile(File);
 
 pub type ParameterMap = BTreeMap<String, ParameterData>;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ParameterData {
     pub cid: String,
     pub digest: String,
@@ -70,6 +84,10 @@ pub fn metadata_id(cache_id: &str) -> String {
     format!("v{}-{}.meta", VERSION, cache_id)
 }
 
+pub fn srs_key_id() -> String {
+    format!("v{}-{}.{}", VERSION, SRS_SHARED_KEY_NAME, SRS_KEY_EXT)
+}
+
 /// Get the correct parameter data for a given cache id.
 pub fn get_parameter_data_from_id(parameter_id: &str) -> Option<&ParameterData> {
     PARAMETERS.get(parameter_id)
@@ -156,7 +174,7 @@ impl Drop for LockedFile {
 }
 
 pub fn parameter_cache_dir_name() -> String {
-    SETTINGS.parameter_cache.clone()
+    settings::current().parameter_cache.clone()
 }
 
 pub fn parameter_cache_dir() -> PathBuf {
@@ -191,31 +209,7 @@ pub fn parameter_cache_srs_key_path(
     _parameter_set_identifier: &str,
     _num_proofs_to_aggregate: usize,
 ) -> PathBuf {
-    let dir = Path::new(&parameter_cache_dir_name()).to_path_buf();
-    dir.join(format!(
-        "v{}-{}.{}",
-        VERSION, SRS_SHARED_KEY_NAME, SRS_KEY_EXT
-    ))
-}
-
-fn ensure_ancestor_dirs_exist(cache_entry_path: PathBuf) -> Result<PathBuf> {
-    info!(
-        "ensuring that all ancestor directories for: {:?} exist",
-        cache_entry_path
-    );
-
-    if let Some(parent_dir) = cache_entry_path.parent() {
-        if let Err(err) = create_dir_all(&parent_dir) {
-            match err.kind() {
-                io::ErrorKind::AlreadyExists => {}
-                _ => return Err(From::from(err)),
-            }
-        }
-    } else {
-        bail!("{:?} has no parent directory", cache_entry_path);
-    }
-
-    Ok(cache_entry_path)
+    parameter_cache_dir().join(srs_key_id())
 }
 
 pub trait ParameterSetMetadata {
@@ -256,11 +250,12 @@ where
 
     fn get_param_metadata(_circuit: C, pub_params: &P) -> Result<CacheEntryMetadata> {
         let id = Self::cache_identifier(pub_params);
+        let store = parameter_store::current();
+        let name = metadata_id(&id);
 
         // generate (or load) metadata
-        let meta_path = ensure_ancestor_dirs_exist(parameter_cache_metadata_path(&id))?;
-        read_cached_metadata(&meta_path)
-            .or_else(|_| write_cached_metadata(&meta_path, Self::cache_meta(pub_params)))
+        read_cached_metadata(&*store, &name)
+            .or_else(|_| write_cached_metadata(&*store, &name, Self::cache_meta(pub_params)))
             .map_err(Into::into)
     }
 
@@ -275,7 +270,8 @@ where
         pub_params: &P,
     ) -> Result<groth16::MappedParameters<Bls12>> {
         let id = Self::cache_identifier(pub_params);
-        let cache_path = ensure_ancestor_dirs_exist(parameter_cache_params_path(&id))?;
+        let store = parameter_store::current();
+        let name = parameter_id(&id);
 
         let generate = || -> Result<_> {
             if let Some(rng) = rng {
@@ -289,21 +285,18 @@ where
                 );
                 Ok(parameters)
             } else {
-                bail!(
-                    "No cached parameters found for {} [failure finding {}]",
-                    id,
-                    cache_path.display()
-                );
+                Err(Error::MissingParameters(parameter_cache_params_path(&id)))
+                    .with_context(|| format!("No cached parameters found for {}", id))
             }
         };
 
         // load or generate Groth parameter mappings
-        read_cached_params(&cache_path).or_else(|err| match err.downcast::<Error>() {
+        read_stored_params(&*store, &name).or_else(|err| match err.downcast::<Error>() {
             Ok(error @ Error::InvalidParameters(_)) => Err(error.into()),
             _ => {
                 // if the file already exists, another process is already trying to generate these.
-                if !cache_path.exists() {
-                    match write_cached_params(&cache_path, generate()?) {
+                if store.get(&name)?.is_none() {
+                    match write_cached_params(&*store, &name, generate()?) {
                         Ok(_) => {}
                         Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
                             // other thread just wrote it, do nothing
@@ -311,7 +304,7 @@ where
                         Err(e) => panic!("{}: failed to write generated parameters to cache", e),
                     }
                 }
-                Ok(read_cached_params(&cache_path)?)
+                Ok(read_stored_params(&*store, &name)?)
             }
         })
     }
@@ -328,8 +321,8 @@ where
         num_proofs_to_aggregate: usize,
     ) -> Result<groth16::aggregate::GenericSRS<Bls12>> {
         let id = Self::cache_identifier(pub_params);
-        let cache_path =
-            ensure_ancestor_dirs_exist(parameter_cache_srs_key_path(&id, num_proofs_to_aggregate))?;
+        let store = parameter_store::current();
+        let name = srs_key_id();
 
         let generate = || -> Result<groth16::aggregate::GenericSRS<Bls12>> {
             if let Some(rng) = rng {
@@ -342,18 +335,18 @@ where
                     num_proofs_to_aggregate,
                 ))
             } else {
-                bail!(
-                    "No cached srs key found for {} [failure finding {}]",
-                    id,
-                    cache_path.display()
-                );
+                Err(Error::MissingParameters(parameter_cache_srs_key_path(
+                    &id,
+                    num_proofs_to_aggregate,
+                )))
+                .with_context(|| format!("No cached srs key found for {}", id))
             }
         };
 
         // generate (or load) srs key
-        match read_cached_srs_key(&cache_path) {
+        match read_cached_srs_key(&*store, &name) {
             Ok(key) => Ok(key),
-            Err(_) => write_cached_srs_key(&cache_path, generate()?).map_err(Into::into),
+            Err(_) => write_cached_srs_key(&*store, &name, generate()?).map_err(Into::into),
         }
     }
 
@@ -376,10 +369,11 @@ where
         };
 
         // generate (or load) verifying key
-        let cache_path = ensure_ancestor_dirs_exist(parameter_cache_verifying_key_path(&id))?;
-        match read_cached_verifying_key(&cache_path) {
+        let store = parameter_store::current();
+        let name = verifying_key_id(&id);
+        match read_cached_verifying_key(&*store, &name) {
             Ok(key) => Ok(key),
-            Err(_) => write_cached_verifying_key(&cache_path, generate()?).map_err(Into::into),
+            Err(_) => write_cached_verifying_key(&*store, &name, generate()?).map_err(Into::into),
         }
     }
 }
@@ -413,25 +407,26 @@ pub fn verify_production_entry(
                 .get(&cache_key)
                 .is_none();
             if not_yet_verified {
-                info!("generating consistency digest for parameters");
-                let hash =
-                    with_exclusive_read_lock::<_, io::Error, _>(cache_entry_path, |mut file| {
-                        let mut hasher = Blake2bParams::new().to_state();
-                        io::copy(&mut file, &mut hasher).expect("copying file into hasher failed");
-                        Ok(hasher.finalize())
-                    })?;
-                info!("generated consistency digest for parameters");
-
-                // The hash in the parameters file is truncated to 256 bits.
-                let digest_hex = &hash.to_hex()[..32];
-                if digest_hex != data.digest {
-                    info!("parameter data is INVALID [{}]", digest_hex);
+                // Files verified by previous runs are skipped, as long as their
+                // stamp in the verification ledger is still valid.
+                let ledger = VerificationLedger::new(
+                    cache_entry_path.parent().unwrap_or_else(|| Path::new(".")),
+                );
+                let valid = with_exclusive_read_lock(cache_entry_path, |_file| {
+                    verify_file(
+                        &ledger,
+                        cache_entry_path,
+                        &data.digest,
+                        false,
+                        Some(&log_progress),
+                    )
+                })?;
+                if !valid {
                     return Err(
                         Error::InvalidParameters(cache_entry_path.display().to_string()).into(),
                     );
                 }
 
-                info!("parameter data is VALID [{}]", digest_hex);
                 VERIFIED_PARAMETERS
                     .lock()
                     .expect("verified parameters lock failed")
@@ -446,12 +441,59 @@ pub fn verify_production_entry(
     Ok(true)
 }
 
+/// Verifies the given parameter files against their digests, in parallel.
+///
+/// Unlike `verify_production_entry`, the files are always hashed, ignoring
+/// both the in-memory cache and the stamps in the verification ledger. The
+/// ledger is updated with the results. Returns whether each file is valid,
+/// files which are not known production parameters are invalid.
+pub fn reverify_parameters(
+    paths: &[PathBuf],
+    progress: Option<ProgressCallback<'_>>,
+) -> Result<Vec<(PathBuf, bool)>> {
+    paths
+        .par_iter()
+        .map(|path| {
+            let cache_key = path
+                .file_name()
+                .and_then(|stem| stem.to_str())
+                .with_context(|| format!("invalid parameter file name {}", path.display()))?;
+            let is_srs = path.extension().and_then(|ext| ext.to_str()) == Some(SRS_KEY_EXT);
+            let data = if is_srs {
+                get_srs_parameter_data_from_id(cache_key)
+            } else {
+                get_parameter_data_from_id(cache_key)
+            };
+            let data = match data {
+                Some(data) => data,
+                None => return Ok((path.clone(), false)),
+            };
+
+            let ledger = VerificationLedger::new(path.parent().unwrap_or_else(|| Path::new(".")));
+            let valid = with_exclusive_read_lock(path, |_file| {
+                verify_file(&ledger, path, &data.digest, true, progress)
+            })?;
+
+            let mut verified = VERIFIED_PARAMETERS
+                .lock()
+                .expect("verified parameters lock failed");
+            if valid {
+                verified.insert(cache_key.to_string());
+            } else {
+                verified.remove(cache_key);
+            }
+
+            Ok((path.clone(), valid))
+        })
+        .collect()
+}
+
 // Reads parameter mappings using mmap so that they can be lazily
 // loaded later.
 pub fn read_cached_params(cache_entry_path: &Path) -> Result<groth16::MappedParameters<Bls12>> {
     info!("checking cache_path: {:?} for parameters", cache_entry_path);
 
-    let verify_production_params = SETTINGS.verify_production_params;
+    let verify_production_params = settings::current().verify_production_params;
     info!(
         "Verify production parameters is {}",
         verify_production_params
@@ -484,13 +526,86 @@ pub fn read_cached_params(cache_entry_path: &Path) -> Result<groth16::MappedPara
     .map_err(Into::into)
 }
 
-fn read_cached_verifying_key(cache_entry_path: &Path) -> Result<groth16::VerifyingKey<Bls12>> {
+/// Reads Groth parameter mappings from `store`. Parameters held in memory are
+/// mapped from a temporary file, as the mappings need a file to read from.
+pub fn read_stored_params(
+    store: &dyn ParameterStore,
+    name: &str,
+) -> Result<groth16::MappedParameters<Bls12>> {
+    match store.get(name)? {
+        Some(ParameterEntry::File(path)) => read_cached_params(&path),
+        Some(ParameterEntry::Memory(data)) => {
+            verify_production_data(name, &data, get_parameter_data_from_id)?;
+            map_params_data(name, &data)
+        }
+        None => Err(missing_entry(name).into()),
+    }
+}
+
+fn map_params_data(name: &str, data: &[u8]) -> Result<groth16::MappedParameters<Bls12>> {
+    static MAPPED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let path = env::temp_dir().join(format!(
+        "{}-{}-{}",
+        process::id(),
+        MAPPED_COUNT.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+    fs::write(&path, data)?;
+    let mapped_params = groth16::Parameters::build_mapped_parameters(path.clone(), false);
+    // The mapping stays valid after the file is removed (on unix), otherwise
+    // the file is left behind in the temp dir.
+    let _ = fs::remove_file(&path);
+
+    Ok(mapped_params?)
+}
+
+fn missing_entry(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} not found in parameter store", name),
+    )
+}
+
+// Same as `verify_production_entry`, for entries which are only held in memory.
+fn verify_production_data(
+    name: &str,
+    data: &[u8],
+    selector: GetParameterDataCallback,
+) -> Result<()> {
+    if !settings::current().verify_production_params {
+        return Ok(());
+    }
+
+    let valid = selector(name)
+        .map(|param| Blake2bParams::new().hash(data).to_hex()[..32] == param.digest)
+        .unwrap_or(false);
+    ensure!(valid, Error::InvalidParameters(name.to_string()));
+
+    Ok(())
+}
+
+fn read_cached_verifying_key(
+    store: &dyn ParameterStore,
+    name: &str,
+) -> Result<groth16::VerifyingKey<Bls12>> {
+    match store.get(name)? {
+        Some(ParameterEntry::File(path)) => read_cached_verifying_key_file(&path),
+        Some(ParameterEntry::Memory(data)) => {
+            verify_production_data(name, &data, get_parameter_data_from_id)?;
+            Ok(groth16::VerifyingKey::read(&data[..])?)
+        }
+        None => Err(missing_entry(name).into()),
+    }
+}
+
+fn read_cached_verifying_key_file(cache_entry_path: &Path) -> Result<groth16::VerifyingKey<Bls12>> {
     info!(
         "checking cache_path: {:?} for verifying key",
         cache_entry_path
     );
 
-    let verify_production_params = SETTINGS.verify_production_params;
+    let verify_production_params = settings::current().verify_production_params;
     info!(
         "Verify production parameters is {}",
         verify_production_params
@@ -521,10 +636,32 @@ fn read_cached_verifying_key(cache_entry_path: &Path) -> Result<groth16::Verifyi
     })
 }
 
-fn read_cached_srs_key(cache_entry_path: &Path) -> Result<groth16::aggregate::GenericSRS<Bls12>> {
+fn read_cached_srs_key(
+    store: &dyn ParameterStore,
+    name: &str,
+) -> Result<groth16::aggregate::GenericSRS<Bls12>> {
+    match store.get(name)? {
+        Some(ParameterEntry::File(path)) => read_cached_srs_key_file(&path),
+        Some(ParameterEntry::Memory(data)) => {
+            verify_production_data(name, &data, get_srs_parameter_data_from_id)?;
+            let mut srs_map = MmapMut::map_anon(data.len())?;
+            srs_map.copy_from_slice(&data);
+            let srs_map = srs_map.make_read_only()?;
+            Ok(groth16::aggregate::GenericSRS::read_mmap(
+                &srs_map,
+                SRS_MAX_READ_LEN,
+            )?)
+        }
+        None => Err(missing_entry(name).into()),
+    }
+}
+
+fn read_cached_srs_key_file(
+    cache_entry_path: &Path,
+) -> Result<groth16::aggregate::GenericSRS<Bls12>> {
     info!("checking cache_path: {:?} for srs", cache_entry_path);
 
-    let verify_production_params = SETTINGS.verify_production_params;
+    let verify_production_params = settings::current().verify_production_params;
     info!(
         "Verify production parameters is {}",
         verify_production_params
@@ -549,19 +686,22 @@ fn read_cached_srs_key(cache_entry_path: &Path) -> Result<groth16::aggregate::Ge
 
     with_exclusive_read_lock(cache_entry_path, |file| {
         let srs_map = unsafe { MmapOptions::new().map(file.as_ref())? };
-        // NOTE: We do not currently support lengths higher than this,
-        // even though the SRS file can handle up to (2 << 19) + 1
-        // elements.  Specifying under that limit speeds up
-        // performance quite a bit.
-        let max_len = (2 << 14) + 1;
-        let key = groth16::aggregate::GenericSRS::read_mmap(&srs_map, max_len)?;
+        let key = groth16::aggregate::GenericSRS::read_mmap(&srs_map, SRS_MAX_READ_LEN)?;
         info!("read srs key from cache {:?} ", cache_entry_path);
 
         Ok(key)
     })
 }
 
-fn read_cached_metadata(cache_entry_path: &Path) -> io::Result<CacheEntryMetadata> {
+fn read_cached_metadata(store: &dyn ParameterStore, name: &str) -> io::Result<CacheEntryMetadata> {
+    match store.get(name)? {
+        Some(ParameterEntry::File(path)) => read_cached_metadata_file(&path),
+        Some(ParameterEntry::Memory(data)) => Ok(serde_json::from_slice(&data)?),
+        None => Err(missing_entry(name)),
+    }
+}
+
+fn read_cached_metadata_file(cache_entry_path: &Path) -> io::Result<CacheEntryMetadata> {
     info!("checking cache_path: {:?} for metadata", cache_entry_path);
     with_exclusive_read_lock(cache_entry_path, |file| {
         let value = serde_json::from_reader(file)?;
@@ -572,54 +712,47 @@ fn read_cached_metadata(cache_entry_path: &Path) -> io::Result<CacheEntryMetadat
 }
 
 fn write_cached_metadata(
-    cache_entry_path: &Path,
+    store: &dyn ParameterStore,
+    name: &str,
     value: CacheEntryMetadata,
 ) -> io::Result<CacheEntryMetadata> {
-    with_exclusive_lock(cache_entry_path, |file| {
-        serde_json::to_writer(file, &value)?;
-        info!("wrote metadata to cache {:?} ", cache_entry_path);
+    store.put(name, &mut |file| Ok(serde_json::to_writer(file, &value)?))?;
+    info!("wrote metadata {} to cache", name);
 
-        Ok(value)
-    })
+    Ok(value)
 }
 
 fn write_cached_verifying_key(
-    cache_entry_path: &Path,
+    store: &dyn ParameterStore,
+    name: &str,
     value: groth16::VerifyingKey<Bls12>,
 ) -> io::Result<groth16::VerifyingKey<Bls12>> {
-    with_exclusive_lock(cache_entry_path, |mut file| {
-        value.write(&mut file)?;
-        file.flush()?;
-        info!("wrote verifying key to cache {:?} ", cache_entry_path);
+    store.put(name, &mut |file| value.write(file))?;
+    info!("wrote verifying key {} to cache", name);
 
-        Ok(value)
-    })
+    Ok(value)
 }
 
 fn write_cached_srs_key(
-    cache_entry_path: &Path,
+    store: &dyn ParameterStore,
+    name: &str,
     value: groth16::aggregate::GenericSRS<Bls12>,
 ) -> io::Result<groth16::aggregate::GenericSRS<Bls12>> {
-    with_exclusive_lock(cache_entry_path, |mut file| {
-        value.write(&mut file)?;
-        file.flush()?;
-        info!("wrote srs key to cache {:?} ", cache_entry_path);
+    store.put(name, &mut |mut file| value.write(&mut file))?;
+    info!("wrote srs key {} to cache", name);
 
-        Ok(value)
-    })
+    Ok(value)
 }
 
 fn write_cached_params(
-    cache_entry_path: &Path,
+    store: &dyn ParameterStore,
+    name: &str,
     value: groth16::Parameters<Bls12>,
 ) -> io::Result<groth16::Parameters<Bls12>> {
-    with_exclusive_lock(cache_entry_path, |mut file| {
-        value.write(&mut file)?;
-        file.flush()?;
-        info!("wrote groth parameters to cache {:?} ", cache_entry_path);
+    store.put(name, &mut |file| value.write(file))?;
+    info!("wrote groth parameters {} to cache", name);
 
-        Ok(value)
-    })
+    Ok(value)
 }
 
 pub fn with_exclusive_lock<T, E, F>(file_path: &Path, f: F) -> std::result::Result<T, E>
@@ -651,3 +784,40 @@ where
     ensure_parent(file_path)?;
     f(&mut open_file(file_path)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reverify_ignores_stamps() {
+        let dir = tempdir().expect("failed to create temp dir");
+        let (name, data) = PARAMETERS.iter().next().expect("no production parameters");
+        let path = dir.path().join(name);
+        fs::write(&path, b"corrupted parameters").expect("failed to write file");
+
+        // Stamp the corrupted file, as if it was verified before it got corrupted.
+        let ledger = VerificationLedger::new(dir.path());
+        ledger
+            .record(&path, &data.digest)
+            .expect("failed to record");
+        assert!(ledger
+            .is_verified(&path, &data.digest)
+            .expect("failed to check"));
+
+        let results = reverify_parameters(&[path.clone()], None).expect("failed to reverify");
+        assert_eq!(results, vec![(path.clone(), false)]);
+
+        // The stamp of the corrupted file is removed.
+        assert!(!ledger
+            .is_verified(&path, &data.digest)
+            .expect("failed to check"));
+        assert!(ledger.entries().expect("failed to read").is_empty());
+        assert!(!VERIFIED_PARAMETERS
+            .lock()
+            .expect("verified parameters lock failed")
+            .contains(name));
+    }
+}
@@ -0,0 +1,379 @@
+//! Persisted verification stamps for the files in the parameter cache.
+//!
+//! Hashing the production parameters takes minutes, so once a file was
+//! verified against its digest in `parameters.json` (or
+//! `srs-inner-product.json`), a stamp is recorded in a ledger file inside the
+//! parameter cache directory. The stamp is keyed by the file name and holds the
+//! size, modification time and inode of the verified file. As long as those
+//! match, the file is not hashed again, even across restarts.
+//!
+//! The ledger is shared between processes, all updates are done while holding
+//! an exclusive lock on `LEDGER_LOCK_FILE`.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use blake2b_simd::Params as Blake2bParams;
+use fs2::FileExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// Name of the ledger file within the parameter cache directory.
+pub const LEDGER_FILE: &str = "verified-parameters.json";
+/// Name of the lock file guarding updates of the ledger.
+pub const LEDGER_LOCK_FILE: &str = "verified-parameters.lock";
+/// Files are hashed in chunks of this size, progress is reported after each chunk.
+pub const VERIFY_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Called with the path of the file being hashed, the number of bytes hashed
+/// so far and the size of the file.
+pub type ProgressCallback<'a> = &'a (dyn Fn(&Path, u64, u64) + Sync);
+
+/// Identifies the exact version of a file on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+    /// Inode number of the file, `0` on platforms without inodes.
+    pub inode: u64,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &Metadata) -> io::Result<Self> {
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Ok(FileStamp {
+            size: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+            inode: inode(metadata),
+        })
+    }
+
+    pub fn of(path: &Path) -> io::Result<Self> {
+        Self::from_metadata(&fs::metadata(path)?)
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// A verification stamp, the file with `stamp` was found to match `digest`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    #[serde(flatten)]
+    pub stamp: FileStamp,
+    pub digest: String,
+}
+
+/// The verification ledger of a parameter cache directory.
+#[derive(Clone, Debug)]
+pub struct VerificationLedger {
+    dir: PathBuf,
+}
+
+impl VerificationLedger {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        VerificationLedger {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(LEDGER_FILE)
+    }
+
+    /// Returns all recorded stamps, keyed by file name.
+    pub fn entries(&self) -> Result<BTreeMap<String, LedgerEntry>> {
+        // Read-only cache directories can't hold a lock file, but the ledger
+        // can't be updated there either, so reading without a lock is fine.
+        let _lock = match self.lock(false) {
+            Ok(lock) => Some(lock),
+            Err(_) if !self.dir.join(LEDGER_LOCK_FILE).exists() => None,
+            Err(err) => return Err(err),
+        };
+        self.read()
+    }
+
+    /// Returns true if `file` was verified to match `digest` and has not
+    /// changed since.
+    pub fn is_verified(&self, file: &Path, digest: &str) -> Result<bool> {
+        let name = file_name(file)?;
+        let entry = match self.entries()?.remove(&name) {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+
+        let stamp = FileStamp::of(file)
+            .with_context(|| format!("failed to read metadata of {}", file.display()))?;
+
+        Ok(entry.digest == digest && entry.stamp == stamp)
+    }
+
+    /// Records that `file`, in its current state, matches `digest`.
+    pub fn record(&self, file: &Path, digest: &str) -> Result<()> {
+        let name = file_name(file)?;
+        let stamp = FileStamp::of(file)
+            .with_context(|| format!("failed to read metadata of {}", file.display()))?;
+
+        self.update(|entries| {
+            entries.insert(
+                name,
+                LedgerEntry {
+                    stamp,
+                    digest: digest.to_string(),
+                },
+            );
+        })
+    }
+
+    /// Removes the stamp of `file`, if any.
+    pub fn remove(&self, file: &Path) -> Result<()> {
+        let name = file_name(file)?;
+        self.update(|entries| {
+            entries.remove(&name);
+        })
+    }
+
+    fn update<F: FnOnce(&mut BTreeMap<String, LedgerEntry>)>(&self, f: F) -> Result<()> {
+        let _lock = self.lock(true)?;
+        let mut entries = self.read()?;
+        f(&mut entries);
+
+        // Write to a temporary file first, so that readers never see a partial ledger.
+        let path = self.path();
+        let tmp_path = path.with_extension("json.tmp");
+        let data = serde_json::to_vec_pretty(&entries)?;
+        fs::write(&tmp_path, data)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to rename {}", tmp_path.display()))?;
+
+        Ok(())
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, LedgerEntry>> {
+        let path = self.path();
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+
+        match serde_json::from_slice(&data) {
+            Ok(entries) => Ok(entries),
+            Err(err) => {
+                // A broken ledger only costs a re-verification.
+                warn!("ignoring invalid ledger {}: {}", path.display(), err);
+                Ok(BTreeMap::new())
+            }
+        }
+    }
+
+    fn lock(&self, exclusive: bool) -> Result<File> {
+        let path = self.dir.join(LEDGER_LOCK_FILE);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        if exclusive {
+            file.lock_exclusive()?;
+        } else {
+            file.lock_shared()?;
+        }
+
+        // The lock is released when the file is closed.
+        Ok(file)
+    }
+}
+
+fn file_name(file: &Path) -> Result<String> {
+    file.file_name()
+        .and_then(|name| name.to_str())
+        .map(ToString::to_string)
+        .with_context(|| format!("invalid file name {}", file.display()))
+}
+
+/// Computes the Blake2b digest of `file`, truncated to 256 bits and hex encoded
+/// as in `parameters.json`. The file is read in chunks of `VERIFY_CHUNK_SIZE`,
+/// `progress` is called after every chunk.
+pub fn hash_file(file: &Path, progress: Option<ProgressCallback<'_>>) -> io::Result<String> {
+    let mut reader = File::open(file)?;
+    let total = reader.metadata()?.len();
+    let mut hasher = Blake2bParams::new().to_state();
+    let mut buf = vec![0u8; VERIFY_CHUNK_SIZE];
+    let mut hashed = 0u64;
+
+    loop {
+        // Fill the whole chunk, unless EOF is reached.
+        let mut filled = 0;
+        while filled < buf.len() {
+            match reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if filled == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..filled]);
+        hashed += filled as u64;
+        if let Some(progress) = progress {
+            progress(file, hashed, total);
+        }
+    }
+
+    Ok(hasher.finalize().to_hex()[..32].to_string())
+}
+
+/// Logs the progress of a verification, in steps of 10%.
+pub fn log_progress(file: &Path, hashed: u64, total: u64) {
+    let percent = |bytes: u64| bytes * 100 / total.max(1);
+    let previous = hashed.saturating_sub(VERIFY_CHUNK_SIZE as u64);
+    if hashed == total || percent(hashed) / 10 != percent(previous) / 10 {
+        info!(
+            "verifying {}: {}% ({} of {} bytes)",
+            file.display(),
+            percent(hashed),
+            hashed,
+            total
+        );
+    }
+}
+
+/// Hashes `file` and compares it against `digest`. Matching files are recorded
+/// in the ledger, the stamps of files which don't match are removed.
+///
+/// Unless `force` is set, files which have a valid stamp are not hashed.
+pub fn verify_file(
+    ledger: &VerificationLedger,
+    file: &Path,
+    digest: &str,
+    force: bool,
+    progress: Option<ProgressCallback<'_>>,
+) -> Result<bool> {
+    if !force {
+        match ledger.is_verified(file, digest) {
+            Ok(true) => {
+                info!("{} has a valid verification stamp", file.display());
+                return Ok(true);
+            }
+            Ok(false) => {}
+            Err(err) => warn!("failed to check verification stamp: {:?}", err),
+        }
+    }
+
+    info!("generating consistency digest for {}", file.display());
+    let actual =
+        hash_file(file, progress).with_context(|| format!("failed to hash {}", file.display()))?;
+    let valid = actual == digest;
+    info!(
+        "parameter data is {} [{}]",
+        if valid { "VALID" } else { "INVALID" },
+        actual
+    );
+
+    // Failing to update the ledger (e.g. in a read-only cache dir) only means
+    // the file will be hashed again next time.
+    let updated = if valid {
+        ledger.record(file, digest)
+    } else {
+        ledger.remove(file)
+    };
+    if let Err(err) = updated {
+        warn!("failed to update verification ledger: {:?}", err);
+    }
+
+    Ok(valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_ledger_stamps() {
+        let dir = tempdir().expect("failed to create temp dir");
+        let ledger = VerificationLedger::new(dir.path());
+        let file = dir.path().join("v28-test.params");
+        fs::write(&file, vec![7u8; 1000]).expect("failed to write file");
+
+        let digest = hash_file(&file, None).expect("failed to hash file");
+        assert_eq!(digest.len(), 32);
+        assert!(!ledger.is_verified(&file, &digest).expect("failed to check"));
+
+        assert!(verify_file(&ledger, &file, &digest, false, None).expect("failed to verify"));
+        assert!(ledger.is_verified(&file, &digest).expect("failed to check"));
+        assert!(!ledger.is_verified(&file, "other").expect("failed to check"));
+
+        // The ledger is persisted, a new instance sees the stamp.
+        let entries = VerificationLedger::new(dir.path())
+            .entries()
+            .expect("failed to read ledger");
+        assert_eq!(entries["v28-test.params"].stamp.size, 1000);
+
+        // Changing the file invalidates the stamp.
+        fs::write(&file, vec![8u8; 1001]).expect("failed to write file");
+        assert!(!ledger.is_verified(&file, &digest).expect("failed to check"));
+
+        // A failed verification removes the stamp.
+        assert!(!verify_file(&ledger, &file, &digest, true, None).expect("failed to verify"));
+        assert!(ledger.entries().expect("failed to read").is_empty());
+    }
+
+    #[test]
+    fn test_hash_file_progress() {
+        let dir = tempdir().expect("failed to create temp dir");
+        let file = dir.path().join("v28-test.vk");
+        fs::write(&file, vec![1u8; 4096]).expect("failed to write file");
+
+        let reported = AtomicU64::new(0);
+        let progress = |_: &Path, hashed: u64, total: u64| {
+            assert_eq!(total, 4096);
+            reported.store(hashed, Ordering::SeqCst);
+        };
+        let digest = hash_file(&file, Some(&progress)).expect("failed to hash file");
+
+        assert_eq!(reported.load(Ordering::SeqCst), 4096);
+        let expected = Blake2bParams::new().hash(&[1u8; 4096]).to_hex()[..32].to_string();
+        assert_eq!(digest, expected);
+    }
+
+    #[test]
+    fn test_invalid_ledger_is_ignored() {
+        let dir = tempdir().expect("failed to create temp dir");
+        let ledger = VerificationLedger::new(dir.path());
+        fs::write(ledger.path(), b"not json").expect("failed to write ledger");
+
+        assert!(ledger.entries().expect("failed to read").is_empty());
+    }
+}
@@ -0,0 +1,343 @@
+//! Storage backends for the parameter cache.
+//!
+//! Groth parameters, verifying keys, metadata and SRS keys are looked up by
+//! their file name (e.g. `v28-stacked-proof-of-replication-<hash>.params`)
+//! through a [`ParameterStore`]. By default this is a [`DirectoryParameterStore`]
+//! built from the settings: the directories in `parameter_search_path` are
+//! searched first (e.g. a read-only NFS share), followed by the writable
+//! `parameter_cache` directory, which is where generated parameters go.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use log::{info, warn};
+
+use crate::{
+    parameter_cache::with_exclusive_lock,
+    settings::{self, Settings},
+};
+
+thread_local! {
+    static CURRENT_STORE: RefCell<Option<Arc<dyn ParameterStore>>> = const { RefCell::new(None) };
+}
+
+/// Error code of a write to a read-only file system (`EROFS`).
+#[cfg(unix)]
+const READ_ONLY_FS_ERROR: i32 = 30;
+
+/// An entry found in a [`ParameterStore`].
+#[derive(Clone, Debug)]
+pub enum ParameterEntry {
+    /// The entry is a file on disk, which can be mapped into memory.
+    File(PathBuf),
+    /// The entry is only held in memory.
+    Memory(Arc<Vec<u8>>),
+}
+
+pub trait ParameterStore: fmt::Debug + Send + Sync {
+    /// Looks up the entry with the given file name, returns `None` if it is not in the store.
+    fn get(&self, name: &str) -> io::Result<Option<ParameterEntry>>;
+
+    /// Adds a new entry, its contents are produced by `write`. Entries are added to the first
+    /// writable location, if the entry already exists there, an error of kind
+    /// `io::ErrorKind::AlreadyExists` is returned.
+    fn put(
+        &self,
+        name: &str,
+        write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<ParameterEntry>;
+}
+
+/// A directory of a [`DirectoryParameterStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterDir {
+    pub path: PathBuf,
+    /// New entries are only written to writable directories.
+    pub writable: bool,
+}
+
+impl ParameterDir {
+    pub fn read_only<P: AsRef<Path>>(path: P) -> Self {
+        ParameterDir {
+            path: path.as_ref().to_path_buf(),
+            writable: false,
+        }
+    }
+
+    pub fn writable<P: AsRef<Path>>(path: P) -> Self {
+        ParameterDir {
+            path: path.as_ref().to_path_buf(),
+            writable: true,
+        }
+    }
+}
+
+/// Searches an ordered list of directories. The first directory containing an
+/// entry wins, new entries are written to the first writable directory.
+#[derive(Clone, Debug, Default)]
+pub struct DirectoryParameterStore {
+    dirs: Vec<ParameterDir>,
+}
+
+impl DirectoryParameterStore {
+    pub fn new(dirs: Vec<ParameterDir>) -> Self {
+        DirectoryParameterStore { dirs }
+    }
+
+    /// The directories of `settings.parameter_search_path` (read-only), followed by
+    /// `settings.parameter_cache` (writable).
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut dirs: Vec<_> = env::split_paths(&settings.parameter_search_path)
+            .filter(|path| !path.as_os_str().is_empty())
+            .map(ParameterDir::read_only)
+            .collect();
+        dirs.push(ParameterDir::writable(&settings.parameter_cache));
+
+        DirectoryParameterStore { dirs }
+    }
+
+    pub fn dirs(&self) -> &[ParameterDir] {
+        &self.dirs
+    }
+}
+
+impl ParameterStore for DirectoryParameterStore {
+    fn get(&self, name: &str) -> io::Result<Option<ParameterEntry>> {
+        Ok(self
+            .dirs
+            .iter()
+            .map(|dir| dir.path.join(name))
+            .find(|path| path.is_file())
+            .map(ParameterEntry::File))
+    }
+
+    fn put(
+        &self,
+        name: &str,
+        write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<ParameterEntry> {
+        let mut last_err = None;
+        for dir in self.dirs.iter().filter(|dir| dir.writable) {
+            let path = dir.path.join(name);
+            let written = with_exclusive_lock(&path, |file| {
+                write(file)?;
+                file.flush()
+            });
+            match written {
+                Ok(()) => {
+                    info!("wrote {} to {:?}", name, dir.path);
+                    return Ok(ParameterEntry::File(path));
+                }
+                Err(err) if is_read_only(&err) => {
+                    warn!("parameter dir {:?} is not writable: {}", dir.path, err);
+                    last_err = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "no writable parameter directory configured",
+            )
+        }))
+    }
+}
+
+fn is_read_only(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        if err.raw_os_error() == Some(READ_ONLY_FS_ERROR) {
+            return true;
+        }
+    }
+
+    err.kind() == io::ErrorKind::PermissionDenied
+}
+
+/// Keeps all entries in memory, meant for tests.
+#[derive(Debug, Default)]
+pub struct MemoryParameterStore {
+    entries: RwLock<BTreeMap<String, Arc<Vec<u8>>>>,
+}
+
+impl MemoryParameterStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces an entry.
+    pub fn insert(&self, name: &str, data: Vec<u8>) {
+        self.entries
+            .write()
+            .expect("parameter store lock poisoned")
+            .insert(name.to_string(), Arc::new(data));
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .read()
+            .expect("parameter store lock poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+impl ParameterStore for MemoryParameterStore {
+    fn get(&self, name: &str) -> io::Result<Option<ParameterEntry>> {
+        Ok(self
+            .entries
+            .read()
+            .expect("parameter store lock poisoned")
+            .get(name)
+            .cloned()
+            .map(ParameterEntry::Memory))
+    }
+
+    fn put(
+        &self,
+        name: &str,
+        write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<ParameterEntry> {
+        let mut data = Vec::new();
+        write(&mut data)?;
+        let data = Arc::new(data);
+
+        let mut entries = self.entries.write().expect("parameter store lock poisoned");
+        if entries.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", name),
+            ));
+        }
+        entries.insert(name.to_string(), data.clone());
+
+        Ok(ParameterEntry::Memory(data))
+    }
+}
+
+/// Returns the parameter store in effect on the current thread. This is the store installed by
+/// [`with_parameter_store`] if called from within it, the directories configured in the current
+/// settings otherwise.
+pub fn current() -> Arc<dyn ParameterStore> {
+    CURRENT_STORE
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| Arc::new(DirectoryParameterStore::from_settings(&settings::current())))
+}
+
+/// Runs `f` with `store` in place of the configured parameter directories on the current thread.
+///
+/// Like [`settings::with_settings`], calls can be nested and the store is not propagated to
+/// other threads.
+pub fn with_parameter_store<T, F: FnOnce() -> T>(store: Arc<dyn ParameterStore>, f: F) -> T {
+    struct Restore(Option<Arc<dyn ParameterStore>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let prior = self.0.take();
+            CURRENT_STORE.with(|current| *current.borrow_mut() = prior);
+        }
+    }
+
+    let prior = CURRENT_STORE.with(|current| current.borrow_mut().replace(store));
+    let _restore = Restore(prior);
+
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    fn put_bytes(
+        store: &dyn ParameterStore,
+        name: &str,
+        data: &[u8],
+    ) -> io::Result<ParameterEntry> {
+        store.put(name, &mut |w| w.write_all(data))
+    }
+
+    #[test]
+    fn test_directory_store_search_order() {
+        let shared = tempdir().expect("failed to create temp dir");
+        let local = tempdir().expect("failed to create temp dir");
+        fs::write(shared.path().join("a.vk"), b"shared").expect("failed to write file");
+        fs::write(local.path().join("a.vk"), b"local").expect("failed to write file");
+        fs::write(local.path().join("b.vk"), b"local").expect("failed to write file");
+
+        let store = DirectoryParameterStore::new(vec![
+            ParameterDir::read_only(shared.path()),
+            ParameterDir::writable(local.path()),
+        ]);
+
+        match store.get("a.vk").expect("failed to get") {
+            Some(ParameterEntry::File(path)) => assert_eq!(path, shared.path().join("a.vk")),
+            other => panic!("unexpected entry {:?}", other),
+        }
+        match store.get("b.vk").expect("failed to get") {
+            Some(ParameterEntry::File(path)) => assert_eq!(path, local.path().join("b.vk")),
+            other => panic!("unexpected entry {:?}", other),
+        }
+        assert!(store.get("c.vk").expect("failed to get").is_none());
+
+        // New entries skip the read-only directory.
+        put_bytes(&store, "c.vk", b"new").expect("failed to put");
+        assert!(!shared.path().join("c.vk").exists());
+        assert_eq!(
+            fs::read(local.path().join("c.vk")).expect("failed to read"),
+            b"new"
+        );
+
+        let err = put_bytes(&store, "c.vk", b"new").expect_err("must fail");
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn test_directory_store_from_settings() {
+        let settings = Settings {
+            parameter_search_path: "/mnt/shared-params:/opt/params".to_string(),
+            parameter_cache: "/var/tmp/filecoin-proof-parameters/".to_string(),
+            ..Settings::default()
+        };
+        let store = DirectoryParameterStore::from_settings(&settings);
+
+        assert_eq!(
+            store.dirs(),
+            &[
+                ParameterDir::read_only("/mnt/shared-params"),
+                ParameterDir::read_only("/opt/params"),
+                ParameterDir::writable("/var/tmp/filecoin-proof-parameters/"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = Arc::new(MemoryParameterStore::new());
+        assert!(store.get("a.meta").expect("failed to get").is_none());
+
+        put_bytes(&*store, "a.meta", b"{}").expect("failed to put");
+        match store.get("a.meta").expect("failed to get") {
+            Some(ParameterEntry::Memory(data)) => assert_eq!(&data[..], b"{}"),
+            other => panic!("unexpected entry {:?}", other),
+        }
+        let err = put_bytes(&*store, "a.meta", b"{}").expect_err("must fail");
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        with_parameter_store(store.clone(), || {
+            assert!(current().get("a.meta").expect("failed to get").is_some());
+        });
+        assert_eq!(store.names(), vec!["a.meta".to_string()]);
+    }
+}
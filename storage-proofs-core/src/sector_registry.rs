@@ -0,0 +1,515 @@
+//! Registry of the supported sector sizes.
+//!
+//! Every sector size proofs can be generated for is registered together with the shape of its
+//! replica tree (`tree_r_last`) and the parameters of its proofs: the number of SDR layers, the
+//! minimum number of PoRep challenges, the number of PoRep partitions, the number of synthetic
+//! PoRep challenges, the minimum number of non-interactive PoRep challenges and the number of
+//! sectors per Window PoSt partition. The published sector sizes and the sizes used for testing are
+//! registered by default. Further sizes, e.g. for devnets, are added with
+//! [`register_sector_size`] or listed in the JSON file named by the `sector_size_registry`
+//! setting, which is read the first time the registry is accessed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{RwLock, RwLockReadGuard};
+
+use anyhow::{ensure, Context, Result};
+use generic_array::typenum::Unsigned;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_version::ApiVersion, error::Error, merkle::MerkleTreeTrait, settings::SETTINGS,
+    util::NODE_SIZE,
+};
+
+lazy_static! {
+    static ref REGISTRY: RwLock<BTreeMap<u64, SectorSizeInfo>> =
+        RwLock::new(initial_registry().expect("invalid sector size registry"));
+}
+
+/// The arities of the base, sub and top trees of a replica tree. A sub or top arity of `0` means
+/// the tree has no such layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TreeShape {
+    pub base: usize,
+    pub sub: usize,
+    pub top: usize,
+}
+
+impl TreeShape {
+    pub const BASE: TreeShape = TreeShape::new(8, 0, 0);
+    pub const SUB2: TreeShape = TreeShape::new(8, 2, 0);
+    pub const SUB4: TreeShape = TreeShape::new(8, 4, 0);
+    pub const SUB8: TreeShape = TreeShape::new(8, 8, 0);
+    pub const TOP2: TreeShape = TreeShape::new(8, 8, 2);
+
+    /// The shapes proofs can be generated for.
+    pub const SUPPORTED: [TreeShape; 5] = [
+        TreeShape::BASE,
+        TreeShape::SUB2,
+        TreeShape::SUB4,
+        TreeShape::SUB8,
+        TreeShape::TOP2,
+    ];
+
+    pub const fn new(base: usize, sub: usize, top: usize) -> Self {
+        TreeShape { base, sub, top }
+    }
+
+    pub fn of<Tree: MerkleTreeTrait>() -> Self {
+        TreeShape::new(
+            Tree::Arity::to_usize(),
+            Tree::SubTreeArity::to_usize(),
+            Tree::TopTreeArity::to_usize(),
+        )
+    }
+
+    /// The number of base trees a replica tree of this shape is split into.
+    pub fn base_tree_count(&self) -> usize {
+        self.sub.max(1) * self.top.max(1)
+    }
+}
+
+impl fmt::Display for TreeShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.base, self.sub, self.top)
+    }
+}
+
+/// A registered sector size and the parameters of its proofs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectorSizeInfo {
+    /// The sector size in bytes.
+    pub sector_size: u64,
+    pub shape: TreeShape,
+    pub layers: usize,
+    pub porep_minimum_challenges: u64,
+    pub porep_partitions: u8,
+    /// The number of synthetic challenges the PoRep challenges are selected from, if synthetic
+    /// PoRep is used.
+    #[serde(default = "default_synthetic_porep_challenges")]
+    pub synthetic_porep_challenges: usize,
+    /// The minimum number of PoRep challenges if non-interactive PoRep is used, `None` if it is
+    /// not supported for this sector size.
+    #[serde(default)]
+    pub ni_porep_minimum_challenges: Option<u64>,
+    // These numbers must match those used for Window PoSt scheduling in the miner actor.
+    // Please coordinate changes with actor code.
+    // https://github.com/filecoin-project/specs-actors/blob/master/actors/abi/sector.go
+    pub window_post_sector_count: usize,
+    /// Whether parameters for this sector size are published in `parameters.json`.
+    #[serde(default)]
+    pub published: bool,
+}
+
+impl SectorSizeInfo {
+    /// The sector size measured in nodes.
+    pub fn nodes(&self) -> usize {
+        (self.sector_size / NODE_SIZE as u64) as usize
+    }
+
+    /// The minimum number of PoRep challenges of `api_version`.
+    pub fn porep_minimum_challenges_for(&self, api_version: ApiVersion) -> Result<u64> {
+        if api_version.supports_non_interactive_porep() {
+            self.ni_porep_minimum_challenges.ok_or_else(|| {
+                Error::InvalidSectorSize(
+                    self.sector_size,
+                    "non-interactive porep is not supported".to_string(),
+                )
+                .into()
+            })
+        } else {
+            Ok(self.porep_minimum_challenges)
+        }
+    }
+
+    /// The number of PoRep partitions of `api_version`. Non-interactive PoRep keeps the number
+    /// of challenges per partition, and therefore the circuit, of interactive PoRep and adds
+    /// partitions until its minimum number of challenges is reached.
+    pub fn porep_partitions_for(&self, api_version: ApiVersion) -> Result<u8> {
+        if !api_version.supports_non_interactive_porep() {
+            return Ok(self.porep_partitions);
+        }
+
+        let minimum_challenges = self.porep_minimum_challenges_for(api_version)?;
+        let partitions = self.porep_partitions as u64;
+        let challenges_per_partition =
+            (self.porep_minimum_challenges + partitions - 1) / partitions;
+        let ni_partitions =
+            (minimum_challenges + challenges_per_partition - 1) / challenges_per_partition;
+        ensure!(
+            ni_partitions <= u8::MAX as u64,
+            Error::InvalidSectorSize(
+                self.sector_size,
+                format!("{} non-interactive porep partitions", ni_partitions)
+            )
+        );
+
+        Ok(ni_partitions as u8)
+    }
+
+    /// Checks that proofs can be generated for this sector size.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| Error::InvalidSectorSize(self.sector_size, reason);
+
+        ensure!(
+            self.sector_size.is_power_of_two() && self.sector_size >= NODE_SIZE as u64,
+            invalid("not a power of two number of nodes".to_string())
+        );
+        ensure!(
+            TreeShape::SUPPORTED.contains(&self.shape),
+            invalid(format!("unsupported tree shape {}", self.shape))
+        );
+
+        // Each base tree must be a full tree of the base arity.
+        let base_trees = self.shape.base_tree_count();
+        let base_leaves = self.nodes() / base_trees;
+        ensure!(
+            self.nodes() % base_trees == 0
+                && base_leaves >= self.shape.base
+                && base_leaves.trailing_zeros() % self.shape.base.trailing_zeros() == 0,
+            invalid(format!(
+                "{} nodes do not fit a tree of shape {}",
+                self.nodes(),
+                self.shape
+            ))
+        );
+
+        ensure!(self.layers > 0, invalid("no layers".to_string()));
+        ensure!(
+            self.porep_minimum_challenges > 0,
+            invalid("no porep challenges".to_string())
+        );
+        ensure!(
+            self.porep_partitions > 0,
+            invalid("no porep partitions".to_string())
+        );
+        ensure!(
+            self.synthetic_porep_challenges > 0,
+            invalid("no synthetic porep challenges".to_string())
+        );
+        if self.ni_porep_minimum_challenges.is_some() {
+            let ni_challenges = self.porep_minimum_challenges_for(ApiVersion::V1_3_0)?;
+            ensure!(
+                ni_challenges >= self.porep_minimum_challenges,
+                invalid("fewer non-interactive than interactive porep challenges".to_string())
+            );
+            self.porep_partitions_for(ApiVersion::V1_3_0)?;
+        }
+        ensure!(
+            self.window_post_sector_count > 0,
+            invalid("no window post sectors".to_string())
+        );
+
+        Ok(())
+    }
+}
+
+/// Registers a sector size, replacing its previous registration if it was registered already.
+/// The registration of a published sector size can't be changed.
+pub fn register_sector_size(info: SectorSizeInfo) -> Result<()> {
+    insert_sector_size(info, false)
+}
+
+/// Registers a sector size like [`register_sector_size`], but also replaces the registration of
+/// a published sector size, e.g. to benchmark other proof parameters. Proofs of a replaced
+/// published sector size don't verify with its published parameters.
+pub fn replace_sector_size(info: SectorSizeInfo) -> Result<()> {
+    insert_sector_size(info, true)
+}
+
+fn insert_sector_size(info: SectorSizeInfo, replace_published: bool) -> Result<()> {
+    info.validate()?;
+
+    let mut registry = REGISTRY.write().expect("sector size registry poisoned");
+    if !replace_published {
+        ensure_replaceable(registry.get(&info.sector_size), &info)?;
+    }
+    match registry.insert(info.sector_size, info) {
+        Some(prior) if prior != info => {
+            warn!(
+                "replacing sector size registration {:?} by {:?}",
+                prior, info
+            )
+        }
+        Some(_) => {}
+        None => info!("registered sector size {:?}", info),
+    }
+
+    Ok(())
+}
+
+/// Returns the registration of `sector_size`.
+pub fn sector_size_info(sector_size: u64) -> Result<SectorSizeInfo> {
+    read_registry()
+        .get(&sector_size)
+        .copied()
+        .ok_or_else(|| Error::UnknownSectorSize(sector_size).into())
+}
+
+pub fn is_registered_sector_size(sector_size: u64) -> bool {
+    read_registry().contains_key(&sector_size)
+}
+
+/// Returns all registered sector sizes, ordered by size.
+pub fn registered_sector_sizes() -> Vec<SectorSizeInfo> {
+    read_registry().values().copied().collect()
+}
+
+/// Returns the sector sizes for which parameters have been published, ordered by size.
+pub fn published_sector_sizes() -> Vec<u64> {
+    read_registry()
+        .values()
+        .filter(|info| info.published)
+        .map(|info| info.sector_size)
+        .collect()
+}
+
+fn read_registry() -> RwLockReadGuard<'static, BTreeMap<u64, SectorSizeInfo>> {
+    REGISTRY.read().expect("sector size registry poisoned")
+}
+
+/// The default sector sizes, followed by the ones listed in the `sector_size_registry` file.
+fn initial_registry() -> Result<BTreeMap<u64, SectorSizeInfo>> {
+    let mut registry: BTreeMap<_, _> = default_sector_sizes()
+        .into_iter()
+        .map(|info| (info.sector_size, info))
+        .collect();
+
+    let path = &SETTINGS.sector_size_registry;
+    if !path.is_empty() {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open sector size registry {}", path))?;
+        let infos: Vec<SectorSizeInfo> = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("failed to parse sector size registry {}", path))?;
+        for info in infos {
+            info.validate()?;
+            ensure_replaceable(registry.get(&info.sector_size), &info)?;
+            info!("registered sector size {:?} from {}", info, path);
+            registry.insert(info.sector_size, info);
+        }
+    }
+
+    Ok(registry)
+}
+
+// Published sector sizes must match the published parameters, so their registration is fixed.
+fn ensure_replaceable(prior: Option<&SectorSizeInfo>, info: &SectorSizeInfo) -> Result<()> {
+    if let Some(prior) = prior {
+        ensure!(
+            !prior.published || prior == info,
+            Error::InvalidSectorSize(
+                info.sector_size,
+                format!("published registration {:?} can't be replaced", prior)
+            )
+        );
+    }
+
+    Ok(())
+}
+
+fn default_synthetic_porep_challenges() -> usize {
+    1 << 18
+}
+
+fn default_sector_sizes() -> Vec<SectorSizeInfo> {
+    let test = |sector_size, shape| SectorSizeInfo {
+        sector_size,
+        shape,
+        layers: 2,
+        porep_minimum_challenges: 2,
+        porep_partitions: 1,
+        synthetic_porep_challenges: 16,
+        ni_porep_minimum_challenges: Some(4),
+        window_post_sector_count: 2,
+        published: false,
+    };
+    let published = |sector_size, shape| SectorSizeInfo {
+        published: true,
+        ..test(sector_size, shape)
+    };
+    let production = |sector_size, shape, window_post_sector_count| SectorSizeInfo {
+        layers: 11,
+        porep_minimum_challenges: 176,
+        porep_partitions: 10,
+        synthetic_porep_challenges: default_synthetic_porep_challenges(),
+        ni_porep_minimum_challenges: Some(2253),
+        window_post_sector_count,
+        ..published(sector_size, shape)
+    };
+
+    vec![
+        // Only used for testing empty sector updates.
+        test(1 << 10, TreeShape::SUB4),
+        test(1 << 13, TreeShape::SUB4),
+        published(1 << 11, TreeShape::BASE),
+        published(1 << 12, TreeShape::SUB2),
+        published(1 << 14, TreeShape::SUB8),
+        published(1 << 15, TreeShape::TOP2),
+        published(1 << 23, TreeShape::BASE),
+        published(1 << 24, TreeShape::SUB2),
+        published(1 << 29, TreeShape::BASE),
+        published(1 << 30, TreeShape::SUB2),
+        // 2349 sectors give 125,279,217 constraints, fitting in a single partition.
+        production(1 << 35, TreeShape::SUB8, 2349),
+        // 2300 sectors give 129,887,900 constraints, fitting in a single partition.
+        production(1 << 36, TreeShape::TOP2, 2300),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use filecoin_hashers::poseidon::PoseidonHasher;
+    use generic_array::typenum::{U0, U2, U8};
+
+    use crate::merkle::LCTree;
+
+    fn devnet(sector_size: u64, shape: TreeShape) -> SectorSizeInfo {
+        SectorSizeInfo {
+            sector_size,
+            shape,
+            layers: 2,
+            porep_minimum_challenges: 2,
+            porep_partitions: 1,
+            synthetic_porep_challenges: 16,
+            ni_porep_minimum_challenges: Some(4),
+            window_post_sector_count: 2,
+            published: false,
+        }
+    }
+
+    #[test]
+    fn test_default_sector_sizes() {
+        for info in default_sector_sizes() {
+            info.validate().expect("invalid default sector size");
+        }
+
+        let info = sector_size_info(1 << 35).expect("32GiB is not registered");
+        assert_eq!(info.shape, TreeShape::SUB8);
+        assert_eq!(info.layers, 11);
+        assert_eq!(info.porep_partitions, 10);
+        assert_eq!(published_sector_sizes().len(), 10);
+        assert!(!published_sector_sizes().contains(&(1 << 10)));
+    }
+
+    #[test]
+    fn test_non_interactive_porep_partitions() -> Result<()> {
+        let info = sector_size_info(1 << 35)?;
+        assert_eq!(info.porep_partitions_for(ApiVersion::V1_2_0)?, 10);
+        assert_eq!(info.porep_minimum_challenges_for(ApiVersion::V1_3_0)?, 2253);
+        // 18 challenges per partition, as for interactive PoRep.
+        assert_eq!(info.porep_partitions_for(ApiVersion::V1_3_0)?, 126);
+
+        let info = SectorSizeInfo {
+            ni_porep_minimum_challenges: None,
+            ..devnet(1 << 27, TreeShape::SUB2)
+        };
+        info.validate()?;
+        assert_eq!(info.porep_partitions_for(ApiVersion::V1_1_0)?, 1);
+        assert!(info.porep_partitions_for(ApiVersion::V1_3_0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_sector_size() {
+        // 128MiB and 4GiB.
+        let sizes = [(1 << 27, TreeShape::SUB2), (1 << 32, TreeShape::BASE)];
+        for (sector_size, shape) in sizes.iter().copied() {
+            register_sector_size(devnet(sector_size, shape)).expect("failed to register");
+            assert_eq!(
+                sector_size_info(sector_size).expect("not registered"),
+                devnet(sector_size, shape)
+            );
+            assert!(!published_sector_sizes().contains(&sector_size));
+        }
+        assert!(registered_sector_sizes()
+            .windows(2)
+            .all(|infos| infos[0].sector_size < infos[1].sector_size));
+
+        // Published sizes can be registered again, but not changed.
+        let published = sector_size_info(1 << 11).expect("2KiB is not registered");
+        register_sector_size(published).expect("failed to register published size again");
+        let err = register_sector_size(SectorSizeInfo {
+            layers: 4,
+            ..published
+        })
+        .expect_err("published size replaced");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidSectorSize(_, _))
+        ));
+        assert_eq!(
+            sector_size_info(1 << 11).expect("2KiB is not registered"),
+            published
+        );
+        // Only an explicit replacement changes them.
+        let replaced = SectorSizeInfo {
+            sector_size: 1 << 15,
+            ..sector_size_info(1 << 15).expect("32KiB is not registered")
+        };
+        let replaced = SectorSizeInfo {
+            layers: replaced.layers + 1,
+            ..replaced
+        };
+        replace_sector_size(replaced).expect("failed to replace published size");
+        assert_eq!(
+            sector_size_info(1 << 15).expect("32KiB is not registered"),
+            replaced
+        );
+
+        let err = sector_size_info(3 << 20).expect_err("unregistered size found");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::UnknownSectorSize(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_sector_sizes() {
+        let invalid = [
+            devnet(3 << 20, TreeShape::BASE),
+            devnet(1 << 27, TreeShape::BASE),
+            devnet(1 << 27, TreeShape::new(8, 16, 0)),
+            SectorSizeInfo {
+                layers: 0,
+                ..devnet(1 << 27, TreeShape::SUB2)
+            },
+            SectorSizeInfo {
+                synthetic_porep_challenges: 0,
+                ..devnet(1 << 27, TreeShape::SUB2)
+            },
+            SectorSizeInfo {
+                ni_porep_minimum_challenges: Some(1),
+                ..devnet(1 << 27, TreeShape::SUB2)
+            },
+            SectorSizeInfo {
+                ni_porep_minimum_challenges: Some(1 << 10),
+                ..devnet(1 << 27, TreeShape::SUB2)
+            },
+        ];
+        for info in invalid.iter() {
+            assert!(register_sector_size(*info).is_err(), "{:?}", info);
+        }
+        assert!(!is_registered_sector_size(3 << 20));
+    }
+
+    #[test]
+    fn test_tree_shape_of() {
+        assert_eq!(
+            TreeShape::of::<LCTree<PoseidonHasher, U8, U0, U0>>(),
+            TreeShape::BASE
+        );
+        assert_eq!(
+            TreeShape::of::<LCTree<PoseidonHasher, U8, U8, U2>>(),
+            TreeShape::TOP2
+        );
+        assert_eq!(TreeShape::SUB2.to_string(), "8-2-0");
+    }
+}
@@ -1,4 +1,6 @@
+use std::cell::RefCell;
 use std::env;
+use std::sync::Arc;
 
 use config::{Config, ConfigError, Environment, File};
 use lazy_static::lazy_static;
@@ -6,12 +8,17 @@ use serde::{Deserialize, Serialize};
 
 lazy_static! {
     pub static ref SETTINGS: Settings = Settings::new().expect("invalid configuration");
+    static ref DEFAULT_SETTINGS: Arc<Settings> = Arc::new(SETTINGS.clone());
+}
+
+thread_local! {
+    static CURRENT_SETTINGS: RefCell<Option<Arc<Settings>>> = const { RefCell::new(None) };
 }
 
 const SETTINGS_PATH: &str = "./rust-fil-proofs.config.toml";
 const PREFIX: &str = "FIL_PROOFS";
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Settings {
     pub verify_cache: bool,
@@ -19,17 +26,58 @@ pub struct Settings {
     pub use_gpu_column_builder: bool,
     pub max_gpu_column_batch_size: u32,
     pub column_write_batch_size: u32,
+    /// Number of threads hashing columns, and building the base trees, when tree c is built on
+    /// the CPU. `0` uses one per logical processor.
+    pub tree_c_cpu_threads: usize,
+    /// Bytes of memory building tree c on the CPU may use for the column hashes of the base
+    /// trees, the layer data read ahead and the columns being hashed. It is exceeded if it
+    /// doesn't hold the column hashes of one base tree.
+    pub tree_c_cpu_memory_budget: u64,
     pub use_gpu_tree_builder: bool,
     pub max_gpu_tree_batch_size: u32,
     pub rows_to_discard: u32,
     pub sdr_parents_cache_size: u32,
     pub window_post_synthesis_num_cpus: u32,
     pub parameter_cache: String,
+    /// Directories searched for parameters before `parameter_cache`, separated like `PATH`.
+    /// They are only read from, generated parameters are written to `parameter_cache`.
+    pub parameter_search_path: String,
     pub parent_cache: String,
+    /// Limits of the in-memory caches of Groth parameters, verifying keys and SRS keys. Once a
+    /// cache exceeds a limit, its least recently used entries are evicted. `0` means unbounded.
+    pub groth_param_memory_cache_max_entries: usize,
+    pub groth_param_memory_cache_max_bytes: u64,
+    pub verifying_key_memory_cache_max_entries: usize,
+    pub srs_memory_cache_max_entries: usize,
+    /// Path of a JSON file listing sector sizes to register in addition to the default ones,
+    /// see [`crate::sector_registry`].
+    pub sector_size_registry: String,
     pub use_multicore_sdr: bool,
     pub multicore_sdr_producers: usize,
     pub multicore_sdr_producer_stride: u64,
     pub multicore_sdr_lookahead: usize,
+    /// Groups of logical processors multicore SDR binds its threads to, e.g. `0-3,4-7;8-11`,
+    /// instead of the detected ones. Empty to detect them.
+    pub multicore_sdr_core_groups: String,
+    /// Keep a copy of the parent cache per NUMA node, next to the cache, for multicore SDR to
+    /// read the parents from memory local to its cores.
+    pub multicore_sdr_numa_parent_cache: bool,
+    /// Huge pages backing the SDR layers and parent cache windows: `transparent` for
+    /// transparent huge pages, `hugetlbfs` for the pages of the `sdr_hugetlbfs_path` mount, or
+    /// empty for none.
+    pub sdr_huge_pages: String,
+    /// The hugetlbfs mount used if `sdr_huge_pages` is `hugetlbfs`.
+    pub sdr_hugetlbfs_path: String,
+    /// Bytes of memory SDR labeling may keep for the labels of the layer being labeled. If set,
+    /// layers are streamed to and from disk instead of keeping two full layers in memory, which
+    /// is slower. `0` keeps two full layers in memory.
+    pub sdr_memory_budget: u64,
+    /// Number of nodes after which SDR labeling checkpoints the layer being labeled, so that an
+    /// interrupted layer resumes from its last checkpoint. `0` disables checkpoints.
+    pub sdr_checkpoint_interval: u64,
+    /// Bytes of the regions in which the layer being labeled is written while labeling
+    /// continues. `0` writes each layer once it is labeled.
+    pub sdr_write_behind_region_size: u64,
 }
 
 impl Default for Settings {
@@ -40,6 +88,8 @@ impl Default for Settings {
             use_gpu_column_builder: false,
             max_gpu_column_batch_size: 400_000,
             column_write_batch_size: 262_144,
+            tree_c_cpu_threads: 0,
+            tree_c_cpu_memory_budget: 1 << 30,
             use_gpu_tree_builder: false,
             max_gpu_tree_batch_size: 700_000,
             rows_to_discard: 2,
@@ -49,15 +99,69 @@ impl Default for Settings {
             // for durable, canonical Groth parameters and verifying keys.
             // The name is retained for backwards compatibility.
             parameter_cache: "/var/tmp/filecoin-proof-parameters/".to_string(),
+            parameter_search_path: String::new(),
             parent_cache: cache("filecoin-parents"),
+            groth_param_memory_cache_max_entries: 0,
+            groth_param_memory_cache_max_bytes: 0,
+            verifying_key_memory_cache_max_entries: 0,
+            srs_memory_cache_max_entries: 0,
+            sector_size_registry: String::new(),
             use_multicore_sdr: false,
             multicore_sdr_producers: 3,
             multicore_sdr_producer_stride: 128,
             multicore_sdr_lookahead: 800,
+            multicore_sdr_core_groups: String::new(),
+            multicore_sdr_numa_parent_cache: false,
+            sdr_huge_pages: String::new(),
+            sdr_hugetlbfs_path: "/dev/hugepages".to_string(),
+            sdr_memory_budget: 0,
+            sdr_checkpoint_interval: 0,
+            sdr_write_behind_region_size: 1 << 26,
         }
     }
 }
 
+/// Settings that are passed explicitly to the proofs API, instead of relying on the global
+/// [`SETTINGS`] only.
+///
+/// A few settings size process-wide state when it is first used and are always taken from the
+/// global [`SETTINGS`], a `ProofsConfig` has no effect on them:
+///
+/// - the in-memory parameter cache limits (`*_memory_cache_max_*`), which can be changed at
+///   runtime with `filecoin_proofs::caches::set_memory_cache_limits` instead,
+/// - the multicore SDR core groups (`multicore_sdr_producers`, `multicore_sdr_core_groups`),
+/// - the sector size registry file (`sector_size_registry`).
+pub type ProofsConfig = Settings;
+
+/// Returns the settings in effect on the current thread. These are the settings installed by
+/// [`with_settings`] if called from within it, the global [`SETTINGS`] otherwise.
+pub fn current() -> Arc<Settings> {
+    CURRENT_SETTINGS
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| DEFAULT_SETTINGS.clone())
+}
+
+/// Runs `f` with `settings` in place of the global [`SETTINGS`] on the current thread.
+///
+/// Calls can be nested; the previous settings are restored once `f` returns or panics. The
+/// settings are not propagated to other threads, so any value needed by work running on another
+/// thread has to be read before that work is spawned.
+pub fn with_settings<T, F: FnOnce() -> T>(settings: Arc<Settings>, f: F) -> T {
+    struct Restore(Option<Arc<Settings>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let prior = self.0.take();
+            CURRENT_SETTINGS.with(|current| *current.borrow_mut() = prior);
+        }
+    }
+
+    let prior = CURRENT_SETTINGS.with(|current| current.borrow_mut().replace(settings));
+    let _restore = Restore(prior);
+
+    f()
+}
+
 /// All cache files and directories paths should be constructed using this function,
 /// which its base directory from the FIL_PROOFS_CACHE_DIR env var, and defaults to /var/tmp.
 /// Note that FIL_PROOFS_CACHE_DIR is not a first class setting and can only be set by env var.
@@ -99,3 +203,37 @@ impl Settings {
             .try_deserialize()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_settings() {
+        assert_eq!(current().rows_to_discard, SETTINGS.rows_to_discard);
+
+        let outer = Arc::new(Settings {
+            rows_to_discard: SETTINGS.rows_to_discard + 1,
+            ..Settings::default()
+        });
+        let inner = Arc::new(Settings {
+            rows_to_discard: SETTINGS.rows_to_discard + 2,
+            ..Settings::default()
+        });
+
+        with_settings(outer.clone(), || {
+            assert_eq!(current().rows_to_discard, outer.rows_to_discard);
+            with_settings(inner.clone(), || {
+                assert_eq!(current().rows_to_discard, inner.rows_to_discard);
+            });
+            assert_eq!(current().rows_to_discard, outer.rows_to_discard);
+
+            // Other threads keep using the global settings.
+            std::thread::spawn(|| assert_eq!(current().rows_to_discard, SETTINGS.rows_to_discard))
+                .join()
+                .expect("thread panicked");
+        });
+
+        assert_eq!(current().rows_to_discard, SETTINGS.rows_to_discard);
+    }
+}
@@ -0,0 +1,129 @@
+//! Optional [`tracing`](https://docs.rs/tracing) integration.
+//!
+//! With the `tracing` feature enabled, `enter_span!` creates and enters an
+//! `INFO` level span, which is exited when the returned guard is dropped.
+//! Spans nest, so phase spans (labeling, tree building, proving, ...) are
+//! children of the span of the API call which carries the sector's context
+//! (`sector_id`, `sector_size` and `api_version`).
+//!
+//! Without the feature, `enter_span!` expands to a no-op guard and its field
+//! expressions are not evaluated.
+
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+pub use tracing as __tracing;
+
+/// Guard of an entered span, the span is exited when it is dropped.
+#[must_use = "the span is exited when the guard is dropped"]
+pub struct SpanGuard {
+    #[cfg(feature = "tracing")]
+    _entered: tracing::span::EnteredSpan,
+}
+
+impl SpanGuard {
+    #[cfg(feature = "tracing")]
+    #[doc(hidden)]
+    pub fn enter(span: tracing::Span) -> Self {
+        SpanGuard {
+            _entered: span.entered(),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[doc(hidden)]
+    pub fn disabled() -> Self {
+        SpanGuard {}
+    }
+}
+
+/// Creates and enters a span with the given name and fields, using the field
+/// syntax of `tracing::info_span!`.
+///
+/// ```
+/// use storage_proofs_core::enter_span;
+///
+/// let layer = 1;
+/// let _span = enter_span!("labeling_layer", layer);
+/// ```
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! enter_span {
+    ($($args:tt)+) => {
+        $crate::spans::SpanGuard::enter($crate::spans::__tracing::info_span!($($args)+))
+    };
+}
+
+/// No-op version of `enter_span!`, used when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[macro_export]
+macro_rules! enter_span {
+    ($($args:tt)+) => {
+        $crate::spans::SpanGuard::disabled()
+    };
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        span::{Attributes, Id, Record},
+        subscriber::with_default,
+        Event, Metadata, Subscriber,
+    };
+
+    /// Records the name of every span which is entered.
+    #[derive(Default)]
+    struct Recorder {
+        names: Mutex<Vec<&'static str>>,
+        entered: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.names.lock().expect("names lock poisoned");
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            let name =
+                self.names.lock().expect("names lock poisoned")[span.into_u64() as usize - 1];
+            self.entered
+                .lock()
+                .expect("entered lock poisoned")
+                .push(name);
+        }
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_enter_span() {
+        let recorder = Recorder::default();
+        let entered = recorder.entered.clone();
+
+        with_default(recorder, || {
+            let sector_id = 7u64;
+            let _outer = enter_span!("seal_pre_commit_phase1", sector_id);
+            for layer in 1..=2 {
+                let _inner = enter_span!("labeling_layer", layer);
+            }
+        });
+
+        assert_eq!(
+            *entered.lock().expect("entered lock poisoned"),
+            vec!["seal_pre_commit_phase1", "labeling_layer", "labeling_layer"]
+        );
+    }
+}
@@ -8,7 +8,7 @@ use bellperson::{
 use ff::PrimeField;
 use merkletree::merkle::get_merkle_tree_row_count;
 
-use crate::{error::Error, settings::SETTINGS};
+use crate::{error::Error, settings};
 
 pub const NODE_SIZE: usize = 32;
 
@@ -165,7 +165,7 @@ pub fn default_rows_to_discard(leafs: usize, arity: usize) -> usize {
 
     // This configurable setting is for a default oct-tree
     // rows_to_discard value, which defaults to 2.
-    let rows_to_discard = SETTINGS.rows_to_discard as usize;
+    let rows_to_discard = settings::current().rows_to_discard as usize;
 
     // Discard at most 'constant value' rows (coded below,
     // differing by arity) while respecting the max number that
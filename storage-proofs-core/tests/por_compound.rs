@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bellperson::{
     util_cs::{metric_cs::MetricCS, test_cs::TestConstraintSystem},
     Circuit,
@@ -18,6 +20,7 @@ use storage_proofs_core::{
         create_base_merkle_tree, generate_tree, get_base_tree_count, MerkleTreeTrait,
         MerkleTreeWrapper, ResTree,
     },
+    parameter_store::{with_parameter_store, MemoryParameterStore},
     por,
     proof::NoRequirements,
     util::data_at_node,
@@ -34,6 +37,33 @@ fn test_por_compound_poseidon_base_8() {
     por_compound::<TreeBase<PoseidonHasher, U8>>();
 }
 
+#[test]
+fn test_por_compound_memory_parameter_store() {
+    type Tree = TreeBase<PoseidonHasher, U2>;
+
+    let store = Arc::new(MemoryParameterStore::new());
+    with_parameter_store(store.clone(), || {
+        por_compound::<Tree>();
+
+        // The verifying key is derived from the stored parameters.
+        let setup_params = compound_proof::SetupParams {
+            vanilla_params: por::SetupParams {
+                leaves: 64 * get_base_tree_count::<Tree>(),
+                private: false,
+            },
+            partitions: None,
+            priority: false,
+        };
+        let public_params = PoRCompound::<Tree>::setup(&setup_params).expect("setup failed");
+        PoRCompound::<Tree>::verifying_key::<XorShiftRng>(None, &public_params.vanilla_params)
+            .expect("failed to get verifying key");
+    });
+
+    let names = store.names();
+    assert!(names.iter().any(|name| name.ends_with(".params")));
+    assert!(names.iter().any(|name| name.ends_with(".vk")));
+}
+
 fn por_compound<Tree: 'static + MerkleTreeTrait>() {
     let mut rng = XorShiftRng::from_seed(TEST_SEED);
 
@@ -17,7 +17,7 @@ use storage_proofs_core::{
     drgraph::{Graph, BASE_DEGREE},
     error::Result,
     parameter_cache::{with_exclusive_lock, LockedFile, ParameterSetMetadata, VERSION},
-    settings::SETTINGS,
+    settings,
     util::NODE_SIZE,
 };
 
@@ -216,14 +216,14 @@ impl ParentCache {
 
                     (
                         None,
-                        SETTINGS.verify_cache,
+                        settings::current().verify_cache,
                         false, // not production since not in manifest
                         "".to_string(),
                     )
                 }
                 Some(pcd) => (
                     Some(pcd),
-                    SETTINGS.verify_cache,
+                    settings::current().verify_cache,
                     true, // is_production since it exists in the manifest
                     pcd.digest.clone(),
                 ),
@@ -406,10 +406,22 @@ impl ParentCache {
     pub fn reset(&mut self) -> Result<()> {
         self.cache.reset()
     }
+
+    /// Moves the partial cache to start at `node`, to resume reading from there.
+    pub fn seek(&mut self, node: u32) -> Result<()> {
+        ensure!(
+            node < self.num_cache_entries,
+            "node {} is out of the cache of {} entries",
+            node,
+            self.num_cache_entries
+        );
+        let new_offset = node.min(self.num_cache_entries - self.cache.len);
+        self.cache.shift(new_offset)
+    }
 }
 
 fn parent_cache_dir_name() -> String {
-    SETTINGS.parent_cache.clone()
+    settings::current().parent_cache.clone()
 }
 
 fn parent_cache_id(path: &Path) -> String {
@@ -1,20 +1,70 @@
+use std::fmt::{self, Debug, Formatter};
+
 use filecoin_hashers::Domain;
 use num_bigint::BigUint;
 use num_traits::cast::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LayerChallenges {
     /// How many layers we are generating challenges for.
     layers: usize,
     /// The maximum count of challenges
     max_count: usize,
+    /// The number of synthetic challenges, if the challenges are selected from synthetic ones.
+    #[serde(default)]
+    synthetic_count: Option<usize>,
+    /// Whether the challenges are derived from `comm_r` instead of an interactive seed.
+    #[serde(default)]
+    non_interactive: bool,
+}
+
+/// Domain separation of the seed of non-interactive challenges.
+const NON_INTERACTIVE_SEED_TAG: &[u8] = b"filecoin.io|NI-PoRep|seed";
+
+// Synthetic and non-interactive challenges do not change the circuit, so they are left out of the
+// parameter identifiers, which include this output.
+impl Debug for LayerChallenges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayerChallenges")
+            .field("layers", &self.layers)
+            .field("max_count", &self.max_count)
+            .finish()
+    }
 }
 
 impl LayerChallenges {
     pub const fn new(layers: usize, max_count: usize) -> Self {
-        LayerChallenges { layers, max_count }
+        LayerChallenges {
+            layers,
+            max_count,
+            synthetic_count: None,
+            non_interactive: false,
+        }
+    }
+
+    /// Challenges selected by the seed from `synthetic_count` synthetic challenges, which only
+    /// depend on the replica and can therefore be proven before the seed is known.
+    pub const fn new_synthetic(layers: usize, max_count: usize, synthetic_count: usize) -> Self {
+        LayerChallenges {
+            layers,
+            max_count,
+            synthetic_count: Some(synthetic_count),
+            non_interactive: false,
+        }
+    }
+
+    /// Challenges derived from the seed returned by
+    /// [`non_interactive_seed`](Self::non_interactive_seed), so that they can be proven right
+    /// after the replica is sealed. The seed passed to [`derive`](Self::derive) is ignored.
+    pub const fn new_non_interactive(layers: usize, max_count: usize) -> Self {
+        LayerChallenges {
+            layers,
+            max_count,
+            synthetic_count: None,
+            non_interactive: true,
+        }
     }
 
     pub fn layers(&self) -> usize {
@@ -25,15 +75,53 @@ impl LayerChallenges {
         self.max_count
     }
 
-    /// Derive all challenges.
+    pub fn synthetic_count(&self) -> Option<usize> {
+        self.synthetic_count
+    }
+
+    pub fn is_non_interactive(&self) -> bool {
+        self.non_interactive
+    }
+
+    /// Whether deriving the challenges requires `comm_r`.
+    pub fn requires_comm_r(&self) -> bool {
+        self.synthetic_count.is_some() || self.non_interactive
+    }
+
+    /// The seed non-interactive challenges are derived from.
+    pub fn non_interactive_seed<D: Domain>(replica_id: &D, comm_r: &D) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(NON_INTERACTIVE_SEED_TAG)
+            .chain_update(replica_id.into_bytes())
+            .chain_update(comm_r.into_bytes())
+            .finalize()
+            .into()
+    }
+
+    /// Derive all challenges. With synthetic challenges, these are the synthetic challenges
+    /// selected by [`derive_synthetic_indexes`](Self::derive_synthetic_indexes). Non-interactive
+    /// challenges ignore `seed` and use the one derived from `comm_r`. Otherwise `comm_r` is not
+    /// used.
     pub fn derive<D: Domain>(
         &self,
         leaves: usize,
         replica_id: &D,
+        comm_r: &D,
         seed: &[u8; 32],
         k: u8,
     ) -> Vec<usize> {
-        self.derive_internal(self.challenges_count_all(), leaves, replica_id, seed, k)
+        match self.synthetic_count {
+            Some(_) => self
+                .derive_synthetic_indexes(replica_id, seed, k)
+                .into_iter()
+                .map(|index| synthetic_challenge(leaves, replica_id, comm_r, index))
+                .collect(),
+            None if self.non_interactive => {
+                let seed = Self::non_interactive_seed(replica_id, comm_r);
+                self.derive_internal(self.challenges_count_all(), leaves, replica_id, &seed, k)
+            }
+            None => self.derive_internal(self.challenges_count_all(), leaves, replica_id, seed, k),
+        }
     }
 
     pub fn derive_internal<D: Domain>(
@@ -48,28 +136,97 @@ impl LayerChallenges {
 
         (0..challenges_count)
             .map(|i| {
-                let j: u32 = ((challenges_count * k as usize) + i) as u32;
-
-                let hash = Sha256::new()
-                    .chain_update(replica_id.into_bytes())
-                    .chain_update(seed)
-                    .chain_update(&j.to_le_bytes())
-                    .finalize();
-
-                let big_challenge = BigUint::from_bytes_le(hash.as_ref());
+                let hash = seed_hash(replica_id, seed, challenges_count, k, i);
 
                 // We cannot try to prove the first node, so make sure the challenge
                 // can never be 0.
-                let big_mod_challenge = big_challenge % (leaves - 1);
-                let big_mod_challenge = big_mod_challenge
-                    .to_usize()
-                    .expect("`big_mod_challenge` exceeds size of `usize`");
-                big_mod_challenge + 1
+                reduce(&hash, leaves - 1) + 1
+            })
+            .collect()
+    }
+
+    /// Derive all synthetic challenges, which only depend on the replica.
+    ///
+    /// Panics if the challenges are not synthetic.
+    pub fn derive_synthetic<D: Domain>(
+        &self,
+        leaves: usize,
+        replica_id: &D,
+        comm_r: &D,
+    ) -> Vec<usize> {
+        let synthetic_count = self.synthetic_count.expect("challenges are not synthetic");
+
+        (0..synthetic_count)
+            .map(|index| synthetic_challenge(leaves, replica_id, comm_r, index))
+            .collect()
+    }
+
+    /// Derive the indexes of the synthetic challenges selected by the seed for partition `k`.
+    ///
+    /// Panics if the challenges are not synthetic.
+    pub fn derive_synthetic_indexes<D: Domain>(
+        &self,
+        replica_id: &D,
+        seed: &[u8; 32],
+        k: u8,
+    ) -> Vec<usize> {
+        let synthetic_count = self.synthetic_count.expect("challenges are not synthetic");
+        assert!(synthetic_count > 0, "no synthetic challenges");
+
+        (0..self.max_count)
+            .map(|i| {
+                let hash = seed_hash(replica_id, seed, self.max_count, k, i);
+                reduce(&hash, synthetic_count)
             })
             .collect()
     }
 }
 
+/// The hash selecting the `i`th challenge of partition `k`.
+fn seed_hash<D: Domain>(
+    replica_id: &D,
+    seed: &[u8; 32],
+    challenges_count: usize,
+    k: u8,
+    i: usize,
+) -> [u8; 32] {
+    let j: u32 = ((challenges_count * k as usize) + i) as u32;
+
+    Sha256::new()
+        .chain_update(replica_id.into_bytes())
+        .chain_update(seed)
+        .chain_update(j.to_le_bytes())
+        .finalize()
+        .into()
+}
+
+/// The synthetic challenge at `index`, which is never 0.
+fn synthetic_challenge<D: Domain>(
+    leaves: usize,
+    replica_id: &D,
+    comm_r: &D,
+    index: usize,
+) -> usize {
+    assert!(leaves > 2, "Too few leaves: {}", leaves);
+
+    let hash: [u8; 32] = Sha256::new()
+        .chain_update(replica_id.into_bytes())
+        .chain_update(comm_r.into_bytes())
+        .chain_update((index as u64).to_le_bytes())
+        .finalize()
+        .into();
+
+    reduce(&hash, leaves - 1) + 1
+}
+
+/// Interprets `hash` as a little-endian integer modulo `modulus`.
+fn reduce(hash: &[u8; 32], modulus: usize) -> usize {
+    let big_challenge = BigUint::from_bytes_le(hash);
+    (big_challenge % modulus)
+        .to_usize()
+        .expect("`big_mod_challenge` exceeds size of `usize`")
+}
+
 #[derive(Debug, Default)]
 pub struct ChallengeRequirements {
     pub minimum_challenges: usize,
@@ -102,6 +259,7 @@ mod test {
         let leaves = 1 << 30;
         let rng = &mut thread_rng();
         let replica_id: Sha256Domain = Sha256Domain::random(rng);
+        let comm_r: Sha256Domain = Sha256Domain::random(rng);
         let seed: [u8; 32] = rng.gen();
         let partitions = 5;
         let total_challenges = partitions * n;
@@ -111,7 +269,7 @@ mod test {
         for _layer in 1..=layers {
             let mut histogram = HashMap::new();
             for k in 0..partitions {
-                let challenges = challenges.derive(leaves, &replica_id, &seed, k as u8);
+                let challenges = challenges.derive(leaves, &replica_id, &comm_r, &seed, k as u8);
 
                 for challenge in challenges {
                     let counter = histogram.entry(challenge).or_insert(0);
@@ -139,6 +297,7 @@ mod test {
         let leaves = 1 << 30;
         let rng = &mut thread_rng();
         let replica_id: Sha256Domain = Sha256Domain::random(rng);
+        let comm_r: Sha256Domain = Sha256Domain::random(rng);
         let seed: [u8; 32] = rng.gen();
         let partitions = 5;
         let layers = 100;
@@ -148,16 +307,113 @@ mod test {
             let one_partition_challenges = LayerChallenges::new(layers, total_challenges).derive(
                 leaves,
                 &replica_id,
+                &comm_r,
                 &seed,
                 0,
             );
             let many_partition_challenges = (0..partitions)
                 .flat_map(|k| {
-                    LayerChallenges::new(layers, n).derive(leaves, &replica_id, &seed, k as u8)
+                    LayerChallenges::new(layers, n).derive(
+                        leaves,
+                        &replica_id,
+                        &comm_r,
+                        &seed,
+                        k as u8,
+                    )
                 })
                 .collect::<Vec<_>>();
 
             assert_eq!(one_partition_challenges, many_partition_challenges);
         }
     }
+
+    #[test]
+    fn synthetic_challenge_selection() {
+        let n = 20;
+        let synthetic_count = 1 << 10;
+        let leaves = 1 << 30;
+        let rng = &mut thread_rng();
+        let replica_id: Sha256Domain = Sha256Domain::random(rng);
+        let comm_r: Sha256Domain = Sha256Domain::random(rng);
+        let partitions = 5;
+
+        let challenges = LayerChallenges::new_synthetic(2, n, synthetic_count);
+        let synthetic = challenges.derive_synthetic(leaves, &replica_id, &comm_r);
+        assert_eq!(synthetic.len(), synthetic_count);
+        assert!(synthetic.iter().all(|&c| c > 0 && c < leaves));
+
+        // Synthetic challenges do not depend on the seed, the selection from them does.
+        let seeds: [[u8; 32]; 2] = [rng.gen(), rng.gen()];
+        let selections = seeds
+            .iter()
+            .map(|seed| {
+                (0..partitions)
+                    .flat_map(|k| {
+                        let indexes = challenges.derive_synthetic_indexes(&replica_id, seed, k);
+                        let selected = challenges.derive(leaves, &replica_id, &comm_r, seed, k);
+                        assert_eq!(indexes.len(), n);
+                        assert!(indexes.iter().all(|&i| i < synthetic_count));
+                        assert_eq!(
+                            indexes.iter().map(|&i| synthetic[i]).collect::<Vec<_>>(),
+                            selected
+                        );
+                        indexes
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_ne!(selections[0], selections[1]);
+
+        let other_comm_r: Sha256Domain = Sha256Domain::random(rng);
+        assert_ne!(
+            synthetic,
+            challenges.derive_synthetic(leaves, &replica_id, &other_comm_r)
+        );
+    }
+
+    #[test]
+    fn non_interactive_challenges_ignore_seed() {
+        let n = 20;
+        let leaves = 1 << 30;
+        let rng = &mut thread_rng();
+        let replica_id: Sha256Domain = Sha256Domain::random(rng);
+        let comm_r: Sha256Domain = Sha256Domain::random(rng);
+        let seeds: [[u8; 32]; 2] = [rng.gen(), rng.gen()];
+
+        let challenges = LayerChallenges::new_non_interactive(2, n);
+        let derived = challenges.derive(leaves, &replica_id, &comm_r, &seeds[0], 1);
+        assert_eq!(
+            derived,
+            challenges.derive(leaves, &replica_id, &comm_r, &seeds[1], 1)
+        );
+
+        // They are the interactive challenges of the seed derived from comm_r.
+        let seed = LayerChallenges::non_interactive_seed(&replica_id, &comm_r);
+        assert_eq!(
+            derived,
+            LayerChallenges::new(2, n).derive(leaves, &replica_id, &comm_r, &seed, 1)
+        );
+
+        let other_comm_r: Sha256Domain = Sha256Domain::random(rng);
+        assert_ne!(
+            derived,
+            challenges.derive(leaves, &replica_id, &other_comm_r, &seeds[0], 1)
+        );
+    }
+
+    #[test]
+    fn synthetic_challenges_keep_identifier() {
+        assert_eq!(
+            format!("{:?}", LayerChallenges::new_synthetic(11, 18, 1 << 18)),
+            format!("{:?}", LayerChallenges::new(11, 18)),
+        );
+        assert_eq!(
+            format!("{:?}", LayerChallenges::new_non_interactive(11, 18)),
+            format!("{:?}", LayerChallenges::new(11, 18)),
+        );
+        assert_eq!(
+            format!("{:?}", LayerChallenges::new(11, 18)),
+            "LayerChallenges { layers: 11, max_count: 18 }"
+        );
+    }
 }
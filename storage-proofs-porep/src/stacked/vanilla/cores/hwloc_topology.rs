@@ -0,0 +1,125 @@
+//! The cpu topology as hwloc detects it, used where there is no sysfs.
+
+use std::sync::Mutex;
+
+use anyhow::{format_err, Result};
+use hwloc::{Bitmap, ObjectType, Topology, CPUBIND_THREAD};
+use lazy_static::lazy_static;
+use log::{debug, warn};
+
+use super::{CoreGroup, CoreIndex};
+
+lazy_static! {
+    pub static ref TOPOLOGY: Mutex<Topology> = Mutex::new(Topology::new());
+}
+
+#[cfg(not(target_os = "windows"))]
+pub type ThreadId = libc::pthread_t;
+
+#[cfg(target_os = "windows")]
+pub type ThreadId = winapi::winnt::HANDLE;
+
+/// Helper method to get the thread id through libc, with current rust stable (1.5.0) its not
+/// possible otherwise I think.
+#[cfg(not(target_os = "windows"))]
+fn get_thread_id() -> ThreadId {
+    unsafe { libc::pthread_self() }
+}
+
+#[cfg(target_os = "windows")]
+fn get_thread_id() -> ThreadId {
+    unsafe { kernel32::GetCurrentThread() }
+}
+
+pub struct Cleanup {
+    tid: ThreadId,
+    prior_state: Option<Bitmap>,
+}
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        if let Some(prior) = self.prior_state.take() {
+            let child_topo = &TOPOLOGY;
+            let mut locked_topo = child_topo.lock().expect("poisded lock");
+            let _ = locked_topo.set_cpubind_for_thread(self.tid, prior, CPUBIND_THREAD);
+        }
+    }
+}
+
+pub fn bind_core(core_index: CoreIndex) -> Result<Cleanup> {
+    let child_topo = &TOPOLOGY;
+    let tid = get_thread_id();
+    let mut locked_topo = child_topo.lock().expect("poisoned lock");
+    let bind_to = Bitmap::from(core_index.0 as u32);
+
+    // Thread binding before explicit set.
+    let before = locked_topo.get_cpubind_for_thread(tid, CPUBIND_THREAD);
+
+    debug!("binding to {:?}", bind_to);
+    // Set the binding.
+    let result = locked_topo
+        .set_cpubind_for_thread(tid, bind_to, CPUBIND_THREAD)
+        .map_err(|err| format_err!("failed to bind CPU: {:?}", err));
+
+    if result.is_err() {
+        warn!("error in bind_core, {:?}", result);
+    }
+
+    Ok(Cleanup {
+        tid,
+        prior_state: before,
+    })
+}
+
+/// NUMA nodes are not detected with hwloc, memory is placed by the OS.
+pub fn numa_node(_core_index: CoreIndex) -> Option<usize> {
+    None
+}
+
+/// Returns the cores grouped by the smallest cache shared by several cores, or in a single group
+/// if no cache is shared.
+pub fn cache_groups() -> Result<Vec<CoreGroup>> {
+    let topo = TOPOLOGY.lock().expect("poisoned lock");
+
+    let core_depth = topo
+        .depth_or_below_for_type(&ObjectType::Core)
+        .map_err(|err| format_err!("failed to find cores: {:?}", err))?;
+    let all_cores = topo
+        .objects_with_type(&ObjectType::Core)
+        .map_err(|err| format_err!("failed to find cores: {:?}", err))?;
+    // Bind to the first logical processor of each core (in case the core is SMT/hyper-threaded).
+    let cores = all_cores
+        .iter()
+        .map(|core| {
+            let cpuset = core
+                .allowed_cpuset()
+                .ok_or_else(|| format_err!("no allowed cpuset for core"))?;
+            Ok(CoreIndex(cpuset.first() as usize))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let core_count = cores.len();
+
+    let mut cache_depth = core_depth;
+    let mut cache_count = 1;
+
+    while cache_depth > 0 {
+        let objs = topo.objects_at_depth(cache_depth);
+        let obj_count = objs.len();
+        if obj_count < core_count {
+            cache_count = obj_count;
+            break;
+        }
+
+        cache_depth -= 1;
+    }
+
+    if core_count % cache_count != 0 {
+        return Ok(vec![cores]);
+    }
+    let group_size = core_count / cache_count;
+
+    Ok(cores
+        .chunks(group_size)
+        .map(|group| group.to_vec())
+        .collect())
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeSet;
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{ensure, format_err, Context, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use storage_proofs_core::settings::SETTINGS;
+
+#[cfg(not(target_os = "linux"))]
+mod hwloc_topology;
+#[cfg(target_os = "linux")]
+mod sysfs;
+
+#[cfg(not(target_os = "linux"))]
+use hwloc_topology as topology;
+#[cfg(target_os = "linux")]
+use sysfs as topology;
+
+pub use topology::{bind_core, numa_node};
+
+type CoreGroup = Vec<CoreIndex>;
+lazy_static! {
+    pub static ref CORE_GROUPS: Option<Vec<Mutex<CoreGroup>>> = {
+        let num_producers = &SETTINGS.multicore_sdr_producers;
+        let cores_per_unit = num_producers + 1;
+
+        configured_core_groups(&SETTINGS.multicore_sdr_core_groups)
+            .or_else(|| core_groups(cores_per_unit))
+            .map(|groups| groups.into_iter().map(Mutex::new).collect())
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// `CoreIndex` is a simple wrapper type for the number the OS gives a logical processor (as in
+/// `/proc/cpuinfo` or `taskset`). A thread bound to a `CoreIndex` runs on that processor only.
+pub struct CoreIndex(usize);
+
+pub fn checkout_core_group() -> Option<MutexGuard<'static, CoreGroup>> {
+    match &*CORE_GROUPS {
+        Some(groups) => {
+            for (i, group) in groups.iter().enumerate() {
+                match group.try_lock() {
+                    Ok(guard) => {
+                        debug!("checked out core group {}", i);
+                        return Some(guard);
+                    }
+                    Err(_) => debug!("core group {} locked, could not checkout", i),
+                }
+            }
+            None
+        }
+        None => None,
+    }
+}
+
+/// Returns the core groups listed in the `multicore_sdr_core_groups` setting, if any are.
+fn configured_core_groups(setting: &str) -> Option<Vec<CoreGroup>> {
+    if setting.trim().is_empty() {
+        return None;
+    }
+
+    match parse_core_groups(setting) {
+        Ok(groups) => {
+            info!("using {} configured core groups", groups.len());
+            Some(groups)
+        }
+        Err(err) => {
+            warn!(
+                "invalid core groups {:?}, detecting them instead: {:#}",
+                setting, err
+            );
+            None
+        }
+    }
+}
+
+/// Parses core groups separated by `;`, each a list of logical processors like `0-3,8,10-11`.
+fn parse_core_groups(groups: &str) -> Result<Vec<CoreGroup>> {
+    let mut seen = BTreeSet::new();
+    let groups = groups
+        .split(';')
+        .filter(|group| !group.trim().is_empty())
+        .map(|group| {
+            let cores = parse_cpu_list(group)?;
+            ensure!(!cores.is_empty(), "empty core group {:?}", group);
+            for core in &cores {
+                ensure!(seen.insert(*core), "core {} is in several groups", core);
+            }
+            Ok(cores.into_iter().map(CoreIndex).collect())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(!groups.is_empty(), "no core groups");
+
+    Ok(groups)
+}
+
+/// Parses a list of logical processors in the format of the kernel, e.g. `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list
+        .trim()
+        .split(',')
+        .filter(|range| !range.trim().is_empty())
+    {
+        let mut bounds = range.splitn(2, '-').map(|bound| {
+            bound
+                .trim()
+                .parse::<usize>()
+                .with_context(|| format!("invalid cpu {:?}", bound))
+        });
+        let first = bounds.next().ok_or_else(|| format_err!("empty range"))??;
+        let last = bounds.next().transpose()?.unwrap_or(first);
+        ensure!(first <= last, "invalid range {}", range);
+        cpus.extend(first..=last);
+    }
+
+    Ok(cpus)
+}
+
+fn core_groups(cores_per_unit: usize) -> Option<Vec<CoreGroup>> {
+    let cache_groups = match topology::cache_groups() {
+        Ok(groups) => groups,
+        Err(err) => {
+            warn!("failed to detect the cpu topology: {:#}", err);
+            return None;
+        }
+    };
+    let core_count = cache_groups.iter().map(Vec::len).sum::<usize>();
+    let cache_count = cache_groups.len();
+
+    if cache_count <= 1 {
+        // If there are not more than one shared caches, there is no benefit in trying to group cores by cache.
+        // In that case, prefer more groups so we can still bind cores and also get some parallelism.
+        // Create as many full groups as possible.
+        let group_count = core_count / cores_per_unit;
+
+        info!(
+            "found only {} shared cache(s), heuristically grouping cores into {} groups",
+            cache_count, group_count
+        );
+
+        let cores = cache_groups.into_iter().flatten().collect::<Vec<_>>();
+        Some(
+            cores
+                .chunks_exact(cores_per_unit)
+                .map(|group| group.to_vec())
+                .collect(),
+        )
+    } else {
+        debug!(
+            "Cores: {}, Shared Caches: {}, cores per cache: {:?}",
+            core_count,
+            cache_count,
+            cache_groups.iter().map(Vec::len).collect::<Vec<_>>()
+        );
+
+        Some(cache_groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cores() {
+        core_groups(2);
+    }
+
+    #[test]
+    fn test_parse_core_groups() {
+        let cores = |cores: &[usize]| cores.iter().copied().map(CoreIndex).collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_core_groups("0-3,4-7;8-11").expect("valid core groups"),
+            vec![cores(&[0, 1, 2, 3, 4, 5, 6, 7]), cores(&[8, 9, 10, 11])]
+        );
+        assert_eq!(
+            parse_core_groups(" 1, 3 ; 16-17 ;").expect("valid core groups"),
+            vec![cores(&[1, 3]), cores(&[16, 17])]
+        );
+        assert_eq!(
+            parse_core_groups("5;2,0-1").expect("valid core groups"),
+            vec![cores(&[5]), cores(&[2, 0, 1])]
+        );
+
+        for invalid in &["0-3;3-4", "a-3", "3-1", "1-2-3", ",", ";", ""] {
+            assert!(parse_core_groups(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "isolated-testing")]
+    // This test should not be run while other tests are running, as
+    // the cores we're working with may otherwise be busy and cause a
+    // failure.
+    fn test_checkout_cores() {
+        let checkout1 = checkout_core_group();
+        dbg!(&checkout1);
+        let checkout2 = checkout_core_group();
+        dbg!(&checkout2);
+
+        // This test might fail if run on a machine with fewer than four cores.
+        match (checkout1, checkout2) {
+            (Some(c1), Some(c2)) => assert!(*c1 != *c2),
+            _ => panic!("failed to get two checkouts"),
+        }
+    }
+}
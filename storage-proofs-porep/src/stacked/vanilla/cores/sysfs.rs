@@ -0,0 +1,371 @@
+//! The cpu topology as the Linux kernel exposes it in sysfs, and thread binding through the
+//! scheduler's affinity masks.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use log::{debug, warn};
+
+use super::{parse_cpu_list, CoreGroup, CoreIndex};
+
+const SYSFS_CPU: &str = "/sys/devices/system/cpu";
+const SYSFS_NODE: &str = "/sys/devices/system/node";
+
+pub struct Cleanup {
+    tid: libc::pthread_t,
+    prior_state: Option<libc::cpu_set_t>,
+}
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        if let Some(prior) = self.prior_state.take() {
+            unsafe {
+                libc::pthread_setaffinity_np(self.tid, mem::size_of::<libc::cpu_set_t>(), &prior);
+            }
+        }
+    }
+}
+
+pub fn bind_core(core_index: CoreIndex) -> Result<Cleanup> {
+    ensure!(
+        core_index.0 < libc::CPU_SETSIZE as usize,
+        "core index {} out of range",
+        core_index.0
+    );
+    let tid = unsafe { libc::pthread_self() };
+
+    // Thread binding before explicit set.
+    let before = unsafe {
+        let mut set = mem::zeroed::<libc::cpu_set_t>();
+        let res = libc::pthread_getaffinity_np(tid, mem::size_of::<libc::cpu_set_t>(), &mut set);
+        if res == 0 {
+            Some(set)
+        } else {
+            None
+        }
+    };
+
+    debug!("binding to {}", core_index.0);
+    // Set the binding.
+    let res = unsafe {
+        let mut bind_to = mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_SET(core_index.0, &mut bind_to);
+        libc::pthread_setaffinity_np(tid, mem::size_of::<libc::cpu_set_t>(), &bind_to)
+    };
+    if res != 0 {
+        warn!(
+            "error in bind_core, failed to bind CPU {}: {}",
+            core_index.0,
+            std::io::Error::from_raw_os_error(res)
+        );
+    }
+
+    Ok(Cleanup {
+        tid,
+        prior_state: before,
+    })
+}
+
+/// Returns the cores this process may run on, grouped by the smallest cache shared by several
+/// cores, or in a single group if no cache is shared.
+pub fn cache_groups() -> Result<Vec<CoreGroup>> {
+    read_cache_groups(Path::new(SYSFS_CPU), &allowed_cpus()?)
+}
+
+/// Returns the NUMA node of a logical processor, if the machine has several nodes.
+pub fn numa_node(core_index: CoreIndex) -> Option<usize> {
+    read_numa_node(Path::new(SYSFS_CPU), Path::new(SYSFS_NODE), core_index.0)
+}
+
+fn read_numa_node(cpu_root: &Path, node_root: &Path, cpu: usize) -> Option<usize> {
+    let nodes = read_cpu_list(&node_root.join("online")).ok()?;
+    if nodes.len() <= 1 {
+        return None;
+    }
+
+    // The node of a logical processor is linked in its directory.
+    fs::read_dir(cpu_dir(cpu_root, cpu))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find_map(|entry| {
+            let name = entry.file_name();
+            let node = name.to_str()?.strip_prefix("node")?;
+            node.parse().ok()
+        })
+}
+
+/// Returns the logical processors this process may run on, which in a container may be fewer
+/// than the ones online.
+fn allowed_cpus() -> Result<BTreeSet<usize>> {
+    let set = unsafe {
+        let mut set = mem::zeroed::<libc::cpu_set_t>();
+        let res = libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set);
+        ensure!(
+            res == 0,
+            "failed to get the cpu affinity: {}",
+            std::io::Error::last_os_error()
+        );
+        set
+    };
+
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+        .collect())
+}
+
+fn read_cpu_list(path: &Path) -> Result<Vec<usize>> {
+    let list =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_cpu_list(&list).with_context(|| format!("invalid cpu list in {}", path.display()))
+}
+
+fn cpu_dir(root: &Path, cpu: usize) -> PathBuf {
+    root.join(format!("cpu{}", cpu))
+}
+
+/// Returns the data caches of a logical processor, the first processor sharing the cache by
+/// the level of the cache.
+fn read_caches(root: &Path, cpu: usize) -> Result<BTreeMap<u32, usize>> {
+    let mut caches = BTreeMap::new();
+    let cache_dir = cpu_dir(root, cpu).join("cache");
+    let entries = match fs::read_dir(&cache_dir) {
+        Ok(entries) => entries,
+        // Some platforms do not expose caches.
+        Err(_) => return Ok(caches),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        let is_index = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| name.starts_with("index"));
+        if !is_index {
+            continue;
+        }
+
+        let read = |name: &str| {
+            fs::read_to_string(path.join(name))
+                .with_context(|| format!("failed to read {}", path.join(name).display()))
+        };
+        if read("type")?.trim() == "Instruction" {
+            continue;
+        }
+        let level = read("level")?
+            .trim()
+            .parse::<u32>()
+            .context("invalid cache level")?;
+        let shared = read_cpu_list(&path.join("shared_cpu_list"))?;
+        caches.insert(level, shared.into_iter().min().unwrap_or(cpu));
+    }
+
+    Ok(caches)
+}
+
+fn read_cache_groups(root: &Path, allowed: &BTreeSet<usize>) -> Result<Vec<CoreGroup>> {
+    // The logical processor to bind to for each core, by the first logical processor of the core.
+    let mut cores = BTreeMap::new();
+    for cpu in read_cpu_list(&root.join("online"))? {
+        if !allowed.contains(&cpu) {
+            continue;
+        }
+        let siblings = read_cpu_list(&cpu_dir(root, cpu).join("topology/thread_siblings_list"))?;
+        let core = siblings.into_iter().min().unwrap_or(cpu);
+        cores.entry(core).or_insert(cpu);
+    }
+    ensure!(!cores.is_empty(), "found no allowed cores");
+
+    let caches = cores
+        .values()
+        .map(|cpu| read_caches(root, *cpu))
+        .collect::<Result<Vec<_>>>()?;
+
+    // As for hwloc, the first level from the cores up at which there are fewer caches than cores.
+    let levels = caches
+        .iter()
+        .flat_map(|caches| caches.keys().copied())
+        .collect::<BTreeSet<_>>();
+    for level in levels {
+        let shared = caches
+            .iter()
+            .map(|caches| caches.get(&level).copied())
+            .collect::<Option<Vec<_>>>();
+        let shared = match shared {
+            Some(shared) => shared,
+            // Not every core has a cache at this level.
+            None => continue,
+        };
+        if shared.iter().collect::<BTreeSet<_>>().len() == cores.len() {
+            continue;
+        }
+
+        // The groups in the order of their first core.
+        let mut groups: Vec<(usize, CoreGroup)> = Vec::new();
+        for (cpu, cache) in cores.values().zip(shared) {
+            match groups.iter_mut().find(|(id, _)| *id == cache) {
+                Some((_, group)) => group.push(CoreIndex(*cpu)),
+                None => groups.push((cache, vec![CoreIndex(*cpu)])),
+            }
+        }
+        debug!("grouping cores by the level {} caches", level);
+
+        return Ok(groups.into_iter().map(|(_, group)| group).collect());
+    }
+
+    Ok(vec![cores.values().copied().map(CoreIndex).collect()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().expect("no parent")).expect("failed to create dir");
+        fs::write(path, contents).expect("failed to write");
+    }
+
+    /// Writes a topology of `cores` cores with two logical processors each, where the cpus
+    /// `i` and `i + cores` are the same core, with a cache per core at level 1 and 2, and a level
+    /// 3 cache for each list of cores in `l3`.
+    fn write_topology(root: &Path, cores: usize, l3: &[&str]) {
+        write(root.join("online"), &format!("0-{}\n", 2 * cores - 1));
+        for cpu in 0..2 * cores {
+            let core = cpu % cores;
+            let siblings = format!("{},{}\n", core, core + cores);
+            let dir = cpu_dir(root, cpu);
+            write(dir.join("topology/thread_siblings_list"), &siblings);
+
+            let caches = [
+                (1, "Data", siblings.clone()),
+                (1, "Instruction", siblings.clone()),
+            ];
+            let l2 = [(2, "Unified", siblings.clone())];
+            let l3 = l3.iter().filter_map(|list| {
+                let cpus = parse_cpu_list(list).expect("invalid list");
+                if cpus.contains(&core) {
+                    let shared = cpus
+                        .iter()
+                        .flat_map(|core| vec![*core, core + cores])
+                        .map(|cpu| cpu.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    Some((3, "Unified", shared))
+                } else {
+                    None
+                }
+            });
+            let all = caches.iter().cloned().chain(l2.iter().cloned()).chain(l3);
+            for (index, (level, kind, shared)) in all.enumerate() {
+                let index = dir.join(format!("cache/index{}", index));
+                write(index.join("level"), &format!("{}\n", level));
+                write(index.join("type"), &format!("{}\n", kind));
+                write(index.join("shared_cpu_list"), &shared);
+            }
+        }
+    }
+
+    fn groups(groups: &[&[usize]]) -> Vec<CoreGroup> {
+        groups
+            .iter()
+            .map(|group| group.iter().copied().map(CoreIndex).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_read_cache_groups() {
+        let all = (0..64).collect::<BTreeSet<_>>();
+
+        // Two core complexes of four cores.
+        let dir = tempdir().expect("tempdir failure");
+        write_topology(dir.path(), 8, &["0-3", "4-7"]);
+        assert_eq!(
+            read_cache_groups(dir.path(), &all).expect("failed to read topology"),
+            groups(&[&[0, 1, 2, 3], &[4, 5, 6, 7]])
+        );
+
+        // In a container only some logical processors may be used, for a core either.
+        let allowed = [2, 3, 4, 5, 8, 9, 10, 15].iter().copied().collect();
+        assert_eq!(
+            read_cache_groups(dir.path(), &allowed).expect("failed to read topology"),
+            groups(&[&[8, 9, 2, 3], &[4, 5, 15]])
+        );
+
+        // Complexes of different sizes.
+        let dir = tempdir().expect("tempdir failure");
+        write_topology(dir.path(), 6, &["0-1", "2-5"]);
+        assert_eq!(
+            read_cache_groups(dir.path(), &all).expect("failed to read topology"),
+            groups(&[&[0, 1], &[2, 3, 4, 5]])
+        );
+
+        // A single shared cache.
+        let dir = tempdir().expect("tempdir failure");
+        write_topology(dir.path(), 4, &["0-3"]);
+        assert_eq!(
+            read_cache_groups(dir.path(), &all).expect("failed to read topology"),
+            groups(&[&[0, 1, 2, 3]])
+        );
+
+        // No shared cache at all.
+        let dir = tempdir().expect("tempdir failure");
+        write_topology(dir.path(), 4, &[]);
+        assert_eq!(
+            read_cache_groups(dir.path(), &all).expect("failed to read topology"),
+            groups(&[&[0, 1, 2, 3]])
+        );
+
+        assert!(read_cache_groups(dir.path(), &BTreeSet::new()).is_err());
+    }
+
+    #[test]
+    fn test_read_numa_node() {
+        let dir = tempdir().expect("tempdir failure");
+        let cpu_root = dir.path().join("cpu");
+        let node_root = dir.path().join("node");
+        write_topology(&cpu_root, 4, &["0-1", "2-3"]);
+        for cpu in 0..8 {
+            let node = cpu % 4 / 2;
+            fs::create_dir_all(cpu_dir(&cpu_root, cpu).join(format!("node{}", node)))
+                .expect("failed to create dir");
+        }
+
+        write(node_root.join("online"), "0-1\n");
+        let nodes = (0..8)
+            .map(|cpu| read_numa_node(&cpu_root, &node_root, cpu))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            [0, 0, 1, 1, 0, 0, 1, 1]
+                .iter()
+                .map(|node| Some(*node))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(read_numa_node(&cpu_root, &node_root, 8), None);
+
+        // A single node needs no placement.
+        write(node_root.join("online"), "0\n");
+        assert_eq!(read_numa_node(&cpu_root, &node_root, 2), None);
+    }
+
+    #[test]
+    fn test_bind_core() {
+        let allowed = allowed_cpus().expect("failed to get the cpu affinity");
+        let cpu = *allowed.iter().next().expect("no allowed cpu");
+        {
+            let _cleanup = bind_core(CoreIndex(cpu)).expect("failed to bind core");
+            assert_eq!(
+                allowed_cpus().expect("failed to get the cpu affinity"),
+                [cpu].iter().copied().collect()
+            );
+        }
+        assert_eq!(
+            allowed_cpus().expect("failed to get the cpu affinity"),
+            allowed
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! Checkpoints within a layer.
+//!
+//! A layer of a large sector takes long to label, so labeling checkpoints the layer being labeled
+//! every `sdr_checkpoint_interval` nodes. A checkpoint consists of
+//!
+//! - a partial label file, holding the labels of the nodes labeled so far, which is appended to
+//!   and synced at every checkpoint, and
+//! - a small metadata file, holding the number of nodes checkpointed and a continuity hash over
+//!   their labels, which is replaced atomically after the labels are synced.
+//!
+//! The continuity hash is seeded with the replica id and the layer, so that a checkpoint of
+//! another sector or layer, or one whose labels were damaged, is not resumed from. Both files are
+//! removed once the layer is written.
+
+use std::fs::{self, remove_file, rename, File, OpenOptions};
+use std::io::Read;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use log::{info, warn};
+use merkletree::store::StoreConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use storage_proofs_core::{metrics, util::NODE_SIZE};
+
+/// The contents of the metadata file of a checkpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CheckpointMeta {
+    layer: usize,
+    nodes: u64,
+    continuity: [u8; 32],
+}
+
+/// The checkpoints of the layer being labeled.
+pub struct LayerCheckpoint {
+    partial_path: PathBuf,
+    meta_path: PathBuf,
+    layer: usize,
+    /// Number of nodes between two checkpoints, `0` if checkpoints are disabled.
+    interval: u64,
+    /// Number of nodes checkpointed so far.
+    nodes: u64,
+    /// Hash over the seed and the labels of the nodes checkpointed so far.
+    continuity: Sha256,
+    partial: Option<File>,
+}
+
+impl LayerCheckpoint {
+    /// Creates the checkpoints of `layer`, which is stored with `config`.
+    pub fn new(config: &StoreConfig, replica_id: &[u8], layer: usize, interval: u64) -> Self {
+        let data_path = StoreConfig::data_path(&config.path, &config.id);
+
+        LayerCheckpoint {
+            partial_path: data_path.with_extension("partial"),
+            meta_path: data_path.with_extension("checkpoint"),
+            layer,
+            interval,
+            nodes: 0,
+            continuity: seed(replica_id, layer),
+            partial: None,
+        }
+    }
+
+    /// Restores the labels of the last checkpoint into `labels` and returns the number of nodes
+    /// restored. Returns `0` if there is no checkpoint, or if it does not match the labels.
+    pub fn restore(&mut self, replica_id: &[u8], labels: &mut [u8]) -> u64 {
+        let meta = match fs::read(&self.meta_path) {
+            Ok(meta) => meta,
+            Err(_) => return 0,
+        };
+
+        match self.verify(replica_id, &meta, labels) {
+            Ok(nodes) => {
+                info!(
+                    "resuming layer {} from checkpoint at node {}",
+                    self.layer, nodes
+                );
+                self.nodes = nodes;
+                nodes
+            }
+            Err(err) => {
+                warn!(
+                    "discarding checkpoint of layer {}, restarting the layer: {:#}",
+                    self.layer, err
+                );
+                self.continuity = seed(replica_id, self.layer);
+                self.remove();
+                0
+            }
+        }
+    }
+
+    /// Reads the checkpointed labels into `labels` and checks them against the continuity hash.
+    fn verify(&mut self, replica_id: &[u8], meta: &[u8], labels: &mut [u8]) -> Result<u64> {
+        let meta: CheckpointMeta =
+            serde_json::from_slice(meta).context("invalid checkpoint metadata")?;
+        ensure!(
+            meta.layer == self.layer,
+            "checkpoint is of layer {}",
+            meta.layer
+        );
+        let len = meta.nodes as usize * NODE_SIZE;
+        ensure!(len < labels.len(), "checkpoint exceeds the layer");
+
+        let mut partial = File::open(&self.partial_path).context("missing partial labels")?;
+        partial
+            .read_exact(&mut labels[..len])
+            .context("partial labels are too short")?;
+        metrics::record_bytes_read("layer", len as u64);
+
+        let mut continuity = seed(replica_id, self.layer);
+        continuity.update(&labels[..len]);
+        ensure!(
+            continuity.clone().finalize()[..] == meta.continuity[..],
+            "continuity hash mismatch"
+        );
+        self.continuity = continuity;
+
+        Ok(meta.nodes)
+    }
+
+    /// Returns whether a checkpoint is due after the first `nodes` nodes of `labels` are labeled.
+    /// A complete layer is written instead.
+    #[inline]
+    fn is_due(&self, labels: &[u8], nodes: u64) -> bool {
+        self.interval != 0
+            && nodes.saturating_sub(self.nodes) >= self.interval
+            && (nodes as usize * NODE_SIZE) < labels.len()
+    }
+
+    /// Checkpoints the labels of the first `nodes` nodes, which `labels` starts with.
+    fn save(&mut self, labels: &[u8], nodes: u64) -> Result<()> {
+        let new = &labels[self.nodes as usize * NODE_SIZE..nodes as usize * NODE_SIZE];
+
+        if self.partial.is_none() {
+            let partial = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(&self.partial_path)
+                .with_context(|| {
+                    format!(
+                        "could not open partial labels {}",
+                        self.partial_path.display()
+                    )
+                })?;
+            self.partial = Some(partial);
+        }
+        let partial = self.partial.as_ref().expect("opened above");
+        partial
+            .write_all_at(new, self.nodes * NODE_SIZE as u64)
+            .context("failed to write partial labels")?;
+        partial
+            .sync_data()
+            .context("failed to sync partial labels")?;
+        metrics::record_bytes_written("layer", new.len() as u64);
+
+        self.continuity.update(new);
+        let meta = CheckpointMeta {
+            layer: self.layer,
+            nodes,
+            continuity: self.continuity.clone().finalize().into(),
+        };
+        write_atomic(
+            &self.meta_path,
+            &serde_json::to_vec(&meta).context("failed to serialize checkpoint")?,
+        )?;
+        self.nodes = nodes;
+
+        Ok(())
+    }
+
+    /// Saves a checkpoint if one is due after the first `nodes` nodes are labeled. Checkpoints
+    /// only save work, so if one fails, labeling continues without them.
+    #[inline]
+    pub fn save_if_due(&mut self, labels: &[u8], nodes: u64) {
+        if self.is_due(labels, nodes) {
+            if let Err(err) = self.save(labels, nodes) {
+                warn!(
+                    "failed to checkpoint layer {}, disabling checkpoints: {:#}",
+                    self.layer, err
+                );
+                self.interval = 0;
+            }
+        }
+    }
+
+    /// Removes the checkpoint files, once the layer is written or the checkpoint is invalid.
+    pub fn remove(&mut self) {
+        self.partial = None;
+        self.nodes = 0;
+        for path in &[&self.meta_path, &self.partial_path] {
+            if path.exists() {
+                if let Err(err) = remove_file(path) {
+                    warn!("failed to delete checkpoint file: {}", err);
+                }
+            }
+        }
+    }
+}
+
+/// Removes the checkpoint files of the layer stored with `config`, if there are any.
+pub fn remove_checkpoint(config: &StoreConfig) {
+    LayerCheckpoint::new(config, &[], 0, 0).remove();
+}
+
+fn seed(replica_id: &[u8], layer: usize) -> Sha256 {
+    let mut hasher = Sha256::new();
+    hasher.update(replica_id);
+    hasher.update(&(layer as u32).to_be_bytes());
+    hasher
+}
+
+/// Replaces the file at `path` atomically, with its contents synced.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("checkpoint.tmp");
+    let file = File::create(&tmp_path).context("failed to create checkpoint")?;
+    file.write_all_at(data, 0)
+        .context("failed to write checkpoint")?;
+    file.sync_data().context("failed to sync checkpoint")?;
+    rename(&tmp_path, path).context("failed to rename checkpoint")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use filecoin_hashers::poseidon::PoseidonHasher;
+    use generic_array::typenum::{U0, U2, U8};
+    use storage_proofs_core::{
+        api_version::ApiVersion,
+        cache_key::CacheKey,
+        drgraph::BASE_DEGREE,
+        merkle::LCTree,
+        settings::{self, Settings},
+    };
+    use tempfile::tempdir;
+
+    use crate::stacked::vanilla::{
+        create_label::single,
+        graph::{StackedBucketGraph, EXP_DEGREE},
+    };
+
+    type Tree = LCTree<PoseidonHasher, U8, U0, U2>;
+
+    const LAYERS: usize = 3;
+    const NODES: usize = 1 << 12;
+
+    fn new_config(dir: &tempfile::TempDir) -> StoreConfig {
+        StoreConfig::new(
+            dir.path(),
+            CacheKey::CommDTree.to_string(),
+            NODES.trailing_zeros() as usize,
+        )
+    }
+
+    fn layer_config(config: &StoreConfig, layer: usize) -> StoreConfig {
+        StoreConfig::from_config(config, CacheKey::label_layer(layer), Some(NODES))
+    }
+
+    fn read(config: &StoreConfig) -> Vec<u8> {
+        fs::read(StoreConfig::data_path(&config.path, &config.id)).expect("failed to read layer")
+    }
+
+    /// Leaves `config` as if labeling was interrupted in layer 2, after checkpointing the first
+    /// `nodes` labels of `labels`.
+    fn interrupt(config: &StoreConfig, replica_id: &[u8], labels: &[u8], nodes: u64) {
+        for layer in 2..=LAYERS {
+            let config = layer_config(config, layer);
+            let _ = fs::remove_file(StoreConfig::data_path(&config.path, &config.id));
+        }
+        let mut checkpoint = LayerCheckpoint::new(&layer_config(config, 2), replica_id, 2, nodes);
+        checkpoint.save_if_due(labels, nodes);
+        assert!(checkpoint.meta_path.exists());
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let dir = tempdir().expect("tempdir failure");
+        let config = layer_config(&new_config(&dir), 1);
+        let replica_id = [5u8; 32];
+        let labels = (0..NODES * NODE_SIZE)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let mut checkpoint = LayerCheckpoint::new(&config, &replica_id, 1, 100);
+        for nodes in 1..=NODES as u64 {
+            checkpoint.save_if_due(&labels, nodes);
+        }
+        assert_eq!(
+            checkpoint.nodes, 4000,
+            "the complete layer is not checkpointed"
+        );
+
+        let mut restored = vec![0u8; labels.len()];
+        let mut checkpoint = LayerCheckpoint::new(&config, &replica_id, 1, 100);
+        assert_eq!(checkpoint.restore(&replica_id, &mut restored), 4000);
+        assert_eq!(&restored[..4000 * NODE_SIZE], &labels[..4000 * NODE_SIZE]);
+
+        // A checkpoint of another sector or layer is not resumed from.
+        let other_id = [6u8; 32];
+        let mut checkpoint = LayerCheckpoint::new(&config, &other_id, 1, 100);
+        assert_eq!(checkpoint.restore(&other_id, &mut restored), 0);
+        assert!(!checkpoint.meta_path.exists());
+        assert!(!checkpoint.partial_path.exists());
+
+        let mut checkpoint = LayerCheckpoint::new(&config, &replica_id, 1, 100);
+        for nodes in 1..=1000 {
+            checkpoint.save_if_due(&labels, nodes);
+        }
+        let mut checkpoint = LayerCheckpoint::new(&config, &replica_id, 2, 100);
+        assert_eq!(checkpoint.restore(&replica_id, &mut restored), 0);
+
+        // Neither is one whose labels were damaged.
+        let mut checkpoint = LayerCheckpoint::new(&config, &replica_id, 1, 100);
+        for nodes in 1..=1000 {
+            checkpoint.save_if_due(&labels, nodes);
+        }
+        let partial = OpenOptions::new()
+            .write(true)
+            .open(&checkpoint.partial_path)
+            .expect("failed to open partial labels");
+        partial
+            .write_all_at(&[0xff], 1234)
+            .expect("failed to damage partial labels");
+        let mut checkpoint = LayerCheckpoint::new(&config, &replica_id, 1, 100);
+        assert_eq!(checkpoint.restore(&replica_id, &mut restored), 0);
+    }
+
+    fn test_resume<F>(cache_window_nodes: u32, create_labels: F)
+    where
+        F: Fn(&StackedBucketGraph<PoseidonHasher>, [u8; 32], StoreConfig) -> Vec<StoreConfig>,
+    {
+        let replica_id = [7u8; 32];
+        let graph = StackedBucketGraph::<PoseidonHasher>::new(
+            None,
+            NODES,
+            BASE_DEGREE,
+            EXP_DEGREE,
+            [123; 32],
+            ApiVersion::V1_1_0,
+        )
+        .expect("stacked bucket graph new failed");
+        let mut cache = graph.parent_cache().expect("parent_cache failed");
+
+        let dir = tempdir().expect("tempdir failure");
+        let (expected, _) = single::create_labels_for_encoding::<Tree, _>(
+            &graph,
+            &mut cache,
+            LAYERS,
+            replica_id,
+            new_config(&dir),
+        )
+        .expect("create_labels_for_encoding failed");
+        let expected = expected.labels.iter().map(read).collect::<Vec<_>>();
+
+        // The layers are written behind the labeling, in regions of a few nodes which include the
+        // resumed labels.
+        let settings = Arc::new(Settings {
+            sdr_checkpoint_interval: 1000,
+            sdr_parents_cache_size: cache_window_nodes,
+            sdr_write_behind_region_size: 5000,
+            ..settings::current().as_ref().clone()
+        });
+        settings::with_settings(settings, || {
+            // Resume within the first parents cache window, past it, and past the first nodes,
+            // whose base parents are all filled in by the consumer.
+            for nodes in &[10, 1500, 3000] {
+                let dir = tempdir().expect("tempdir failure");
+                let config = new_config(&dir);
+                fs::write(
+                    StoreConfig::data_path(&config.path, &CacheKey::label_layer(1)),
+                    &expected[0],
+                )
+                .expect("failed to write layer");
+                interrupt(&config, &replica_id, &expected[1], *nodes);
+
+                let labels = create_labels(&graph, replica_id, config);
+                for (layer, config) in labels.iter().enumerate() {
+                    assert_eq!(read(config), expected[layer], "resumed from {}", nodes);
+                }
+                let checkpoint = LayerCheckpoint::new(&labels[1], &replica_id, 2, 0);
+                assert!(!checkpoint.meta_path.exists());
+                assert!(!checkpoint.partial_path.exists());
+            }
+
+            // The labels are taken from the checkpoint, not recomputed.
+            let dir = tempdir().expect("tempdir failure");
+            let config = new_config(&dir);
+            let mut damaged = expected[1].clone();
+            damaged[..NODE_SIZE].copy_from_slice(&[0; NODE_SIZE]);
+            interrupt(&config, &replica_id, &damaged, 3000);
+
+            let labels = create_labels(&graph, replica_id, config);
+            assert_eq!(read(&labels[0]), expected[0]);
+            assert_eq!(&read(&labels[1])[..NODE_SIZE], &[0; NODE_SIZE]);
+        });
+    }
+
+    #[test]
+    fn test_resume_single() {
+        test_resume(2048, |graph, replica_id, config| {
+            let mut cache = graph.parent_cache().expect("parent_cache failed");
+            let (labels, _) = single::create_labels_for_encoding::<Tree, _>(
+                graph, &mut cache, LAYERS, replica_id, config,
+            )
+            .expect("create_labels_for_encoding failed");
+            labels.labels
+        });
+    }
+
+    #[cfg(feature = "multicore-sdr")]
+    #[test]
+    fn test_resume_multi() {
+        use crate::stacked::vanilla::create_label::multi;
+
+        test_resume(1024, |graph, replica_id, config| {
+            let cache = graph.parent_cache().expect("parent_cache failed");
+            let (labels, _) = multi::create_labels_for_encoding::<Tree, _>(
+                graph, &cache, LAYERS, replica_id, config,
+            )
+            .expect("create_labels_for_encoding failed");
+            labels.labels
+        });
+    }
+}
@@ -0,0 +1,345 @@
+//! SDR labeling within a memory budget.
+//!
+//! Instead of two full layers, only a window of the most recent labels of the layer being
+//! labeled is kept in memory. Labels leaving the window are written to the layer's `.tmp` file,
+//! older base parents are read back from it. Expander parents are spread over the whole previous
+//! layer, so the previous layer is read from its file on disk. Both files are accessed through
+//! read-only mappings, whose pages the kernel can evict, so the resident memory is bounded by the
+//! budget plus what the page cache can spare, at the cost of throughput.
+
+use std::fs::{create_dir_all, rename, File, OpenOptions};
+use std::marker::PhantomData;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use anyhow::{ensure, Context, Result};
+use log::info;
+use mapr::{Mmap, MmapOptions};
+use merkletree::store::StoreConfig;
+use sha2raw::Sha256;
+use storage_proofs_core::{
+    drgraph::{Graph, BASE_DEGREE},
+    enter_span,
+    merkle::MerkleTreeTrait,
+    metrics,
+    util::NODE_SIZE,
+};
+
+use crate::stacked::vanilla::{
+    cache::ParentCache,
+    create_label::prepare_layers,
+    graph::{hash_parents, hash_parents_exp, StackedBucketGraph, DEGREE},
+    proof::LayerState,
+    Labels,
+};
+
+/// The labels of the layer being labeled, the most recent ones in a ring buffer in memory and
+/// the others in the layer file.
+struct LabelWindow {
+    /// Ring buffer of the labels of the nodes `flushed..`.
+    ring: Vec<u8>,
+    /// The number of nodes the ring buffer holds.
+    window_nodes: usize,
+    /// The number of nodes written to the layer file.
+    flushed: usize,
+    file: File,
+    /// Read-only mapping of the layer file, for the labels of the nodes `..flushed`.
+    written: Mmap,
+}
+
+impl LabelWindow {
+    fn create(path: &Path, nodes: usize, window_nodes: usize) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .with_context(|| format!("could not create layer file {}", path.display()))?;
+        file.set_len((nodes * NODE_SIZE) as u64)?;
+        let written = unsafe { MmapOptions::new().map(&file) }
+            .with_context(|| format!("could not mmap layer file {}", path.display()))?;
+
+        Ok(LabelWindow {
+            ring: vec![0u8; window_nodes * NODE_SIZE],
+            window_nodes,
+            flushed: 0,
+            file,
+            written,
+        })
+    }
+
+    #[inline]
+    fn label(&self, node: usize) -> &[u8] {
+        if node >= self.flushed {
+            let start = (node % self.window_nodes) * NODE_SIZE;
+            &self.ring[start..start + NODE_SIZE]
+        } else {
+            &self.written[node * NODE_SIZE..(node + 1) * NODE_SIZE]
+        }
+    }
+
+    /// Makes room for the label of `node`, writing the oldest half of the window to the file
+    /// when it is full.
+    fn reserve(&mut self, node: usize) -> Result<()> {
+        if node - self.flushed == self.window_nodes {
+            self.flush(self.window_nodes / 2)?;
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, node: usize, label: &[u8]) {
+        let start = (node % self.window_nodes) * NODE_SIZE;
+        self.ring[start..start + NODE_SIZE].copy_from_slice(label);
+    }
+
+    /// Writes the labels of the next `count` nodes to the file, these must not wrap around the
+    /// end of the ring buffer.
+    fn flush(&mut self, count: usize) -> Result<()> {
+        let start = (self.flushed % self.window_nodes) * NODE_SIZE;
+        self.file
+            .write_all_at(
+                &self.ring[start..start + count * NODE_SIZE],
+                (self.flushed * NODE_SIZE) as u64,
+            )
+            .context("failed to write labels")?;
+        self.flushed += count;
+        metrics::record_bytes_written("layer", (count * NODE_SIZE) as u64);
+
+        Ok(())
+    }
+
+    /// Writes the labels still in the window to the file.
+    fn finish(mut self, nodes: usize) -> Result<()> {
+        let chunk = self.window_nodes / 2;
+        while self.flushed < nodes {
+            let count = chunk.min(nodes - self.flushed);
+            self.flush(count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of labels a window within `memory_budget` bytes holds, an even number so
+/// that the window is flushed in halves.
+fn window_nodes(memory_budget: u64, nodes: usize) -> Result<usize> {
+    let budget_nodes = (memory_budget / NODE_SIZE as u64).min(nodes as u64 + 1) as usize;
+    let window_nodes = budget_nodes & !1;
+    ensure!(
+        window_nodes >= 2,
+        "SDR memory budget of {} bytes is too small, it must hold at least 2 labels",
+        memory_budget
+    );
+
+    Ok(window_nodes)
+}
+
+/// Creates the labels as [`super::single::create_labels_for_encoding`] does, but keeps at most
+/// `memory_budget` bytes of labels in memory.
+#[allow(clippy::type_complexity)]
+pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]>>(
+    graph: &StackedBucketGraph<Tree::Hasher>,
+    parents_cache: &mut ParentCache,
+    layers: usize,
+    replica_id: T,
+    config: StoreConfig,
+    memory_budget: u64,
+) -> Result<(Labels<Tree>, Vec<LayerState>)> {
+    info!("generate labels within {} bytes", memory_budget);
+
+    let layer_states = prepare_layers::<Tree>(graph, &config, layers);
+    let window_nodes = window_nodes(memory_budget, graph.size())?;
+
+    for (layer, layer_state) in (1..=layers).zip(layer_states.iter()) {
+        let _span = enter_span!("labeling_layer", layer);
+        info!("generating layer: {}", layer);
+        if layer_state.generated {
+            // The previous layer is read from disk, so there is nothing to load.
+            info!("skipping layer {}, already generated", layer);
+            continue;
+        }
+
+        parents_cache.reset()?;
+
+        let exp_labels = if layer == 1 {
+            None
+        } else {
+            let exp_config = &layer_states[layer - 2].config;
+            let exp_path = StoreConfig::data_path(&exp_config.path, &exp_config.id);
+            let file = File::open(&exp_path)
+                .with_context(|| format!("could not open layer {}", exp_path.display()))?;
+            let exp_labels = unsafe { MmapOptions::new().map(&file) }
+                .with_context(|| format!("could not mmap layer {}", exp_path.display()))?;
+            Some(exp_labels)
+        };
+
+        let layer_config = &layer_state.config;
+        let data_path = StoreConfig::data_path(&layer_config.path, &layer_config.id);
+        let tmp_data_path = data_path.with_extension(".tmp");
+        if let Some(parent) = data_path.parent() {
+            create_dir_all(parent).context("failed to create parent directories")?;
+        }
+
+        let mut labels = LabelWindow::create(&tmp_data_path, graph.size(), window_nodes)?;
+        for node in 0..graph.size() {
+            labels.reserve(node)?;
+            let label = create_label(
+                parents_cache,
+                replica_id.as_ref(),
+                &labels,
+                exp_labels.as_deref(),
+                layer,
+                node,
+            )?;
+            labels.store(node, &label);
+        }
+        labels.finish(graph.size())?;
+
+        info!("  storing labels on disk");
+        rename(tmp_data_path, data_path).context("failed to rename tmp data")?;
+
+        info!(
+            "  generated layer {} store with id {}",
+            layer, layer_config.id
+        );
+    }
+
+    Ok((
+        Labels::<Tree> {
+            labels: layer_states.iter().map(|s| s.config.clone()).collect(),
+            _h: PhantomData,
+        },
+        layer_states,
+    ))
+}
+
+fn create_label(
+    parents_cache: &mut ParentCache,
+    replica_id: &[u8],
+    labels: &LabelWindow,
+    exp_labels: Option<&[u8]>,
+    layer_index: usize,
+    node: usize,
+) -> Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 32];
+
+    buffer[..4].copy_from_slice(&(layer_index as u32).to_be_bytes());
+    buffer[4..12].copy_from_slice(&(node as u64).to_be_bytes());
+    hasher.input(&[replica_id, &buffer[..]][..]);
+
+    // hash parents for all non 0 nodes
+    let mut hash = if node > 0 {
+        let parents = parents_cache.read(node as u32)?;
+        let mut base = [&[][..]; BASE_DEGREE];
+        for (data, parent) in base.iter_mut().zip(parents.iter()) {
+            *data = labels.label(*parent as usize);
+        }
+
+        match exp_labels {
+            None => hash_parents(&base, hasher),
+            Some(exp_labels) => {
+                let mut all = [&[][..]; DEGREE];
+                all[..BASE_DEGREE].copy_from_slice(&base);
+                for (data, parent) in all[BASE_DEGREE..]
+                    .iter_mut()
+                    .zip(parents[BASE_DEGREE..].iter())
+                {
+                    let start = *parent as usize * NODE_SIZE;
+                    *data = &exp_labels[start..start + NODE_SIZE];
+                }
+                hash_parents_exp(&all, hasher)
+            }
+        }
+    } else {
+        hasher.finish()
+    };
+
+    // strip last two bits, to ensure result is in Fr.
+    hash[NODE_SIZE - 1] &= 0b0011_1111;
+
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use filecoin_hashers::poseidon::PoseidonHasher;
+    use generic_array::typenum::{U0, U2, U8};
+    use storage_proofs_core::{api_version::ApiVersion, cache_key::CacheKey, merkle::LCTree};
+    use tempfile::tempdir;
+
+    use crate::stacked::vanilla::{create_label::single, graph::EXP_DEGREE};
+
+    #[test]
+    fn test_create_labels_low_memory() {
+        type Tree = LCTree<PoseidonHasher, U8, U0, U2>;
+
+        let layers = 3;
+        let nodes = 1 << 12;
+        let replica_id = [7u8; 32];
+
+        let graph = StackedBucketGraph::<PoseidonHasher>::new(
+            None,
+            nodes,
+            BASE_DEGREE,
+            EXP_DEGREE,
+            [123; 32],
+            ApiVersion::V1_1_0,
+        )
+        .expect("stacked bucket graph new failed");
+        let mut cache = graph.parent_cache().expect("parent_cache failed");
+
+        let new_config = |dir: &tempfile::TempDir| {
+            StoreConfig::new(
+                dir.path(),
+                CacheKey::CommDTree.to_string(),
+                nodes.trailing_zeros() as usize,
+            )
+        };
+        let read = |config: &StoreConfig| {
+            std::fs::read(StoreConfig::data_path(&config.path, &config.id))
+                .expect("failed to read layer")
+        };
+
+        let dir = tempdir().expect("tempdir failure");
+        let (expected, _) = single::create_labels_for_encoding::<Tree, _>(
+            &graph,
+            &mut cache,
+            layers,
+            replica_id,
+            new_config(&dir),
+        )
+        .expect("create_labels_for_encoding failed");
+
+        // Windows much smaller than the layer and one holding all of it.
+        for budget in &[2 * NODE_SIZE as u64, 101 * NODE_SIZE as u64, 1 << 30] {
+            let dir = tempdir().expect("tempdir failure");
+            let (labels, _) = create_labels_for_encoding::<Tree, _>(
+                &graph,
+                &mut cache,
+                layers,
+                replica_id,
+                new_config(&dir),
+                *budget,
+            )
+            .expect("create_labels_for_encoding failed");
+
+            for (config, expected_config) in labels.labels.iter().zip(expected.labels.iter()) {
+                assert_eq!(read(config), read(expected_config), "budget {}", budget);
+            }
+        }
+
+        let dir = tempdir().expect("tempdir failure");
+        assert!(create_labels_for_encoding::<Tree, _>(
+            &graph,
+            &mut cache,
+            layers,
+            replica_id,
+            new_config(&dir),
+            NODE_SIZE as u64,
+        )
+        .is_err());
+    }
+}
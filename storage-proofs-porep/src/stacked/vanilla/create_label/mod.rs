@@ -6,14 +6,17 @@ use filecoin_hashers::Hasher;
 use log::{info, warn};
 use merkletree::{merkle::Element, store::StoreConfig};
 use storage_proofs_core::{
-    cache_key::CacheKey, drgraph::Graph, error::Result, merkle::MerkleTreeTrait,
+    cache_key::CacheKey, drgraph::Graph, error::Result, merkle::MerkleTreeTrait, metrics,
 };
 
 use crate::stacked::vanilla::{proof::LayerState, StackedBucketGraph};
 
+pub mod checkpoint;
+pub mod low_memory;
 #[cfg(feature = "multicore-sdr")]
 pub mod multi;
 pub mod single;
+pub mod writer;
 
 /// Prepares the necessary `StoreConfig`s with which the layers are stored.
 /// Also checks for already existing layers and marks them as such.
@@ -36,6 +39,7 @@ pub fn prepare_layers<Tree: 'static + MerkleTreeTrait>(
         if generated {
             // succesful load
             info!("found valid labels for layer {}", layer);
+            checkpoint::remove_checkpoint(&label_config);
         }
 
         states.push(LayerState {
@@ -57,6 +61,7 @@ pub fn write_layer(data: &[u8], config: &StoreConfig) -> Result<()> {
     }
     fs::write(&tmp_data_path, data).context("failed to write layer data")?;
     rename(tmp_data_path, data_path).context("failed to rename tmp data")?;
+    metrics::record_bytes_written("layer", data.len() as u64);
 
     Ok(())
 }
@@ -66,7 +71,8 @@ pub fn read_layer(config: &StoreConfig, mut data: &mut [u8]) -> Result<()> {
     let data_path = StoreConfig::data_path(&config.path, &config.id);
     let file = File::open(data_path).context("failed to open layer")?;
     let mut buffered = BufReader::new(file);
-    io::copy(&mut buffered, &mut data).context("failed to read layer")?;
+    let read = io::copy(&mut buffered, &mut data).context("failed to read layer")?;
+    metrics::record_bytes_read("layer", read);
 
     Ok(())
 }
@@ -8,7 +8,7 @@ use std::sync::{
 use std::thread;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use byte_slice_cast::{AsByteSlice, AsMutSliceOf};
 use filecoin_hashers::Hasher;
 use generic_array::{
@@ -21,16 +21,17 @@ use merkletree::store::{DiskStore, Store, StoreConfig};
 use storage_proofs_core::{
     cache_key::CacheKey,
     drgraph::{Graph, BASE_DEGREE},
+    enter_span,
     merkle::MerkleTreeTrait,
-    settings::SETTINGS,
+    settings,
     util::NODE_SIZE,
 };
 
 use crate::stacked::vanilla::{
     cache::ParentCache,
-    cores::{bind_core, checkout_core_group, CoreIndex},
-    create_label::{prepare_layers, read_layer, write_layer},
-    graph::{StackedBucketGraph, DEGREE, EXP_DEGREE},
+    cores::{bind_core, checkout_core_group, numa_node, CoreIndex},
+    create_label::{checkpoint::LayerCheckpoint, prepare_layers, read_layer, writer::LayerWriter},
+    graph::{StackedBucketGraph, DEGREE},
     memory_handling::{setup_create_label_memory, CacheReader},
     params::{Labels, LabelsCache},
     proof::LayerState,
@@ -42,6 +43,13 @@ const MIN_BASE_PARENT_NODE: u64 = 2000;
 const NODE_WORDS: usize = NODE_SIZE / size_of::<u32>();
 const SHA_BLOCK_SIZE: usize = 64;
 
+/// The number of sectors whose nodes are passed to multi-buffer SHA-256 at once, the lanes of
+/// the widest kernel.
+const HASH_LANES: usize = 16;
+
+/// Label data bytes per node, the first block followed by the parents.
+const BYTES_PER_NODE: usize = (NODE_SIZE * DEGREE) + SHA_BLOCK_SIZE;
+
 const SHA256_INITIAL_DIGEST: [u32; 8] = [
     0x6a09_e667,
     0xbb67_ae85,
@@ -66,13 +74,6 @@ fn fill_buffer(
     let cur_node_swap = cur_node.to_be_bytes(); // Note switch to big endian
     buf[36..44].copy_from_slice(&cur_node_swap); // update buf with current node
 
-    // Perform the first hash
-    let cur_node_ptr =
-        unsafe { &mut layer_labels.as_mut_slice()[cur_node as usize * NODE_WORDS as usize..] };
-
-    cur_node_ptr[..8].copy_from_slice(&SHA256_INITIAL_DIGEST);
-    compress256!(cur_node_ptr, buf, 1);
-
     // Fill in the base parents
     // Node 5 (prev node) will always be missing, and there tend to be
     // frequent close references.
@@ -122,6 +123,25 @@ fn fill_buffer(
     }
 }
 
+/// Compresses `blocks` SHA-256 blocks into the state of every lane, where lane `l` reads its
+/// blocks from `data[l * BYTES_PER_NODE + offset..]`. The lanes are the sectors labeled in
+/// lockstep, which all hash the same number of blocks, so they are hashed together with
+/// multi-buffer SHA-256.
+#[inline]
+fn compress256_lanes(states: &mut [[u32; 8]], data: &[u8], offset: usize, blocks: usize) {
+    let mut messages = [&data[..0]; HASH_LANES];
+    for (states, data) in states
+        .chunks_mut(HASH_LANES)
+        .zip(data.chunks(HASH_LANES * BYTES_PER_NODE))
+    {
+        for (lane, message) in messages[..states.len()].iter_mut().enumerate() {
+            let start = lane * BYTES_PER_NODE + offset;
+            *message = &data[start..start + blocks * SHA_BLOCK_SIZE];
+        }
+        sha2raw::compress256_multi(states, &messages[..states.len()]);
+    }
+}
+
 // This implements a producer, i.e. a thread that pre-fills the buffer
 // with parent node data.
 // - cur_consumer - The node currently being processed (consumed) by the
@@ -135,13 +155,17 @@ fn fill_buffer(
 //                  thread synchronization
 // - lookahead    - ring_buf size, in nodes
 // - base_parent_missing - Bit mask of any base parent nodes that could not
-//                         be filled in. This is an array of size lookahead.
+//                         be filled in. This is an array of size lookahead
+//                         times the number of sectors.
 // - is_layer0    - Indicates first (no expander parents) or subsequent layer
+//
+// A slot of the ring_buf holds the buffers of all sectors for a node, the
+// parents read from the cache are used for each of them.
 #[allow(clippy::too_many_arguments)]
 fn create_label_runner(
     parents_cache: &CacheReader<u32>,
-    layer_labels: &UnsafeSlice<'_, u32>,
-    exp_labels: Option<&UnsafeSlice<'_, u32>>, // None for layer 0
+    layer_labels: &[UnsafeSlice<'_, u32>],
+    exp_labels: Option<&[UnsafeSlice<'_, u32>]>, // None for layer 1
     num_nodes: u64,
     cur_producer: &AtomicU64,
     cur_awaiting: &AtomicU64,
@@ -151,7 +175,9 @@ fn create_label_runner(
     base_parent_missing: &UnsafeSlice<'_, BitMask>,
 ) {
     info!("created label runner");
-    // Label data bytes per node
+    let sectors = layer_labels.len();
+    let mut states = vec![SHA256_INITIAL_DIGEST; sectors];
+
     loop {
         // Get next work items
         let work = cur_awaiting.fetch_add(stride, SeqCst);
@@ -175,19 +201,30 @@ fn create_label_runner(
                 thread::sleep(Duration::from_micros(10));
             }
 
-            let buf = unsafe { ring_buf.slot_mut(cur_slot as usize) };
-            let bpm = unsafe { base_parent_missing.get_mut(cur_slot as usize) };
-
+            let slot = unsafe { ring_buf.slot_mut(cur_slot as usize) };
             let pc = unsafe { parents_cache.slice_at(cur_node as usize * DEGREE as usize) };
-            fill_buffer(
-                cur_node,
-                parents_cache,
-                pc,
-                layer_labels,
-                exp_labels,
-                buf,
-                bpm,
-            );
+            for (sector, buf) in slot.chunks_exact_mut(BYTES_PER_NODE).enumerate() {
+                let bpm =
+                    unsafe { base_parent_missing.get_mut(cur_slot as usize * sectors + sector) };
+                fill_buffer(
+                    cur_node,
+                    parents_cache,
+                    pc,
+                    &layer_labels[sector],
+                    exp_labels.map(|labels| &labels[sector]),
+                    buf,
+                    bpm,
+                );
+            }
+
+            // Perform the first hash, its state is kept in the label of the node.
+            states.fill(SHA256_INITIAL_DIGEST);
+            compress256_lanes(&mut states, slot, 0, 1);
+            let offset = cur_node as usize * NODE_WORDS;
+            for (labels, state) in layer_labels.iter().zip(states.iter()) {
+                let cur_node_ptr = unsafe { &mut labels.as_mut_slice()[offset..offset + 8] };
+                cur_node_ptr.copy_from_slice(state);
+            }
         }
 
         // Wait for the previous node to finish
@@ -200,19 +237,32 @@ fn create_label_runner(
     }
 }
 
+/// Creates the labels of layer `cur_layer` of all sectors, the `i`th of which has replica id
+/// `replica_ids[i]` and labels `layer_labels[i]`. The sectors are labeled in lockstep, so that
+/// the parents are read from the cache once for all of them.
+///
+/// Labeling starts at node `start`, whose preceding labels must already be in `layer_labels`,
+/// and the labels of the `i`th sector are checkpointed with `checkpoints[i]`, if there is one.
+#[allow(clippy::too_many_arguments)]
 fn create_layer_labels(
     parents_cache: &CacheReader<u32>,
-    replica_id: &[u8],
-    layer_labels: &mut MmapMut,
-    exp_labels: Option<&mut MmapMut>,
+    replica_ids: &[&[u8]],
+    layer_labels: &mut [MmapMut],
+    exp_labels: Option<&mut [MmapMut]>,
     num_nodes: u64,
     cur_layer: u32,
+    start: u64,
+    checkpoints: &mut [LayerCheckpoint],
+    writers: &mut [Option<LayerWriter>],
     core_group: Arc<Option<MutexGuard<'_, Vec<CoreIndex>>>>,
-) {
+) -> Result<()> {
     info!("Creating labels for layer {}", cur_layer);
+    let sectors = replica_ids.len();
+    assert_eq!(sectors, layer_labels.len(), "missing labels");
+
     // num_producers is the number of producer threads
     let (lookahead, num_producers, producer_stride) = {
-        let settings = &SETTINGS;
+        let settings = settings::current();
         let lookahead = settings.multicore_sdr_lookahead;
         let num_producers = settings.multicore_sdr_producers;
         // NOTE: Stride must not exceed the number of nodes in parents_cache's window. If it does, the process will deadlock
@@ -224,39 +274,54 @@ fn create_layer_labels(
         (lookahead, num_producers, producer_stride)
     };
 
-    const BYTES_PER_NODE: usize = (NODE_SIZE * DEGREE) + SHA_BLOCK_SIZE;
-
-    let mut ring_buf = RingBuf::new(BYTES_PER_NODE, lookahead);
-    let mut base_parent_missing = vec![BitMask::default(); lookahead];
+    let mut ring_buf = RingBuf::new(BYTES_PER_NODE * sectors, lookahead);
+    let mut base_parent_missing = vec![BitMask::default(); lookahead * sectors];
 
     // Fill in the fixed portion of all buffers
-    for buf in ring_buf.iter_slot_mut() {
-        prepare_block(replica_id, cur_layer, buf);
+    for slot in ring_buf.iter_slot_mut() {
+        for (buf, replica_id) in slot.chunks_exact_mut(BYTES_PER_NODE).zip(replica_ids) {
+            prepare_block(replica_id, cur_layer, buf);
+        }
+    }
+
+    // Node 0 is special, it has no parents.
+    let first = start.max(1);
+    if start > 0 {
+        parents_cache.seek(start)?;
     }
 
     // Highest node that is ready from the producer
-    let cur_producer = AtomicU64::new(0);
+    let cur_producer = AtomicU64::new(first - 1);
     // Next node to be filled
-    let cur_awaiting = AtomicU64::new(1);
+    let cur_awaiting = AtomicU64::new(first);
 
     // These UnsafeSlices are managed through the 2 Atomics above and the `CacheReader`, to
     // minimize any locking overhead.
-    let layer_labels = UnsafeSlice::from_slice(
-        layer_labels
-            .as_mut_slice_of::<u32>()
-            .expect("failed as mut slice of"),
-    );
-    let exp_labels = exp_labels.map(|m| {
-        UnsafeSlice::from_slice(m.as_mut_slice_of::<u32>().expect("failed as mut slice of"))
+    let layer_labels = layer_labels
+        .iter_mut()
+        .map(|m| {
+            UnsafeSlice::from_slice(m.as_mut_slice_of::<u32>().expect("failed as mut slice of"))
+        })
+        .collect::<Vec<_>>();
+    let exp_labels = exp_labels.map(|exp_labels| {
+        exp_labels
+            .iter_mut()
+            .map(|m| {
+                UnsafeSlice::from_slice(m.as_mut_slice_of::<u32>().expect("failed as mut slice of"))
+            })
+            .collect::<Vec<_>>()
     });
     let base_parent_missing = UnsafeSlice::from_slice(&mut base_parent_missing);
+    // The producers only stop at the end of the layer, so a failed write stops write-behind for
+    // its sector and is returned then.
+    let mut write_error = None;
 
     crossbeam::thread::scope(|s| {
         let mut runners = Vec::with_capacity(num_producers);
 
         for i in 0..num_producers {
             let layer_labels = &layer_labels;
-            let exp_labels = exp_labels.as_ref();
+            let exp_labels = exp_labels.as_deref();
             let cur_producer = &cur_producer;
             let cur_awaiting = &cur_awaiting;
             let ring_buf = &ring_buf;
@@ -289,33 +354,40 @@ fn create_layer_labels(
             }));
         }
 
-        let mut cur_node_ptr = unsafe { layer_labels.as_mut_slice() };
-        let mut cur_parent_ptr = unsafe { parents_cache.consumer_slice_at(DEGREE) };
-        let mut cur_parent_ptr_offset = DEGREE;
+        let mut cur_parent_ptr_offset = first as usize * DEGREE;
+        let mut cur_parent_ptr = unsafe { parents_cache.consumer_slice_at(cur_parent_ptr_offset) };
 
         // Calculate node 0 (special case with no parents)
         // Which is replica_id || cur_layer || 0
         // TODO - Hash and save intermediate result: replica_id || cur_layer
-        let mut buf = [0u8; (NODE_SIZE * DEGREE) + 64];
-        prepare_block(replica_id, cur_layer, &mut buf);
+        if start == 0 {
+            for (labels, replica_id) in layer_labels.iter().zip(replica_ids) {
+                let cur_node_ptr = unsafe { labels.as_mut_slice() };
+                let mut buf = [0u8; BYTES_PER_NODE];
+                prepare_block(replica_id, cur_layer, &mut buf);
 
-        cur_node_ptr[..8].copy_from_slice(&SHA256_INITIAL_DIGEST);
-        compress256!(cur_node_ptr, buf, 2);
+                cur_node_ptr[..8].copy_from_slice(&SHA256_INITIAL_DIGEST);
+                compress256!(cur_node_ptr, buf, 2);
 
-        // Fix endianess
-        cur_node_ptr[..8].iter_mut().for_each(|x| *x = x.to_be());
+                // Fix endianess
+                cur_node_ptr[..8].iter_mut().for_each(|x| *x = x.to_be());
 
-        cur_node_ptr[7] &= 0x3FFF_FFFF; // Strip last two bits to ensure in Fr
+                cur_node_ptr[7] &= 0x3FFF_FFFF; // Strip last two bits to ensure in Fr
+            }
+        }
+
+        // The hashing states of all sectors, which are compressed together.
+        let mut states = vec![[0u32; 8]; sectors];
 
         // Keep track of which node slot in the ring_buffer to use
-        let mut cur_slot = 0;
+        let mut cur_slot = (first as usize - 1) % lookahead;
         let mut count_not_ready = 0;
 
         // Calculate nodes 1 to n
 
         // Skip first node.
-        parents_cache.store_consumer(1);
-        let mut i = 1;
+        parents_cache.store_consumer(first);
+        let mut i = first;
         while i < num_nodes {
             // Ensure next buffer is ready
             let mut counted = false;
@@ -342,76 +414,70 @@ fn create_layer_labels(
                     }
                 }
 
-                cur_node_ptr = &mut cur_node_ptr[8..];
+                let node_offset = i as usize * NODE_WORDS;
                 // Grab the current slot of the ring_buf
-                let buf = unsafe { ring_buf.slot_mut(cur_slot) };
-                // Fill in the base parents
-                for k in 0..BASE_DEGREE {
-                    let bpm = unsafe { base_parent_missing.get(cur_slot) };
-                    if bpm.get(k) {
-                        let source = unsafe {
-                            let start = cur_parent_ptr[0] as usize * NODE_WORDS;
-                            let end = start + NODE_WORDS;
-                            &layer_labels.as_slice()[start..end]
-                        };
-
-                        buf[64 + (NODE_SIZE * k)..64 + (NODE_SIZE * (k + 1))]
-                            .copy_from_slice(source.as_byte_slice());
+                let slot = unsafe { ring_buf.slot_mut(cur_slot) };
+                for (sector, buf) in slot.chunks_exact_mut(BYTES_PER_NODE).enumerate() {
+                    let labels = unsafe { layer_labels[sector].as_slice() };
+
+                    // Fill in the base parents
+                    let bpm = unsafe { base_parent_missing.get(cur_slot * sectors + sector) };
+                    for k in 0..BASE_DEGREE {
+                        if bpm.get(k) {
+                            let start = cur_parent_ptr[k] as usize * NODE_WORDS;
+                            let source = &labels[start..start + NODE_WORDS];
+
+                            buf[64 + (NODE_SIZE * k)..64 + (NODE_SIZE * (k + 1))]
+                                .copy_from_slice(source.as_byte_slice());
+                        }
                     }
-                    cur_parent_ptr = &cur_parent_ptr[1..];
-                    cur_parent_ptr_offset += 1;
+
+                    // The state after the first hash, left by the producer.
+                    states[sector].copy_from_slice(&labels[node_offset..node_offset + 8]);
                 }
 
                 // Expanders are already all filled in (layer 1 doesn't use expanders)
-                cur_parent_ptr = &cur_parent_ptr[EXP_DEGREE..];
-                cur_parent_ptr_offset += EXP_DEGREE;
+                cur_parent_ptr = &cur_parent_ptr[DEGREE..];
+                cur_parent_ptr_offset += DEGREE;
 
                 if cur_layer == 1 {
                     // Six rounds of all base parents
                     for _j in 0..6 {
-                        compress256!(cur_node_ptr, &buf[64..], 3);
+                        compress256_lanes(&mut states, slot, 64, 3);
                     }
 
                     // round 7 is only first parent
-                    memset(&mut buf[96..128], 0); // Zero out upper half of last block
-                    buf[96] = 0x80; // Padding
-                    buf[126] = 0x27; // Length (0x2700 = 9984 bits -> 1248 bytes)
-                    compress256!(cur_node_ptr, &buf[64..], 1);
+                    for buf in slot.chunks_exact_mut(BYTES_PER_NODE) {
+                        memset(&mut buf[96..128], 0); // Zero out upper half of last block
+                        buf[96] = 0x80; // Padding
+                        buf[126] = 0x27; // Length (0x2700 = 9984 bits -> 1248 bytes)
+                    }
+                    compress256_lanes(&mut states, slot, 64, 1);
                 } else {
                     // Two rounds of all parents
-                    let blocks = [
-                        *GenericArray::<u8, U64>::from_slice(&buf[64..128]),
-                        *GenericArray::<u8, U64>::from_slice(&buf[128..192]),
-                        *GenericArray::<u8, U64>::from_slice(&buf[192..256]),
-                        *GenericArray::<u8, U64>::from_slice(&buf[256..320]),
-                        *GenericArray::<u8, U64>::from_slice(&buf[320..384]),
-                        *GenericArray::<u8, U64>::from_slice(&buf[384..448]),
-                        *GenericArray::<u8, U64>::from_slice(&buf[448..512]),
-                    ];
-                    sha2::compress256(
-                        (&mut cur_node_ptr[..8])
-                            .try_into()
-                            .expect("compress failed"),
-                        &blocks,
-                    );
-                    sha2::compress256(
-                        (&mut cur_node_ptr[..8])
-                            .try_into()
-                            .expect("compress failed"),
-                        &blocks,
-                    );
+                    compress256_lanes(&mut states, slot, 64, 7);
+                    compress256_lanes(&mut states, slot, 64, 7);
 
                     // Final round is only nine parents
-                    memset(&mut buf[352..384], 0); // Zero out upper half of last block
-                    buf[352] = 0x80; // Padding
-                    buf[382] = 0x27; // Length (0x2700 = 9984 bits -> 1248 bytes)
-                    compress256!(cur_node_ptr, &buf[64..], 5);
+                    for buf in slot.chunks_exact_mut(BYTES_PER_NODE) {
+                        memset(&mut buf[352..384], 0); // Zero out upper half of last block
+                        buf[352] = 0x80; // Padding
+                        buf[382] = 0x27; // Length (0x2700 = 9984 bits -> 1248 bytes)
+                    }
+                    compress256_lanes(&mut states, slot, 64, 5);
                 }
 
-                // Fix endianess
-                cur_node_ptr[..8].iter_mut().for_each(|x| *x = x.to_be());
+                for (labels, state) in layer_labels.iter().zip(states.iter()) {
+                    let cur_node_ptr =
+                        unsafe { &mut labels.as_mut_slice()[node_offset..node_offset + 8] };
 
-                cur_node_ptr[7] &= 0x3FFF_FFFF; // Strip last two bits to fit in Fr
+                    // Fix endianess
+                    for (label, word) in cur_node_ptr.iter_mut().zip(state.iter()) {
+                        *label = word.to_be();
+                    }
+
+                    cur_node_ptr[7] &= 0x3FFF_FFFF; // Strip last two bits to fit in Fr
+                }
 
                 // Safety:
                 // It's possible that this increment will trigger moving the cache window.
@@ -423,6 +489,19 @@ fn create_layer_labels(
                 }
                 i += 1;
                 cur_slot = (cur_slot + 1) % lookahead;
+
+                for (checkpoint, labels) in checkpoints.iter_mut().zip(layer_labels.iter()) {
+                    checkpoint.save_if_due(unsafe { labels.as_slice() }.as_byte_slice(), i);
+                }
+                for (writer, labels) in writers.iter_mut().zip(layer_labels.iter()) {
+                    if let Some(layer_writer) = writer {
+                        let labels = unsafe { labels.as_slice() }.as_byte_slice();
+                        if let Err(err) = layer_writer.write(&labels[..i as usize * NODE_SIZE]) {
+                            write_error.get_or_insert(err);
+                            *writer = None;
+                        }
+                    }
+                }
             }
         }
 
@@ -433,6 +512,11 @@ fn create_layer_labels(
         }
     })
     .expect("crossbeam scope failure");
+
+    match write_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -443,13 +527,57 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
     replica_id: T,
     config: StoreConfig,
 ) -> Result<(Labels<Tree>, Vec<LayerState>)> {
-    info!("create labels");
+    let mut labels = create_labels_for_encoding_lockstep(
+        graph,
+        parents_cache,
+        layers,
+        &[replica_id],
+        vec![config],
+    )?;
 
-    let layer_states = prepare_layers::<Tree>(graph, &config, layers);
+    Ok(labels.remove(0))
+}
+
+/// Creates the labels of several sectors of the same graph, which only differ in their replica
+/// ids, in lockstep. The parents of each node are read from the cache once for all sectors and
+/// the sectors' nodes are hashed together. The labels are identical to the ones of labeling each
+/// sector on its own with [`create_labels_for_encoding`].
+///
+/// Layers which are already written for some sectors are recomputed for all of them, as the
+/// sectors only advance together, but not written again.
+#[allow(clippy::type_complexity)]
+pub fn create_labels_for_encoding_lockstep<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]>>(
+    graph: &StackedBucketGraph<Tree::Hasher>,
+    parents_cache: &ParentCache,
+    layers: usize,
+    replica_ids: &[T],
+    configs: Vec<StoreConfig>,
+) -> Result<Vec<(Labels<Tree>, Vec<LayerState>)>> {
+    info!("create labels of {} sectors", replica_ids.len());
+    ensure!(!replica_ids.is_empty(), "no sectors to label");
+    ensure!(
+        replica_ids.len() == configs.len(),
+        "{} replica ids for {} sectors",
+        replica_ids.len(),
+        configs.len()
+    );
+
+    let layer_states = configs
+        .iter()
+        .map(|config| prepare_layers::<Tree>(graph, config, layers))
+        .collect::<Vec<_>>();
+    let replica_ids = replica_ids.iter().map(AsRef::as_ref).collect::<Vec<_>>();
 
     let sector_size = graph.size() * NODE_SIZE;
     let node_count = graph.size() as u64;
-    let cache_window_nodes = SETTINGS.sdr_parents_cache_size as usize;
+    let (cache_window_nodes, checkpoint_interval, region_size) = {
+        let settings = settings::current();
+        (
+            settings.sdr_parents_cache_size as usize,
+            settings.sdr_checkpoint_interval,
+            settings.sdr_write_behind_region_size as usize,
+        )
+    };
 
     let default_cache_size = DEGREE * 4 * cache_window_nodes;
 
@@ -462,26 +590,82 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
         debug!("binding core in main thread");
         group.get(0).map(|core_index| bind_core(*core_index))
     });
+    // The memory is placed on the NUMA node of the cores.
+    let numa_node = (*core_group)
+        .as_ref()
+        .and_then(|group| group.get(0))
+        .and_then(|core_index| numa_node(*core_index));
 
-    // NOTE: this means we currently keep 2x sector size around, to improve speed
+    // NOTE: this means we currently keep 2x sector size around per sector, to improve speed
     let (parents_cache, mut layer_labels, mut exp_labels) = setup_create_label_memory(
         sector_size,
+        replica_ids.len(),
         DEGREE,
         Some(default_cache_size as usize),
         &parents_cache.path,
+        numa_node,
     )?;
 
-    for (layer, layer_state) in (1..=layers).zip(layer_states.iter()) {
+    for layer in 1..=layers {
+        let _span = enter_span!("labeling_layer", layer);
         info!("Layer {}", layer);
 
-        if layer_state.generated {
+        let states = layer_states
+            .iter()
+            .map(|states| &states[layer - 1])
+            .collect::<Vec<_>>();
+
+        if states.iter().all(|state| state.generated) {
             info!("skipping layer {}, already generated", layer);
 
             // load the already generated layer into exp_labels
-            read_layer(&layer_state.config, &mut exp_labels)?;
+            for (state, labels) in states.iter().zip(exp_labels.iter_mut()) {
+                read_layer(&state.config, labels)?;
+            }
             continue;
         }
 
+        // Sectors resume from their last checkpoint, as they advance together, the one with the
+        // least progress determines where.
+        let mut checkpoints = Vec::with_capacity(states.len());
+        let mut start = node_count;
+        for ((state, replica_id), labels) in states
+            .iter()
+            .zip(replica_ids.iter())
+            .zip(layer_labels.iter_mut())
+        {
+            let interval = if state.generated {
+                0
+            } else {
+                checkpoint_interval
+            };
+            let mut checkpoint = LayerCheckpoint::new(&state.config, replica_id, layer, interval);
+            if !state.generated {
+                start = start.min(checkpoint.restore(replica_id, labels));
+            }
+            checkpoints.push(checkpoint);
+        }
+        if start > 0 {
+            // The labels of sectors which have the layer written are needed as parents too.
+            for (state, labels) in states.iter().zip(layer_labels.iter_mut()) {
+                if state.generated {
+                    read_layer(&state.config, labels)?;
+                }
+            }
+        }
+
+        // Completed regions of the layer are written while the rest is labeled.
+        let mut writers = states
+            .iter()
+            .map(|state| {
+                if state.generated {
+                    Ok(None)
+                } else {
+                    LayerWriter::new(&state.config, region_size).map(Some)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         // Cache reset happens in two parts.
         // The second part (the finish) happens before each layer but the first.
         if layers != 1 {
@@ -490,17 +674,20 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
 
         create_layer_labels(
             &parents_cache,
-            replica_id.as_ref(),
+            &replica_ids,
             &mut layer_labels,
             if layer == 1 {
                 None
             } else {
-                Some(&mut exp_labels)
+                Some(&mut exp_labels[..])
             },
             node_count,
             layer as u32,
+            start,
+            &mut checkpoints,
+            &mut writers,
             core_group.clone(),
-        );
+        )?;
 
         // Cache reset happens in two parts.
         // The first part (the start) happens after each layer but the last.
@@ -509,11 +696,24 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
         }
 
         mem::swap(&mut layer_labels, &mut exp_labels);
+        for (((state, labels), checkpoint), writer) in states
+            .iter()
+            .zip(exp_labels.iter())
+            .zip(checkpoints.iter_mut())
+            .zip(writers)
         {
-            let layer_config = &layer_state.config;
+            let layer_config = &state.config;
+            let writer = match writer {
+                Some(writer) => writer,
+                None => {
+                    info!("  labels with id {} already stored", layer_config.id);
+                    continue;
+                }
+            };
 
             info!("  storing labels on disk");
-            write_layer(&exp_labels, layer_config).context("failed to store labels")?;
+            writer.finish(labels).context("failed to store labels")?;
+            checkpoint.remove();
 
             info!(
                 "  generated layer {} store with id {}",
@@ -522,13 +722,18 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
         }
     }
 
-    Ok((
-        Labels::<Tree> {
-            labels: layer_states.iter().map(|s| s.config.clone()).collect(),
-            _h: PhantomData,
-        },
-        layer_states,
-    ))
+    Ok(layer_states
+        .into_iter()
+        .map(|states| {
+            (
+                Labels::<Tree> {
+                    labels: states.iter().map(|s| s.config.clone()).collect(),
+                    _h: PhantomData,
+                },
+                states,
+            )
+        })
+        .collect())
 }
 
 #[allow(clippy::type_complexity)]
@@ -547,7 +752,7 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
 
     let sector_size = graph.size() * NODE_SIZE;
     let node_count = graph.size() as u64;
-    let cache_window_nodes = (&SETTINGS.sdr_parents_cache_size / 2) as usize;
+    let cache_window_nodes = (settings::current().sdr_parents_cache_size / 2) as usize;
 
     let default_cache_size = DEGREE * 4 * cache_window_nodes;
 
@@ -560,16 +765,24 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
         debug!("binding core in main thread");
         group.get(0).map(|core_index| bind_core(*core_index))
     });
+    // The memory is placed on the NUMA node of the cores.
+    let numa_node = (*core_group)
+        .as_ref()
+        .and_then(|group| group.get(0))
+        .and_then(|core_index| numa_node(*core_index));
 
     // NOTE: this means we currently keep 2x sector size around, to improve speed
     let (parents_cache, mut layer_labels, mut exp_labels) = setup_create_label_memory(
         sector_size,
+        1,
         DEGREE,
         Some(default_cache_size as usize),
         &parents_cache.path,
+        numa_node,
     )?;
 
     for layer in 1..=layers {
+        let _span = enter_span!("labeling_layer", layer);
         info!("Layer {}", layer);
 
         // Cache reset happens in two parts.
@@ -580,17 +793,20 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
 
         create_layer_labels(
             &parents_cache,
-            replica_id.as_ref(),
+            &[replica_id.as_ref()],
             &mut layer_labels,
             if layer == 1 {
                 None
             } else {
-                Some(&mut exp_labels)
+                Some(&mut exp_labels[..])
             },
             node_count,
             layer as u32,
+            0,
+            &mut [],
+            &mut [],
             core_group.clone(),
-        );
+        )?;
 
         // Cache reset happens in two parts.
         // The first part (the start) happens after each layer but the last.
@@ -608,7 +824,7 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
                 DiskStore::new_from_slice_with_config(
                     graph.size(),
                     Tree::Arity::to_usize(),
-                    &layer_labels,
+                    &layer_labels[0],
                     layer_config.clone(),
                 )?;
             info!(
@@ -643,6 +859,8 @@ mod tests {
     use storage_proofs_core::{api_version::ApiVersion, merkle::LCTree};
     use tempfile::tempdir;
 
+    use crate::stacked::vanilla::{create_label::single, graph::EXP_DEGREE};
+
     #[test]
     fn test_create_labels() {
         let layers = 11;
@@ -714,6 +932,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_create_labels_lockstep() {
+        type Tree = LCTree<PoseidonHasher, U8, U0, U2>;
+
+        let layers = 3;
+        // Enough nodes for base parents to be filled in by the producers.
+        let nodes = 1 << 12;
+        let replica_ids = [[1u8; 32], [2u8; 32], [3u8; 32]];
+
+        let graph = StackedBucketGraph::<PoseidonHasher>::new(
+            None,
+            nodes,
+            BASE_DEGREE,
+            EXP_DEGREE,
+            [123; 32],
+            ApiVersion::V1_1_0,
+        )
+        .expect("stacked bucket graph new failed");
+        let mut cache = graph.parent_cache().expect("parent_cache failed");
+
+        let new_config = |dir: &tempfile::TempDir| {
+            StoreConfig::new(
+                dir.path(),
+                CacheKey::CommDTree.to_string(),
+                nodes.trailing_zeros() as usize,
+            )
+        };
+        let lockstep_dirs = replica_ids
+            .iter()
+            .map(|_| tempdir().expect("tempdir failure"))
+            .collect::<Vec<_>>();
+        let configs = lockstep_dirs.iter().map(new_config).collect::<Vec<_>>();
+
+        // The first layer of the first sector was already written, it is not written again.
+        single::create_labels_for_encoding::<Tree, _>(
+            &graph,
+            &mut cache,
+            1,
+            replica_ids[0],
+            configs[0].clone(),
+        )
+        .expect("create_labels_for_encoding failed");
+
+        let lockstep = create_labels_for_encoding_lockstep::<Tree, _>(
+            &graph,
+            &cache,
+            layers,
+            &replica_ids,
+            configs,
+        )
+        .expect("create_labels_for_encoding_lockstep failed");
+        assert_eq!(lockstep.len(), replica_ids.len());
+        assert!(lockstep[0].1[0].generated);
+        assert!(!lockstep[1].1[0].generated);
+
+        for (replica_id, (labels, _)) in replica_ids.iter().zip(lockstep.iter()) {
+            let dir = tempdir().expect("tempdir failure");
+            let (expected, _) = single::create_labels_for_encoding::<Tree, _>(
+                &graph,
+                &mut cache,
+                layers,
+                replica_id,
+                new_config(&dir),
+            )
+            .expect("create_labels_for_encoding failed");
+
+            for (config, expected_config) in labels.labels.iter().zip(expected.labels.iter()) {
+                let read = |config: &StoreConfig| {
+                    std::fs::read(StoreConfig::data_path(&config.path, &config.id))
+                        .expect("failed to read layer")
+                };
+                assert_eq!(read(config), read(expected_config));
+            }
+        }
+    }
+
     fn test_create_labels_aux(
         sector_size: usize,
         layers: usize,
@@ -9,13 +9,17 @@ use merkletree::store::{DiskStore, Store, StoreConfig};
 use sha2raw::Sha256;
 use storage_proofs_core::{
     drgraph::Graph,
+    enter_span,
     merkle::MerkleTreeTrait,
+    settings,
     util::{data_at_node_offset, NODE_SIZE},
 };
 
 use crate::stacked::vanilla::{
     cache::ParentCache,
-    create_label::{prepare_layers, read_layer, write_layer},
+    create_label::{
+        checkpoint::LayerCheckpoint, prepare_layers, read_layer, write_layer, writer::LayerWriter,
+    },
     proof::LayerState,
     Labels, LabelsCache, StackedBucketGraph,
 };
@@ -31,6 +35,13 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
     info!("generate labels");
 
     let layer_states = prepare_layers::<Tree>(graph, &config, layers);
+    let (checkpoint_interval, region_size) = {
+        let settings = settings::current();
+        (
+            settings.sdr_checkpoint_interval,
+            settings.sdr_write_behind_region_size as usize,
+        )
+    };
 
     let layer_size = graph.size() * NODE_SIZE;
     // NOTE: this means we currently keep 2x sector size around, to improve speed.
@@ -38,6 +49,7 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
     let mut exp_labels = vec![0u8; layer_size]; // Buffer for labels of the previous layer, needed for expander parents
 
     for (layer, layer_state) in (1..=layers).zip(layer_states.iter()) {
+        let _span = enter_span!("labeling_layer", layer);
         info!("generating layer: {}", layer);
         if layer_state.generated {
             info!("skipping layer {}, already generated", layer);
@@ -49,8 +61,21 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
 
         parents_cache.reset()?;
 
+        let mut checkpoint = LayerCheckpoint::new(
+            &layer_state.config,
+            replica_id.as_ref(),
+            layer,
+            checkpoint_interval,
+        );
+        let start = checkpoint.restore(replica_id.as_ref(), &mut layer_labels) as usize;
+        if start > 0 {
+            parents_cache.seek(start as u32)?;
+        }
+        // Completed regions of the layer are written while the rest is labeled.
+        let mut writer = LayerWriter::new(&layer_state.config, region_size)?;
+
         if layer == 1 {
-            for node in 0..graph.size() {
+            for node in start..graph.size() {
                 create_label(
                     graph,
                     Some(parents_cache),
@@ -59,9 +84,11 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
                     layer,
                     node,
                 )?;
+                checkpoint.save_if_due(&layer_labels, node as u64 + 1);
+                writer.write(&layer_labels[..(node + 1) * NODE_SIZE])?;
             }
         } else {
-            for node in 0..graph.size() {
+            for node in start..graph.size() {
                 create_label_exp(
                     graph,
                     Some(parents_cache),
@@ -71,6 +98,8 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
                     layer,
                     node,
                 )?;
+                checkpoint.save_if_due(&layer_labels, node as u64 + 1);
+                writer.write(&layer_labels[..(node + 1) * NODE_SIZE])?;
             }
         }
 
@@ -78,7 +107,10 @@ pub fn create_labels_for_encoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
         let layer_config = &layer_state.config;
 
         info!("  storing labels on disk");
-        write_layer(&layer_labels, layer_config).context("failed to store labels")?;
+        writer
+            .finish(&layer_labels)
+            .context("failed to store labels")?;
+        checkpoint.remove();
 
         info!(
             "  generated layer {} store with id {}",
@@ -117,6 +149,7 @@ pub fn create_labels_for_decoding<Tree: 'static + MerkleTreeTrait, T: AsRef<[u8]
     let mut exp_labels = vec![0u8; layer_size]; // Buffer for labels of the previous layer, needed for expander parents
 
     for layer in 1..=layers {
+        let _span = enter_span!("labeling_layer", layer);
         info!("generating layer: {}", layer);
 
         parents_cache.reset()?;
@@ -0,0 +1,174 @@
+//! Write-behind persistence of the layer being labeled.
+//!
+//! Instead of writing a layer once it is labeled, completed regions of the layer are copied into
+//! a bounded queue while labeling continues, and written to the layer's `.tmp` file by a writer
+//! thread. Once the layer is labeled, the rest of it is written, the file is synced with
+//! `fdatasync` and renamed into place. A layer file thus only exists once it is complete, which
+//! is what [`super::is_layer_written`] relies on when labeling resumes.
+
+use std::fs::{create_dir_all, remove_file, rename, File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use anyhow::{format_err, Context, Result};
+use merkletree::store::StoreConfig;
+use storage_proofs_core::metrics;
+
+/// The number of regions queued for writing before labeling waits for the writer.
+const QUEUE_DEPTH: usize = 2;
+
+/// Writes the layer being labeled behind the labeling.
+pub struct LayerWriter {
+    data_path: PathBuf,
+    tmp_path: PathBuf,
+    /// Bytes written at once, `0` if the layer is only written once it is labeled.
+    region_size: usize,
+    /// Bytes of the layer queued for writing so far.
+    queued: usize,
+    sender: Option<SyncSender<(usize, Vec<u8>)>>,
+    writer: Option<JoinHandle<Result<File>>>,
+    finished: bool,
+}
+
+impl LayerWriter {
+    /// Starts writing the layer stored with `config`, in regions of `region_size` bytes.
+    pub fn new(config: &StoreConfig, region_size: usize) -> Result<Self> {
+        let data_path = StoreConfig::data_path(&config.path, &config.id);
+        let tmp_path = data_path.with_extension(".tmp");
+
+        if let Some(parent) = data_path.parent() {
+            create_dir_all(parent).context("failed to create parent directories")?;
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)
+            .with_context(|| format!("could not create layer file {}", tmp_path.display()))?;
+
+        let (sender, receiver) = sync_channel::<(usize, Vec<u8>)>(QUEUE_DEPTH);
+        let writer = thread::spawn(move || {
+            for (offset, region) in receiver {
+                file.write_all_at(&region, offset as u64)
+                    .context("failed to write layer data")?;
+                metrics::record_bytes_written("layer", region.len() as u64);
+            }
+            Ok(file)
+        });
+
+        Ok(LayerWriter {
+            data_path,
+            tmp_path,
+            region_size,
+            queued: 0,
+            sender: Some(sender),
+            writer: Some(writer),
+            finished: false,
+        })
+    }
+
+    /// Queues the regions of the layer completed since the last call, `labels` being the labels
+    /// of the layer labeled so far. Waits for the writer if the queue is full.
+    pub fn write(&mut self, labels: &[u8]) -> Result<()> {
+        if self.region_size == 0 {
+            return Ok(());
+        }
+        while labels.len() - self.queued >= self.region_size {
+            let region = labels[self.queued..self.queued + self.region_size].to_vec();
+            let sent = match &self.sender {
+                Some(sender) => sender.send((self.queued, region)).is_ok(),
+                None => false,
+            };
+            if !sent {
+                // The writer only stops early on errors.
+                self.join()?;
+                return Err(format_err!("layer writer stopped"));
+            }
+            self.queued += self.region_size;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the rest of the layer `labels`, syncs it and moves it into place.
+    pub fn finish(mut self, labels: &[u8]) -> Result<()> {
+        let file = self.join()?;
+        file.write_all_at(&labels[self.queued..], self.queued as u64)
+            .context("failed to write layer data")?;
+        metrics::record_bytes_written("layer", (labels.len() - self.queued) as u64);
+        file.sync_data().context("failed to sync layer data")?;
+        rename(&self.tmp_path, &self.data_path).context("failed to rename tmp data")?;
+        self.finished = true;
+
+        Ok(())
+    }
+
+    /// Closes the queue and waits for the writer to write the queued regions.
+    fn join(&mut self) -> Result<File> {
+        self.sender.take();
+        self.writer
+            .take()
+            .ok_or_else(|| format_err!("layer writer already stopped"))?
+            .join()
+            .map_err(|_| format_err!("layer writer panicked"))?
+    }
+}
+
+impl Drop for LayerWriter {
+    fn drop(&mut self) {
+        // A layer which is not finished is discarded.
+        if !self.finished {
+            let _ = self.join();
+            let _ = remove_file(&self.tmp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use storage_proofs_core::cache_key::CacheKey;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_layer_writer() {
+        let dir = tempdir().expect("tempdir failure");
+        let config = StoreConfig::new(dir.path(), CacheKey::label_layer(1), 0);
+        let data_path = StoreConfig::data_path(&config.path, &config.id);
+        let labels = (0..1000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        for &region_size in &[0, 64, 100, 1000] {
+            let mut writer = LayerWriter::new(&config, region_size).expect("failed to create");
+            for end in (0..labels.len()).step_by(7) {
+                writer.write(&labels[..end]).expect("failed to write");
+                assert!(!data_path.exists());
+            }
+            writer.finish(&labels).expect("failed to finish");
+
+            assert_eq!(fs::read(&data_path).expect("failed to read layer"), labels);
+            assert_eq!(
+                fs::read_dir(dir.path())
+                    .expect("failed to read dir")
+                    .count(),
+                1
+            );
+            fs::remove_file(&data_path).expect("failed to remove layer");
+        }
+
+        // An unfinished layer leaves no files behind.
+        let mut writer = LayerWriter::new(&config, 64).expect("failed to create");
+        writer.write(&labels[..500]).expect("failed to write");
+        drop(writer);
+        assert_eq!(
+            fs::read_dir(dir.path())
+                .expect("failed to read dir")
+                .count(),
+            0
+        );
+    }
+}
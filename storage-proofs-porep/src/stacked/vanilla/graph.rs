@@ -16,7 +16,7 @@ use storage_proofs_core::{
     drgraph::{BucketGraph, Graph, BASE_DEGREE},
     error::Result,
     parameter_cache::ParameterSetMetadata,
-    settings::SETTINGS,
+    settings,
     util::NODE_SIZE,
     PoRepID,
 };
@@ -77,6 +77,45 @@ fn read_node<'a>(i: usize, parents: &[u32], data: &'a [u8]) -> &'a [u8] {
     &data[start..end]
 }
 
+/// Hashes the labels of the base parents of a node of the first layer, after the node's
+/// `replica_id || layer || node` prefix has been input into `hasher`.
+pub(crate) fn hash_parents(parents: &[&[u8]; BASE_DEGREE], mut hasher: Sha256) -> [u8; 32] {
+    // round 1 (0..6)
+    hasher.input(parents);
+
+    // round 2 (6..12)
+    hasher.input(parents);
+
+    // round 3 (12..18)
+    hasher.input(parents);
+
+    // round 4 (18..24)
+    hasher.input(parents);
+
+    // round 5 (24..30)
+    hasher.input(parents);
+
+    // round 6 (30..36)
+    hasher.input(parents);
+
+    // round 7 (37)
+    hasher.finish_with(parents[0])
+}
+
+/// Hashes the labels of the base and expander parents of a node of the layers after the first,
+/// after the node's `replica_id || layer || node` prefix has been input into `hasher`.
+pub(crate) fn hash_parents_exp(parents: &[&[u8]; DEGREE], mut hasher: Sha256) -> [u8; 32] {
+    // round 1 (14)
+    hasher.input(parents);
+
+    // round 2 (14)
+    hasher.input(parents);
+
+    // round 3 (9)
+    hasher.input(&parents[..8]);
+    hasher.finish_with(parents[8])
+}
+
 pub fn derive_feistel_keys(porep_id: PoRepID) -> [u64; 4] {
     let mut feistel_keys = [0u64; 4];
     let raw_seed = derive_porep_domain_seed(FEISTEL_DST, porep_id);
@@ -135,7 +174,7 @@ where
     /// Returns a reference to the parent cache.
     pub fn parent_cache(&self) -> Result<ParentCache> {
         // Number of nodes to be cached in memory
-        let default_cache_size = SETTINGS.sdr_parents_cache_size;
+        let default_cache_size = settings::current().sdr_parents_cache_size;
         let cache_entries = self.size() as u32;
         let cache_size = cache_entries.min(default_cache_size);
 
@@ -187,7 +226,7 @@ where
         cache_parents: &[u32],
         base_data: &[u8],
         exp_data: &[u8],
-        mut hasher: Sha256,
+        hasher: Sha256,
     ) -> [u8; 32] {
         prefetch(&cache_parents[..BASE_DEGREE], base_data);
         prefetch(&cache_parents[BASE_DEGREE..], exp_data);
@@ -210,22 +249,14 @@ where
             read_node(13, cache_parents, exp_data),
         ];
 
-        // round 1 (14)
-        hasher.input(&parents);
-
-        // round 2 (14)
-        hasher.input(&parents);
-
-        // round 3 (9)
-        hasher.input(&parents[..8]);
-        hasher.finish_with(parents[8])
+        hash_parents_exp(&parents, hasher)
     }
 
     fn copy_parents_data_inner(
         &self,
         cache_parents: &[u32],
         base_data: &[u8],
-        mut hasher: Sha256,
+        hasher: Sha256,
     ) -> [u8; 32] {
         prefetch(&cache_parents[..BASE_DEGREE], base_data);
 
@@ -239,26 +270,7 @@ where
             read_node(5, cache_parents, base_data),
         ];
 
-        // round 1 (0..6)
-        hasher.input(&parents);
-
-        // round 2 (6..12)
-        hasher.input(&parents);
-
-        // round 3 (12..18)
-        hasher.input(&parents);
-
-        // round 4 (18..24)
-        hasher.input(&parents);
-
-        // round 5 (24..30)
-        hasher.input(&parents);
-
-        // round 6 (30..36)
-        hasher.input(&parents);
-
-        // round 7 (37)
-        hasher.finish_with(parents[0])
+        hash_parents(&parents, hasher)
     }
 }
 
@@ -374,8 +386,10 @@ where
 
         match self.api_version {
             ApiVersion::V1_0_0 => transformed as u32 / self.expansion_degree as u32,
-            ApiVersion::V1_1_0 => u32::try_from(transformed as u64 / self.expansion_degree as u64)
-                .expect("invalid transformation"),
+            ApiVersion::V1_1_0 | ApiVersion::V1_2_0 | ApiVersion::V1_3_0 => {
+                u32::try_from(transformed as u64 / self.expansion_degree as u64)
+                    .expect("invalid transformation")
+            }
         }
 
         // Collapse the output in the matrix search space to the row of the corresponding
@@ -531,7 +545,7 @@ mod tests {
 
         let expect_pathological = match api_version {
             ApiVersion::V1_0_0 => true,
-            ApiVersion::V1_1_0 => false,
+            ApiVersion::V1_1_0 | ApiVersion::V1_2_0 | ApiVersion::V1_3_0 => false,
         };
 
         let graph = StackedBucketGraph::<PoseidonHasher>::new_stacked(
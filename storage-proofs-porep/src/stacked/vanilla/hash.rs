@@ -1,6 +1,9 @@
 use blstrs::Scalar as Fr;
 use filecoin_hashers::{POSEIDON_CONSTANTS_11, POSEIDON_CONSTANTS_2};
-use neptune::poseidon::Poseidon;
+use neptune::{
+    poseidon::{Poseidon, PoseidonConstants},
+    Arity,
+};
 
 /// Hash all elements in the given column.
 pub fn hash_single_column(column: &[Fr]) -> Fr {
@@ -16,3 +19,59 @@ pub fn hash_single_column(column: &[Fr]) -> Fr {
         _ => panic!("unsupported column size: {}", column.len()),
     }
 }
+
+/// Hashes the columns laid out one after the other in `columns` into `hashes`, reusing one hasher
+/// for all of them.
+pub fn hash_columns<D: From<Fr>>(columns: &[Fr], hashes: &mut [D]) {
+    if hashes.is_empty() {
+        return;
+    }
+    match columns.len() / hashes.len() {
+        2 => hash_columns_with(columns, hashes, &*POSEIDON_CONSTANTS_2),
+        11 => hash_columns_with(columns, hashes, &*POSEIDON_CONSTANTS_11),
+        len => panic!("unsupported column size: {}", len),
+    }
+}
+
+fn hash_columns_with<A: Arity<Fr>, D: From<Fr>>(
+    columns: &[Fr],
+    hashes: &mut [D],
+    constants: &PoseidonConstants<Fr, A>,
+) {
+    assert_eq!(columns.len(), hashes.len() * A::to_usize());
+
+    let mut hasher = Poseidon::new(constants);
+    for (column, hash) in columns.chunks_exact(A::to_usize()).zip(hashes) {
+        hasher.set_preimage(column);
+        *hash = hasher.hash().into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use storage_proofs_core::TEST_SEED;
+
+    #[test]
+    fn test_hash_columns() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+        for &layers in &[2, 11] {
+            let columns = (0..5 * layers)
+                .map(|_| Fr::random(&mut rng))
+                .collect::<Vec<_>>();
+            let mut hashes = vec![Fr::zero(); 5];
+            hash_columns(&columns, &mut hashes);
+
+            let expected = columns
+                .chunks(layers)
+                .map(hash_single_column)
+                .collect::<Vec<_>>();
+            assert_eq!(hashes, expected);
+        }
+    }
+}
@@ -1,19 +1,27 @@
 use std::cell::UnsafeCell;
-use std::fs::File;
+use std::fs::{self, File, OpenOptions};
 use std::hint::spin_loop;
 use std::marker::{PhantomData, Sync};
 use std::mem::size_of;
-use std::path::Path;
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
 use std::slice;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use byte_slice_cast::{AsSliceOf, FromByteSlice};
 use log::{info, warn};
 use mapr::{Mmap, MmapMut, MmapOptions};
+use sha2::{Digest, Sha256};
+use storage_proofs_core::settings::{self, Settings};
 
 pub struct CacheReader<T> {
     file: File,
+    /// How the windows are backed by huge pages.
+    huge_pages: HugePages,
     bufs: UnsafeCell<[Mmap; 2]>,
     size: usize,
     degree: usize,
@@ -93,7 +101,12 @@ impl IncrementingCursor {
 }
 
 impl<T: FromByteSlice> CacheReader<T> {
-    pub fn new(filename: &Path, window_size: Option<usize>, degree: usize) -> Result<Self> {
+    pub fn new(
+        filename: &Path,
+        window_size: Option<usize>,
+        degree: usize,
+        huge_pages: HugePages,
+    ) -> Result<Self> {
         info!("initializing cache");
         let file = File::open(filename)?;
         let size = File::metadata(&file)?.len() as usize;
@@ -115,10 +128,13 @@ impl<T: FromByteSlice> CacheReader<T> {
             }
         };
 
-        let buf0 = Self::map_buf(0, window_size, &file)?;
-        let buf1 = Self::map_buf(window_size as u64, window_size, &file)?;
+        let huge_pages = huge_pages.fitting(window_size, "parent cache windows");
+        let buf0 = Self::map_buf(0, window_size, &file, &huge_pages)?;
+        report_page_sizes("parent cache window", &buf0);
+        let buf1 = Self::map_buf(window_size as u64, window_size, &file, &huge_pages)?;
         Ok(Self {
             file,
+            huge_pages,
             bufs: UnsafeCell::new([buf0, buf1]),
             size,
             degree,
@@ -163,8 +179,7 @@ impl<T: FromByteSlice> CacheReader<T> {
         slice::from_raw_parts_mut((*self.bufs.get()).as_mut_ptr(), 2)
     }
 
-    #[allow(dead_code)]
-    // This is unused, but included to document the meaning of its components.
+    // This documents the meaning of its components.
     // This allows splitting the reset in order to avoid a pause.
     pub fn reset(&self) -> Result<()> {
         self.start_reset()?;
@@ -172,14 +187,19 @@ impl<T: FromByteSlice> CacheReader<T> {
     }
 
     pub fn start_reset(&self) -> Result<()> {
-        let buf0 = Self::map_buf(0, self.window_size, &self.file)?;
+        let buf0 = Self::map_buf(0, self.window_size, &self.file, &self.huge_pages)?;
         let bufs = unsafe { self.get_mut_bufs() };
         bufs[0] = buf0;
         Ok(())
     }
 
     pub fn finish_reset(&self) -> Result<()> {
-        let buf1 = Self::map_buf(self.window_size as u64, self.window_size, &self.file)?;
+        let buf1 = Self::map_buf(
+            self.window_size as u64,
+            self.window_size,
+            &self.file,
+            &self.huge_pages,
+        )?;
         let bufs = unsafe { self.get_mut_bufs() };
         bufs[1] = buf1;
         self.cursor.store(0);
@@ -187,15 +207,45 @@ impl<T: FromByteSlice> CacheReader<T> {
         Ok(())
     }
 
-    fn map_buf(offset: u64, len: usize, file: &File) -> Result<Mmap> {
-        unsafe {
-            MmapOptions::new()
-                .offset(offset)
-                .len(len)
-                .private()
-                .map(file)
-                .map_err(|e| e.into())
+    /// Positions the cache at the parents of `node`, so that reading resumes from there instead
+    /// of from the beginning. Must not be called while the cache is being read.
+    pub fn seek(&self, node: u64) -> Result<()> {
+        let window = node as usize * self.degree / self.window_element_count();
+        if window == 0 {
+            self.reset()?;
+        } else {
+            let buf = Self::map_buf(
+                (window * self.window_size) as u64,
+                self.window_size,
+                &self.file,
+                &self.huge_pages,
+            )?;
+            let bufs = unsafe { self.get_mut_bufs() };
+            bufs[window % 2] = buf;
+            self.cursor.store(window);
         }
+        self.store_consumer(node);
+        Ok(())
+    }
+
+    fn map_buf(offset: u64, len: usize, file: &File, huge_pages: &HugePages) -> Result<Mmap> {
+        if *huge_pages == HugePages::Off {
+            return unsafe {
+                MmapOptions::new()
+                    .offset(offset)
+                    .len(len)
+                    .private()
+                    .map(file)
+                    .map_err(|e| e.into())
+            };
+        }
+
+        // A file is mapped with its page cache pages, so the window is read into huge pages.
+        let mut buf = map_anon(len, huge_pages)?;
+        let available = file.metadata()?.len().saturating_sub(offset) as usize;
+        file.read_exact_at(&mut buf[..len.min(available)], offset)
+            .context("failed to read parent cache window")?;
+        Ok(buf.make_read_only()?)
     }
 
     #[inline]
@@ -266,6 +316,7 @@ impl<T: FromByteSlice> CacheReader<T> {
             (new_window * self.window_size) as u64,
             self.window_size as usize,
             &self.file,
+            &self.huge_pages,
         )
         .expect("map_buf failed");
 
@@ -275,36 +326,521 @@ impl<T: FromByteSlice> CacheReader<T> {
     }
 }
 
-fn allocate_layer(sector_size: usize) -> Result<MmapMut> {
-    match MmapOptions::new()
-        .len(sector_size)
-        .private()
-        .clone()
-        .lock()
-        .map_anon()
-        .and_then(|mut layer| {
-            layer.mlock()?;
-            Ok(layer)
-        }) {
-        Ok(layer) => Ok(layer),
-        Err(err) => {
-            // fallback to not locked if permissions are not available
-            warn!("failed to lock map {:?}, falling back", err);
-            let layer = MmapOptions::new().len(sector_size).private().map_anon()?;
-            Ok(layer)
+/// Sets the memory policy of `data` to prefer `node`, so that its pages are allocated there.
+#[cfg(target_os = "linux")]
+fn bind_to_node(data: &mut [u8], node: usize) -> Result<()> {
+    const MPOL_PREFERRED: libc::c_long = 1;
+    const MAX_NODES: usize = 1024;
+    const BITS: usize = 8 * size_of::<libc::c_ulong>();
+
+    anyhow::ensure!(node < MAX_NODES, "NUMA node {} out of range", node);
+    let mut nodemask = [0 as libc::c_ulong; MAX_NODES / BITS];
+    nodemask[node / BITS] |= 1 << (node % BITS);
+
+    // The kernel reads one bit less than `maxnode`.
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            data.as_mut_ptr(),
+            data.len(),
+            MPOL_PREFERRED,
+            nodemask.as_ptr(),
+            MAX_NODES + 1,
+            0,
+        )
+    };
+    anyhow::ensure!(
+        res == 0,
+        "mbind failed: {}",
+        std::io::Error::last_os_error()
+    );
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bind_to_node(_data: &mut [u8], _node: usize) -> Result<()> {
+    Ok(())
+}
+
+/// How SDR buffers are backed by huge pages, which reduce the TLB misses of the random parent
+/// reads.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HugePages {
+    /// Regular pages.
+    Off,
+    /// Transparent huge pages, requested with `MADV_HUGEPAGE`.
+    Transparent,
+    /// Huge pages of a hugetlbfs mount, which are reserved up front, falling back to transparent
+    /// huge pages if there are not enough.
+    Hugetlbfs(PathBuf),
+}
+
+impl HugePages {
+    pub fn from_settings(settings: &Settings) -> Self {
+        match settings.sdr_huge_pages.as_str() {
+            "" | "off" => HugePages::Off,
+            "transparent" => HugePages::Transparent,
+            "hugetlbfs" => HugePages::Hugetlbfs(PathBuf::from(&settings.sdr_hugetlbfs_path)),
+            other => {
+                warn!(
+                    "unknown huge pages setting {:?}, not using huge pages",
+                    other
+                );
+                HugePages::Off
+            }
+        }
+    }
+
+    /// Returns how buffers of `len` bytes can be backed, hugetlbfs pages only back whole ones.
+    fn fitting(self, len: usize, what: &str) -> Self {
+        if let HugePages::Hugetlbfs(path) = &self {
+            match hugetlbfs_page_size(path) {
+                Ok(page_size) if len % page_size == 0 => {}
+                Ok(page_size) => {
+                    warn!(
+                        "{} of {} bytes are not a multiple of the huge page size {}, using transparent huge pages",
+                        what, len, page_size
+                    );
+                    return HugePages::Transparent;
+                }
+                Err(err) => {
+                    warn!(
+                        "cannot use hugetlbfs for {}, using transparent huge pages: {:#}",
+                        what, err
+                    );
+                    return HugePages::Transparent;
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Returns the page size of the hugetlbfs mount at `path`.
+#[cfg(target_os = "linux")]
+fn hugetlbfs_page_size(path: &Path) -> Result<usize> {
+    const HUGETLBFS_MAGIC: u32 = 0x9584_58f6;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = unsafe { std::mem::zeroed::<libc::statfs>() };
+    let res = unsafe { libc::statfs(c_path.as_ptr(), &mut stat) };
+    anyhow::ensure!(
+        res == 0,
+        "failed to stat {}: {}",
+        path.display(),
+        std::io::Error::last_os_error()
+    );
+    anyhow::ensure!(
+        stat.f_type as u32 == HUGETLBFS_MAGIC,
+        "{} is not a hugetlbfs mount",
+        path.display()
+    );
+
+    Ok(stat.f_bsize as usize)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn hugetlbfs_page_size(_path: &Path) -> Result<usize> {
+    anyhow::bail!("hugetlbfs is only supported on Linux")
+}
+
+/// Maps `len` bytes of hugetlbfs pages, from an unlinked file in the mount at `path`.
+fn map_hugetlbfs(len: usize, path: &Path) -> Result<MmapMut> {
+    let page_size = hugetlbfs_page_size(path)?;
+    anyhow::ensure!(
+        len % page_size == 0,
+        "{} bytes are not a multiple of the huge page size {}",
+        len,
+        page_size
+    );
+
+    let file_path = path.join(format!(
+        "fil-proofs-sdr-{}-{}",
+        std::process::id(),
+        HUGETLBFS_FILES.fetch_add(1, Ordering::SeqCst)
+    ));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&file_path)
+        .with_context(|| format!("could not create {}", file_path.display()))?;
+    // The pages are released once the map is dropped.
+    fs::remove_file(&file_path)?;
+    file.set_len(len as u64)?;
+
+    // Mapping reserves the pages, so that it fails rather than faulting if there are too few.
+    let map = unsafe { MmapOptions::new().len(len).map_mut(&file) }
+        .context("could not map huge pages")?;
+
+    Ok(map)
+}
+
+static HUGETLBFS_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Asks the kernel to back `data` with transparent huge pages.
+#[cfg(target_os = "linux")]
+fn advise_huge_pages(data: &mut [u8]) -> Result<()> {
+    let res = unsafe {
+        libc::madvise(
+            data.as_mut_ptr() as *mut libc::c_void,
+            data.len(),
+            libc::MADV_HUGEPAGE,
+        )
+    };
+    anyhow::ensure!(
+        res == 0,
+        "madvise failed: {}",
+        std::io::Error::last_os_error()
+    );
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn advise_huge_pages(_data: &mut [u8]) -> Result<()> {
+    Ok(())
+}
+
+/// Maps `len` bytes of anonymous memory, backed by huge pages as `huge_pages` says if possible.
+fn map_anon(len: usize, huge_pages: &HugePages) -> Result<MmapMut> {
+    if let HugePages::Hugetlbfs(path) = huge_pages {
+        match map_hugetlbfs(len, path) {
+            Ok(map) => return Ok(map),
+            Err(err) => warn!(
+                "failed to map hugetlbfs pages, using transparent huge pages: {:#}",
+                err
+            ),
+        }
+    }
+
+    let mut map = MmapOptions::new().len(len).private().map_anon()?;
+    if *huge_pages != HugePages::Off {
+        if let Err(err) = advise_huge_pages(&mut map) {
+            warn!("failed to request transparent huge pages: {:#}", err);
         }
     }
+
+    Ok(map)
 }
 
+/// Logs the page sizes backing `data`, as far as its pages are present.
+fn report_page_sizes(what: &str, data: &[u8]) {
+    match page_sizes(data.as_ptr() as usize) {
+        Some((page_size, huge)) => info!(
+            "{} of {} bytes: {} KiB pages, {} KiB in transparent huge pages",
+            what,
+            data.len(),
+            page_size,
+            huge
+        ),
+        None => info!("{} of {} bytes: page sizes unknown", what, data.len()),
+    }
+}
+
+/// Returns the size of the pages of the mapping at `addr`, and how much of it is in transparent
+/// huge pages, both in KiB.
+fn page_sizes(addr: usize) -> Option<(u64, u64)> {
+    let smaps = fs::read_to_string("/proc/self/smaps").ok()?;
+
+    let mut in_map = false;
+    let mut page_size = None;
+    for line in smaps.lines() {
+        let mut fields = line.split_whitespace();
+        let name = fields.next()?;
+        if let Some((start, end)) = name.split_once('-') {
+            if let (Ok(start), Ok(end)) = (
+                usize::from_str_radix(start, 16),
+                usize::from_str_radix(end, 16),
+            ) {
+                if in_map {
+                    // The mapping ended without the fields.
+                    return None;
+                }
+                in_map = start <= addr && addr < end;
+                continue;
+            }
+        }
+        if !in_map {
+            continue;
+        }
+
+        let value = fields.next().and_then(|value| value.parse::<u64>().ok());
+        match name {
+            "KernelPageSize:" => page_size = value,
+            "AnonHugePages:" => return page_size.zip(value),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Allocates a layer buffer, placed on the NUMA node `numa_node` if given.
+fn allocate_layer(
+    sector_size: usize,
+    numa_node: Option<usize>,
+    huge_pages: &HugePages,
+) -> Result<MmapMut> {
+    // Pages are only allocated when the map is locked, so the map is placed on the node first.
+    let mut layer = map_anon(sector_size, huge_pages)?;
+    if let Some(node) = numa_node {
+        if let Err(err) = bind_to_node(&mut layer, node) {
+            warn!("failed to place layer on NUMA node {}: {:#}", node, err);
+        }
+    }
+
+    if let Err(err) = layer.mlock() {
+        // fallback to not locked if permissions are not available
+        warn!("failed to lock map {:?}, falling back", err);
+    }
+    if *huge_pages != HugePages::Off {
+        report_page_sizes("layer", &layer);
+    }
+
+    Ok(layer)
+}
+
+/// Returns the copy of the parent cache at `cache_path` for the NUMA node `node`, copying the
+/// cache if there is no copy of it yet. The page cache of a file is allocated on the node reading
+/// it first, so the threads of each node read their own copy from local memory.
+///
+/// The size and modification time of the cache a copy was made from are kept next to it, so that
+/// the copy is replaced once the cache changes. If `verify_cache` is set, the copy is also checked
+/// against the cache, like the cache is checked against its digest when it is opened.
+fn numa_parent_cache(cache_path: &Path, node: usize) -> Result<PathBuf> {
+    let copy_path = cache_path.with_extension(format!("numa{}.cache", node));
+    let source_path = cache_path.with_extension(format!("numa{}.source", node));
+    let metadata = fs::metadata(cache_path)
+        .with_context(|| format!("could not open path={}", cache_path.display()))?;
+    let mtime = metadata
+        .modified()
+        .context("parent cache has no modification time")?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let source = format!("{} {}", metadata.len(), mtime.as_nanos());
+
+    let copied = fs::read_to_string(&source_path).map_or(false, |copied| copied == source)
+        && fs::metadata(&copy_path).map_or(false, |copy| copy.len() == metadata.len());
+    if copied && (!settings::current().verify_cache || same_digest(cache_path, &copy_path)?) {
+        return Ok(copy_path);
+    }
+
+    info!(
+        "copying parent cache {} for NUMA node {}",
+        cache_path.display(),
+        node
+    );
+    let tmp_path = copy_path.with_extension(format!("tmp{}", std::process::id()));
+    fs::copy(cache_path, &tmp_path).context("failed to copy parent cache")?;
+    fs::rename(&tmp_path, &copy_path).context("failed to rename parent cache copy")?;
+    // The source is only recorded once the copy is complete.
+    fs::write(&tmp_path, source).context("failed to write parent cache copy source")?;
+    fs::rename(&tmp_path, &source_path).context("failed to rename parent cache copy source")?;
+
+    Ok(copy_path)
+}
+
+/// Returns whether the files at `a` and `b` have the same SHA-256 digest.
+fn same_digest(a: &Path, b: &Path) -> Result<bool> {
+    let digest = |path: &Path| -> Result<_> {
+        let file =
+            File::open(path).with_context(|| format!("could not open path={}", path.display()))?;
+        let data = unsafe {
+            MmapOptions::new()
+                .map(&file)
+                .with_context(|| format!("could not mmap path={}", path.display()))?
+        };
+        Ok(Sha256::digest(&data))
+    };
+
+    info!("verifying parent cache copy {}", b.display());
+    Ok(digest(a)? == digest(b)?)
+}
+
+/// Sets up the parent cache and the layer buffers of `sectors` sectors, on the NUMA node
+/// `numa_node` if given.
 pub fn setup_create_label_memory(
     sector_size: usize,
+    sectors: usize,
     degree: usize,
     window_size: Option<usize>,
     cache_path: &Path,
-) -> Result<(CacheReader<u32>, MmapMut, MmapMut)> {
-    let parents_cache = CacheReader::new(cache_path, window_size, degree)?;
-    let layer_labels = allocate_layer(sector_size)?;
-    let exp_labels = allocate_layer(sector_size)?;
+    numa_node: Option<usize>,
+) -> Result<(CacheReader<u32>, Vec<MmapMut>, Vec<MmapMut>)> {
+    let cache_path = match numa_node {
+        Some(node) if settings::current().multicore_sdr_numa_parent_cache => {
+            numa_parent_cache(cache_path, node)?
+        }
+        _ => cache_path.to_path_buf(),
+    };
+
+    let huge_pages = HugePages::from_settings(&settings::current());
+    let parents_cache = CacheReader::new(&cache_path, window_size, degree, huge_pages.clone())?;
+    let huge_pages = huge_pages.fitting(sector_size, "layers");
+    let layer_labels = (0..sectors)
+        .map(|_| allocate_layer(sector_size, numa_node, &huge_pages))
+        .collect::<Result<_>>()?;
+    let exp_labels = (0..sectors)
+        .map(|_| allocate_layer(sector_size, numa_node, &huge_pages))
+        .collect::<Result<_>>()?;
 
     Ok((parents_cache, layer_labels, exp_labels))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_allocate_layer_on_node() {
+        let mut layer =
+            allocate_layer(1 << 20, Some(0), &HugePages::Off).expect("failed to allocate layer");
+        layer.iter_mut().for_each(|byte| *byte = 1);
+        assert!(layer.iter().all(|byte| *byte == 1));
+    }
+
+    #[test]
+    fn test_numa_parent_cache() {
+        let dir = tempdir().expect("tempdir failure");
+        let cache_path = dir.path().join("v28-sdr-parent-abc.cache");
+        let data = (0..4096u32).map(|i| (i % 256) as u8).collect::<Vec<_>>();
+        fs::write(&cache_path, &data).expect("failed to write cache");
+
+        let copy_path = numa_parent_cache(&cache_path, 1).expect("failed to copy cache");
+        assert_eq!(copy_path, dir.path().join("v28-sdr-parent-abc.numa1.cache"));
+        assert_eq!(fs::read(&copy_path).expect("failed to read copy"), data);
+
+        // An existing copy is reused, an incomplete one replaced.
+        fs::write(&copy_path, &data[..100]).expect("failed to truncate copy");
+        numa_parent_cache(&cache_path, 1).expect("failed to copy cache");
+        assert_eq!(fs::read(&copy_path).expect("failed to read copy"), data);
+
+        // A changed copy is reused too, unless the cache is verified.
+        let changed = data.iter().map(|byte| !byte).collect::<Vec<_>>();
+        fs::write(&copy_path, &changed).expect("failed to change copy");
+        numa_parent_cache(&cache_path, 1).expect("failed to copy cache");
+        assert_eq!(fs::read(&copy_path).expect("failed to read copy"), changed);
+
+        let verify = Settings {
+            verify_cache: true,
+            ..Default::default()
+        };
+        settings::with_settings(Arc::new(verify), || numa_parent_cache(&cache_path, 1))
+            .expect("failed to copy cache");
+        assert_eq!(fs::read(&copy_path).expect("failed to read copy"), data);
+
+        // A copy of a changed cache is replaced. The modification time changes at the latest
+        // after a second, whatever its resolution.
+        thread::sleep(Duration::from_millis(1100));
+        fs::write(&cache_path, &changed).expect("failed to write cache");
+        numa_parent_cache(&cache_path, 1).expect("failed to copy cache");
+        assert_eq!(fs::read(&copy_path).expect("failed to read copy"), changed);
+
+        // The cache, its copy and the source of the copy.
+        assert_eq!(
+            fs::read_dir(dir.path())
+                .expect("failed to read dir")
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_huge_pages_from_settings() {
+        let huge_pages = |setting: &str| {
+            HugePages::from_settings(&Settings {
+                sdr_huge_pages: setting.to_string(),
+                sdr_hugetlbfs_path: "/mnt/huge".to_string(),
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(huge_pages(""), HugePages::Off);
+        assert_eq!(huge_pages("off"), HugePages::Off);
+        assert_eq!(huge_pages("transparent"), HugePages::Transparent);
+        assert_eq!(
+            huge_pages("hugetlbfs"),
+            HugePages::Hugetlbfs(PathBuf::from("/mnt/huge"))
+        );
+        assert_eq!(huge_pages("gigantic"), HugePages::Off);
+    }
+
+    #[test]
+    fn test_allocate_layer_huge_pages() {
+        // A directory that is not a hugetlbfs mount falls back to transparent huge pages.
+        let dir = tempdir().expect("tempdir failure");
+        let hugetlbfs = HugePages::Hugetlbfs(dir.path().to_path_buf());
+        assert_eq!(
+            hugetlbfs.clone().fitting(4 << 20, "layers"),
+            HugePages::Transparent
+        );
+
+        for huge_pages in &[HugePages::Transparent, hugetlbfs] {
+            let mut layer =
+                allocate_layer(4 << 20, None, huge_pages).expect("failed to allocate layer");
+            layer.iter_mut().for_each(|byte| *byte = 1);
+            assert!(layer.iter().all(|byte| *byte == 1));
+        }
+        assert_eq!(
+            fs::read_dir(dir.path())
+                .expect("failed to read dir")
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_page_sizes() {
+        let mut layer =
+            allocate_layer(4 << 20, None, &HugePages::Transparent).expect("failed to allocate");
+        layer.iter_mut().for_each(|byte| *byte = 1);
+
+        let (page_size, huge) = page_sizes(layer.as_ptr() as usize).expect("no page sizes");
+        assert!(page_size >= 4);
+        assert!(huge <= 4 << 10);
+        assert_eq!(page_sizes(0), None);
+    }
+
+    #[test]
+    fn test_cache_reader_huge_pages() {
+        let degree = 14;
+        let window_size = degree * size_of::<u32>() * 1024;
+        let dir = tempdir().expect("tempdir failure");
+        let cache_path = dir.path().join("v28-sdr-parent-abc.cache");
+        let data = (0..(4 * window_size / size_of::<u32>()) as u32)
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>();
+        fs::write(&cache_path, &data).expect("failed to write cache");
+
+        for huge_pages in &[HugePages::Off, HugePages::Transparent] {
+            let cache =
+                CacheReader::<u32>::new(&cache_path, Some(window_size), degree, huge_pages.clone())
+                    .expect("failed to open cache");
+            let window_elements = window_size / size_of::<u32>();
+
+            unsafe {
+                assert_eq!(cache.consumer_slice_at(0)[..3], [0, 1, 2]);
+                assert_eq!(
+                    cache.consumer_slice_at(window_elements + 5)[0],
+                    window_elements as u32 + 5
+                );
+
+                let node = 3 * window_elements / degree;
+                cache.seek(node as u64).expect("failed to seek");
+                assert_eq!(
+                    cache.consumer_slice_at(node * degree)[..2],
+                    [(node * degree) as u32, (node * degree) as u32 + 1]
+                );
+            }
+        }
+    }
+}
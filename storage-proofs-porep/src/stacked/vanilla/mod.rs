@@ -19,6 +19,7 @@ mod params;
 mod porep;
 mod proof;
 mod proof_scheme;
+mod synthetic;
 #[cfg(feature = "multicore-sdr")]
 mod utils;
 
@@ -29,4 +30,4 @@ pub use encoding_proof::EncodingProof;
 pub use graph::{StackedBucketGraph, StackedGraph, EXP_DEGREE};
 pub use labeling_proof::LabelingProof;
 pub use params::*;
-pub use proof::{StackedDrg, TreeRElementData, TOTAL_PARENTS};
+pub use proof::{StackedDrg, TreeRElementData, SYNTHETIC_PROOFS_BATCH_SIZE, TOTAL_PARENTS};
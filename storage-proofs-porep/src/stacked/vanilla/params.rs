@@ -132,7 +132,19 @@ impl<T: Domain, S: Domain> PublicInputs<T, S> {
     ) -> Vec<usize> {
         let k = partition_k.unwrap_or(0);
 
-        layer_challenges.derive::<T>(leaves, &self.replica_id, &self.seed, k as u8)
+        // Synthetic and non-interactive challenges are derived from comm_r, so they require tau.
+        let comm_r = match self.tau {
+            Some(ref tau) => tau.comm_r,
+            None => {
+                assert!(
+                    !layer_challenges.requires_comm_r(),
+                    "synthetic and non-interactive challenges require comm_r"
+                );
+                T::default()
+            }
+        };
+
+        layer_challenges.derive::<T>(leaves, &self.replica_id, &comm_r, &self.seed, k as u8)
     }
 }
 
@@ -5,7 +5,7 @@ use std::panic::panic_any;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
-use anyhow::Context;
+use anyhow::{ensure, Context};
 use bincode::deserialize;
 use blstrs::Scalar as Fr;
 use fdlimit::raise_fd_limit;
@@ -19,12 +19,14 @@ use merkletree::{
     store::{DiskStore, Store, StoreConfig},
 };
 use rayon::prelude::{
-    IndexedParallelIterator, IntoParallelIterator, ParallelIterator, ParallelSliceMut,
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+    ParallelSliceMut,
 };
 use storage_proofs_core::{
     cache_key::CacheKey,
     data::Data,
     drgraph::Graph,
+    enter_span,
     error::Result,
     measurements::{measure_op, Operation},
     merkle::{
@@ -32,7 +34,7 @@ use storage_proofs_core::{
         split_config_and_replica, BinaryMerkleTree, DiskTree, LCTree, MerkleProofTrait, MerkleTree,
         MerkleTreeTrait,
     },
-    settings::SETTINGS,
+    settings,
     util::{default_rows_to_discard, NODE_SIZE},
 };
 use yastl::Pool;
@@ -44,12 +46,13 @@ use crate::{
         column::Column,
         create_label,
         graph::StackedBucketGraph,
-        hash::hash_single_column,
+        hash::hash_columns,
         params::{
             get_node, Labels, LabelsCache, PersistentAux, Proof, PublicInputs, PublicParams,
             ReplicaColumnProof, Tau, TemporaryAux, TemporaryAuxCache, TransformedLayers,
             BINARY_ARITY,
         },
+        synthetic::{self, SyntheticProofsWriter},
         EncodingProof, LabelingProof,
     },
     PoRep,
@@ -57,6 +60,9 @@ use crate::{
 
 pub const TOTAL_PARENTS: usize = 37;
 
+/// The number of synthetic challenges proven at once, bounding the proofs held in memory.
+pub const SYNTHETIC_PROOFS_BATCH_SIZE: usize = 1 << 10;
+
 lazy_static! {
     /// Ensure that only one `TreeBuilder` or `ColumnTreeBuilder` uses the GPU at a time.
     /// Curently, this is accomplished by only instantiating at most one at a time.
@@ -67,6 +73,65 @@ lazy_static! {
     static ref THREAD_POOL: Pool = Pool::new(num_cpus::get());
 }
 
+/// The number of chunks of layer data read ahead of the chunk being hashed when tree c is built
+/// on the CPU.
+const TREE_C_READ_AHEAD: usize = 1;
+
+/// Returns the number of nodes of each layer read and hashed at once when tree c is built on the
+/// CPU, and whether a base tree is built while the columns of the next one are hashed.
+///
+/// The `tree_c_cpu_memory_budget` holds the column hashes of the base trees being hashed and
+/// built, the chunks being read and hashed, and the columns being hashed. A base tree is only
+/// built while the next one is hashed if the budget holds the hashes of both.
+fn tree_c_cpu_chunks(layers: usize, nodes_count: usize, tree_count: usize) -> (usize, bool) {
+    let memory_budget = settings::current().tree_c_cpu_memory_budget as usize;
+    let hashes_size = nodes_count * NODE_SIZE;
+    let chunk_node_size = (TREE_C_READ_AHEAD + 2) * layers * NODE_SIZE;
+    let overlap = tree_count > 1 && memory_budget >= 2 * hashes_size + chunk_node_size;
+    let hashes_buffers = if overlap { 2 } else { 1 };
+    let chunk_nodes = (memory_budget.saturating_sub(hashes_buffers * hashes_size)
+        / chunk_node_size)
+        .max(1)
+        .min(nodes_count);
+
+    (chunk_nodes, overlap)
+}
+
+/// Hashes the columns of a chunk of `chunk_nodes` nodes of each layer into `hashes`, splitting
+/// them over as many threads as there are column buffers in `columns`.
+fn hash_tree_c_chunk<D: Domain>(
+    pool: &Pool,
+    chunk: &[u8],
+    chunk_nodes: usize,
+    layers: usize,
+    hashes: &mut [D],
+    columns: &mut [Vec<Fr>],
+) {
+    use fr32::bytes_into_fr;
+
+    let part_nodes = (hashes.len() + columns.len() - 1) / columns.len();
+
+    pool.scoped(|s| {
+        for (part, (hashes, columns)) in hashes
+            .chunks_mut(part_nodes)
+            .zip(columns.iter_mut())
+            .enumerate()
+        {
+            s.execute(move || {
+                columns.clear();
+                for node in part * part_nodes..part * part_nodes + hashes.len() {
+                    columns.extend((0..layers).map(|layer_index| {
+                        let start = (layer_index * chunk_nodes + node) * NODE_SIZE;
+                        bytes_into_fr(&chunk[start..start + NODE_SIZE])
+                            .expect("Could not create Fr from bytes.")
+                    }));
+                }
+                hash_columns(columns, hashes);
+            });
+        }
+    });
+}
+
 #[derive(Debug)]
 pub struct StackedDrg<'a, Tree: MerkleTreeTrait, G: Hasher> {
     _a: PhantomData<&'a Tree>,
@@ -104,6 +169,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         layers: usize,
         partition_count: usize,
     ) -> Result<Vec<Vec<Proof<Tree, G>>>> {
+        let _span = enter_span!("prove_layers", partitions = partition_count);
         assert!(layers > 0);
         assert_eq!(t_aux.labels.len(), layers);
 
@@ -116,6 +182,108 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
             t_aux.tree_d.root()
         );
 
+        (0..partition_count)
+            .map(|k| {
+                trace!("proving partition {}/{}", k + 1, partition_count);
+
+                // Derive the set of challenges we are proving over. Synthetic challenges selected
+                // by the seed are proven from the layers just like interactive ones.
+                let challenges = pub_inputs.challenges(layer_challenges, graph_size, Some(k));
+
+                Self::prove_challenges(
+                    graph,
+                    &pub_inputs.replica_id,
+                    p_aux,
+                    t_aux,
+                    layers,
+                    &challenges,
+                )
+            })
+            .collect()
+    }
+
+    /// Generates the vanilla proofs of all synthetic challenges of a replica and persists them
+    /// to `path`, after which the layers are no longer needed to prove the challenges selected by
+    /// the seed, see [`read_synthetic_proofs`](Self::read_synthetic_proofs).
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_synthetic_layers(
+        graph: &StackedBucketGraph<Tree::Hasher>,
+        replica_id: &<Tree::Hasher as Hasher>::Domain,
+        tau: &Tau<<Tree::Hasher as Hasher>::Domain, <G as Hasher>::Domain>,
+        p_aux: &PersistentAux<<Tree::Hasher as Hasher>::Domain>,
+        t_aux: &TemporaryAuxCache<Tree, G>,
+        layer_challenges: &LayerChallenges,
+        path: &Path,
+    ) -> Result<()> {
+        let layers = layer_challenges.layers();
+        let _span = enter_span!("synthetic_vanilla_proofs", layers = layers);
+        assert_eq!(t_aux.labels.len(), layers);
+        assert_eq!(tau.comm_d, t_aux.tree_d.root());
+
+        let challenges = layer_challenges.derive_synthetic(graph.size(), replica_id, &tau.comm_r);
+        info!(
+            "proving {} synthetic challenges into {:?}",
+            challenges.len(),
+            path
+        );
+
+        let mut writer = SyntheticProofsWriter::create(path, challenges.len())?;
+        for chunk in challenges.chunks(SYNTHETIC_PROOFS_BATCH_SIZE) {
+            for proof in Self::prove_challenges(graph, replica_id, p_aux, t_aux, layers, chunk)? {
+                writer.write(&proof)?;
+            }
+        }
+
+        writer.finish()
+    }
+
+    /// Reads the proofs of the synthetic challenges selected by the seed of `pub_inputs` from a
+    /// file written by [`prove_synthetic_layers`](Self::prove_synthetic_layers).
+    pub fn read_synthetic_proofs(
+        pub_inputs: &PublicInputs<<Tree::Hasher as Hasher>::Domain, <G as Hasher>::Domain>,
+        layer_challenges: &LayerChallenges,
+        partition_count: usize,
+        path: &Path,
+    ) -> Result<Vec<Vec<Proof<Tree, G>>>> {
+        let synthetic_count = layer_challenges
+            .synthetic_count()
+            .context("challenges are not synthetic")?;
+
+        (0..partition_count)
+            .map(|k| {
+                let indexes = layer_challenges.derive_synthetic_indexes(
+                    &pub_inputs.replica_id,
+                    &pub_inputs.seed,
+                    k as u8,
+                );
+                synthetic::read_synthetic_proofs(path, synthetic_count, &indexes)
+            })
+            .collect()
+    }
+
+    /// Returns the serialized size of the vanilla proof of a challenge of a replica of `nodes`
+    /// nodes and `layers` layers.
+    pub fn proof_size(nodes: usize, layers: usize) -> u64 {
+        synthetic::proof_size::<Tree>(nodes as u64, layers as u64)
+    }
+
+    /// Returns the size of the file written by
+    /// [`prove_synthetic_layers`](Self::prove_synthetic_layers) for a replica of `nodes` nodes, or
+    /// `None` if the challenges are not synthetic.
+    pub fn synthetic_proofs_size(nodes: usize, layer_challenges: &LayerChallenges) -> Option<u64> {
+        layer_challenges.synthetic_count().map(|count| {
+            synthetic::synthetic_proofs_size::<Tree>(nodes, layer_challenges.layers(), count)
+        })
+    }
+
+    fn prove_challenges(
+        graph: &StackedBucketGraph<Tree::Hasher>,
+        replica_id: &<Tree::Hasher as Hasher>::Domain,
+        p_aux: &PersistentAux<<Tree::Hasher as Hasher>::Domain>,
+        t_aux: &TemporaryAuxCache<Tree, G>,
+        layers: usize,
+        challenges: &[usize],
+    ) -> Result<Vec<Proof<Tree, G>>> {
         let get_drg_parents_columns = |x: usize| -> Result<Vec<Column<Tree::Hasher>>> {
             let base_degree = graph.base_graph().degree();
 
@@ -146,146 +314,136 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
                 .collect()
         };
 
-        (0..partition_count)
-            .map(|k| {
-                trace!("proving partition {}/{}", k + 1, partition_count);
-
-                // Derive the set of challenges we are proving over.
-                let challenges = pub_inputs.challenges(layer_challenges, graph_size, Some(k));
-
-                // Stacked commitment specifics
-                challenges
-                    .into_par_iter()
-                    .enumerate()
-                    .map(|(challenge_index, challenge)| {
-                        trace!(" challenge {} ({})", challenge, challenge_index);
-                        assert!(challenge < graph.size(), "Invalid challenge");
-                        assert!(challenge > 0, "Invalid challenge");
-
-                        // Initial data layer openings (c_X in Comm_D)
-                        let comm_d_proof = t_aux.tree_d.gen_proof(challenge)?;
-                        assert!(comm_d_proof.validate(challenge));
-
-                        // Stacked replica column openings
-                        let rcp = {
-                            let (c_x, drg_parents, exp_parents) = {
-                                assert_eq!(p_aux.comm_c, t_aux.tree_c.root());
-                                let tree_c = &t_aux.tree_c;
-
-                                // All labels in C_X
-                                trace!("  c_x");
-                                let c_x = t_aux.column(challenge as u32)?.into_proof(tree_c)?;
-
-                                // All labels in the DRG parents.
-                                trace!("  drg_parents");
-                                let drg_parents = get_drg_parents_columns(challenge)?
-                                    .into_iter()
-                                    .map(|column| column.into_proof(tree_c))
-                                    .collect::<Result<_>>()?;
-
-                                // Labels for the expander parents
-                                trace!("  exp_parents");
-                                let exp_parents = get_exp_parents_columns(challenge)?
-                                    .into_iter()
-                                    .map(|column| column.into_proof(tree_c))
-                                    .collect::<Result<_>>()?;
-
-                                (c_x, drg_parents, exp_parents)
-                            };
-
-                            ReplicaColumnProof {
-                                c_x,
-                                drg_parents,
-                                exp_parents,
-                            }
-                        };
-
-                        // Final replica layer openings
-                        trace!("final replica layer openings");
-                        let comm_r_last_proof = t_aux.tree_r_last.gen_cached_proof(
-                            challenge,
-                            Some(t_aux.tree_r_last_config_rows_to_discard),
-                        )?;
-
-                        debug_assert!(comm_r_last_proof.validate(challenge));
-
-                        // Labeling Proofs Layer 1..l
-                        let mut labeling_proofs = Vec::with_capacity(layers);
-                        let mut encoding_proof = None;
+        // Stacked commitment specifics
+        challenges
+            .par_iter()
+            .copied()
+            .enumerate()
+            .map(|(challenge_index, challenge)| {
+                trace!(" challenge {} ({})", challenge, challenge_index);
+                assert!(challenge < graph.size(), "Invalid challenge");
+                assert!(challenge > 0, "Invalid challenge");
+
+                // Initial data layer openings (c_X in Comm_D)
+                let comm_d_proof = t_aux.tree_d.gen_proof(challenge)?;
+                assert!(comm_d_proof.validate(challenge));
+
+                // Stacked replica column openings
+                let rcp = {
+                    let (c_x, drg_parents, exp_parents) = {
+                        assert_eq!(p_aux.comm_c, t_aux.tree_c.root());
+                        let tree_c = &t_aux.tree_c;
+
+                        // All labels in C_X
+                        trace!("  c_x");
+                        let c_x = t_aux.column(challenge as u32)?.into_proof(tree_c)?;
+
+                        // All labels in the DRG parents.
+                        trace!("  drg_parents");
+                        let drg_parents = get_drg_parents_columns(challenge)?
+                            .into_iter()
+                            .map(|column| column.into_proof(tree_c))
+                            .collect::<Result<_>>()?;
+
+                        // Labels for the expander parents
+                        trace!("  exp_parents");
+                        let exp_parents = get_exp_parents_columns(challenge)?
+                            .into_iter()
+                            .map(|column| column.into_proof(tree_c))
+                            .collect::<Result<_>>()?;
+
+                        (c_x, drg_parents, exp_parents)
+                    };
 
-                        for layer in 1..=layers {
-                            trace!("  encoding proof layer {}", layer,);
-                            let parents_data: Vec<<Tree::Hasher as Hasher>::Domain> = if layer == 1
-                            {
-                                let mut parents = vec![0; graph.base_graph().degree()];
-                                graph.base_parents(challenge, &mut parents)?;
+                    ReplicaColumnProof {
+                        c_x,
+                        drg_parents,
+                        exp_parents,
+                    }
+                };
 
-                                parents
-                                    .into_par_iter()
-                                    .map(|parent| t_aux.domain_node_at_layer(layer, parent))
-                                    .collect::<Result<_>>()?
-                            } else {
-                                let mut parents = vec![0; graph.degree()];
-                                graph.parents(challenge, &mut parents)?;
-                                let base_parents_count = graph.base_graph().degree();
-
-                                parents
-                                    .into_par_iter()
-                                    .enumerate()
-                                    .map(|(i, parent)| {
-                                        if i < base_parents_count {
-                                            // parents data for base parents is from the current layer
-                                            t_aux.domain_node_at_layer(layer, parent)
-                                        } else {
-                                            // parents data for exp parents is from the previous layer
-                                            t_aux.domain_node_at_layer(layer - 1, parent)
-                                        }
-                                    })
-                                    .collect::<Result<_>>()?
-                            };
+                // Final replica layer openings
+                trace!("final replica layer openings");
+                let comm_r_last_proof = t_aux
+                    .tree_r_last
+                    .gen_cached_proof(challenge, Some(t_aux.tree_r_last_config_rows_to_discard))?;
+
+                debug_assert!(comm_r_last_proof.validate(challenge));
+
+                // Labeling Proofs Layer 1..l
+                let mut labeling_proofs = Vec::with_capacity(layers);
+                let mut encoding_proof = None;
+
+                for layer in 1..=layers {
+                    trace!("  encoding proof layer {}", layer,);
+                    let parents_data: Vec<<Tree::Hasher as Hasher>::Domain> = if layer == 1 {
+                        let mut parents = vec![0; graph.base_graph().degree()];
+                        graph.base_parents(challenge, &mut parents)?;
+
+                        parents
+                            .into_par_iter()
+                            .map(|parent| t_aux.domain_node_at_layer(layer, parent))
+                            .collect::<Result<_>>()?
+                    } else {
+                        let mut parents = vec![0; graph.degree()];
+                        graph.parents(challenge, &mut parents)?;
+                        let base_parents_count = graph.base_graph().degree();
+
+                        parents
+                            .into_par_iter()
+                            .enumerate()
+                            .map(|(i, parent)| {
+                                if i < base_parents_count {
+                                    // parents data for base parents is from the current layer
+                                    t_aux.domain_node_at_layer(layer, parent)
+                                } else {
+                                    // parents data for exp parents is from the previous layer
+                                    t_aux.domain_node_at_layer(layer - 1, parent)
+                                }
+                            })
+                            .collect::<Result<_>>()?
+                    };
 
-                            // repeat parents
-                            let mut parents_data_full = vec![Default::default(); TOTAL_PARENTS];
-                            for chunk in parents_data_full.chunks_mut(parents_data.len()) {
-                                chunk.copy_from_slice(&parents_data[..chunk.len()]);
-                            }
+                    // repeat parents
+                    let mut parents_data_full = vec![Default::default(); TOTAL_PARENTS];
+                    for chunk in parents_data_full.chunks_mut(parents_data.len()) {
+                        chunk.copy_from_slice(&parents_data[..chunk.len()]);
+                    }
 
-                            let proof = LabelingProof::<Tree::Hasher>::new(
-                                layer as u32,
-                                challenge as u64,
-                                parents_data_full.clone(),
-                            );
+                    let proof = LabelingProof::<Tree::Hasher>::new(
+                        layer as u32,
+                        challenge as u64,
+                        parents_data_full.clone(),
+                    );
 
-                            {
-                                let labeled_node = rcp.c_x.get_node_at_layer(layer)?;
-                                assert!(
-                                    proof.verify(&pub_inputs.replica_id, labeled_node),
-                                    "Invalid encoding proof generated at layer {}",
-                                    layer,
-                                );
-                                trace!("Valid encoding proof generated at layer {}", layer);
-                            }
+                    {
+                        let labeled_node = rcp.c_x.get_node_at_layer(layer)?;
+                        assert!(
+                            proof.verify(replica_id, labeled_node),
+                            "Invalid encoding proof generated at layer {}",
+                            layer,
+                        );
+                        trace!("Valid encoding proof generated at layer {}", layer);
+                    }
 
-                            labeling_proofs.push(proof);
+                    labeling_proofs.push(proof);
 
-                            if layer == layers {
-                                encoding_proof = Some(EncodingProof::new(
-                                    layer as u32,
-                                    challenge as u64,
-                                    parents_data_full,
-                                ));
-                            }
-                        }
+                    if layer == layers {
+                        encoding_proof = Some(EncodingProof::new(
+                            layer as u32,
+                            challenge as u64,
+                            parents_data_full,
+                        ));
+                    }
+                }
 
-                        Ok(Proof {
-                            comm_d_proofs: comm_d_proof,
-                            replica_column_proofs: rcp,
-                            comm_r_last_proof,
-                            labeling_proofs,
-                            encoding_proof: encoding_proof.expect("invalid tapering"),
-                        })
-                    })
-                    .collect()
+                Ok(Proof {
+                    comm_d_proofs: comm_d_proof,
+                    replica_column_proofs: rcp,
+                    comm_r_last_proof,
+                    labeling_proofs,
+                    encoding_proof: encoding_proof.expect("invalid tapering"),
+                })
             })
             .collect()
     }
@@ -331,11 +489,25 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         replica_id: &<Tree::Hasher as Hasher>::Domain,
         config: StoreConfig,
     ) -> Result<(Labels<Tree>, Vec<LayerState>)> {
+        let _span = enter_span!("labeling", layers = layer_challenges.layers());
         let mut parent_cache = graph.parent_cache()?;
 
+        let memory_budget = settings::current().sdr_memory_budget;
+        if memory_budget != 0 {
+            info!("low memory replication");
+            return create_label::low_memory::create_labels_for_encoding(
+                graph,
+                &mut parent_cache,
+                layer_challenges.layers(),
+                replica_id,
+                config,
+                memory_budget,
+            );
+        }
+
         #[cfg(feature = "multicore-sdr")]
         {
-            if SETTINGS.use_multicore_sdr {
+            if settings::current().use_multicore_sdr {
                 info!("multi core replication");
                 create_label::multi::create_labels_for_encoding(
                     graph,
@@ -369,6 +541,50 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         }
     }
 
+    /// Generates the layers of several sectors as needed for encoding. With multicore SDR the
+    /// sectors are labeled in lockstep, sharing the reads of the parent cache, otherwise they are
+    /// labeled one after the other.
+    #[allow(clippy::type_complexity)]
+    pub fn generate_labels_for_encoding_lockstep(
+        graph: &StackedBucketGraph<Tree::Hasher>,
+        layer_challenges: &LayerChallenges,
+        replica_ids: &[<Tree::Hasher as Hasher>::Domain],
+        configs: Vec<StoreConfig>,
+    ) -> Result<Vec<(Labels<Tree>, Vec<LayerState>)>> {
+        ensure!(
+            replica_ids.len() == configs.len(),
+            "{} replica ids for {} sectors",
+            replica_ids.len(),
+            configs.len()
+        );
+
+        #[cfg(feature = "multicore-sdr")]
+        {
+            let settings = settings::current();
+            // Labeling within a memory budget handles one sector at a time.
+            if settings.use_multicore_sdr && settings.sdr_memory_budget == 0 {
+                let _span = enter_span!("labeling", layers = layer_challenges.layers());
+                info!("multi core replication of {} sectors", replica_ids.len());
+                let parent_cache = graph.parent_cache()?;
+                return create_label::multi::create_labels_for_encoding_lockstep(
+                    graph,
+                    &parent_cache,
+                    layer_challenges.layers(),
+                    replica_ids,
+                    configs,
+                );
+            }
+        }
+
+        replica_ids
+            .iter()
+            .zip(configs)
+            .map(|(replica_id, config)| {
+                Self::generate_labels_for_encoding(graph, layer_challenges, replica_id, config)
+            })
+            .collect()
+    }
+
     /// Generates the layers, as needed for decoding.
     pub fn generate_labels_for_decoding(
         graph: &StackedBucketGraph<Tree::Hasher>,
@@ -380,7 +596,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
 
         #[cfg(feature = "multicore-sdr")]
         {
-            if SETTINGS.use_multicore_sdr {
+            if settings::current().use_multicore_sdr {
                 info!("multi core replication");
                 create_label::multi::create_labels_for_decoding(
                     graph,
@@ -437,17 +653,28 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         Ok(tree)
     }
 
+    /// Bytes of memory used to build tree c on the CPU, for base trees of `nodes_count` nodes:
+    /// the column hashes of one or two base trees, the chunks of the layers and the columns
+    /// being hashed.
+    pub fn tree_c_cpu_memory(layers: usize, nodes_count: usize, tree_count: usize) -> u64 {
+        let (chunk_nodes, overlap) = tree_c_cpu_chunks(layers, nodes_count, tree_count);
+        let hashes_buffers = if overlap { 2 } else { 1 };
+
+        ((hashes_buffers * nodes_count + (TREE_C_READ_AHEAD + 2) * layers * chunk_nodes)
+            * NODE_SIZE) as u64
+    }
+
     // Even if the column builder is enabled, the GPU column builder
     // only supports Poseidon hashes.
     pub fn use_gpu_column_builder() -> bool {
-        SETTINGS.use_gpu_column_builder
+        settings::current().use_gpu_column_builder
             && TypeId::of::<Tree::Hasher>() == TypeId::of::<PoseidonHasher>()
     }
 
     // Even if the tree builder is enabled, the GPU tree builder
     // only supports Poseidon hashes.
     pub fn use_gpu_tree_builder() -> bool {
-        SETTINGS.use_gpu_tree_builder
+        settings::current().use_gpu_tree_builder
             && TypeId::of::<Tree::Hasher>() == TypeId::of::<PoseidonHasher>()
     }
 
@@ -528,6 +755,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         };
 
         info!("generating tree c using the GPU");
+        let _span = enter_span!("tree_c", device = "gpu");
         // Build the tree for CommC
         measure_op(Operation::GenerateTreeC, || {
             info!("Building column hashes");
@@ -541,9 +769,9 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
             // Override these values with care using environment variables:
             // FIL_PROOFS_MAX_GPU_COLUMN_BATCH_SIZE, FIL_PROOFS_MAX_GPU_TREE_BATCH_SIZE, and
             // FIL_PROOFS_COLUMN_WRITE_BATCH_SIZE respectively.
-            let max_gpu_column_batch_size = SETTINGS.max_gpu_column_batch_size as usize;
-            let max_gpu_tree_batch_size = SETTINGS.max_gpu_tree_batch_size as usize;
-            let column_write_batch_size = SETTINGS.column_write_batch_size as usize;
+            let max_gpu_column_batch_size = settings::current().max_gpu_column_batch_size as usize;
+            let max_gpu_tree_batch_size = settings::current().max_gpu_tree_batch_size as usize;
+            let column_write_batch_size = settings::current().column_write_batch_size as usize;
 
             // This channel will receive batches of columns and add them to the ColumnTreeBuilder.
             let (builder_tx, builder_rx) = channel(0);
@@ -776,56 +1004,122 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         ColumnArity: PoseidonArity,
         TreeArity: PoseidonArity,
     {
+        use std::cmp::min;
+        use std::sync::mpsc::sync_channel as channel;
+
         info!("generating tree c using the CPU");
+        let _span = enter_span!("tree_c", device = "cpu");
         measure_op(Operation::GenerateTreeC, || {
             info!("Building column hashes");
 
-            let mut trees = Vec::with_capacity(tree_count);
-            for (i, config) in configs.iter().enumerate() {
-                let mut hashes: Vec<<Tree::Hasher as Hasher>::Domain> =
-                    vec![<Tree::Hasher as Hasher>::Domain::default(); nodes_count];
-
-                THREAD_POOL.scoped(|s| {
-                    let n = num_cpus::get();
-
-                    // only split if we have at least two elements per thread
-                    let num_chunks = if n > nodes_count * 2 { 1 } else { n };
-
-                    // chunk into n chunks
-                    let chunk_size = (nodes_count as f64 / num_chunks as f64).ceil() as usize;
-
-                    // calculate all n chunks in parallel
-                    for (chunk, hashes_chunk) in hashes.chunks_mut(chunk_size).enumerate() {
-                        let labels = &labels;
-
-                        s.execute(move || {
-                            for (j, hash) in hashes_chunk.iter_mut().enumerate() {
-                                let data: Vec<_> = (1..=layers)
-                                    .map(|layer| {
-                                        let store = labels.labels_for_layer(layer);
-                                        let el: <Tree::Hasher as Hasher>::Domain = store
-                                            .read_at((i * nodes_count) + j + chunk * chunk_size)
-                                            .expect("store read_at failure");
-                                        el.into()
-                                    })
-                                    .collect();
+            let threads = match settings::current().tree_c_cpu_threads {
+                0 => num_cpus::get(),
+                threads => threads,
+            };
+            let pool = Pool::new(threads);
+            let builder_pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .context("failed to create the tree_c builder pool")?;
 
-                                *hash = hash_single_column(&data).into();
+            let (chunk_nodes, overlap) = tree_c_cpu_chunks(layers, nodes_count, tree_count);
+            info!(
+                "hashing columns in chunks of {} nodes with {} threads, overlapping tree builds: {}",
+                chunk_nodes, threads, overlap
+            );
+
+            crossbeam::thread::scope(|s| {
+                // Buffers go round from the reader to the hashing and back, so that they are
+                // allocated once.
+                let (free_tx, free_rx) = channel::<Vec<u8>>(TREE_C_READ_AHEAD + 1);
+                let (chunk_tx, chunk_rx) = channel::<Result<Vec<u8>>>(TREE_C_READ_AHEAD);
+                for _ in 0..=TREE_C_READ_AHEAD {
+                    free_tx
+                        .send(vec![0u8; layers * chunk_nodes * NODE_SIZE])
+                        .expect("failed to send buffer");
+                }
+
+                // Reads chunks of all layers, laid out layer after layer, ahead of the hashing.
+                s.spawn(move |_| {
+                    for i in 0..tree_count {
+                        for start in (0..nodes_count).step_by(chunk_nodes) {
+                            let end = min(start + chunk_nodes, nodes_count);
+                            let mut chunk = match free_rx.recv() {
+                                Ok(chunk) => chunk,
+                                Err(_) => return,
+                            };
+                            let read = (0..layers).try_for_each(|layer_index| {
+                                let offset = layer_index * chunk_nodes * NODE_SIZE;
+                                labels.labels_for_layer(layer_index + 1).read_range_into(
+                                    (i * nodes_count) + start,
+                                    (i * nodes_count) + end,
+                                    &mut chunk[offset..offset + (end - start) * NODE_SIZE],
+                                )
+                            });
+                            let failed = read.is_err();
+                            if chunk_tx.send(read.map(|_| chunk)).is_err() || failed {
+                                return;
                             }
-                        });
+                        }
                     }
                 });
 
-                info!("building base tree_c {}/{}", i + 1, tree_count);
-                trees.push(
-                    DiskTree::<Tree::Hasher, Tree::Arity, U0, U0>::from_par_iter_with_config(
-                        hashes.into_par_iter(),
-                        config.clone(),
-                    ),
-                );
-            }
+                // Builds each base tree, while the columns of the next one are hashed if they
+                // overlap.
+                let (tree_tx, tree_rx) = channel::<(usize, Vec<_>)>(0);
+                let (built_tx, built_rx) = channel::<()>(1);
+                let configs = &configs;
+                let builder_pool = &builder_pool;
+                let builder = s.spawn(move |_| -> Result<()> {
+                    for (i, hashes) in tree_rx {
+                        info!("building base tree_c {}/{}", i + 1, tree_count);
+                        builder_pool.install(|| {
+                            DiskTree::<Tree::Hasher, Tree::Arity, U0, U0>::from_par_iter_with_config(
+                                hashes.into_par_iter(),
+                                configs[i].clone(),
+                            )
+                        })?;
+                        if !overlap {
+                            // The hashing is gone after the last tree.
+                            let _ = built_tx.send(());
+                        }
+                    }
+                    Ok(())
+                });
+
+                let part_nodes = (chunk_nodes + threads - 1) / threads;
+                let mut columns = vec![Vec::with_capacity(part_nodes * layers); threads];
+                for i in 0..tree_count {
+                    let mut hashes: Vec<<Tree::Hasher as Hasher>::Domain> =
+                        vec![<Tree::Hasher as Hasher>::Domain::default(); nodes_count];
+                    for hashes_chunk in hashes.chunks_mut(chunk_nodes) {
+                        let chunk = chunk_rx.recv().context("layer reader stopped")??;
+                        hash_tree_c_chunk(
+                            &pool,
+                            &chunk,
+                            chunk_nodes,
+                            layers,
+                            hashes_chunk,
+                            &mut columns,
+                        );
+                        // The reader is gone after the last chunk.
+                        let _ = free_tx.send(chunk);
+                    }
 
-            assert_eq!(tree_count, trees.len());
+                    if tree_tx.send((i, hashes)).is_err() {
+                        break;
+                    }
+                    // Without overlap, the hashes of the next tree are only allocated once this
+                    // one is built.
+                    if !overlap && built_rx.recv().is_err() {
+                        break;
+                    }
+                }
+                drop(tree_tx);
+
+                builder.join().expect("tree_c builder failure")
+            })
+            .expect("crossbeam scope failure")?;
 
             create_disk_tree::<
                 DiskTree<Tree::Hasher, Tree::Arity, Tree::SubTreeArity, Tree::TopTreeArity>,
@@ -1021,7 +1315,8 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         )?;
 
         info!("generating tree r last using the GPU");
-        let max_gpu_tree_batch_size = SETTINGS.max_gpu_tree_batch_size as usize;
+        let _span = enter_span!("tree_r_last", device = "gpu");
+        let max_gpu_tree_batch_size = settings::current().max_gpu_tree_batch_size as usize;
 
         // This channel will receive batches of leaf nodes and add them to the TreeBuilder.
         let (builder_tx, builder_rx) = channel::<(Vec<Fr>, bool)>(0);
@@ -1188,6 +1483,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         )?;
 
         info!("generating tree r last using the CPU");
+        let _span = enter_span!("tree_r_last", device = "cpu");
 
         // Note that nodes_count is the count of nodes in each base tree
         let mut start = 0;
@@ -1472,6 +1768,27 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         Ok(labels)
     }
 
+    /// Phase1 of replication of several sectors, see
+    /// [`StackedDrg::generate_labels_for_encoding_lockstep`].
+    pub fn replicate_phase1_lockstep(
+        pp: &'a PublicParams<Tree>,
+        replica_ids: &[<Tree::Hasher as Hasher>::Domain],
+        configs: Vec<StoreConfig>,
+    ) -> Result<Vec<Labels<Tree>>> {
+        info!("replicate_phase1_lockstep");
+
+        let labels = measure_op(Operation::EncodeWindowTimeAll, || {
+            Self::generate_labels_for_encoding_lockstep(
+                &pp.graph,
+                &pp.layer_challenges,
+                replica_ids,
+                configs,
+            )
+        })?;
+
+        Ok(labels.into_iter().map(|(labels, _)| labels).collect())
+    }
+
     /// Phase2 of replication.
     #[allow(clippy::type_complexity)]
     pub fn replicate_phase2(
@@ -1533,7 +1850,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
 
         if Self::use_gpu_tree_builder() {
             info!("generating tree r last using the GPU");
-            let max_gpu_tree_batch_size = SETTINGS.max_gpu_tree_batch_size as usize;
+            let max_gpu_tree_batch_size = settings::current().max_gpu_tree_batch_size as usize;
 
             let _gpu_lock = GPU_LOCK.lock().expect("failed to get gpu lock");
             let batcher = match Batcher::pick_gpu(max_gpu_tree_batch_size) {
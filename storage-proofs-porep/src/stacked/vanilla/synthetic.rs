@@ -0,0 +1,222 @@
+//! Persistence of the vanilla proofs of synthetic challenges.
+//!
+//! The proofs of all synthetic challenges are written once, after which the layers are no longer
+//! needed to prove the challenges selected by the seed. The file starts with a magic number, the
+//! number of proofs and the offsets of the proofs, so that only the selected proofs are read.
+
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context};
+use bincode::{deserialize, serialize_into, serialized_size};
+use filecoin_hashers::Hasher;
+use generic_array::typenum::Unsigned;
+use storage_proofs_core::{
+    drgraph::BASE_DEGREE, error::Result, merkle::MerkleTreeTrait, util::NODE_SIZE,
+};
+
+use crate::stacked::vanilla::{graph::EXP_DEGREE, params::Proof, proof::TOTAL_PARENTS};
+
+const MAGIC: &[u8; 8] = b"SYNPROOF";
+/// The magic number and the number of proofs.
+const HEADER_LEN: u64 = 16;
+
+/// Writes the proofs of all synthetic challenges in order. The file only appears at its path
+/// once all proofs are written and synced.
+pub(crate) struct SyntheticProofsWriter {
+    file: BufWriter<File>,
+    path: PathBuf,
+    tmp_path: PathBuf,
+    count: usize,
+    /// The start of every written proof, followed by the end of the last one.
+    offsets: Vec<u64>,
+}
+
+impl SyntheticProofsWriter {
+    pub(crate) fn create(path: &Path, count: usize) -> Result<Self> {
+        let tmp_path = path.with_extension("tmp");
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("could not create file {:?}", tmp_path))?;
+        let mut file = BufWriter::new(file);
+
+        // The offsets are only known once all proofs are written.
+        let offsets_len = 8 * (count as u64 + 1);
+        file.write_all(MAGIC)?;
+        file.write_all(&(count as u64).to_le_bytes())?;
+        file.write_all(&vec![0u8; offsets_len as usize])?;
+
+        Ok(SyntheticProofsWriter {
+            file,
+            path: path.to_path_buf(),
+            tmp_path,
+            count,
+            offsets: vec![HEADER_LEN + offsets_len],
+        })
+    }
+
+    pub(crate) fn write<Tree: MerkleTreeTrait, G: Hasher>(
+        &mut self,
+        proof: &Proof<Tree, G>,
+    ) -> Result<()> {
+        ensure!(
+            self.offsets.len() <= self.count,
+            "more than {} synthetic proofs written",
+            self.count
+        );
+
+        let size = serialized_size(proof)?;
+        serialize_into(&mut self.file, proof)?;
+        let end = self.offsets.last().expect("offsets are never empty") + size;
+        self.offsets.push(end);
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        ensure!(
+            self.offsets.len() == self.count + 1,
+            "{} of {} synthetic proofs written",
+            self.offsets.len() - 1,
+            self.count
+        );
+
+        let SyntheticProofsWriter {
+            file,
+            path,
+            tmp_path,
+            offsets,
+            ..
+        } = self;
+
+        let mut file = file
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .with_context(|| format!("could not write file {:?}", tmp_path))?;
+        file.seek(SeekFrom::Start(HEADER_LEN))?;
+        for offset in &offsets {
+            file.write_all(&offset.to_le_bytes())?;
+        }
+        file.sync_all()
+            .with_context(|| format!("could not sync file {:?}", tmp_path))?;
+
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("could not move {:?} to {:?}", tmp_path, path))?;
+
+        Ok(())
+    }
+}
+
+/// Returns the size of a file holding `count` proofs of challenges of a replica of `nodes` nodes
+/// and `layers` layers.
+pub(crate) fn synthetic_proofs_size<Tree: MerkleTreeTrait>(
+    nodes: usize,
+    layers: usize,
+    count: usize,
+) -> u64 {
+    let count = count as u64;
+
+    HEADER_LEN + 8 * (count + 1) + count * proof_size::<Tree>(nodes as u64, layers as u64)
+}
+
+/// The serialized size of a [`Proof`], which only depends on the tree shape and the number of
+/// layers. Domains, lengths and `usize`s take 32, 8 and 8 bytes, enum tags and `u32`s take 4.
+pub(crate) fn proof_size<Tree: MerkleTreeTrait>(nodes: u64, layers: u64) -> u64 {
+    let node_size = NODE_SIZE as u64;
+    // `Column` and its inclusion proof.
+    let column_proof = 4 + 8 + layers * node_size + merkle_proof_size::<Tree>(nodes);
+    // `LabelingProof` and `EncodingProof`.
+    let parents_proof = 8 + TOTAL_PARENTS as u64 * node_size + 4 + 8;
+
+    let comm_d_proof = 4 + 2 * node_size + path_size(2, log_arity(nodes, 2));
+    let replica_column_proofs =
+        column_proof + 8 + BASE_DEGREE as u64 * column_proof + 8 + EXP_DEGREE as u64 * column_proof;
+
+    comm_d_proof
+        + merkle_proof_size::<Tree>(nodes)
+        + replica_column_proofs
+        + 8
+        + layers * parents_proof
+        + parents_proof
+}
+
+/// The serialized size of a `MerkleProof` in a tree of `Tree`'s shape with `nodes` leaves.
+fn merkle_proof_size<Tree: MerkleTreeTrait>(nodes: u64) -> u64 {
+    let base_arity = Tree::Arity::to_u64();
+    let sub_arity = Tree::SubTreeArity::to_u64();
+    let top_arity = Tree::TopTreeArity::to_u64();
+    let base_nodes = nodes / sub_arity.max(1) / top_arity.max(1);
+
+    let mut size =
+        4 + 2 * NODE_SIZE as u64 + path_size(base_arity, log_arity(base_nodes, base_arity));
+    if sub_arity > 0 {
+        size += path_size(sub_arity, 1);
+    }
+    if top_arity > 0 {
+        size += path_size(top_arity, 1);
+    }
+
+    size
+}
+
+/// The serialized size of an `InclusionPath` of `levels` levels.
+fn path_size(arity: u64, levels: u64) -> u64 {
+    8 + levels * (8 + (arity - 1) * NODE_SIZE as u64 + 8)
+}
+
+fn log_arity(nodes: u64, arity: u64) -> u64 {
+    u64::from(nodes.trailing_zeros() / arity.trailing_zeros())
+}
+
+/// Reads the proofs at `indexes` from a file holding `count` synthetic proofs.
+pub(crate) fn read_synthetic_proofs<Tree: MerkleTreeTrait, G: Hasher>(
+    path: &Path,
+    count: usize,
+    indexes: &[usize],
+) -> Result<Vec<Proof<Tree, G>>> {
+    let mut file = File::open(path).with_context(|| format!("could not open file {:?}", path))?;
+
+    let mut header = [0u8; HEADER_LEN as usize];
+    file.read_exact(&mut header)
+        .with_context(|| format!("could not read header of {:?}", path))?;
+    ensure!(
+        &header[..8] == MAGIC,
+        "{:?} is not a synthetic proofs file",
+        path
+    );
+    let stored = u64::from_le_bytes(header[8..].try_into().expect("invalid header length"));
+    ensure!(
+        stored == count as u64,
+        "{:?} holds {} synthetic proofs, expected {}",
+        path,
+        stored,
+        count
+    );
+
+    indexes
+        .iter()
+        .map(|&index| {
+            ensure!(
+                index < count,
+                "synthetic proof {} out of range {}",
+                index,
+                count
+            );
+
+            let mut range = [0u8; 16];
+            file.seek(SeekFrom::Start(HEADER_LEN + 8 * index as u64))?;
+            file.read_exact(&mut range)?;
+            let start = u64::from_le_bytes(range[..8].try_into().expect("invalid offset length"));
+            let end = u64::from_le_bytes(range[8..].try_into().expect("invalid offset length"));
+            ensure!(start <= end, "invalid offsets of synthetic proof {}", index);
+
+            let mut bytes = vec![0u8; (end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut bytes)
+                .with_context(|| format!("could not read synthetic proof {}", index))?;
+
+            Ok(deserialize(&bytes)?)
+        })
+        .collect()
+}
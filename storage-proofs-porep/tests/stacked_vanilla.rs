@@ -1,4 +1,5 @@
-use std::fs::remove_file;
+use std::fs::{self, remove_file};
+use std::sync::Arc;
 
 use blstrs::Scalar as Fr;
 use ff::{Field, PrimeField};
@@ -17,6 +18,7 @@ use storage_proofs_core::{
     drgraph::BASE_DEGREE,
     merkle::{get_base_tree_count, DiskTree, MerkleTreeTrait},
     proof::ProofScheme,
+    settings::{self, Settings},
     table_tests,
     test_helper::setup_replica,
     util::{default_rows_to_discard, NODE_SIZE},
@@ -307,6 +309,79 @@ fn test_stacked_porep_resume_seal() {
     cache_dir.close().expect("Failed to remove cache dir");
 }
 
+#[test]
+fn test_stacked_porep_tree_c_chunks() {
+    type Tree = DiskTree<PoseidonHasher, U8, U8, U2>;
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let replica_id = <PoseidonHasher as Hasher>::Domain::random(&mut rng);
+    let nodes = 64 * get_base_tree_count::<Tree>();
+
+    let data: Vec<u8> = (0..nodes)
+        .flat_map(|_| {
+            let v = <PoseidonHasher as Hasher>::Domain::random(&mut rng);
+            v.into_bytes()
+        })
+        .collect();
+
+    let cache_dir = tempdir().expect("tempdir failure");
+    let config = StoreConfig::new(
+        cache_dir.path(),
+        CacheKey::CommDTree.to_string(),
+        default_rows_to_discard(nodes, BINARY_ARITY),
+    );
+
+    let sp = SetupParams {
+        nodes,
+        degree: BASE_DEGREE,
+        expansion_degree: EXP_DEGREE,
+        porep_id: [32; 32],
+        layer_challenges: LayerChallenges::new(DEFAULT_STACKED_LAYERS, 5),
+        api_version: ApiVersion::V1_1_0,
+    };
+
+    let pp = StackedDrg::<Tree, Blake2sHasher>::setup(&sp).expect("setup failed");
+
+    let replicate = |name: &str| {
+        let replica_path = cache_dir.path().join(name);
+        let mut mmapped_data = setup_replica(&data, &replica_path);
+        let (_, (p_aux, _)) = StackedDrg::<Tree, Blake2sHasher>::replicate(
+            &pp,
+            &replica_id,
+            (mmapped_data.as_mut()).into(),
+            None,
+            config.clone(),
+            replica_path,
+        )
+        .expect("replication failed");
+        p_aux.comm_c
+    };
+
+    let comm_c = replicate("replica-path-1");
+
+    // Columns hashed on 3 threads in chunks of a single node, with the budget only holding the
+    // hashes of one base tree, and in chunks of 5 nodes, which do not divide the base trees, with
+    // the builds overlapping the hashing.
+    let hashes_size = 64 * NODE_SIZE;
+    let chunks_size = 3 * DEFAULT_STACKED_LAYERS * NODE_SIZE * 5;
+    for (i, budget) in [hashes_size, 2 * hashes_size + chunks_size]
+        .iter()
+        .enumerate()
+    {
+        let settings = Settings {
+            tree_c_cpu_threads: 3,
+            tree_c_cpu_memory_budget: *budget as u64,
+            ..Settings::default()
+        };
+        let name = format!("replica-path-chunked-{}", i);
+        let chunked_comm_c = settings::with_settings(Arc::new(settings), || replicate(&name));
+
+        assert_eq!(comm_c, chunked_comm_c);
+    }
+
+    cache_dir.close().expect("Failed to remove cache dir");
+}
+
 table_tests! {
     test_prove_verify_fixed {
        test_stacked_porep_prove_verify(64);
@@ -440,6 +515,115 @@ fn test_prove_verify<Tree: 'static + MerkleTreeTrait>(n: usize, challenges: Laye
     cache_dir.close().expect("Failed to remove cache dir");
 }
 
+#[test]
+fn test_stacked_porep_prove_verify_synthetic() {
+    type Tree = DiskTree<PoseidonHasher, U8, U2, U0>;
+
+    let nodes = 64 * get_base_tree_count::<Tree>();
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+    let replica_id = <PoseidonHasher as Hasher>::Domain::random(&mut rng);
+    let data: Vec<u8> = (0..nodes)
+        .flat_map(|_| fr_into_bytes(&Fr::random(&mut rng)))
+        .collect();
+
+    let cache_dir = tempdir().expect("tempdir failure");
+    let config = StoreConfig::new(
+        cache_dir.path(),
+        CacheKey::CommDTree.to_string(),
+        default_rows_to_discard(nodes, BINARY_ARITY),
+    );
+    let replica_path = cache_dir.path().join("replica-path");
+    let mut mmapped_data = setup_replica(&data, &replica_path);
+
+    let partitions = 2;
+    let layer_challenges = LayerChallenges::new_synthetic(DEFAULT_STACKED_LAYERS, 5, 32);
+    let sp = SetupParams {
+        nodes,
+        degree: BASE_DEGREE,
+        expansion_degree: EXP_DEGREE,
+        porep_id: [93; 32],
+        layer_challenges: layer_challenges.clone(),
+        api_version: ApiVersion::V1_2_0,
+    };
+
+    let pp = StackedDrg::<Tree, Blake2sHasher>::setup(&sp).expect("setup failed");
+    let (tau, (p_aux, t_aux)) = StackedDrg::<Tree, Blake2sHasher>::replicate(
+        &pp,
+        &replica_id,
+        (mmapped_data.as_mut()).into(),
+        None,
+        config,
+        replica_path.clone(),
+    )
+    .expect("replication failed");
+
+    // All synthetic challenges are proven before the seed is known.
+    let synthetic_path = cache_dir
+        .path()
+        .join(CacheKey::SyntheticPoRepProofs.to_string());
+    let t_aux_cache = TemporaryAuxCache::<Tree, Blake2sHasher>::new(&t_aux, replica_path)
+        .expect("failed to restore contents of t_aux");
+    StackedDrg::<Tree, Blake2sHasher>::prove_synthetic_layers(
+        &pp.graph,
+        &replica_id,
+        &tau,
+        &p_aux,
+        &t_aux_cache,
+        &layer_challenges,
+        &synthetic_path,
+    )
+    .expect("failed to prove synthetic challenges");
+    drop(t_aux_cache);
+    assert_eq!(
+        fs::metadata(&synthetic_path)
+            .expect("failed to stat synthetic proofs")
+            .len(),
+        StackedDrg::<Tree, Blake2sHasher>::synthetic_proofs_size(nodes, &layer_challenges)
+            .expect("challenges are synthetic"),
+    );
+
+    // The layers are no longer needed once the synthetic proofs are persisted.
+    TemporaryAux::<Tree, Blake2sHasher>::clear_temp(t_aux).expect("t_aux delete failed");
+    let layer_path =
+        StoreConfig::data_path(&cache_dir.path().to_path_buf(), &CacheKey::label_layer(1));
+    assert!(!layer_path.exists());
+
+    let mut pub_inputs =
+        PublicInputs::<<PoseidonHasher as Hasher>::Domain, <Blake2sHasher as Hasher>::Domain> {
+            replica_id,
+            seed: rng.gen(),
+            tau: Some(tau),
+            k: None,
+        };
+    let all_partition_proofs = StackedDrg::<Tree, Blake2sHasher>::read_synthetic_proofs(
+        &pub_inputs,
+        &layer_challenges,
+        partitions,
+        &synthetic_path,
+    )
+    .expect("failed to read synthetic proofs");
+    assert_eq!(all_partition_proofs.len(), partitions);
+
+    assert!(StackedDrg::<Tree, Blake2sHasher>::verify_all_partitions(
+        &pp,
+        &pub_inputs,
+        &all_partition_proofs,
+    )
+    .expect("failed to verify partition proofs"));
+
+    // The proofs are only valid for the challenges selected by their seed.
+    pub_inputs.seed = rng.gen();
+    assert!(!StackedDrg::<Tree, Blake2sHasher>::verify_all_partitions(
+        &pp,
+        &pub_inputs,
+        &all_partition_proofs,
+    )
+    .expect("failed to verify partition proofs"));
+
+    cache_dir.close().expect("Failed to remove cache dir");
+}
+
 // We are seeing a bug, in which setup never terminates for some sector sizes. This test is to
 // debug that and should remain as a regression test.
 #[test]
@@ -13,7 +13,6 @@ use storage_proofs_core::{
     },
     merkle::MerkleTreeTrait,
     por,
-    settings::SETTINGS,
     util::NODE_SIZE,
 };
 
@@ -23,6 +22,8 @@ use crate::fallback::{PublicParams, PublicSector, SectorProof};
 pub struct FallbackPoStCircuit<Tree: MerkleTreeTrait> {
     pub prover_id: Option<Fr>,
     pub sectors: Vec<Sector<Tree>>,
+    /// The number of threads synthesizing the sectors in parallel.
+    pub synthesis_num_cpus: usize,
 }
 
 // We must manually implement Clone for all types generic over MerkleTreeTrait (instead of using
@@ -34,6 +35,7 @@ impl<Tree: 'static + MerkleTreeTrait> Clone for FallbackPoStCircuit<Tree> {
         FallbackPoStCircuit {
             prover_id: self.prover_id,
             sectors: self.sectors.clone(),
+            synthesis_num_cpus: self.synthesis_num_cpus,
         }
     }
 }
@@ -212,9 +214,13 @@ impl<Tree: 'static + MerkleTreeTrait> FallbackPoStCircuit<Tree> {
         self,
         cs: &mut CS,
     ) -> Result<(), SynthesisError> {
-        let FallbackPoStCircuit { sectors, .. } = self;
+        let FallbackPoStCircuit {
+            sectors,
+            synthesis_num_cpus,
+            ..
+        } = self;
 
-        let num_chunks = SETTINGS.window_post_synthesis_num_cpus as usize;
+        let num_chunks = synthesis_num_cpus.max(1);
 
         let chunk_size = (sectors.len() / num_chunks).max(1);
         let css = sectors
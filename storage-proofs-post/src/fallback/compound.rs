@@ -144,6 +144,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait>
         Ok(FallbackPoStCircuit {
             prover_id: Some(pub_in.prover_id.into()),
             sectors: res_sectors,
+            synthesis_num_cpus: pub_params.synthesis_num_cpus,
         })
     }
 
@@ -157,6 +158,7 @@ impl<'a, Tree: 'static + MerkleTreeTrait>
         FallbackPoStCircuit {
             prover_id: None,
             sectors,
+            synthesis_num_cpus: pub_params.synthesis_num_cpus,
         }
     }
 }
@@ -16,9 +16,11 @@ use storage_proofs_core::{
     api_version::ApiVersion,
     error::{Error, Result},
     merkle::{MerkleProof, MerkleProofTrait, MerkleTreeTrait, MerkleTreeWrapper},
+    metrics,
     parameter_cache::ParameterSetMetadata,
     proof::ProofScheme,
     sector::SectorId,
+    settings,
     util::{default_rows_to_discard, NODE_SIZE},
 };
 
@@ -42,6 +44,9 @@ pub struct PublicParams {
     /// Number of challenged sectors.
     pub sector_count: usize,
     pub api_version: ApiVersion,
+    /// The number of threads synthesizing the circuit in parallel. It is read from the settings
+    /// during setup, as the circuits are synthesized on other threads.
+    pub synthesis_num_cpus: usize,
 }
 
 #[derive(Debug, Default)]
@@ -317,6 +322,7 @@ impl<'a, Tree: 'a + MerkleTreeTrait> ProofScheme<'a> for FallbackPoSt<'a, Tree>
             challenge_count: sp.challenge_count,
             sector_count: sp.sector_count,
             api_version: sp.api_version,
+            synthesis_num_cpus: settings::current().window_post_synthesis_num_cpus as usize,
         })
     }
 
@@ -487,6 +493,7 @@ impl<'a, Tree: 'a + MerkleTreeTrait> ProofScheme<'a> for FallbackPoSt<'a, Tree>
         if faulty_sectors.is_empty() {
             Ok(partition_proofs)
         } else {
+            metrics::record_faulty_sectors(faulty_sectors.len());
             Err(Error::FaultySectors(faulty_sectors.into_iter().collect()).into())
         }
     }
@@ -74,6 +74,7 @@ fn test_fallback_post<Tree: 'static + MerkleTreeTrait>(
         challenge_count: 5,
         sector_count,
         api_version: ApiVersion::V1_1_0,
+        synthesis_num_cpus: 2,
     };
 
     let temp_dir = tempdir().expect("tempdir failure");
@@ -157,6 +158,7 @@ fn test_fallback_post<Tree: 'static + MerkleTreeTrait>(
         let instance = FallbackPoStCircuit::<Tree> {
             sectors: circuit_sectors,
             prover_id: Some(prover_id.into()),
+            synthesis_num_cpus: pub_params.synthesis_num_cpus,
         };
 
         instance
@@ -128,6 +128,7 @@ fn test_fallback_post<Tree: MerkleTreeTrait>(
         challenge_count: 10,
         sector_count,
         api_version,
+        synthesis_num_cpus: 1,
     };
 
     let randomness = <Tree::Hasher as Hasher>::Domain::random(rng);
@@ -299,6 +300,7 @@ fn test_invalid_fallback_post<Tree: MerkleTreeTrait>(
         challenge_count: 10,
         sector_count,
         api_version,
+        synthesis_num_cpus: 1,
     };
 
     let randomness = <Tree::Hasher as Hasher>::Domain::random(rng);
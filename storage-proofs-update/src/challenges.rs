@@ -143,10 +143,9 @@ mod tests {
     use storage_proofs_core::TEST_SEED;
 
     use crate::constants::{
-        TreeRDomain, ALLOWED_SECTOR_SIZES, SECTOR_SIZE_16_KIB, SECTOR_SIZE_16_MIB,
-        SECTOR_SIZE_1_KIB, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB, SECTOR_SIZE_32_KIB,
-        SECTOR_SIZE_4_KIB, SECTOR_SIZE_512_MIB, SECTOR_SIZE_64_GIB, SECTOR_SIZE_8_KIB,
-        SECTOR_SIZE_8_MIB,
+        TreeRDomain, SECTOR_SIZE_16_KIB, SECTOR_SIZE_16_MIB, SECTOR_SIZE_1_KIB, SECTOR_SIZE_2_KIB,
+        SECTOR_SIZE_32_GIB, SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB, SECTOR_SIZE_512_MIB,
+        SECTOR_SIZE_64_GIB, SECTOR_SIZE_8_KIB, SECTOR_SIZE_8_MIB, TEST_SECTOR_SIZES,
     };
 
     #[test]
@@ -253,7 +252,7 @@ mod tests {
     fn test_challenge_bucketing() {
         let mut rng = XorShiftRng::from_seed(TEST_SEED);
 
-        for sector_nodes in ALLOWED_SECTOR_SIZES.iter().copied() {
+        for sector_nodes in TEST_SECTOR_SIZES.iter().copied() {
             let comm_r_new = TreeRDomain::random(&mut rng);
 
             let partitions = partition_count(sector_nodes);
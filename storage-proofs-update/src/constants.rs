@@ -3,7 +3,7 @@ use filecoin_hashers::{
     poseidon::{PoseidonDomain, PoseidonHasher},
     sha256::{Sha256Domain, Sha256Hasher},
 };
-use generic_array::typenum::{Unsigned, U0, U2, U8};
+use generic_array::typenum::{U0, U2, U8};
 use lazy_static::lazy_static;
 use merkletree::store::DiskStore;
 use neptune::{
@@ -11,7 +11,10 @@ use neptune::{
     poseidon::PoseidonConstants,
     Strength,
 };
-use storage_proofs_core::merkle::{BinaryMerkleTree, LCStore, LCTree, MerkleTreeTrait};
+use storage_proofs_core::{
+    merkle::{BinaryMerkleTree, LCStore, LCTree, MerkleTreeTrait},
+    sector_registry::{sector_size_info, TreeShape},
+};
 
 // Use a custom domain separation tag when generating randomness phi, rho, and challenges bits.
 pub const HASH_TYPE_GEN_RANDOMNESS: HashType<Fr, U2> = HashType::Custom(CType::Arbitrary(1));
@@ -34,7 +37,10 @@ pub const SECTOR_SIZE_512_MIB: usize = 1 << 24;
 pub const SECTOR_SIZE_32_GIB: usize = 1 << 30;
 pub const SECTOR_SIZE_64_GIB: usize = 1 << 31;
 
-pub const ALLOWED_SECTOR_SIZES: [usize; 11] = [
+// The sector-sizes iterated by tests; `SECTOR_SIZE_1_KIB` and `SECTOR_SIZE_8_KIB` are registered
+// for testing only.
+#[cfg(test)]
+pub(crate) const TEST_SECTOR_SIZES: [usize; 11] = [
     // testing sector-sizes
     SECTOR_SIZE_1_KIB,
     SECTOR_SIZE_2_KIB,
@@ -110,26 +116,18 @@ pub const fn apex_leaf_count(sector_nodes: usize) -> usize {
     }
 }
 
-pub fn validate_tree_r_shape<TreeR: MerkleTreeTrait>(sector_nodes: usize) {
-    let base_arity = TreeR::Arity::to_usize();
-    let sub_arity = TreeR::SubTreeArity::to_usize();
-    let top_arity = TreeR::TopTreeArity::to_usize();
-    let arities = (base_arity, sub_arity, top_arity);
+// Returns the sector-size measured in nodes, panics if the sector-size is not registered.
+pub fn sector_nodes_from_sector_size(sector_bytes: u64) -> usize {
+    sector_size_info(sector_bytes)
+        .expect("provided sector-size is not allowed")
+        .nodes()
+}
 
-    let arities_expected = match sector_nodes {
-        SECTOR_SIZE_1_KIB => (8, 4, 0),
-        SECTOR_SIZE_2_KIB => (8, 0, 0),
-        SECTOR_SIZE_4_KIB => (8, 2, 0),
-        SECTOR_SIZE_8_KIB => (8, 4, 0),
-        SECTOR_SIZE_16_KIB => (8, 8, 0),
-        SECTOR_SIZE_32_KIB => (8, 8, 2),
-        SECTOR_SIZE_8_MIB => (8, 0, 0),
-        SECTOR_SIZE_16_MIB => (8, 2, 0),
-        SECTOR_SIZE_512_MIB => (8, 0, 0),
-        SECTOR_SIZE_32_GIB => (8, 8, 0),
-        SECTOR_SIZE_64_GIB => (8, 8, 2),
-        _ => unreachable!(),
-    };
+pub fn validate_tree_r_shape<TreeR: MerkleTreeTrait>(sector_nodes: usize) {
+    let sector_bytes = (sector_nodes << 5) as u64;
+    let shape_expected = sector_size_info(sector_bytes)
+        .expect("provided sector-size is not allowed")
+        .shape;
 
-    assert_eq!(arities, arities_expected);
+    assert_eq!(TreeShape::of::<TreeR>(), shape_expected);
 }
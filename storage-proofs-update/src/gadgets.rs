@@ -287,8 +287,8 @@ mod tests {
         challenges::Challenges,
         constants::{
             apex_leaf_count, challenge_count, partition_count, TreeDDomain, TreeDHasher,
-            TreeRDomain, ALLOWED_SECTOR_SIZES, SECTOR_SIZE_16_KIB, SECTOR_SIZE_1_KIB,
-            SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB, SECTOR_SIZE_8_KIB,
+            TreeRDomain, SECTOR_SIZE_16_KIB, SECTOR_SIZE_1_KIB, SECTOR_SIZE_2_KIB,
+            SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB, SECTOR_SIZE_8_KIB, TEST_SECTOR_SIZES,
         },
     };
 
@@ -298,7 +298,7 @@ mod tests {
 
         let num_constraints_expected = [568, 568, 568, 568, 568, 568, 568, 568, 4544, 5680, 5680];
 
-        for (sector_nodes, constraints_expected) in ALLOWED_SECTOR_SIZES
+        for (sector_nodes, constraints_expected) in TEST_SECTOR_SIZES
             .iter()
             .copied()
             .zip(num_constraints_expected.iter().copied())
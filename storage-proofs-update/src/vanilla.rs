@@ -35,9 +35,9 @@ use storage_proofs_porep::stacked::{StackedDrg, TreeRElementData};
 
 use crate::{
     constants::{
-        apex_leaf_count, challenge_count, challenge_count_poseidon, hs, partition_count, TreeD,
-        TreeDArity, TreeDDomain, TreeDHasher, TreeDStore, TreeRDomain, TreeRHasher,
-        ALLOWED_SECTOR_SIZES, POSEIDON_CONSTANTS_GEN_RANDOMNESS,
+        apex_leaf_count, challenge_count, challenge_count_poseidon, hs, partition_count,
+        sector_nodes_from_sector_size, TreeD, TreeDArity, TreeDDomain, TreeDHasher, TreeDStore,
+        TreeRDomain, TreeRHasher, POSEIDON_CONSTANTS_GEN_RANDOMNESS,
     },
     Challenges,
 };
@@ -88,11 +88,7 @@ impl ParameterSetMetadata for PublicParams {
 impl PublicParams {
     pub fn from_sector_size(sector_bytes: u64) -> Self {
         // The sector-size measured in 32-byte nodes.
-        let sector_nodes = ALLOWED_SECTOR_SIZES
-            .iter()
-            .copied()
-            .find(|allowed_nodes| (allowed_nodes << 5) as u64 == sector_bytes)
-            .expect("provided sector-size is not allowed");
+        let sector_nodes = sector_nodes_from_sector_size(sector_bytes);
 
         // `sector_nodes` is guaranteed to be a power of two.
         let challenge_bit_len = sector_nodes.trailing_zeros() as usize;
@@ -118,11 +114,7 @@ impl PublicParams {
     }
 
     pub fn from_sector_size_poseidon(sector_bytes: u64) -> Self {
-        let sector_nodes = ALLOWED_SECTOR_SIZES
-            .iter()
-            .copied()
-            .find(|allowed_nodes| (allowed_nodes << 5) as u64 == sector_bytes)
-            .expect("provided sector-size is not allowed");
+        let sector_nodes = sector_nodes_from_sector_size(sector_bytes);
 
         let challenge_bit_len = sector_nodes.trailing_zeros() as usize;
         let challenge_count = challenge_count_poseidon(sector_nodes);